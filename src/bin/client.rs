@@ -17,31 +17,255 @@
 //   0b) BD reçoit pk_other relayée par le serveur
 //   - Chaque BD connaît maintenant la vraie pk de l'autre
 //   - phase2 peut chiffrer ses Ft sous n1 ET n2 corrects
+//
+// Mode --multikey :
+//   Phase 2 : Ft chiffrés UNE fois, sous pk_self uniquement
+//   Phase 4 : déchiffrement conjoint — BD1 linéarise et agrège,
+//             les parts s'échangent chiffrées sous la clé du
+//             destinataire, relayées par le serveur
+//
+// Mode --kea :
+//   Phase 0 : chaque BD publie ct_delta = (Enc(1), Enc(ξ)) avec pk
+//   Phase 4 : chaque triplet passe la vérification d'image KEA
+//             avant d'être compté ; seule une composante altérée
+//             indépendamment de l'autre est détectée (le serveur
+//             connaît ct_delta et peut forger des paires valides)
+//
+// Phase 5 (modes standard, --reveal, --kea, --checkpoint) : chaque BD
+//   s'engage sur son cardinal (SHA-256 avec aléa), puis ouvre sous
+//   pk_other via le serveur ; un cardinal différent sous pk1 et pk2
+//   interrompt avec un diagnostic (cf. exactmatch/consistency.rs).
+//
+// Phase 0d (tous modes) : accord sur la clé HMAC des positions.
+//   Chaque BD envoie une part chiffrée sous pk_other, relayée par le
+//   serveur ; K = SHA-256(part1 || part2 || psk).
+//
+// --threads N       : taille du pool des Phases 2 et 4 (0 = un thread
+//                     par cœur ; défaut : PSI_THREADS ou 0)
+// --table-bits B    : table de 2^B positions (défaut 30, identique
+//                     des deux côtés)
+// --hash-psk <hex>  : clé pré-partagée hors bande (32 octets), mêlée
+//                     à K ; doit être identique des deux côtés
+//
+// Mode --cuckoo :
+//   Phase 0e : échange des tailles d'ensemble (relayées par le serveur)
+//   Phase 1  : BD1 construit une table cuckoo, BD2 des bins simples
+//   Phase 4  : compte les comparaisons qui se déchiffrent en 0
+//   --stash S : taille du stash (défaut 2, identique des deux côtés)
+//
+// Option --size-hiding --pad-to P (mode standard ou --cuckoo) : le serveur
+// n'apprend plus la taille de la base. Il voit toujours les positions
+// communes, donc le cardinal : seules les tailles sont masquées.
+//   standard : P positions envoyées, les positions actives complétées
+//              par des positions factices aléatoires (Ft(0)) ;
+//              --pad-to identique pour le serveur et les deux BD
+//   cuckoo   : P annoncé au lieu de la taille réelle
+//   Le surcoût de bourrage figure dans le rapport de bande passante.
+//
+// Option --threshold t (mode standard, --size-hiding possible) : PSI-CA à
+//   seuil. Le BD ne reçoit que des masques re-randomisés par le serveur,
+//   renvoie Enc(Σ masques), puis une comparaison bit à bit (DGK) ne lui
+//   révèle que le bit [ |BD1 ∩ BD2| >= t ] (t fixé par le serveur,
+//   vérifié ici).
+//
+// Option --dp ε (mode standard, --size-hiding possible) : cardinal
+//   différentiellement privé. Comme --threshold, le BD ne reçoit que des
+//   masques ; il renvoie Enc(Σ masques) et une part de bruit de Laplace
+//   discret chiffrée sous pk1 et pk2. Le serveur ajoute la sienne : les
+//   deux BD déchiffrent le même c + bruit. Les masques étant
+//   re-randomisés par le serveur, un BD ne peut pas recompter c à partir
+//   de ses masques de Phase 2 : chaque partie ne connaît que sa part de
+//   bruit, jamais le bruit total. Le bruit ne protège que face à l'autre
+//   BD : le serveur voit les positions communes, donc c exact.
+//   --dp-ledger <registre.json> : budget de confidentialité de la base,
+//   débité de ε avant l'exécution ; --dp-budget B le crée. ε doit être
+//   identique pour le serveur et les deux BD.
+//
+// Option --setops a-b,b-a,union[,inter] (mode standard, --size-hiding
+//   --pad-to P requis) : cardinaux d'opérations ensemblistes sur les
+//   bundles bourrés (cf. exactmatch/setops.rs). Le BD ne reçoit que P
+//   masques re-randomisés et un chiffré par sortie choisie ; les autres
+//   ne lui parviennent pas.
+//   Phase 0e : sorties choisies envoyées au serveur, qui renvoie les
+//              siennes ; la liste doit être identique pour les trois.
+//   Avec sa propre taille, un BD déduit des sorties les cardinaux
+//   qu'elles impliquent : n'annoncer que ce qui peut être révélé.
+//
+// Option --multiset <min|product> (mode standard) : doublons comptés au lieu
+//   d'être fusionnés (cf. exactmatch/multiset.rs). Les statistiques de
+//   doublons figurent en Phase 1 dans tous les modes.
+//   min     : Σ min(a_i, b_i), une position par occurrence
+//   product : Σ a_i·b_i, Ft(multiplicité) par clé
+//   Phase 0e : sémantique échangée via le serveur (--multiset requis côté
+//              serveur), identique des deux côtés.
+//
+// Option --sum (mode standard) : PSI-Sum, Σ valeur sur l'intersection.
+//   BD2 chiffre la colonne --value-col (défaut « montant ») à la place
+//   de l'indicateur 1 ; --decimals d (défaut 0) fixe l'encodage virgule
+//   fixe. --sum et --decimals doivent être identiques des deux côtés
+//   (--sum aussi côté serveur). Le serveur agrège les produits : le BD
+//   ne déchiffre que la somme, jamais la valeur d'un individu.
+//
+// Mode --fuzzy : appariement approximatif par encodages CLK (filtres de
+//   Bloom des bigrammes des colonnes --fields, défaut nom,prenom,date_naissance).
+//   Phase 0e : échange du nombre d'enregistrements et de (L, k)
+//   Phase 4  : compte les paires avec Dice >= --dice (défaut 0.8) ou
+//              Hamming <= --hamming h
+//   --clk-bits L / --clk-hashes k : identiques des deux côtés
+//
+// Option --similarity (mode standard, --size-hiding possible) : en plus du
+//   cardinal, |BD1|, |BD2|, |BD1 ∪ BD2|, Jaccard et taux d'inclusion.
+//   Phase 0e : échange des tailles (positions actives) via le serveur ;
+//   --hide-sizes les chiffre sous pk_other (le serveur relaie sans lire).
+//
+// Mode --minhash : mêmes métriques, estimées sur des signatures MinHash
+//   de --minhash-k composantes (défaut 128) au lieu des tables complètes.
+//   Phase 0e : tailles (cf. --hide-sizes) et k, identique des deux côtés
+//   Phase 4  : Ĵ = composantes égales / k
+//
+// Option --reveal (mode standard, --size-hiding possible) : PSI complète.
+//   Phase 4 : chaque triplet est déchiffré séparément ; les positions
+//   à 1 sont rapportées aux enregistrements locaux et à leur colonne
+//   --id-col (défaut « identification »), écrits dans --reveal-out.
+//   Le serveur et l'autre BD n'apprennent rien des non-communs.
+//   Deux clés locales sur une même position commune sont toutes deux
+//   rapportées (collision intra-table) : un avertissement le signale.
+//   --reveal doit être passé au serveur et aux deux BD.
+//
+// Mode --delta <session.json> : PSI incrémentale (cf. exactmatch/delta.rs).
+//   La paire Paillier est relue depuis --key-file (défaut
+//   keys/bd<N>_keypair.json, créée au premier passage) ; la session
+//   conserve K, pk_other et les positions de la dernière époque.
+//   Phase 0c : le serveur annonce (session, époque, création ?)
+//   Phase 2  : positions retirées + Ft des positions ajoutées
+//   Phase 3b : Enc(b·b') pour les masques des nouveaux produits
+//   Phase 4  : un seul chiffré, le cardinal courant
+//
+// Mode --stream [--chunk N] : mémoire bornée, indépendante de la taille
+//   du CSV (cf. exactmatch/stream.rs).
+//   Phase 1 : CSV lu ligne à ligne, positions triées par tri externe
+//   Phase 2 : lots de N Ft par ordre croissant de position, lot vide = fin
+//   Phase 4 : paires de masques reçues par lots et cumulées, puis
+//             c = Dec(Π C0) + Σ b·b'
+//
+// Mode --checkpoint <dir> : mode standard avec reprise sur incident
+//   (cf. exactmatch/checkpoint.rs). <dir> garde la paire Paillier, la
+//   session (id, empreintes des pk et du CSV, K) et le bundle de Phase 2.
+//   Phase 0c : id de la session locale envoyé (0 si CSV ou clés changés),
+//              le serveur répond reprise / nouvelle session
+//   Phase 2  : bundle envoyé seulement s'il manque au serveur
+//
+// --key-config <clés.json> (tous modes) : clé composite normalisée
+//   (colonnes, trim, casse, accents, dates… cf. records/normalize.rs),
+//   identique des deux côtés. En mode --fuzzy, ses composantes
+//   remplacent --fields. Les lignes rejetées sont listées au chargement.
+//
+// --input <nss|hash|verify> (hors --fuzzy, sans --key-config) : source de
+//   la clé, identique des deux côtés pour hash et verify.
+//   nss    : NSS brut (défaut)
+//   hash   : colonne HASH(NSS) seule (SHA-256 hex, exports pseudonymisés)
+//   verify : SHA-256(NSS) recalculé ; les lignes dont HASH(NSS) diffère
+//            sont rejetées et listées. Même clé que hash.
 // =========================================================
 
 use std::env;
 use std::net::{TcpListener, TcpStream};
 use std::io;
 use std::time::Instant;
+use std::collections::HashSet;
+
+use num_bigint::BigInt;
 
 use paillier_crypto::exactmatch::{
-    load_nss_from_csv,
     phase0_keygen, phase1_build_table,
-    phase2_prepare_dual_ft, phase4_decrypt_and_count,
-    DualFtBundle, FtBundle,
+    phase2_prepare_dual_ft, phase2_prepare_dual_ft_padded, phase4_decrypt_and_count,
+    phase1_build_value_table, phase2_prepare_dual_ft_values, phase4_decrypt_sum,
+    phase4_threshold_mask_sum, phase4_threshold_bits, phase4_threshold_decide,
+    phase4_dp_noise_share, phase4_dp_decrypt, discrete_laplace_variance, check_epsilon,
+    DpLedger, DP_NOISE_SHARES,
+    phase2_prepare_mk_ft, phase4_mk_linearize,
+    phase4_mk_partial_dec, phase4_mk_combine,
+    phase0_kea_keygen, phase2_prepare_dual_ft_kea, phase4_decrypt_and_count_kea,
+    phase0_hash_key_share, phase0_derive_hasher, check_table_bits,
+    HashKey, DEFAULT_TABLE_BITS,
+    DualFtBundle, FtBundle, DualKeaFtBundle, KeaFtBundle, SparseTable,
+    phase1_build_cuckoo, phase1_build_simple_bins, phase2_prepare_slot_ft, phase4_count_zeros,
+    CuckooParams, DEFAULT_CUCKOO_HASHES, DEFAULT_STASH_SIZE,
+    phase4_decrypt_positions, reveal_matching_records,
+    phase1_build_clks, phase2_prepare_clk_ft, phase4_count_similar,
+    ClkParams, Similarity, DEFAULT_CLK_BITS, DEFAULT_CLK_HASHES,
+    SetOverlap, DEFAULT_MINHASH_K, check_minhash_k,
+    phase0_size_share, phase0_open_size, minhash_signature, phase4_estimate_jaccard,
+    DeltaSession, phase4_delta_mask_products, phase4_delta_decrypt_total,
+    PositionSorter, StreamTally, DEFAULT_STREAM_CHUNK, check_stream_chunk, phase2_stream_chunk,
+    ClientCheckpoint, input_digest,
+    phase5_commit, phase5_seal, phase5_verify,
+    SetOp, SetOps, phase4_setops_decrypt,
+    MultisetSemantics, MULTISET_MAX_COUNT, ValueTable, phase1_build_multiset, phase4_multiset_product,
 };
+use paillier_crypto::paillier::p_encrypt::p_encrypt::p_encrypt;
+use paillier_crypto::parallel::{default_pool, set_default_threads};
+use paillier_crypto::exactmatch::position_hash::{PositionHasher, psk_id};
+use paillier_crypto::key_management::{
+    hex_to_fingerprint, load_keypair_json, save_keypair_json, key_file_exists, ensure_keys_directory,
+};
+use paillier_crypto::cf_stats::FixedPoint;
+use paillier_crypto::records::{KeyConfig, CsvTable, derive_keys, load_records, stream_keys, verify_hash_column};
+use paillier_crypto::KeyPair;
 use paillier_crypto::paillier::p_keygen::PublicKey;
 use paillier_crypto::net_protocol::{
     BandwidthMeter,
-    MsgPubKey, MsgDualBundle, MsgFtBundle, MsgTriplets,
+    MsgPubKey, MsgHashKeyShare, MsgCuckooSize, MsgSetSize, MsgClkParams, MsgClkScores, MsgDualBundle, MsgPosTriplets, MsgMaskPairs, MsgSndSum, MsgThresholdResult, MsgThresholdCmp, MsgDpShare, MsgDpResult, MsgFtBundle, MsgTriplets,
+    MsgMkQuads, MsgMkRelay, MsgMkShare,
+    MsgKeaDelta, MsgKeaFtBundle, MsgKeaDualBundle, MsgKeaTriplets,
+    MsgDeltaSession, MsgDelta, MsgCtList, MsgDeltaResult,
+    MsgResume, MsgResumePlan,
+    MsgCardinalCommit, MsgCardinal,
+    MsgSetOps, MsgSetOpsResult,
+    MsgMultiset,
     send_tracked, recv_tracked,
 };
 
+// --reveal : identifiants affichés dans le bandeau (le reste va dans --reveal-out)
+const REVEAL_PRINT_MAX: usize = 20;
+
 const SERVER_ADDR_BD1: &str = "127.0.0.1:7001";
 const SERVER_ADDR_BD2: &str = "127.0.0.1:7002";
 const LISTEN_PORT_BD1: u16  = 7003;
 const LISTEN_PORT_BD2: u16  = 7004;
 
+const USAGE: &str = "Usage : client --bd <1|2> --csv <fichier.csv> [--multikey | --kea | --cuckoo | --fuzzy | --minhash [--minhash-k <k>]] \
+                     [--threads <N>] [--table-bits <8..48>] [--hash-psk <64 hex>] [--stash <S>] \
+                     [--size-hiding --pad-to <P>] [--sum [--value-col <col>] [--decimals <d>]] [--threshold <t>] \
+                     [--dp <epsilon> [--dp-ledger <registre.json>] [--dp-budget <B>]] \
+                     [--fields <c1,c2,..>] [--clk-bits <L>] [--clk-hashes <k>] [--dice <0..1> | --hamming <h>] \
+                     [--similarity] [--hide-sizes] [--key-config <clés.json>] [--reveal [--id-col <col>] [--reveal-out <fichier>]] \
+                     [--setops <a-b,b-a,union,inter>] [--multiset <min|product>] \
+                     [--delta <session.json> [--key-file <paire.json>]] [--stream [--chunk <N>]] [--checkpoint <dir>] [--input <nss|hash|verify>]
+  --kea : contrôle d'image KEA ; le serveur connaît ct_delta, ce n'est pas un calcul vérifiable
+  --dp : bruit face à l'autre BD uniquement, le serveur voit le cardinal exact";
+
+// ─────────────────────────────────────────────────────────
+// Variante du protocole (doit être identique côté serveur)
+// ─────────────────────────────────────────────────────────
+#[derive(Clone, Copy, PartialEq)]
+enum Mode {
+    Standard,
+    MultiKey,
+    Kea,
+    Cuckoo,
+    Fuzzy,
+    MinHash,
+}
+
+/// Source de la clé (--input)
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum KeyInput {
+    Nss,
+    Hash,
+    Verify,
+}
+
 // ─────────────────────────────────────────────────────────
 // Reconstruction d'une PublicKey depuis un message réseau
 //
@@ -58,19 +282,521 @@ fn pubkey_from_msg(msg: MsgPubKey) -> PublicKey {
 // ─────────────────────────────────────────────────────────
 // Sérialisation DualFtBundle -> message réseau
 // ─────────────────────────────────────────────────────────
+fn ft_bundle_to_msg(b: &FtBundle) -> MsgFtBundle {
+    MsgFtBundle {
+        entries: b.ft_by_pos.iter()
+            .map(|(&pos, ft)| (pos, ft.clone()))
+            .collect(),
+    }
+}
+
 fn bundle_to_msg(b: &DualFtBundle) -> MsgDualBundle {
     MsgDualBundle {
-        under_pk1: MsgFtBundle {
-            entries: b.under_pk1.ft_by_pos.iter()
-                .map(|(&pos, ft)| (pos, ft.clone()))
-                .collect(),
-        },
-        under_pk2: MsgFtBundle {
-            entries: b.under_pk2.ft_by_pos.iter()
-                .map(|(&pos, ft)| (pos, ft.clone()))
-                .collect(),
-        },
+        under_pk1: ft_bundle_to_msg(&b.under_pk1),
+        under_pk2: ft_bundle_to_msg(&b.under_pk2),
+    }
+}
+
+fn kea_ft_bundle_to_msg(b: &KeaFtBundle) -> MsgKeaFtBundle {
+    MsgKeaFtBundle {
+        entries: b.ft_by_pos.iter()
+            .map(|(&pos, ft)| (pos, ft.clone()))
+            .collect(),
+    }
+}
+
+fn kea_bundle_to_msg(b: &DualKeaFtBundle) -> MsgKeaDualBundle {
+    MsgKeaDualBundle {
+        under_pk1: kea_ft_bundle_to_msg(&b.under_pk1),
+        under_pk2: kea_ft_bundle_to_msg(&b.under_pk2),
+    }
+}
+
+// ─────────────────────────────────────────────────────────
+// Phases 3-4 en mode multi-clés (sur la connexion retour)
+//
+//   BD1 : reçoit Vec<MkCfSnd>, linéarise, envoie (A2, Enc2(part1)),
+//         reçoit Enc1(part2)
+//   BD2 : reçoit (A2, Enc2(part1)), renvoie Enc1(part2)
+//   Le cardinal recombiné est borné par la taille locale (et, chez BD1,
+//   le nombre de produits reçus) ; au-delà, il est refusé.
+//
+// Le meter est déjà ouvert sur « Phase 3 » à l'appel.
+// ─────────────────────────────────────────────────────────
+fn run_multikey_phase4(
+    bd_id:    u8,
+    label:    &str,
+    stream:   &mut TcpStream,
+    kp_self:  &KeyPair,
+    pk_other: &PublicKey,
+    active:   usize,
+    meter:    &mut BandwidthMeter,
+) -> io::Result<usize> {
+    let buf = recv_tracked(stream, meter)?;
+    meter.end();
+
+    if bd_id == 1 {
+        let quads = MsgMkQuads::decode(&buf)?.quads;
+        println!(
+            "[{}] Phase 3 terminée — {} produits multi-clés ({:.1} Ko).",
+            label, quads.len(), buf.len() as f64 / 1024.0
+        );
+
+        println!("\n[{}] Phase 4 : linéarisation + déchiffrement partiel...", label);
+        meter.begin("Phase 4 — déchiffrement conjoint");
+        let (agg_pk1, agg_pk2) = phase4_mk_linearize(label, &quads, kp_self, pk_other).map_err(io::Error::other)?;
+        let part_self = phase4_mk_partial_dec(label, &agg_pk1, kp_self).map_err(io::Error::other)?;
+        let share     = p_encrypt(&part_self, pk_other).map_err(io::Error::other)?;
+        let relay     = MsgMkRelay { aggregate: agg_pk2, share }.encode();
+        send_tracked(stream, &relay, meter)?;
+
+        let buf        = recv_tracked(stream, meter)?;
+        let enc_other  = MsgMkShare::decode(&buf)?.share;
+        let part_other = phase4_mk_partial_dec(label, &enc_other, kp_self).map_err(io::Error::other)?;
+        meter.end();
+
+        phase4_mk_combine(label, &part_self, &part_other, quads.len().min(active)).map_err(io::Error::other)
+    } else {
+        let relay = MsgMkRelay::decode(&buf)?;
+        println!("[{}] Phase 3 terminée — agrégat multi-clés reçu.", label);
+
+        println!("\n[{}] Phase 4 : déchiffrement partiel...", label);
+        meter.begin("Phase 4 — déchiffrement conjoint");
+        let part_self  = phase4_mk_partial_dec(label, &relay.aggregate, kp_self).map_err(io::Error::other)?;
+        let part_other = phase4_mk_partial_dec(label, &relay.share, kp_self).map_err(io::Error::other)?;
+        let share      = p_encrypt(&part_self, pk_other).map_err(io::Error::other)?;
+        send_tracked(stream, &MsgMkShare { share }.encode(), meter)?;
+        meter.end();
+
+        phase4_mk_combine(label, &part_self, &part_other, active).map_err(io::Error::other)
+    }
+}
+
+// ─────────────────────────────────────────────────────────
+// Connexion, Phases 0a-0b et 0d (communes à tous les modes)
+// ─────────────────────────────────────────────────────────
+fn connect_server(label: &str, server_addr: &str) -> TcpStream {
+    println!("[{}] Connexion au serveur {}...", label, server_addr);
+    loop {
+        match TcpStream::connect(server_addr) {
+            Ok(s)  => { println!("[{}] Connecté.", label); return s; }
+            Err(_) => {
+                eprint!(".");
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
+        }
+    }
+}
+
+/// Envoie pk_self (jamais sk), reçoit pk_other
+fn exchange_pk(stream: &mut TcpStream, kp_self: &KeyPair, meter: &mut BandwidthMeter) -> io::Result<PublicKey> {
+    let pk_payload = MsgPubKey {
+        n:         kp_self.public_key.n.clone(),
+        g:         kp_self.public_key.g.clone(),
+        n_squared: kp_self.public_key.n_squared.clone(),
+    }.encode();
+    send_tracked(stream, &pk_payload, meter)?;
+    Ok(pubkey_from_msg(MsgPubKey::decode(&recv_tracked(stream, meter)?)?))
+}
+
+/// Phase 0d : parts de clé de hachage sous pk_other, relayées par le serveur
+#[allow(clippy::too_many_arguments)]
+fn agree_hash_key(
+    label:      &str,
+    bd_id:      u8,
+    stream:     &mut TcpStream,
+    kp_self:    &KeyPair,
+    pk_other:   &PublicKey,
+    table_bits: u32,
+    psk:        Option<&HashKey>,
+    meter:      &mut BandwidthMeter,
+) -> io::Result<PositionHasher> {
+    meter.begin("Phase 0d — échange des parts de clé de hachage");
+    let (own_share, own_ct) = phase0_hash_key_share(label, pk_other);
+    let share_payload = MsgHashKeyShare {
+        share:      own_ct,
+        table_bits: table_bits as u8,
+        psk_id:     psk_id(psk),
+    }.encode();
+    send_tracked(stream, &share_payload, meter)?;
+    let other_share = MsgHashKeyShare::decode(&recv_tracked(stream, meter)?)?;
+    meter.end();
+    if other_share.table_bits as u32 != table_bits || other_share.psk_id != psk_id(psk) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Phase 0d : configuration de hachage différente de l'autre BD \
+                 (table_bits {} / {}, psk identique : {})",
+                table_bits, other_share.table_bits, other_share.psk_id == psk_id(psk)
+            ),
+        ));
+    }
+    phase0_derive_hasher(label, bd_id, &own_share, &other_share.share, kp_self, psk, table_bits)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 5 : engagement sur le cardinal, ouverture, comparaison
+// (modes standard, --reveal, --kea, --checkpoint)
+// ─────────────────────────────────────────────────────────
+#[allow(clippy::too_many_arguments)]
+fn confirm_cardinal(
+    label:    &str,
+    bd_id:    u8,
+    stream:   &mut TcpStream,
+    kp_self:  &KeyPair,
+    pk_other: &PublicKey,
+    cardinal: usize,
+    meter:    &mut BandwidthMeter,
+) -> io::Result<()> {
+    let (pk1, pk2) = if bd_id == 1 { (&kp_self.public_key, pk_other) } else { (pk_other, &kp_self.public_key) };
+    let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    println!("\n[{}] Phase 5 : engagement sur le cardinal, puis ouverture croisée...", label);
+    meter.begin("Phase 5 — cohérence des cardinaux");
+    let (opening, commitment) = phase5_commit(cardinal, bd_id, pk1, pk2);
+    send_tracked(stream, &MsgCardinalCommit { commitment }.encode(), meter)?;
+    let other_commitment = MsgCardinalCommit::decode(&recv_tracked(stream, meter)?)?.commitment;
+    let sealed = phase5_seal(&opening, pk_other).map_err(to_io)?;
+    send_tracked(stream, &MsgCardinal { sealed }.encode(), meter)?;
+    let other_sealed = MsgCardinal::decode(&recv_tracked(stream, meter)?)?.sealed;
+    meter.end();
+    phase5_verify(label, cardinal, 3 - bd_id, &other_commitment, &other_sealed, kp_self, pk1, pk2)
+        .map_err(|e| {
+            eprintln!("[{}] Résultat REJETÉ : {}", label, e);
+            to_io(e)
+        })?;
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Mode --delta : une époque de la session incrémentale
+// ─────────────────────────────────────────────────────────
+#[allow(clippy::too_many_arguments)]
+fn run_delta(
+    bd_id:        u8,
+    label:        &str,
+    server_addr:  &str,
+    listen_port:  u16,
+    nss_list:     &[String],
+    table_bits:   u32,
+    psk:          Option<&HashKey>,
+    session_path: &str,
+    key_path:     &str,
+    meter:        &mut BandwidthMeter,
+) -> io::Result<()> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let t_total = Instant::now();
+
+    // ── Phase 0a : paire Paillier persistante ────────────────────────
+    let kp_self = if key_file_exists(key_path) {
+        let kp = load_keypair_json(key_path)?;
+        println!("\n[{}] Phase 0a : clés relues depuis {} (n = {} bits).", label, key_path, kp.public_key.n.bits());
+        kp
+    } else {
+        println!("\n[{}] Phase 0a : génération des clés Paillier (enregistrées dans {})...", label, key_path);
+        let kp = phase0_keygen(label, 1024);
+        if let Some(dir) = std::path::Path::new(key_path).parent().and_then(|d| d.to_str()).filter(|d| !d.is_empty()) {
+            ensure_keys_directory(dir)?;
+        }
+        save_keypair_json(&kp, key_path)?;
+        kp
+    };
+
+    let mut stream = connect_server(label, server_addr);
+
+    // ── Phases 0a-0c : pk, pk_other, annonce de session ──────────────
+    meter.begin("Phase 0 — clés et session");
+    let pk_other = exchange_pk(&mut stream, &kp_self, meter)?;
+    let announce = MsgDeltaSession::decode(&recv_tracked(&mut stream, meter)?)?;
+    meter.end();
+
+    let mut session = if announce.fresh {
+        // ── Phase 0d (création uniquement) : clé de hachage K ────────
+        let hasher = agree_hash_key(label, bd_id, &mut stream, &kp_self, &pk_other, table_bits, psk, meter)?;
+        if std::path::Path::new(session_path).exists() {
+            println!("[{}] Phase 0c : nouvelle session, {} sera remplacé.", label, session_path);
+        }
+        DeltaSession::new(announce.session_id, bd_id, &hasher, &pk_other)
+    } else {
+        let session = DeltaSession::load(session_path).map_err(to_io)?;
+        if session.session_id != announce.session_id || session.epoch != announce.epoch || session.bd_id != bd_id {
+            return Err(invalid(format!(
+                "Phase 0c : session locale {:016x} époque {} (BD{}), serveur {:016x} époque {}",
+                session.session_id, session.epoch, session.bd_id, announce.session_id, announce.epoch
+            )));
+        }
+        if session.pk_other().map_err(to_io)?.n != pk_other.n {
+            return Err(invalid("Phase 0c : pk_other différente de celle de la session".into()));
+        }
+        session
+    };
+    println!(
+        "[{}] Phase 0c : session {:016x}, époque {} ({}).",
+        label, session.session_id, session.epoch, if announce.fresh { "création" } else { "reprise" }
+    );
+
+    // ── Phases 1-2 : différence avec l'époque précédente ─────────────
+    let hasher = session.hasher().map_err(to_io)?;
+    println!("\n[{}] Phase 1 : construction de la table creuse...", label);
+    let table = phase1_build_table(label, nss_list, &hasher);
+    let (added, removed) = session.diff(&table);
+    let (pk1, pk2) = if bd_id == 1 { (&kp_self.public_key, &pk_other) } else { (&pk_other, &kp_self.public_key) };
+    println!("[{}] Phase 2 : +{} / -{} positions, Ft des ajoutées sous pk1 et pk2...", label, added.len(), removed.len());
+    let bundle = phase2_prepare_dual_ft(label, &SparseTable { active: added.iter().copied().collect() }, pk1, pk2);
+    let payload = MsgDelta { removed: removed.clone(), added: bundle_to_msg(&bundle) }.encode();
+    meter.begin("Phase 2 — envoi différence");
+    send_tracked(&mut stream, &payload, meter)?;
+    meter.end();
+    println!("[{}] Phase 2 terminée — {:.1} Ko envoyés.", label, payload.len() as f64 / 1024.0);
+
+    // ── Phase 3b : masques des nouveaux produits ─────────────────────
+    println!("\n[{}] Phase 3 : ouverture :{}...", label, listen_port);
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", listen_port))?;
+    let (mut ret_stream, _) = listener.accept()?;
+    meter.begin("Phase 3 — masques des nouveaux produits");
+    let pairs = MsgMaskPairs::decode(&recv_tracked(&mut ret_stream, meter)?)?.pairs;
+    let cts = phase4_delta_mask_products(label, &pairs, &kp_self).map_err(to_io)?;
+    send_tracked(&mut ret_stream, &MsgCtList { cts }.encode(), meter)?;
+    meter.end();
+
+    // ── Phase 4 : cardinal courant ───────────────────────────────────
+    meter.begin("Phase 4 — déchiffrement");
+    let res = MsgDeltaResult::decode(&recv_tracked(&mut ret_stream, meter)?)?;
+    meter.end();
+    if res.epoch != session.epoch + 1 {
+        return Err(invalid(format!(
+            "Phase 4 : époque {} reçue, {} attendue", res.epoch, session.epoch + 1
+        )));
+    }
+    let cardinal = phase4_delta_decrypt_total(label, &res.total, &kp_self).map_err(to_io)?;
+    session.advance(res.epoch, &table);
+    session.save(session_path).map_err(to_io)?;
+
+    println!("\n╔══════════════════════════════════════════════════════╗");
+    println!("║  {} — RÉSULTAT (session {:016x})             ║", label, session.session_id);
+    println!("╠══════════════════════════════════════════════════════╣");
+    println!("║  |BD1 ^ BD2|  =  {}", cardinal);
+    println!("║  Époque {}  :  +{} / -{} positions", res.epoch, added.len(), removed.len());
+    println!("║  Temps total  :  {:.3?}", t_total.elapsed());
+    println!("╚══════════════════════════════════════════════════════╝");
+
+    println!("\n[{}] ─── Rapport de bande passante ───", label);
+    meter.report();
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Mode --checkpoint : mode standard, étapes persistées dans `dir`
+// ─────────────────────────────────────────────────────────
+#[allow(clippy::too_many_arguments)]
+fn run_checkpoint(
+    bd_id:       u8,
+    label:       &str,
+    server_addr: &str,
+    listen_port: u16,
+    nss_list:    &[String],
+    table_bits:  u32,
+    psk:         Option<&HashKey>,
+    dir:         &str,
+    meter:       &mut BandwidthMeter,
+) -> io::Result<()> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let t_total = Instant::now();
+
+    // ── Phase 0a : paire Paillier conservée dans `dir` ───────────────
+    let mut cp = ClientCheckpoint::open(dir).map_err(to_io)?;
+    println!("\n[{}] Phase 0a : clés Paillier ({}/keypair.json)...", label, dir);
+    let kp_self = cp.keypair(1024).map_err(to_io)?;
+    let digest = input_digest(nss_list);
+
+    let mut stream = connect_server(label, server_addr);
+
+    // ── Phases 0a-0c : pk, pk_other, plan de reprise ─────────────────
+    meter.begin("Phase 0 — clés et session");
+    let pk_other = exchange_pk(&mut stream, &kp_self, meter)?;
+    let local = cp.resumable(bd_id, &kp_self.public_key, &pk_other, &digest);
+    send_tracked(&mut stream, &MsgResume { session_id: local.unwrap_or(0) }.encode(), meter)?;
+    let plan = MsgResumePlan::decode(&recv_tracked(&mut stream, meter)?)?;
+    meter.end();
+
+    let hasher = if plan.fresh {
+        // ── Phase 0d (nouvelle session uniquement) : clé de hachage K ─
+        let hasher = agree_hash_key(label, bd_id, &mut stream, &kp_self, &pk_other, table_bits, psk, meter)?;
+        cp.start(plan.session_id, bd_id, &kp_self.public_key, &pk_other, &digest, &hasher).map_err(to_io)?;
+        hasher
+    } else {
+        if local != Some(plan.session_id) {
+            return Err(invalid(format!(
+                "Phase 0c : reprise de la session {:016x} demandée, session locale {}",
+                plan.session_id, local.map_or("absente".into(), |id| format!("{:016x}", id))
+            )));
+        }
+        cp.hasher().map_err(to_io)?
+    };
+    println!(
+        "[{}] Phase 0c : session {:016x} ({}).",
+        label, plan.session_id, if plan.fresh { "nouvelle" } else { "reprise" }
+    );
+
+    // ── Phases 1-2 : bundle relu, ou calculé puis enregistré ─────────
+    let (pk1, pk2) = if bd_id == 1 { (&kp_self.public_key, &pk_other) } else { (&pk_other, &kp_self.public_key) };
+    let mut reused = false;
+    if plan.send_bundle {
+        let bundle = match cp.load_bundle(pk1, pk2).map_err(to_io)? {
+            Some(b) => {
+                println!("[{}] Phase 2 : bundle relu depuis {} ({} positions).", label, dir, b.under_pk1.ft_by_pos.len());
+                reused = true;
+                b
+            }
+            None => {
+                println!("\n[{}] Phase 1 : construction de la table creuse...", label);
+                let table = phase1_build_table(label, nss_list, &hasher);
+                println!("[{}] Phase 2 : Ft sous pk1 et pk2...", label);
+                let b = phase2_prepare_dual_ft(label, &table, pk1, pk2);
+                cp.save_bundle(&b, pk1, pk2).map_err(to_io)?;
+                b
+            }
+        };
+        let payload = bundle_to_msg(&bundle).encode();
+        meter.begin("Phase 2 — envoi Ft");
+        send_tracked(&mut stream, &payload, meter)?;
+        meter.end();
+        println!("[{}] Phase 2 terminée — {:.1} Ko envoyés.", label, payload.len() as f64 / 1024.0);
+    } else {
+        println!("[{}] Phase 2 : bundle déjà enregistré par le serveur, rien à envoyer.", label);
+    }
+
+    // ── Phases 3-4 : triplets, puis déchiffrement ────────────────────
+    println!("\n[{}] Phase 3 : ouverture :{}...", label, listen_port);
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", listen_port))?;
+    let (mut ret_stream, _) = listener.accept()?;
+    meter.begin("Phase 3 — réception triplets");
+    let triplets = MsgTriplets::decode(&recv_tracked(&mut ret_stream, meter)?)?.triplets;
+    meter.end();
+    println!("[{}] Phase 3 terminée — {} triplets.", label, triplets.len());
+    meter.begin("Phase 4 — déchiffrement");
+    let cardinal = phase4_decrypt_and_count(label, &triplets, &kp_self).map_err(|e| {
+        eprintln!("[{}] Résultat du serveur REJETÉ : {}", label, e);
+        to_io(e)
+    })?;
+    meter.end();
+    confirm_cardinal(label, bd_id, &mut stream, &kp_self, &pk_other, cardinal, meter)?;
+
+    println!("\n╔══════════════════════════════════════════════════════╗");
+    println!("║  {} — RÉSULTAT (session {:016x})             ║", label, plan.session_id);
+    println!("╠══════════════════════════════════════════════════════╣");
+    println!("║  |BD1 ^ BD2|  =  {}", cardinal);
+    println!(
+        "║  Reprise  :  {}",
+        match (plan.fresh, plan.send_bundle, reused) {
+            (true, _, _)         => "non (nouvelle session)",
+            (false, false, _)    => "bundle déjà chez le serveur",
+            (false, true, true)  => "bundle relu et renvoyé",
+            (false, true, false) => "bundle recalculé",
+        }
+    );
+    println!("║  Temps total  :  {:.3?}", t_total.elapsed());
+    println!("╚══════════════════════════════════════════════════════╝");
+
+    println!("\n[{}] ─── Rapport de bande passante ───", label);
+    meter.report();
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Mode --stream : CSV, Ft et résultats traités par lots
+// ─────────────────────────────────────────────────────────
+#[allow(clippy::too_many_arguments)]
+fn run_stream(
+    bd_id:       u8,
+    label:       &str,
+    server_addr: &str,
+    listen_port: u16,
+    csv_path:    &str,
+    key_config:  &KeyConfig,
+    table_bits:  u32,
+    psk:         Option<&HashKey>,
+    chunk:       usize,
+    meter:       &mut BandwidthMeter,
+) -> io::Result<()> {
+    let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let t_total = Instant::now();
+
+    println!("\n[{}] Phase 0a : génération des clés Paillier...", label);
+    let kp_self = phase0_keygen(label, 1024);
+    let mut stream = connect_server(label, server_addr);
+    meter.begin("Phase 0a/0b — échange des pk");
+    let pk_other = exchange_pk(&mut stream, &kp_self, meter)?;
+    meter.end();
+    let hasher = agree_hash_key(label, bd_id, &mut stream, &kp_self, &pk_other, table_bits, psk, meter)?;
+    let (pk1, pk2) = if bd_id == 1 { (&kp_self.public_key, &pk_other) } else { (&pk_other, &kp_self.public_key) };
+
+    // ── Phase 1 : positions en flux, tri externe ─────────────────────
+    println!("\n[{}] Phase 1 : lecture en flux de {} (lots de {})...", label, csv_path, chunk);
+    let mut keys = stream_keys(csv_path, key_config).map_err(io::Error::other)?;
+    let mut sorter = PositionSorter::new(chunk).map_err(to_io)?;
+    for key in keys.by_ref() {
+        sorter.push(hasher.position(&key.map_err(io::Error::other)?)).map_err(to_io)?;
+    }
+    println!("[{}] {} enregistrements ({}) lus.", label, keys.accepted, key_config.column_names().join(" + "));
+    keys.print_rejected(label, 10);
+    let mut positions = sorter.finish().map_err(to_io)?;
+
+    // ── Phase 2 : lots de Ft triés par position ──────────────────────
+    println!("[{}] Phase 2 : envoi des Ft par lots (sous pk1 et pk2)...", label);
+    meter.begin("Phase 2 — envoi des lots");
+    let (mut active, mut lots, mut sent) = (0usize, 0usize, 0usize);
+    loop {
+        let lot: Vec<usize> = positions.by_ref().take(chunk).collect::<Result<_, _>>().map_err(to_io)?;
+        let (ft1, ft2) = phase2_stream_chunk(&lot, pk1, pk2);
+        let payload = MsgDualBundle {
+            under_pk1: MsgFtBundle { entries: ft1 },
+            under_pk2: MsgFtBundle { entries: ft2 },
+        }.encode();
+        send_tracked(&mut stream, &payload, meter)?;
+        sent += payload.len();
+        if lot.is_empty() {
+            break;
+        }
+        active += lot.len();
+        lots += 1;
+    }
+    meter.end();
+    println!(
+        "[{}] Phase 2 terminée — {} positions en {} lot(s), {:.1} Ko envoyés.",
+        label, active, lots, sent as f64 / 1024.0
+    );
+
+    // ── Phases 3-4 : masques cumulés lot par lot, puis Π C0 ──────────
+    println!("\n[{}] Phase 3 : ouverture :{}...", label, listen_port);
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", listen_port))?;
+    let (mut ret_stream, _) = listener.accept()?;
+    meter.begin("Phase 3/4 — masques et déchiffrement");
+    let mut tally = StreamTally::new();
+    loop {
+        let pairs = MsgMaskPairs::decode(&recv_tracked(&mut ret_stream, meter)?)?.pairs;
+        if pairs.is_empty() {
+            break;
+        }
+        tally.absorb(&pairs, &kp_self).map_err(to_io)?;
     }
+    let alpha = MsgMkShare::decode(&recv_tracked(&mut ret_stream, meter)?)?.share;
+    let cardinal = tally.finish(label, &alpha, &kp_self).map_err(to_io)?;
+    meter.end();
+
+    println!("\n╔══════════════════════════════════════════════════════╗");
+    println!("║  {} — RÉSULTAT                                      ║", label);
+    println!("╠══════════════════════════════════════════════════════╣");
+    println!("║  |BD1 ^ BD2|  =  {}", cardinal);
+    println!("║  Flux : {} positions, lots de {}", active, chunk);
+    println!("║  Temps total  :  {:.3?}", t_total.elapsed());
+    println!("╚══════════════════════════════════════════════════════╝");
+
+    println!("\n[{}] ─── Rapport de bande passante ───", label);
+    meter.report();
+    Ok(())
 }
 
 // ─────────────────────────────────────────────────────────
@@ -82,12 +808,259 @@ fn main() -> io::Result<()> {
         .position(|a| a == "--bd")
         .and_then(|i| args.get(i + 1))
         .and_then(|v| v.parse().ok())
-        .expect("Usage : client --bd <1|2> --csv <fichier.csv>");
+        .expect(USAGE);
     let csv_path: &str = args.iter()
         .position(|a| a == "--csv")
         .and_then(|i| args.get(i + 1))
         .map(String::as_str)
-        .expect("Usage : client --bd <1|2> --csv <fichier.csv>");
+        .expect(USAGE);
+    let mode = match (
+        args.iter().any(|a| a == "--multikey"),
+        args.iter().any(|a| a == "--kea"),
+        args.iter().any(|a| a == "--cuckoo"),
+        args.iter().any(|a| a == "--fuzzy"),
+        args.iter().any(|a| a == "--minhash"),
+    ) {
+        (false, false, false, false, false) => Mode::Standard,
+        (true,  false, false, false, false) => Mode::MultiKey,
+        (false, true,  false, false, false) => Mode::Kea,
+        (false, false, true,  false, false) => Mode::Cuckoo,
+        (false, false, false, true,  false) => Mode::Fuzzy,
+        (false, false, false, false, true)  => Mode::MinHash,
+        _ => {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, USAGE));
+        }
+    };
+    if let Some(i) = args.iter().position(|a| a == "--threads") {
+        let n: usize = args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE);
+        set_default_threads(n);
+    }
+    let table_bits: u32 = match args.iter().position(|a| a == "--table-bits") {
+        Some(i) => args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE),
+        None    => DEFAULT_TABLE_BITS,
+    };
+    check_table_bits(table_bits)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let stash: u8 = match args.iter().position(|a| a == "--stash") {
+        Some(i) => args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE),
+        None    => DEFAULT_STASH_SIZE as u8,
+    };
+    let size_hiding = args.iter().any(|a| a == "--size-hiding");
+    let pad_to: Option<u64> = args.iter().position(|a| a == "--pad-to").map(|i| {
+        args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE)
+    });
+    let size_hiding_ok = matches!(mode, Mode::Standard | Mode::Cuckoo) && pad_to.is_some();
+    if size_hiding != size_hiding_ok {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--size-hiding : mode standard ou --cuckoo, avec --pad-to <P>\n{}", USAGE),
+        ));
+    }
+    let sum = args.iter().any(|a| a == "--sum");
+    let value_col: &str = args.iter()
+        .position(|a| a == "--value-col")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE))
+        .unwrap_or("montant");
+    let fp = FixedPoint::new(match args.iter().position(|a| a == "--decimals") {
+        Some(i) => args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE),
+        None    => 0,
+    });
+    if sum && (mode != Mode::Standard || size_hiding) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--sum : mode standard uniquement\n{}", USAGE),
+        ));
+    }
+    let threshold: Option<u64> = args.iter().position(|a| a == "--threshold").map(|i| {
+        args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE)
+    });
+    if threshold.is_some() && (mode != Mode::Standard || sum) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--threshold : mode standard uniquement, sans --sum\n{}", USAGE),
+        ));
+    }
+    let dp: Option<f64> = args.iter().position(|a| a == "--dp").map(|i| {
+        args.get(i + 1).and_then(|v| v.parse().ok()).and_then(|e| check_epsilon(e).ok()).expect(USAGE)
+    });
+    if dp.is_some() && (mode != Mode::Standard || sum || threshold.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--dp : mode standard uniquement, sans --sum ni --threshold\n{}", USAGE),
+        ));
+    }
+    let dp_ledger_path: Option<&str> = args.iter()
+        .position(|a| a == "--dp-ledger")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE));
+    let dp_budget: Option<f64> = args.iter().position(|a| a == "--dp-budget").map(|i| {
+        args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE)
+    });
+    if dp.is_none() && (dp_ledger_path.is_some() || dp_budget.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--dp-ledger / --dp-budget : --dp requis\n{}", USAGE),
+        ));
+    }
+    let reveal = args.iter().any(|a| a == "--reveal");
+    if reveal && (mode != Mode::Standard || sum || threshold.is_some() || dp.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--reveal : mode standard uniquement, sans --sum, --threshold ni --dp\n{}", USAGE),
+        ));
+    }
+    let similarity_metrics = args.iter().any(|a| a == "--similarity");
+    if similarity_metrics && (mode != Mode::Standard || sum || threshold.is_some() || dp.is_some()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--similarity : mode standard uniquement, sans --sum, --threshold ni --dp\n{}", USAGE),
+        ));
+    }
+    let setops: Option<SetOps> = match args.iter().position(|a| a == "--setops") {
+        Some(i) => Some(
+            SetOps::parse(args.get(i + 1).expect(USAGE))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?,
+        ),
+        None => None,
+    };
+    if setops.is_some()
+        && (mode != Mode::Standard || !size_hiding || sum || threshold.is_some() || dp.is_some() || reveal || similarity_metrics)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--setops : mode standard avec --size-hiding, sans autre option de résultat\n{}", USAGE),
+        ));
+    }
+    let multiset: Option<MultisetSemantics> = match args.iter().position(|a| a == "--multiset") {
+        Some(i) => Some(
+            MultisetSemantics::parse(args.get(i + 1).expect(USAGE))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?,
+        ),
+        None => None,
+    };
+    let hide_sizes = args.iter().any(|a| a == "--hide-sizes");
+    let delta_path: Option<&str> = args.iter()
+        .position(|a| a == "--delta")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE));
+    if delta_path.is_some()
+        && (mode != Mode::Standard || size_hiding || sum || threshold.is_some() || dp.is_some() || reveal || similarity_metrics)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--delta : mode standard uniquement, sans autre option de résultat\n{}", USAGE),
+        ));
+    }
+    let stream_mode = args.iter().any(|a| a == "--stream");
+    if stream_mode
+        && (mode != Mode::Standard || size_hiding || sum || threshold.is_some() || dp.is_some() || reveal
+            || similarity_metrics || delta_path.is_some())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--stream : mode standard uniquement, sans autre option de résultat\n{}", USAGE),
+        ));
+    }
+    let checkpoint: Option<&str> = args.iter()
+        .position(|a| a == "--checkpoint")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE));
+    if checkpoint.is_some()
+        && (mode != Mode::Standard || size_hiding || sum || threshold.is_some() || dp.is_some() || reveal
+            || similarity_metrics || delta_path.is_some() || stream_mode)
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--checkpoint : mode standard uniquement, sans autre option de résultat\n{}", USAGE),
+        ));
+    }
+    if multiset.is_some()
+        && (mode != Mode::Standard || size_hiding || sum || threshold.is_some() || dp.is_some() || reveal
+            || similarity_metrics || setops.is_some() || delta_path.is_some() || stream_mode || checkpoint.is_some())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--multiset : mode standard uniquement, sans autre option de résultat\n{}", USAGE),
+        ));
+    }
+    let chunk = check_stream_chunk(match args.iter().position(|a| a == "--chunk") {
+        Some(i) => args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE),
+        None    => DEFAULT_STREAM_CHUNK,
+    }).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let default_key_file = format!("keys/bd{}_keypair.json", bd_id);
+    let key_file: &str = args.iter()
+        .position(|a| a == "--key-file")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE))
+        .unwrap_or(&default_key_file);
+    if hide_sizes && !(similarity_metrics || mode == Mode::MinHash) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--hide-sizes : --similarity ou --minhash requis\n{}", USAGE),
+        ));
+    }
+    let minhash_k = check_minhash_k(match args.iter().position(|a| a == "--minhash-k") {
+        Some(i) => args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE),
+        None    => DEFAULT_MINHASH_K,
+    }).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let id_col: &str = args.iter()
+        .position(|a| a == "--id-col")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE))
+        .unwrap_or("identification");
+    let reveal_out: Option<&str> = args.iter()
+        .position(|a| a == "--reveal-out")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE));
+    let fields: Vec<&str> = args.iter()
+        .position(|a| a == "--fields")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE))
+        .unwrap_or("nom,prenom,date_naissance")
+        .split(',')
+        .map(str::trim)
+        .collect();
+    let clk_params = ClkParams::new(
+        match args.iter().position(|a| a == "--clk-bits") {
+            Some(i) => args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE),
+            None    => DEFAULT_CLK_BITS,
+        },
+        match args.iter().position(|a| a == "--clk-hashes") {
+            Some(i) => args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE),
+            None    => DEFAULT_CLK_HASHES,
+        },
+    ).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let similarity = match (
+        args.iter().position(|a| a == "--dice"),
+        args.iter().position(|a| a == "--hamming"),
+    ) {
+        (Some(i), None) => Similarity::Dice(
+            args.get(i + 1).and_then(|v| v.parse().ok()).filter(|t| (0.0..=1.0).contains(t)).expect(USAGE),
+        ),
+        (None, Some(i)) => Similarity::Hamming(args.get(i + 1).and_then(|v| v.parse().ok()).expect(USAGE)),
+        (None, None)    => Similarity::Dice(0.8),
+        _ => return Err(io::Error::new(io::ErrorKind::InvalidInput, USAGE)),
+    };
+    let key_config_path: Option<&str> = args.iter()
+        .position(|a| a == "--key-config")
+        .map(|i| args.get(i + 1).map(String::as_str).expect(USAGE));
+    let key_input = match args.iter().position(|a| a == "--input").map(|i| args.get(i + 1).map(String::as_str)) {
+        None | Some(Some("nss")) => KeyInput::Nss,
+        Some(Some("hash"))       => KeyInput::Hash,
+        Some(Some("verify"))     => KeyInput::Verify,
+        Some(_)                  => return Err(io::Error::new(io::ErrorKind::InvalidInput, USAGE)),
+    };
+    if key_input != KeyInput::Nss && (key_config_path.is_some() || mode == Mode::Fuzzy) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--input hash / verify : sans --key-config ni --fuzzy\n{}", USAGE),
+        ));
+    }
+    if key_input == KeyInput::Verify && stream_mode {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("--input verify : incompatible avec --stream (lecture en une passe)\n{}", USAGE),
+        ));
+    }
+    // 32 octets hex : même format qu'une empreinte de clé
+    let psk: Option<HashKey> = args.iter().position(|a| a == "--hash-psk").map(|i| {
+        args.get(i + 1)
+            .and_then(|v| hex_to_fingerprint(v).ok())
+            .expect(USAGE)
+    });
 
     let label       = format!("BD{}", bd_id);
     let server_addr = if bd_id == 1 { SERVER_ADDR_BD1 } else { SERVER_ADDR_BD2 };
@@ -100,9 +1073,88 @@ fn main() -> io::Result<()> {
     let mut meter = BandwidthMeter::new();
     let t_total   = Instant::now();
 
-    // Chargement CSV
-    let nss_list = load_nss_from_csv(csv_path);
-    println!("[{}] {} NSS chargés depuis {}.", label, nss_list.len(), csv_path);
+    // Chargement CSV (RFC 4180) et dérivation des clés
+    // --key-config : clé composite normalisée ; sinon NSS seul, ou les
+    // colonnes --fields en mode --fuzzy
+    let key_config = match (&key_config_path, mode, key_input) {
+        (Some(path), _, _)          => KeyConfig::from_json_file(path).map_err(io::Error::other)?,
+        (None, Mode::Fuzzy, _)      => KeyConfig::columns(&fields),
+        (None, _, KeyInput::Nss)    => KeyConfig::nss(),
+        (None, _, KeyInput::Hash)   => KeyConfig::nss_hash(),
+        (None, _, KeyInput::Verify) => KeyConfig::nss_sha256(),
+    };
+    if stream_mode {
+        return run_stream(
+            bd_id, &label, server_addr, listen_port, csv_path, &key_config, table_bits, psk.as_ref(), chunk, &mut meter,
+        );
+    }
+    // PSI-Sum : BD2 lit aussi la colonne de valeurs
+    // --reveal : chaque BD lit aussi sa colonne d'identification
+    let extra: &[&str] = if sum && bd_id == 2 {
+        &[value_col]
+    } else if reveal {
+        &[id_col]
+    } else {
+        &[]
+    };
+    let loaded = if key_input == KeyInput::Verify {
+        let table = CsvTable::read(csv_path).map_err(io::Error::other)?;
+        let mut loaded = derive_keys(&table, &key_config, extra).map_err(io::Error::other)?;
+        let bad = verify_hash_column(&table, &mut loaded, "NSS", "HASH(NSS)").map_err(io::Error::other)?;
+        println!(
+            "[{}] Vérification HASH(NSS) : {} ligne(s) sur {} en désaccord avec SHA-256(NSS).",
+            label, bad, table.rows.len()
+        );
+        loaded
+    } else {
+        load_records(csv_path, &key_config, extra).map_err(io::Error::other)?
+    };
+    println!(
+        "[{}] {} enregistrements ({}{}) chargés depuis {}.",
+        label, loaded.len(), key_config.column_names().join(" + "),
+        if key_input == KeyInput::Nss { "" } else { ", clé SHA-256" }, csv_path
+    );
+    loaded.print_rejected(&label, 10);
+
+    let value_rows: Option<Vec<(String, String)>> = if sum && bd_id == 2 {
+        println!("[{}] PSI-Sum : colonne de valeurs « {} ».", label, value_col);
+        Some(loaded.keys.iter().cloned().zip(loaded.extra.iter().map(|e| e[0].clone())).collect())
+    } else {
+        None
+    };
+    // --fuzzy : composantes séparées, encodées champ par champ
+    let records = if mode == Mode::Fuzzy { Some(loaded.fields.clone()) } else { None };
+    let nss_list = loaded.keys.clone();
+    let distinct = nss_list.iter().collect::<HashSet<_>>().len();
+    println!("[{}] Pool de calcul : {} thread(s).", label, default_pool().threads());
+
+    if let Some(path) = delta_path {
+        return run_delta(
+            bd_id, &label, server_addr, listen_port, &nss_list, table_bits, psk.as_ref(), path, key_file, &mut meter,
+        );
+    }
+    if let Some(dir) = checkpoint {
+        return run_checkpoint(
+            bd_id, &label, server_addr, listen_port, &nss_list, table_bits, psk.as_ref(), dir, &mut meter,
+        );
+    }
+
+    // --dp : ε débité avant toute connexion (une exécution interrompue
+    // après déchiffrement aurait déjà consommé le budget)
+    if let Some(eps) = dp {
+        match dp_ledger_path {
+            Some(path) => {
+                let mut ledger = DpLedger::open(path, dp_budget).map_err(io::Error::other)?;
+                ledger.charge(eps, csv_path).map_err(|e| io::Error::new(io::ErrorKind::PermissionDenied, e.to_string()))?;
+                ledger.save(path).map_err(io::Error::other)?;
+                println!(
+                    "[{}] DP : epsilon = {} débité ({:.4} dépensé, {:.4} restant sur {}).",
+                    label, eps, ledger.spent(), ledger.remaining(), ledger.budget
+                );
+            }
+            None => println!("[{}] DP : epsilon = {} (aucun registre : budget non suivi).", label, eps),
+        }
+    }
 
     // ── Phase 0a : génération de la clé Paillier locale ──────────────
     // kp_self contient pk (publique) + sk (SECRÈTE, ne quitte jamais cette machine)
@@ -112,47 +1164,182 @@ fn main() -> io::Result<()> {
         "[{}] Clé générée : n = {} bits, sk reste locale.",
         label, kp_self.public_key.n.bits()
     );
-
-    // Connexion au serveur
-    println!("[{}] Connexion au serveur {}...", label, server_addr);
-    let mut stream = loop {
-        match TcpStream::connect(server_addr) {
-            Ok(s)  => { println!("[{}] Connecté.", label); break s; }
-            Err(_) => {
-                eprint!(".");
-                std::thread::sleep(std::time::Duration::from_millis(500));
-            }
-        }
+    let kea_self = if mode == Mode::Kea {
+        Some(phase0_kea_keygen(&label, &kp_self))
+    } else {
+        None
     };
 
-    // ── Phase 0a : envoi de pk_self ───────────────────────────────────
-    // On n'envoie QUE la clé publique (n, g, n²) — jamais sk.
-    meter.begin("Phase 0a — envoi pk_self");
-    let pk_payload = MsgPubKey {
-        n:         kp_self.public_key.n.clone(),
-        g:         kp_self.public_key.g.clone(),
-        n_squared: kp_self.public_key.n_squared.clone(),
-    }.encode();
-    send_tracked(&mut stream, &pk_payload, &mut meter)?;
-    meter.end();
-    println!(
-        "[{}] Phase 0a : pk_self envoyée ({} octets, sk NON envoyée).",
-        label, pk_payload.len()
-    );
+    let mut stream = connect_server(&label, server_addr);
 
-    // ── Phase 0b : réception de pk_other (vraie clé de l'autre BD) ───
-    // Le serveur a reçu pk1 et pk2, et les a croisées.
-    // BD1 reçoit pk2 (la vraie clé publique de BD2).
-    // BD2 reçoit pk1 (la vraie clé publique de BD1).
-    meter.begin("Phase 0b — réception pk_other");
-    let pk_other_buf = recv_tracked(&mut stream, &mut meter)?;
+    // ── Phases 0a-0b : pk_self envoyée (jamais sk), pk_other reçue ───
+    // Le serveur a reçu pk1 et pk2 et les a croisées : BD1 reçoit pk2,
+    // BD2 reçoit pk1.
+    meter.begin("Phase 0a/0b — échange des pk");
+    let pk_other = exchange_pk(&mut stream, &kp_self, &mut meter)?;
     meter.end();
-    let pk_other: PublicKey = pubkey_from_msg(MsgPubKey::decode(&pk_other_buf)?);
     println!(
-        "[{}] Phase 0b : pk_other reçue (n_other = {} bits).",
+        "[{}] Phase 0b : pk_other reçue (n_other = {} bits, sk NON envoyée).",
         label, pk_other.n.bits()
     );
 
+    // ── Phase 0c (KEA) : ct_delta est public, il s'échange comme pk ──
+    let delta_other = match &kea_self {
+        Some(kea) => {
+            meter.begin("Phase 0c — échange des ct_delta");
+            send_tracked(&mut stream, &MsgKeaDelta { ct_delta: kea.ct_delta.clone() }.encode(), &mut meter)?;
+            let buf = recv_tracked(&mut stream, &mut meter)?;
+            meter.end();
+            Some(MsgKeaDelta::decode(&buf)?.ct_delta)
+        }
+        None => None,
+    };
+
+    // ── Phase 0d : accord sur la clé de hachage des positions ────────
+    // La part locale part chiffrée sous pk_other : le serveur relaie
+    // sans pouvoir la lire.
+    let hasher = agree_hash_key(&label, bd_id, &mut stream, &kp_self, &pk_other, table_bits, psk.as_ref(), &mut meter)?;
+
+    // ── Phase 0e (cuckoo) : échange des tailles d'ensemble ───────────
+    // Les dimensions des tables dépendent de n1 et n2 : elles sont
+    // publiques, comme le nombre de positions actives du mode standard.
+    let cuckoo_params = if mode == Mode::Cuckoo {
+        meter.begin("Phase 0e — échange des tailles");
+        // --size-hiding : seule la borne publique P est annoncée
+        let size = match (size_hiding, pad_to) {
+            (true, Some(p)) if (distinct as u64) > p => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("--pad-to {} < {} NSS distincts", p, distinct),
+                ));
+            }
+            (true, Some(p)) => p,
+            _               => distinct as u64,
+        };
+        let own = MsgCuckooSize {
+            size,
+            hashes: DEFAULT_CUCKOO_HASHES as u8,
+            stash,
+        };
+        send_tracked(&mut stream, &own.encode(), &mut meter)?;
+        let other = MsgCuckooSize::decode(&recv_tracked(&mut stream, &mut meter)?)?;
+        meter.end();
+        if other.hashes != own.hashes || other.stash != own.stash {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Phase 0e : paramètres cuckoo différents de l'autre BD (k {} / {}, stash {} / {})",
+                    own.hashes, other.hashes, own.stash, other.stash
+                ),
+            ));
+        }
+        let (n1, n2) = if bd_id == 1 { (own.size, other.size) } else { (other.size, own.size) };
+        let params = CuckooParams::new(n1 as usize, n2 as usize, own.hashes as usize, own.stash as usize)
+            .map_err(io::Error::other)?;
+        println!(
+            "[{}] Phase 0e : n1 = {}, n2 = {} -> {} bins, bin_size = {}, {} comparaisons.",
+            label, n1, n2, params.bins, params.bin_size, params.comparisons()
+        );
+        Some(params)
+    } else {
+        None
+    };
+
+    // ── Phase 0e (fuzzy) : nombre d'enregistrements et (L, k) ─────────
+    if let Some(records) = &records {
+        meter.begin("Phase 0e — paramètres CLK");
+        let own = MsgClkParams {
+            records: records.len() as u64,
+            bits:    clk_params.bits as u32,
+            hashes:  clk_params.hashes as u8,
+        };
+        send_tracked(&mut stream, &own.encode(), &mut meter)?;
+        let other = MsgClkParams::decode(&recv_tracked(&mut stream, &mut meter)?)?;
+        meter.end();
+        if other.bits != own.bits || other.hashes != own.hashes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Phase 0e : paramètres CLK différents de l'autre BD (L {} / {}, k {} / {})",
+                    own.bits, other.bits, own.hashes, other.hashes
+                ),
+            ));
+        }
+        println!(
+            "[{}] Phase 0e : {} x {} paires à comparer sur {} bits.",
+            label, own.records, other.records, own.bits
+        );
+    }
+
+    // ── Phase 0e (--similarity / --minhash) : tailles des ensembles ──
+    // Unité du cardinal : positions actives (standard), clés distinctes
+    // (MinHash). --hide-sizes : la taille part sous pk_other.
+    let sizes = if similarity_metrics || mode == Mode::MinHash {
+        let own_size = if mode == Mode::MinHash { distinct } else { SparseTable::build(&nss_list, &hasher).len() };
+        meter.begin("Phase 0e — échange des tailles");
+        let own = MsgSetSize {
+            size:      phase0_size_share(own_size, &pk_other, hide_sizes).map_err(io::Error::other)?,
+            hidden:    hide_sizes,
+            minhash_k: if mode == Mode::MinHash { minhash_k as u32 } else { 0 },
+        };
+        send_tracked(&mut stream, &own.encode(), &mut meter)?;
+        let other = MsgSetSize::decode(&recv_tracked(&mut stream, &mut meter)?)?;
+        meter.end();
+        if other.minhash_k != own.minhash_k {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Phase 0e : k MinHash différent de l'autre BD ({} / {})", own.minhash_k, other.minhash_k),
+            ));
+        }
+        let other_size = phase0_open_size(&other.size, other.hidden, &kp_self)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        println!(
+            "[{}] Phase 0e : |self| = {}, |other| = {} (tailles {}).",
+            label, own_size, other_size, if hide_sizes { "chiffrées" } else { "en clair" }
+        );
+        Some((own_size, other_size))
+    } else {
+        None
+    };
+
+    // ── Phase 0e (--setops) : sorties révélées, identiques partout ───
+    if let Some(ops) = setops {
+        meter.begin("Phase 0e — sorties ensemblistes");
+        send_tracked(&mut stream, &MsgSetOps { ops: ops.bits() }.encode(), &mut meter)?;
+        let server_ops = MsgSetOps::decode(&recv_tracked(&mut stream, &mut meter)?)?.ops;
+        meter.end();
+        if server_ops != ops.bits() {
+            let shown = SetOps::from_bits(server_ops).map(|o| o.to_string()).unwrap_or_else(|_| format!("{:#04x}", server_ops));
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Phase 0e : le serveur applique --setops {}, différent de --setops {}", shown, ops),
+            ));
+        }
+        println!("[{}] Phase 0e : sorties convenues avec le serveur : {}.", label, ops);
+    }
+
+    // ── Phase 0e (--multiset) : même sémantique que l'autre BD ────────
+    if let Some(semantics) = multiset {
+        meter.begin("Phase 0e — sémantique multiset");
+        let own = MsgMultiset { semantics: semantics.code(), max_count: MULTISET_MAX_COUNT };
+        send_tracked(&mut stream, &own.encode(), &mut meter)?;
+        let other = MsgMultiset::decode(&recv_tracked(&mut stream, &mut meter)?)?;
+        meter.end();
+        if (other.semantics, other.max_count) != (own.semantics, own.max_count) {
+            let shown = MultisetSemantics::from_code(other.semantics)
+                .map(|s| s.name().to_string())
+                .unwrap_or_else(|_| format!("code {}", other.semantics));
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Phase 0e : l'autre BD utilise --multiset {} (≤ {}), ici {} (≤ {})",
+                    shown, other.max_count, semantics.name(), MULTISET_MAX_COUNT
+                ),
+            ));
+        }
+        println!("[{}] Phase 0e : multiensembles, sémantique {} des deux côtés.", label, semantics.name());
+    }
+
     // Assignation (pk1, pk2) selon le rôle du BD
     // BD1 -> pk1 = kp_self.public_key, pk2 = pk_other
     // BD2 -> pk1 = pk_other,           pk2 = kp_self.public_key
@@ -162,21 +1349,91 @@ fn main() -> io::Result<()> {
         (&pk_other, &kp_self.public_key)
     };
 
-    // ── Phase 1 : table creuse locale ────────────────────────────────
-    println!("\n[{}] Phase 1 : construction de la table creuse...", label);
-    let table = phase1_build_table(&label, &nss_list);
+    // ── Phases 1-2 (cuckoo) : table cuckoo (BD1) / bins simples (BD2) ─
+    let bundle_payload = if let Some(records) = &records {
+        println!("\n[{}] Phase 1 : encodages CLK...", label);
+        let clks = phase1_build_clks(&label, records, clk_params, &hasher);
+        println!("[{}] Phase 2 : préparation Ft des bits CLK sous pk1 et pk2...", label);
+        let bundle = phase2_prepare_clk_ft(&label, &clks, pk1, pk2).map_err(io::Error::other)?;
+        bundle_to_msg(&bundle).encode()
+    } else if mode == Mode::MinHash {
+        println!("\n[{}] Phase 1 : signature MinHash ({} composantes)...", label, minhash_k);
+        let sig = minhash_signature(&nss_list, &hasher, minhash_k).map_err(io::Error::other)?;
+        println!("[{}] Phase 2 : préparation Ft des {} composantes sous pk1 et pk2...", label, sig.len());
+        let bundle = phase2_prepare_slot_ft(&label, &sig, pk1, pk2);
+        bundle_to_msg(&bundle).encode()
+    } else if let Some(params) = cuckoo_params {
+        println!("\n[{}] Phase 1 : construction {}...", label,
+            if bd_id == 1 { "de la table cuckoo" } else { "des bins simples" });
+        let slots = if bd_id == 1 {
+            phase1_build_cuckoo(&label, &nss_list, &hasher, params).map_err(io::Error::other)?.slots
+        } else {
+            phase1_build_simple_bins(&label, &nss_list, &hasher, params).map_err(io::Error::other)?.slots
+        };
+        println!("[{}] Phase 2 : préparation Ft des {} slots sous pk1 et pk2...", label, slots.len());
+        let bundle = phase2_prepare_slot_ft(&label, &slots, pk1, pk2);
+        bundle_to_msg(&bundle).encode()
+    } else {
+        // ── Phase 1 : table creuse locale ────────────────────────────────
+        println!("\n[{}] Phase 1 : construction de la table creuse...", label);
+        let (table, multiset_values) = match multiset {
+            Some(s) => {
+                let vt = phase1_build_multiset(&label, &nss_list, &hasher, s).map_err(io::Error::other)?;
+                (vt.table, Some(vt.values))
+            }
+            None => (phase1_build_table(&label, &nss_list, &hasher), None),
+        };
 
-    // ── Phase 2 : préparation + envoi DualFtBundle ───────────────────
-    // phase2_prepare_dual_ft prend &PublicKey — pas de KeyPair factice.
-    // Les Ft sont chiffrés sous les vrais modules n1 et n2.
-    println!("[{}] Phase 2 : préparation Ft sous pk1 (n={} bits) et pk2 (n={} bits)...",
-        label, pk1.n.bits(), pk2.n.bits());
-    let bundle = phase2_prepare_dual_ft(&label, &table, pk1, pk2);
+        // ── Phase 2 : préparation + envoi DualFtBundle ───────────────────
+        // phase2_prepare_dual_ft prend &PublicKey — pas de KeyPair factice.
+        // Les Ft sont chiffrés sous les vrais modules n1 et n2.
+        match (mode, &kea_self, &delta_other) {
+            (Mode::MultiKey, _, _) => {
+                println!("[{}] Phase 2 : préparation Ft multi-clés sous pk_self (n={} bits)...",
+                    label, kp_self.public_key.n.bits());
+                let bundle = phase2_prepare_mk_ft(&label, &table, &kp_self.public_key);
+                ft_bundle_to_msg(&bundle).encode()
+            }
+            (Mode::Kea, Some(kea), Some(delta_other)) => {
+                println!("[{}] Phase 2 : préparation Ft KEA sous pk1 et pk2...", label);
+                let (delta1, delta2) = if bd_id == 1 {
+                    (&kea.ct_delta, delta_other)
+                } else {
+                    (delta_other, &kea.ct_delta)
+                };
+                let bundle = phase2_prepare_dual_ft_kea(&label, &table, pk1, delta1, pk2, delta2);
+                kea_bundle_to_msg(&bundle).encode()
+            }
+            _ => {
+                println!("[{}] Phase 2 : préparation Ft sous pk1 (n={} bits) et pk2 (n={} bits)...",
+                    label, pk1.n.bits(), pk2.n.bits());
+                let bundle = if let (true, Some(p)) = (size_hiding, pad_to) {
+                    phase2_prepare_dual_ft_padded(&label, &table, &hasher, p as usize, pk1, pk2)
+                        .map_err(io::Error::other)?
+                } else if let Some(rows) = &value_rows {
+                    let vt = phase1_build_value_table(&label, rows, &hasher, fp).map_err(io::Error::other)?;
+                    phase2_prepare_dual_ft_values(&label, &vt, pk1, pk2).map_err(io::Error::other)?
+                } else if let Some(values) = multiset_values {
+                    phase2_prepare_dual_ft_values(&label, &ValueTable { table, values }, pk1, pk2)
+                        .map_err(io::Error::other)?
+                } else {
+                    phase2_prepare_dual_ft(&label, &table, pk1, pk2)
+                };
+                bundle_to_msg(&bundle).encode()
+            }
+        }
+    };
 
     meter.begin("Phase 2 — envoi bundle");
-    let bundle_payload = bundle_to_msg(&bundle).encode();
     send_tracked(&mut stream, &bundle_payload, &mut meter)?;
     meter.end();
+    if let (true, Some(p)) = (size_hiding, pad_to) {
+        let real = match mode {
+            Mode::Cuckoo => distinct,
+            _            => SparseTable::build(&nss_list, &hasher).len(),
+        };
+        meter.record_padding("Phase 2 — envoi bundle", real, p as usize);
+    }
     println!(
         "[{}] Phase 2 terminée — {:.1} Ko envoyés.",
         label, bundle_payload.len() as f64 / 1024.0
@@ -189,30 +1446,323 @@ fn main() -> io::Result<()> {
 
     meter.begin("Phase 3 — réception triplets");
     let (mut ret_stream, _) = listener.accept()?;
-    let buf = recv_tracked(&mut ret_stream, &mut meter)?;
-    meter.end();
 
-    let triplets = MsgTriplets::decode(&buf)?.triplets;
-    println!(
-        "[{}] Phase 3 terminée — {} triplets ({:.1} Ko).",
-        label, triplets.len(), buf.len() as f64 / 1024.0
-    );
+    let mut sum_result: Option<String> = None;
+    let mut threshold_result: Option<(u64, bool)> = None;
+    let mut dp_result: Option<(f64, BigInt)> = None;
+    let mut revealed: Option<Vec<usize>> = None;
+    let mut overlap: Option<SetOverlap> = None;
+    let mut setops_result: Option<Vec<(SetOp, u64)>> = None;
+    let cardinal = if mode == Mode::MultiKey {
+        run_multikey_phase4(bd_id, &label, &mut ret_stream, &kp_self, &pk_other, distinct, &mut meter)?
+    } else if let Some(t) = threshold {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+        let pairs = MsgMaskPairs::decode(&buf)?.pairs;
+        println!(
+            "[{}] Phase 3 terminée — {} paires de masques ({:.1} Ko).",
+            label, pairs.len(), buf.len() as f64 / 1024.0
+        );
 
-    // ── Phase 4 : déchiffrement avec la clé secrète locale ───────────
-    // kp_self est le seul KeyPair complet disponible sur cette machine.
-    // sk.lambda et sk.mu n'ont jamais transité sur le réseau.
-    println!("\n[{}] Phase 4 : déchiffrement Dec2 avec sk locale...", label);
-    meter.begin("Phase 4 — déchiffrement");
-    let cardinal = phase4_decrypt_and_count(&label, &triplets, &kp_self);
-    meter.end();
+        // ── Phase 4 : Enc(Σ masques) puis un seul bit ────────────────
+        println!("\n[{}] Phase 4 : comparaison au seuil {}...", label, t);
+        meter.begin("Phase 4 — comparaison au seuil");
+        let enc_b = phase4_threshold_mask_sum(&label, &pairs, &kp_self).map_err(io::Error::other)?;
+        send_tracked(&mut ret_stream, &MsgMkShare { share: enc_b }.encode(), &mut meter)?;
+        let res = MsgThresholdResult::decode(&recv_tracked(&mut ret_stream, &mut meter)?)?;
+        if res.threshold != t {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Phase 4 : seuil appliqué par le serveur ({}) différent de --threshold {}", res.threshold, t),
+            ));
+        }
+        let (bits, share) = phase4_threshold_bits(&label, &res.z, &kp_self).map_err(io::Error::other)?;
+        send_tracked(&mut ret_stream, &MsgCtList { cts: bits }.encode(), &mut meter)?;
+        let cmp = MsgThresholdCmp::decode(&recv_tracked(&mut ret_stream, &mut meter)?)?;
+        meter.end();
+        let above = phase4_threshold_decide(&label, &cmp.cts, cmp.hint, &share, &kp_self).map_err(io::Error::other)?;
+        threshold_result = Some((t, above));
+        0
+    } else if let Some(ops) = setops {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        let pairs = MsgMaskPairs::decode(&buf)?.pairs;
+        let res = MsgSetOpsResult::decode(&recv_tracked(&mut ret_stream, &mut meter)?)?;
+        meter.end();
+        println!(
+            "[{}] Phase 3 terminée — {} paires de masques, {} résultat(s) chiffré(s) ({:.1} Ko).",
+            label, pairs.len(), res.alphas.len(), buf.len() as f64 / 1024.0
+        );
+        if res.ops != ops.bits() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Phase 3 : sorties envoyées par le serveur ({:#04x}) différentes de --setops {}", res.ops, ops),
+            ));
+        }
+        // Moins de P paires trahiraient le nombre de positions communes
+        let p = pad_to.expect("--setops exige --pad-to") as usize;
+        if pairs.len() != p {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Phase 3 : {} paires de masques, {} attendues (--pad-to)", pairs.len(), p),
+            ));
+        }
+
+        // ── Phase 4 : M = Σ masques, puis chaque sortie ──────────────
+        println!("\n[{}] Phase 4 : déchiffrement des sorties {}...", label, ops);
+        meter.begin("Phase 4 — déchiffrement");
+        let counts = phase4_setops_decrypt(&label, ops, &res.alphas, &pairs, 2 * p, &kp_self);
+        meter.end();
+        let counts = counts.map_err(|e| {
+            eprintln!("[{}] Résultat du serveur REJETÉ : {}", label, e);
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        })?;
+        let inter = counts.iter().find(|(o, _)| *o == SetOp::Inter).map_or(0, |&(_, c)| c);
+        setops_result = Some(counts);
+        inter as usize
+    } else if let Some(eps) = dp {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+        let pairs = MsgMaskPairs::decode(&buf)?.pairs;
+        println!(
+            "[{}] Phase 3 terminée — {} paires de masques ({:.1} Ko).",
+            label, pairs.len(), buf.len() as f64 / 1024.0
+        );
+
+        // ── Phase 4 : Enc(Σ masques) + part de bruit, puis c + bruit ──
+        println!("\n[{}] Phase 4 : publication bruitée (epsilon = {})...", label, eps);
+        meter.begin("Phase 4 — publication bruitée");
+        let mask_sum = phase4_threshold_mask_sum(&label, &pairs, &kp_self).map_err(io::Error::other)?;
+        let (noise1, noise2) = phase4_dp_noise_share(&label, eps, pk1, pk2).map_err(io::Error::other)?;
+        send_tracked(&mut ret_stream, &MsgDpShare { epsilon: eps, mask_sum, noise1, noise2 }.encode(), &mut meter)?;
+        let res = MsgDpResult::decode(&recv_tracked(&mut ret_stream, &mut meter)?)?;
+        meter.end();
+        if res.epsilon.to_bits() != eps.to_bits() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Phase 4 : epsilon appliqué par le serveur ({}) différent de --dp {}", res.epsilon, eps),
+            ));
+        }
+        dp_result = Some((eps, phase4_dp_decrypt(&label, &res.noisy, &kp_self).map_err(io::Error::other)?));
+        0
+    } else if reveal {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+
+        let entries = MsgPosTriplets::decode(&buf)?.entries;
+        println!(
+            "[{}] Phase 3 terminée — {} triplets positionnés ({:.1} Ko).",
+            label, entries.len(), buf.len() as f64 / 1024.0
+        );
+
+        // ── Phase 4 : une position à 1 = un élément commun, rapporté
+        // à nos propres enregistrements
+        println!("\n[{}] Phase 4 : déchiffrement position par position...", label);
+        meter.begin("Phase 4 — déchiffrement");
+        let matched = phase4_decrypt_positions(&label, &entries, &kp_self).map_err(io::Error::other)?;
+        meter.end();
+        let rows = reveal_matching_records(&loaded.keys, &matched, &hasher);
+        let keys: HashSet<&String> = rows.iter().map(|&i| &loaded.keys[i]).collect();
+        if keys.len() > matched.len() {
+            println!(
+                "[{}] Attention : {} clé(s) locale(s) en collision sur une position commune, rapportées sans figurer forcément chez l'autre BD.",
+                label, keys.len() - matched.len()
+            );
+        }
+        revealed = Some(rows);
+        matched.len()
+    } else if mode == Mode::Fuzzy {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+
+        let scores = MsgClkScores::decode(&buf)?.scores;
+        println!(
+            "[{}] Phase 3 terminée — {} scores de paires ({:.1} Ko).",
+            label, scores.len(), buf.len() as f64 / 1024.0
+        );
+
+        // ── Phase 4 : Dice / Hamming de chaque paire, en clair localement
+        println!("\n[{}] Phase 4 : déchiffrement des scores ({:?})...", label, similarity);
+        meter.begin("Phase 4 — déchiffrement");
+        let res = phase4_count_similar(&label, &scores, clk_params.bits, similarity, &kp_self);
+        meter.end();
+        res.map_err(io::Error::other)?
+    } else if mode == Mode::MinHash {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+
+        let cts: Vec<_> = MsgFtBundle::decode(&buf)?.entries.into_iter().map(|(_, ct)| ct).collect();
+        println!(
+            "[{}] Phase 3 terminée — {} comparaisons MinHash ({:.1} Ko).",
+            label, cts.len(), buf.len() as f64 / 1024.0
+        );
+
+        // ── Phase 4 : composantes égales -> Ĵ, puis |A ∩ B| estimé
+        println!("\n[{}] Phase 4 : déchiffrement des comparaisons MinHash...", label);
+        meter.begin("Phase 4 — déchiffrement");
+        let res = phase4_estimate_jaccard(&label, &cts, minhash_k, &kp_self);
+        meter.end();
+        let (own, other) = sizes.expect("tailles échangées en Phase 0e");
+        let est = SetOverlap::from_jaccard(own, other, res.map_err(io::Error::other)?);
+        overlap = Some(est);
+        est.inter.round() as usize
+    } else if mode == Mode::Cuckoo {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+
+        let cts: Vec<_> = MsgFtBundle::decode(&buf)?.entries.into_iter().map(|(_, ct)| ct).collect();
+        println!(
+            "[{}] Phase 3 terminée — {} comparaisons ({:.1} Ko).",
+            label, cts.len(), buf.len() as f64 / 1024.0
+        );
+
+        // ── Phase 4 : une comparaison qui se déchiffre en 0 = un élément commun
+        println!("\n[{}] Phase 4 : déchiffrement + comptage des zéros...", label);
+        meter.begin("Phase 4 — déchiffrement");
+        let res = phase4_count_zeros(&label, &cts, &kp_self);
+        meter.end();
+        res.map_err(io::Error::other)?
+    } else if let Some(kea) = &kea_self {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+
+        let triplets = MsgKeaTriplets::decode(&buf)?.triplets;
+        println!(
+            "[{}] Phase 3 terminée — {} triplets KEA ({:.1} Ko).",
+            label, triplets.len(), buf.len() as f64 / 1024.0
+        );
+
+        // ── Phase 4 : vérification d'image puis déchiffrement ────────
+        println!("\n[{}] Phase 4 : vérification KEA + Dec2 avec sk locale...", label);
+        meter.begin("Phase 4 — vérification + déchiffrement");
+        let res = phase4_decrypt_and_count_kea(&label, &triplets, &kp_self, kea);
+        meter.end();
+        res.map_err(|e| {
+            eprintln!("[{}] Résultat du serveur REJETÉ : {}", label, e);
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        })?
+    } else if sum || multiset == Some(MultisetSemantics::Product) {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+        let agg = MsgSndSum::decode(&buf)?.sum;
+        println!(
+            "[{}] Phase 3 terminée — somme agrégée, {} paires de masques ({:.1} Ko).",
+            label, agg.betas.len(), buf.len() as f64 / 1024.0
+        );
+
+        // ── Phase 4 : Dec(α) + Σ masques, un seul total ──────────────
+        println!("\n[{}] Phase 4 : déchiffrement de la somme agrégée...", label);
+        meter.begin("Phase 4 — déchiffrement");
+        let cardinal = if sum {
+            let total = phase4_decrypt_sum(&label, &agg, &kp_self).map_err(io::Error::other)?;
+            sum_result = Some(fp.format(&total));
+            agg.betas.len()
+        } else {
+            phase4_multiset_product(&label, &agg, &kp_self).map_err(|e| {
+                eprintln!("[{}] Résultat du serveur REJETÉ : {}", label, e);
+                io::Error::new(io::ErrorKind::InvalidData, e)
+            })? as usize
+        };
+        meter.end();
+        cardinal
+    } else {
+        let buf = recv_tracked(&mut ret_stream, &mut meter)?;
+        meter.end();
+
+        let triplets = MsgTriplets::decode(&buf)?.triplets;
+        println!(
+            "[{}] Phase 3 terminée — {} triplets ({:.1} Ko).",
+            label, triplets.len(), buf.len() as f64 / 1024.0
+        );
+
+        // ── Phase 4 : déchiffrement avec la clé secrète locale ───────
+        // kp_self est le seul KeyPair complet disponible sur cette machine.
+        // sk.lambda et sk.mu n'ont jamais transité sur le réseau.
+        println!("\n[{}] Phase 4 : déchiffrement Dec2 avec sk locale...", label);
+        meter.begin("Phase 4 — déchiffrement");
+        let cardinal = phase4_decrypt_and_count(&label, &triplets, &kp_self).map_err(|e| {
+            eprintln!("[{}] Résultat du serveur REJETÉ : {}", label, e);
+            io::Error::new(io::ErrorKind::InvalidData, e)
+        })?;
+        meter.end();
+        cardinal
+    };
+
+    if matches!(mode, Mode::Standard | Mode::Kea) && threshold.is_none() && dp.is_none() && setops.is_none() {
+        confirm_cardinal(&label, bd_id, &mut stream, &kp_self, &pk_other, cardinal, &mut meter)?;
+    }
+
+    if overlap.is_none() {
+        overlap = sizes.map(|(own, other)| SetOverlap::exact(own, other, cardinal));
+    }
 
     println!("\n╔══════════════════════════════════════════════════════╗");
     println!("║  {} — RÉSULTAT                                      ║", label);
     println!("╠══════════════════════════════════════════════════════╣");
-    println!("║  |BD1 ^ BD2|  =  {}", cardinal);
+    if let Some((eps, noisy)) = &dp_result {
+        let sigma = (DP_NOISE_SHARES as f64 * discrete_laplace_variance(*eps)).sqrt();
+        println!("║  |BD1 ^ BD2| bruité  =  {}", noisy);
+        println!("║  epsilon = {}, écart-type du bruit ≈ {:.2}", eps, sigma);
+    }
+    if let Some(counts) = &setops_result {
+        for (op, c) in counts {
+            println!("║  {:<13}=  {}", op.label(), c);
+        }
+    }
+    match (&sum_result, threshold_result) {
+        (Some(total), _) => {
+            println!("║  Σ {} sur BD1 ^ BD2  =  {}", value_col, total);
+            println!("║  Positions communes  :  {}", cardinal);
+        }
+        (None, Some((t, above))) => {
+            println!("║  |BD1 ^ BD2| >= {}  :  {}", t, if above { "OUI" } else { "NON" });
+        }
+        (None, None) if mode == Mode::Fuzzy => {
+            println!("║  Paires similaires  =  {}", cardinal);
+            println!("║  Critère            :  {:?}", similarity);
+        }
+        (None, None) if dp_result.is_some() || setops_result.is_some() => {}
+        (None, None) if overlap.is_some() => {}
+        (None, None) if multiset.is_some() => {
+            println!("║  {} sur BD1 ^ BD2  =  {}", multiset.map_or("", MultisetSemantics::label), cardinal);
+        }
+        (None, None) => println!("║  |BD1 ^ BD2|  =  {}", cardinal),
+    }
+    if let Some(o) = &overlap {
+        let (self_l, other_l) = if bd_id == 1 { ("BD1", "BD2") } else { ("BD2", "BD1") };
+        let approx = if o.estimated { "≈" } else { "=" };
+        println!("║  |{}| = {}, |{}| = {}", self_l, o.own, other_l, o.other);
+        println!("║  |BD1 ^ BD2|  {} {:.0}", approx, o.inter);
+        println!("║  |BD1 U BD2|  {} {:.0}", approx, o.union());
+        println!("║  Jaccard      {} {:.4}", approx, o.jaccard());
+        println!("║  Inclusion {} dans {}  {} {:.4}", self_l, other_l, approx, o.containment_own());
+        println!("║  Inclusion {} dans {}  {} {:.4}", other_l, self_l, approx, o.containment_other());
+        if o.estimated {
+            println!("║  (MinHash, k = {} : écart-type de Jaccard ≈ {:.4})",
+                minhash_k, (o.jaccard() * (1.0 - o.jaccard()) / minhash_k as f64).sqrt());
+        }
+    }
+    if let Some(rows) = &revealed {
+        println!("║  Enregistrements communs ({})  :  {}", id_col, rows.len());
+        for &i in rows.iter().take(REVEAL_PRINT_MAX) {
+            println!("║    ligne {:>6}  {}", loaded.lines[i], loaded.extra[i][0]);
+        }
+        if rows.len() > REVEAL_PRINT_MAX {
+            println!("║    … et {} autre(s)", rows.len() - REVEAL_PRINT_MAX);
+        }
+    }
     println!("║  Temps total  :  {:.3?}", t_total.elapsed());
     println!("╚══════════════════════════════════════════════════════╝");
 
+    if let (Some(rows), Some(path)) = (&revealed, reveal_out) {
+        let mut out = format!("{}\n", id_col);
+        for &i in rows {
+            out.push_str(&loaded.extra[i][0]);
+            out.push('\n');
+        }
+        std::fs::write(path, out)?;
+        println!("[{}] {} identifiant(s) écrit(s) dans {}.", label, rows.len(), path);
+    }
+
     meter.report();
     Ok(())
 }
\ No newline at end of file
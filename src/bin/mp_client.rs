@@ -0,0 +1,187 @@
+// =========================================================
+// src/bin/mp_client.rs — Client PSI ExactMatch à N parties
+//
+// Usage :
+//   cargo run --bin mp_client -- --csv <fichier.csv> [--server 127.0.0.1:7010]
+//                                [--threads <N>] [--table-bits <8..48>] [--hash-psk <64 hex>]
+//                                [--key-config <clés.json>]
+//
+// Flux (une seule connexion vers mp_server) :
+//   Phase 0a : génère sa clé, s'inscrit en envoyant pk_self
+//   Phase 0b : reçoit son identifiant et les N pk
+//   Phase 0d : part de clé HMAC chiffrée sous les N-1 autres pk,
+//              relayée par le serveur ; K = SHA-256(s_1 || … || s_N || psk)
+//   Phase 2  : Ft(1) de chaque position occupée, sous les N pk
+//   Phase 3  : N-2 tours de rechiffrement — reçoit des produits
+//              aveuglés sous pk_self, renvoie des Ft frais
+//   Phase 4  : déchiffre les produits finaux : |BD1 ∩ … ∩ BDN|
+//
+// La sk ne quitte jamais cette machine ; les valeurs déchiffrées
+// pendant les tours de rechiffrement sont masquées par le serveur.
+// =========================================================
+
+use std::env;
+use std::io;
+use std::net::TcpStream;
+use std::time::Instant;
+
+use paillier_crypto::exactmatch::{
+    phase0_keygen, phase1_build_table, phase4_decrypt_and_count,
+    check_table_bits, mp_hash_key_shares, mp_derive_hasher, mp_prepare_ft, mp_reencrypt,
+    HashKey, DEFAULT_TABLE_BITS,
+};
+use paillier_crypto::exactmatch::position_hash::psk_id;
+use paillier_crypto::key_management::hex_to_fingerprint;
+use paillier_crypto::records::{KeyConfig, load_records};
+use paillier_crypto::paillier::p_keygen::PublicKey;
+use paillier_crypto::parallel::{default_pool, set_default_threads};
+use paillier_crypto::net_protocol::{
+    BandwidthMeter,
+    MsgPubKey, MsgPartyInfo, MsgMpShares, MsgMpBundle, MsgFtBundle, MsgTriplets,
+    send_tracked, recv_tracked,
+};
+
+const DEFAULT_SERVER: &str = "127.0.0.1:7010";
+const USAGE: &str = "Usage : mp_client --csv <fichier.csv> [--server <hôte:port>] [--threads <N>] \
+                     [--table-bits <8..48>] [--hash-psk <64 hex>] [--key-config <clés.json>]";
+
+fn crypto_err(e: paillier_crypto::CryptoError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+// ─────────────────────────────────────────────────────────
+// main
+// ─────────────────────────────────────────────────────────
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let arg = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1));
+    let bad = || io::Error::new(io::ErrorKind::InvalidInput, USAGE);
+
+    let csv_path: &str = arg("--csv").map(String::as_str).ok_or_else(bad)?;
+    let server_addr: &str = arg("--server").map(String::as_str).unwrap_or(DEFAULT_SERVER);
+    if let Some(v) = arg("--threads") {
+        set_default_threads(v.parse().map_err(|_| bad())?);
+    }
+    let table_bits: u32 = match arg("--table-bits") {
+        Some(v) => v.parse().map_err(|_| bad())?,
+        None    => DEFAULT_TABLE_BITS,
+    };
+    check_table_bits(table_bits)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{}\n{}", e, USAGE)))?;
+    // 32 octets hex : même format qu'une empreinte de clé
+    let psk: Option<HashKey> = match arg("--hash-psk") {
+        Some(v) => Some(hex_to_fingerprint(v).map_err(|_| bad())?),
+        None    => None,
+    };
+
+    let mut meter = BandwidthMeter::new();
+    let t_total   = Instant::now();
+
+    let key_config = match arg("--key-config") {
+        Some(path) => KeyConfig::from_json_file(path).map_err(io::Error::other)?,
+        None       => KeyConfig::nss(),
+    };
+    let loaded = load_records(csv_path, &key_config, &[]).map_err(io::Error::other)?;
+    println!(
+        "[BD?] {} enregistrements ({}) chargés depuis {}.",
+        loaded.len(), key_config.column_names().join(" + "), csv_path
+    );
+    loaded.print_rejected("BD?", 10);
+    let nss_list = loaded.keys;
+    println!("[BD?] Pool de calcul : {} thread(s).", default_pool().threads());
+
+    // ── Phase 0a : clé locale + inscription ──────────────────────────
+    let kp_self = phase0_keygen("BD?", 1024);
+    println!("[BD?] Connexion au serveur {}...", server_addr);
+    let mut stream = loop {
+        match TcpStream::connect(server_addr) {
+            Ok(s)  => break s,
+            Err(_) => {
+                eprint!(".");
+                std::thread::sleep(std::time::Duration::from_millis(500));
+            }
+        }
+    };
+    meter.begin("Phase 0a — inscription");
+    let pk_payload = MsgPubKey {
+        n:         kp_self.public_key.n.clone(),
+        g:         kp_self.public_key.g.clone(),
+        n_squared: kp_self.public_key.n_squared.clone(),
+    }.encode();
+    send_tracked(&mut stream, &pk_payload, &mut meter)?;
+    meter.end();
+
+    // ── Phase 0b : identifiant + annuaire des pk ─────────────────────
+    meter.begin("Phase 0b — annuaire pk");
+    let info = MsgPartyInfo::decode(&recv_tracked(&mut stream, &mut meter)?)?;
+    meter.end();
+    let own_id = info.party_id as usize;
+    let pks: Vec<PublicKey> = info.pks.into_iter()
+        .map(|m| PublicKey { n: m.n, g: m.g, n_squared: m.n_squared })
+        .collect();
+    let n_parties = pks.len();
+    if pks.get(own_id).map(|pk| &pk.n) != Some(&kp_self.public_key.n) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, "Phase 0b : pk_self absente de l'annuaire"));
+    }
+    let label = format!("BD{}", own_id + 1);
+
+    println!("\n╔══════════════════════════════════════════════════════╗");
+    println!("║   CLIENT PSI — {} / {} parties                       ║", label, n_parties);
+    println!("╚══════════════════════════════════════════════════════╝\n");
+
+    // ── Phase 0d : clé HMAC commune ──────────────────────────────────
+    let (own_share, shares) = mp_hash_key_shares(&label, own_id, &pks).map_err(crypto_err)?;
+    meter.begin("Phase 0d — parts de clé HMAC");
+    let payload = MsgMpShares { table_bits: table_bits as u8, psk_id: psk_id(psk.as_ref()), shares }.encode();
+    send_tracked(&mut stream, &payload, &mut meter)?;
+    let received = MsgMpShares::decode(&recv_tracked(&mut stream, &mut meter)?)?;
+    meter.end();
+    if received.table_bits as u32 != table_bits || received.psk_id != psk_id(psk.as_ref()) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "Phase 0d : configuration de hachage différente entre les parties (--table-bits / --hash-psk)",
+        ));
+    }
+    let hasher = mp_derive_hasher(
+        &label, own_id, &own_share, &received.shares, &kp_self, psk.as_ref(), table_bits,
+    ).map_err(crypto_err)?;
+
+    // ── Phases 1-2 : table + Ft sous les N pk ────────────────────────
+    let table  = phase1_build_table(&label, &nss_list, &hasher);
+    let bundle = mp_prepare_ft(&label, &table, &pks);
+    let payload = MsgMpBundle {
+        bundles: bundle.under.iter()
+            .map(|b| MsgFtBundle { entries: b.ft_by_pos.iter().map(|(&pos, ft)| (pos, ft.clone())).collect() })
+            .collect(),
+    }.encode();
+    meter.begin("Phase 2 — envoi Ft (N clés)");
+    send_tracked(&mut stream, &payload, &mut meter)?;
+    meter.end();
+    println!("[{}] Phase 2 terminée — {:.1} Ko envoyés.", label, payload.len() as f64 / 1024.0);
+
+    // ── Phase 3 : tours de rechiffrement aveuglé ─────────────────────
+    for round in 1..n_parties.saturating_sub(1) {
+        meter.begin(&format!("Phase 3 — rechiffrement {}", round));
+        let blinded = MsgTriplets::decode(&recv_tracked(&mut stream, &mut meter)?)?.triplets;
+        let fresh   = mp_reencrypt(&label, &blinded, &kp_self).map_err(crypto_err)?;
+        let reply   = MsgFtBundle { entries: fresh.into_iter().enumerate().collect() }.encode();
+        send_tracked(&mut stream, &reply, &mut meter)?;
+        meter.end();
+    }
+
+    // ── Phase 4 : produits finaux sous pk_self ───────────────────────
+    meter.begin("Phase 4 — déchiffrement");
+    let triplets = MsgTriplets::decode(&recv_tracked(&mut stream, &mut meter)?)?.triplets;
+    let cardinal = phase4_decrypt_and_count(&label, &triplets, &kp_self).map_err(crypto_err)?;
+    meter.end();
+
+    println!("\n╔══════════════════════════════════════════════════════╗");
+    println!("║  {} — RÉSULTAT                                      ║", label);
+    println!("╠══════════════════════════════════════════════════════╣");
+    println!("║  |BD1 ^ … ^ BD{}|  =  {}", n_parties, cardinal);
+    println!("║  Temps total  :  {:.3?}", t_total.elapsed());
+    println!("╚══════════════════════════════════════════════════════╝");
+
+    meter.report();
+    Ok(())
+}
@@ -0,0 +1,257 @@
+// =========================================================
+// src/bin/mp_server.rs — Serveur PSI ExactMatch à N parties
+//
+// Usage :
+//   cargo run --bin mp_server -- --parties <N> [--port 7010] [--threads <N>]
+//
+// Une seule connexion par BD (pas de port retour) :
+//   Phase 0a : inscription — les BD se connectent sur --port et
+//              envoient leur pk ; l'identifiant suit l'ordre d'arrivée
+//   Phase 0b : envoie à chaque BD son identifiant et les N pk
+//   Phase 0d : relaie les parts de clé HMAC (chiffrées sous la pk
+//              du destinataire), vérifie table_bits / psk_id
+//   Phase 2  : reçoit de chaque BD ses Ft sous les N clés
+//   Phase 3  : pour chaque clé k, x_1·x_2·…·x_N sur les positions
+//              communes aux N tables : N-1 cf_mul, entrecoupés de
+//              N-2 tours de rechiffrement aveuglé avec la BD k
+//              (cf. exactmatch/multiparty.rs), puis envoi des
+//              produits finaux à la BD k
+// =========================================================
+
+use std::env;
+use std::io;
+use std::net::{TcpListener, TcpStream};
+use std::time::Instant;
+
+use paillier_crypto::exactmatch::{
+    CfFst, CfSnd, FtBundle, MultiFtBundle, SparseTable,
+    check_party_count, mp_common_positions, mp_column, mp_multiply, mp_blind, mp_fresh_in_order, mp_unblind, mp_table_of,
+};
+use paillier_crypto::paillier::p_keygen::PublicKey;
+use paillier_crypto::parallel::{default_pool, set_default_threads};
+use paillier_crypto::net_protocol::{
+    BandwidthMeter,
+    MsgPubKey, MsgPartyInfo, MsgMpShares, MsgMpBundle, MsgFtBundle, MsgTriplets,
+    send_tracked, recv_tracked,
+};
+
+const DEFAULT_PORT: u16 = 7010;
+const USAGE: &str = "Usage : mp_server --parties <N> [--port <P>] [--threads <N>]";
+
+// ─────────────────────────────────────────────────────────
+// Une BD inscrite
+// ─────────────────────────────────────────────────────────
+struct Party {
+    label:  String,
+    stream: TcpStream,
+    pk:     PublicKey,
+    meter:  BandwidthMeter,
+}
+
+fn crypto_err(e: paillier_crypto::CryptoError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 0a : inscription des N parties
+// ─────────────────────────────────────────────────────────
+fn register_parties(listener: &TcpListener, n_parties: usize) -> io::Result<Vec<Party>> {
+    let mut parties = Vec::with_capacity(n_parties);
+    while parties.len() < n_parties {
+        let (mut stream, addr) = listener.accept()?;
+        let label     = format!("BD{}", parties.len() + 1);
+        let mut meter = BandwidthMeter::new();
+        meter.begin("Phase0a inscription");
+        let msg = MsgPubKey::decode(&recv_tracked(&mut stream, &mut meter)?)?;
+        meter.end();
+        let pk = PublicKey { n: msg.n, g: msg.g, n_squared: msg.n_squared };
+        println!(
+            "[Serveur] {} inscrite depuis {} (|n|={} bits) — {}/{}",
+            label, addr, pk.n.bits(), parties.len() + 1, n_parties
+        );
+        parties.push(Party { label, stream, pk, meter });
+    }
+    Ok(parties)
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 0b : identifiant + annuaire des pk
+// ─────────────────────────────────────────────────────────
+fn send_party_info(parties: &mut [Party]) -> io::Result<()> {
+    let pks: Vec<PublicKey> = parties.iter().map(|p| p.pk.clone()).collect();
+    for (id, p) in parties.iter_mut().enumerate() {
+        let info = MsgPartyInfo {
+            party_id: id as u32,
+            pks: pks.iter()
+                .map(|pk| MsgPubKey { n: pk.n.clone(), g: pk.g.clone(), n_squared: pk.n_squared.clone() })
+                .collect(),
+        };
+        p.meter.begin("Phase0b annuaire pk");
+        send_tracked(&mut p.stream, &info.encode(), &mut p.meter)?;
+        p.meter.end();
+    }
+    println!("[Serveur] Phase 0b terminée — {} pk diffusées.", pks.len());
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 0d : relais des parts de clé HMAC
+// ─────────────────────────────────────────────────────────
+fn relay_hash_key_shares(parties: &mut [Party]) -> io::Result<()> {
+    let n = parties.len();
+    let mut inbox: Vec<Vec<(u32, num_bigint::BigUint)>> = vec![Vec::new(); n];
+    let mut config = None;
+
+    for (from, p) in parties.iter_mut().enumerate() {
+        p.meter.begin("Phase0d recv key shares");
+        let msg = MsgMpShares::decode(&recv_tracked(&mut p.stream, &mut p.meter)?)?;
+        p.meter.end();
+
+        let cfg = (msg.table_bits, msg.psk_id);
+        if *config.get_or_insert(cfg) != cfg {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "Phase 0d : {} annonce une configuration de hachage différente", p.label
+            )));
+        }
+        for (to, ct) in msg.shares {
+            let dest = inbox.get_mut(to as usize).filter(|_| to as usize != from).ok_or_else(|| {
+                io::Error::new(io::ErrorKind::InvalidData, format!("Phase 0d : destinataire {} invalide", to))
+            })?;
+            dest.push((from as u32, ct));
+        }
+    }
+
+    let (table_bits, psk_id) = config.expect("au moins deux parties");
+    for (p, shares) in parties.iter_mut().zip(inbox) {
+        p.meter.begin("Phase0d send key shares");
+        let payload = MsgMpShares { table_bits, psk_id, shares }.encode();
+        send_tracked(&mut p.stream, &payload, &mut p.meter)?;
+        p.meter.end();
+    }
+    println!("[Serveur] Phase 0d terminée — parts relayées (2^{} positions).", table_bits);
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 2 : Ft de chaque BD sous les N clés
+// ─────────────────────────────────────────────────────────
+fn recv_bundles(parties: &mut [Party]) -> io::Result<Vec<MultiFtBundle>> {
+    let n = parties.len();
+    let mut bundles = Vec::with_capacity(n);
+    for p in parties.iter_mut() {
+        p.meter.begin("Phase2 recv bundle");
+        let buf = recv_tracked(&mut p.stream, &mut p.meter)?;
+        p.meter.end();
+        let msg = MsgMpBundle::decode(&buf)?;
+        if msg.bundles.len() != n {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "{} : {} bundles reçus, {} attendus", p.label, msg.bundles.len(), n
+            )));
+        }
+        let bundle = MultiFtBundle {
+            under: msg.bundles.into_iter()
+                .map(|b| FtBundle { ft_by_pos: b.entries.into_iter().collect() })
+                .collect(),
+        };
+        println!(
+            "[Serveur] {} Phase 2 : {} positions reçues ({:.1} Ko)",
+            p.label, mp_table_of(&bundle).len(), buf.len() as f64 / 1024.0
+        );
+        bundles.push(bundle);
+    }
+    Ok(bundles)
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 3 : produit des N indicateurs, sous chaque clé
+// ─────────────────────────────────────────────────────────
+fn compute_products(parties: &mut [Party], bundles: &[MultiFtBundle]) -> io::Result<()> {
+    let n = parties.len();
+    let tables: Vec<SparseTable> = bundles.iter().map(mp_table_of).collect();
+    let common = mp_common_positions(&tables);
+    println!("[Serveur] Phase 3 : {} position(s) commune(s) aux {} parties.", common.len(), n);
+
+    // acc[k] : produit partiel sous pk_k, en Première Forme
+    let mut acc: Vec<Vec<CfFst>> = (0..n)
+        .map(|k| Ok(mp_column(bundles, 0, k, &common).map_err(crypto_err)?.into_iter().cloned().collect()))
+        .collect::<io::Result<_>>()?;
+
+    for level in 1..n {
+        let t = Instant::now();
+        let products: Vec<Vec<CfSnd>> = (0..n)
+            .map(|k| {
+                let column = mp_column(bundles, level, k, &common).map_err(crypto_err)?;
+                mp_multiply(&acc[k], &column, &parties[k].pk).map_err(crypto_err)
+            })
+            .collect::<io::Result<_>>()?;
+        println!("[Serveur] Niveau {}/{} : {} cf_mul x {} clés en {:.3?}", level, n - 1, common.len(), n, t.elapsed());
+
+        if level == n - 1 {
+            for (p, triplets) in parties.iter_mut().zip(products) {
+                p.meter.begin("Phase3 send produits");
+                send_tracked(&mut p.stream, &MsgTriplets { triplets }.encode(), &mut p.meter)?;
+                p.meter.end();
+            }
+            break;
+        }
+
+        // Tour de rechiffrement : envoi à toutes les BD, puis réception
+        let mut offsets = Vec::with_capacity(n);
+        for (p, prod) in parties.iter_mut().zip(&products) {
+            let (blinded, s) = mp_blind(prod, &p.pk).map_err(crypto_err)?;
+            offsets.push(s);
+            p.meter.begin(&format!("Phase3 rechiffrement {}", level));
+            send_tracked(&mut p.stream, &MsgTriplets { triplets: blinded }.encode(), &mut p.meter)?;
+        }
+        for (k, p) in parties.iter_mut().enumerate() {
+            let entries = MsgFtBundle::decode(&recv_tracked(&mut p.stream, &mut p.meter)?)?.entries;
+            p.meter.end();
+            let fresh = mp_fresh_in_order(entries, offsets[k].len()).map_err(crypto_err)?;
+            acc[k] = mp_unblind(&fresh, &offsets[k], &p.pk).map_err(crypto_err)?;
+        }
+    }
+    println!("[Serveur] Phase 3 terminée — produits envoyés aux {} parties.", n);
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// main
+// ─────────────────────────────────────────────────────────
+fn main() -> io::Result<()> {
+    let args: Vec<String> = env::args().collect();
+    let arg = |flag: &str| args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1));
+    let bad = || io::Error::new(io::ErrorKind::InvalidInput, USAGE);
+
+    let n_parties: usize = arg("--parties").and_then(|v| v.parse().ok()).ok_or_else(bad)?;
+    check_party_count(n_parties).map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+    let port: u16 = match arg("--port") {
+        Some(v) => v.parse().map_err(|_| bad())?,
+        None    => DEFAULT_PORT,
+    };
+    if let Some(v) = arg("--threads") {
+        set_default_threads(v.parse().map_err(|_| bad())?);
+    }
+
+    println!("\n╔══════════════════════════════════════════════════════╗");
+    println!("║   SERVEUR PSI — {} parties                            ║", n_parties);
+    println!("║   inscription → :{}                                ║", port);
+    println!("╚══════════════════════════════════════════════════════╝\n");
+    println!("[Serveur] Pool de calcul : {} thread(s).", default_pool().threads());
+
+    let t_total  = Instant::now();
+    let listener = TcpListener::bind(format!("127.0.0.1:{}", port))?;
+    let mut parties = register_parties(&listener, n_parties)?;
+    println!("\n[Serveur] Phase 0a terminée — {} parties inscrites.", parties.len());
+
+    send_party_info(&mut parties)?;
+    relay_hash_key_shares(&mut parties)?;
+    let bundles = recv_bundles(&mut parties)?;
+    compute_products(&mut parties, &bundles)?;
+
+    println!("\n[Serveur] Durée totale : {:.3?}", t_total.elapsed());
+    for p in &parties {
+        println!("\n[Serveur] ─── Rapport {} ↔ Serveur ───", p.label);
+        p.meter.report();
+    }
+    Ok(())
+}
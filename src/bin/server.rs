@@ -5,9 +5,129 @@
 // Flux complet :
 //   Phase 0a : reçoit pk1 de BD1, pk2 de BD2
 //   Phase 0b : renvoie pk2 à BD1 et pk1 à BD2 
+//   Phase 0d : relaie les parts de clé de hachage (chiffrées sous la pk
+//              du destinataire) et vérifie table_bits / psk_id
 //   Phase 2  : reçoit DualFtBundle de BD1 et BD2
+//   Phase 5  : relaie les engagements sur les cardinaux, puis leurs
+//              ouvertures chiffrées (standard, --reveal, --kea, --checkpoint)
+//
+// Mode --multikey :
+//   Phase 2  : reçoit un FtBundle (sous pk_self) de BD1 et BD2
+//   Phase 3  : CF.Mul multi-clés pk1 × pk2, envoi à BD1
+//   Phase 4  : relaie (A2, Enc2(part1)) BD1 → BD2 puis Enc1(part2) BD2 → BD1
+//
+// Mode --kea :
+//   Phase 0c : reçoit ct_delta1, ct_delta2 et les croise
+//              (la Phase 0d suit la Phase 0c)
+//   Phase 2  : reçoit un DualKeaFtBundle de BD1 et BD2
+//   Phase 3  : cf_kea_mul (les BD vérifient l'image en Phase 4 ;
+//              ct_delta étant connu du serveur, la vérification ne
+//              détecte qu'une altération d'une seule composante)
+//
+// Mode --cuckoo :
+//   Phase 0e : relaie les tailles d'ensemble (n1, n2) et calcule
+//              les mêmes paramètres cuckoo que les BD
+//   Phase 2  : reçoit les Ft des slots (table cuckoo / bins simples)
+//   Phase 3  : r·(Ft1 - Ft2) pour chaque paire à comparer, mélangés
+//
+// Mode --fuzzy :
+//   Phase 0e : relaie (nombre d'enregistrements, L, k) des deux BD
+//   Phase 2  : reçoit les Ft des bits CLK de chaque enregistrement
+//   Phase 3  : produit scalaire et |a| + |b| chiffrés pour chaque
+//              paire d'enregistrements, mélangés
+//
+// Option --size-hiding --pad-to P (mode standard) :
+//   Phase 2  : exige de chaque BD un bundle d'exactement P positions
+//              (réelles et factices, indiscernables) : le serveur
+//              n'apprend plus les tailles. Il voit toujours les
+//              positions communes ; les collisions sont estimées pour
+//              deux tables de P positions. En mode --cuckoo, les BD
+//              annoncent --pad-to au lieu de leur taille et le serveur
+//              n'a rien à changer (sans --pad-to côté serveur).
+//
+// Option --sum (mode standard) : PSI-Sum.
+//   Phase 3  : triplets agrégés (α = Π C0, masques re-randomisés et
+//              mélangés) : chaque BD ne déchiffre que Σ v, jamais un
+//              produit isolé
+//
+// Mode --minhash :
+//   Phase 0e : relaie les tailles (éventuellement chiffrées) et vérifie k
+//   Phase 2  : reçoit les Ft des k composantes MinHash de chaque BD
+//   Phase 3  : r·(Ft1_i - Ft2_i) par composante, mélangés
+//
+// Mode --delta <état.json> : session persistante (PSI incrémentale)
+//   Phase 0c : annonce (session, époque) ; pk vérifiées contre l'état,
+//              Phase 0d seulement à la création
+//   Phase 2  : reçoit de chaque BD positions retirées + Ft ajoutés
+//   Phase 3  : CF.Mul des seules positions nouvellement communes ;
+//              chaque BD convertit ses masques en Enc(b·b')
+//   Phase 4  : Enc(c) mis à jour (· E_p à l'ajout, · E_p^-1 au
+//              retrait), état enregistré, Enc(c) envoyé aux BD
+//
+// Mode --checkpoint <dir> : mode standard avec reprise sur incident
+//   Phase 0c : reçoit l'id de session de chaque BD ; reprise si les deux
+//              correspondent à l'état enregistré (empreintes pk1, pk2
+//              vérifiées), sinon nouvelle session et Phase 0d
+//   Phase 2  : seuls les bundles absents de <dir> sont redemandés
+//   Phase 3  : CF.Mul par lots, chaque lot écrit avant d'être compté ;
+//              une reprise repart du premier lot manquant
+//
+// Mode --stream [--chunk N] : mémoire bornée (cf. exactmatch/stream.rs)
+//   Phase 2  : reçoit de chaque BD des lots de Ft triés par position
+//              (lot vide = fin) et les joint par fusion au fil de l'eau
+//   Phase 3  : CF.Mul par lots ; Π C0 gardé par clé, (C1, C2) sur disque,
+//              envoyés par lots une fois les deux flux épuisés, puis Π C0
+//
+// Option --similarity (mode standard) :
+//   Phase 0e : relaie les tailles d'ensemble entre les BD (illisibles
+//              avec --hide-sizes côté BD) ; le reste est inchangé
+//
+// Option --threshold t (mode standard) :
+//   Phase 3  : triplets complétés à min(|T1|, |T2|) puis agrégés ;
+//              seuls les masques partent vers chaque BD
+//   Phase 4  : reçoit Enc(Σ masques), renvoie Enc(c - t + 2^ℓ + r),
+//              puis compare bit à bit (DGK) ; le BD n'apprend que le
+//              bit [ |BD1 ∩ BD2| >= t ]
+//   Le serveur voit toujours le nombre de positions communes, y compris
+//   avec --size-hiding (qui ne lui cache que les tailles).
+//
+// Option --dp ε (mode standard, --size-hiding possible) : cardinal bruité.
+//   Phase 3  : agrégation bourrée comme --threshold, masques vers
+//              les deux BD
+//   Phase 4  : reçoit de chaque BD Enc(Σ masques) et sa part de bruit
+//              sous pk1 et pk2, ajoute la sienne : les deux BD
+//              déchiffrent le même c + z_s + z_1 + z_2
+//   Le serveur connaît c exact (positions communes) : le bruit ne
+//   protège que face à l'autre BD.
+//
+// Option --reveal (mode standard, --size-hiding possible) : PSI complète.
+//   Phase 3  : chaque triplet part avec sa position ; chaque BD
+//              retrouve ses propres enregistrements communs
+//
+// Option --setops a-b,b-a,union[,inter] (avec --size-hiding --pad-to P) :
+//   cardinaux d'opérations ensemblistes (cf. exactmatch/setops.rs).
+//   Phase 0e : reçoit les sorties choisies par chaque BD, renvoie les
+//              siennes ; tout désaccord interrompt
+//   Phase 3  : CF.Mul sur les positions communes, triplets complétés à P,
+//              puis par clé les masques re-randomisés et mélangés et un
+//              α_o = Enc(L_o)·α^-1 par sortie choisie
+//   Un BD qui connaît sa propre taille déduit des sorties révélées les
+//   cardinaux qu'elles impliquent (|A ∩ B| = |A| − |A \ B|). Le serveur
+//   apprend |A ∩ B| (nombre de triplets avant bourrage).
+//
+// Option --multiset (mode standard) : multiplicités des clés prises en
+//   compte (cf. exactmatch/multiset.rs).
+//   Phase 0e : relaie la sémantique choisie par chaque BD (min / product)
+//              et vérifie qu'elle est identique
+//   Phases 2-3 : Ft par occurrence (min) ou Ft(multiplicité)
+//              (product), CF.Mul sur les positions communes ; en
+//              product, triplets bourrés et agrégés comme --sum
+//
+// --threads N : taille du pool de la Phase 3 (0 = un thread par
+//               cœur ; défaut : PSI_THREADS ou 0)
 // =========================================================
 
+use std::env;
 use std::net::{TcpListener, TcpStream};
 use std::io;
 use std::thread;
@@ -16,16 +136,38 @@ use std::time::Instant;
 use std::collections::HashSet;
 
 use num_traits::Zero;
+use rand_core::OsRng;
 
 use paillier_crypto::exactmatch::{
     SparseTable, DualFtBundle, FtBundle,
-    phase3_server_compute, CfSnd,
+    phase3_server_compute_positions, phase3_server_compute_mk, CfSnd,
+    phase3_server_compute_kea, DualKeaFtBundle, KeaFtBundle,
+    report_cross_collisions, check_padded_bundle,
+    phase3_server_compute_cuckoo, CuckooParams,
+    phase3_server_compute_fuzzy, ClkParams,
+    phase3_server_compute_minhash, check_minhash_k,
+    DeltaState, new_session_id, phase3_delta_mask_pairs, phase3_delta_unshuffle,
+    ServerCheckpoint, CHECKPOINT_BATCH,
+    StreamEntry, DEFAULT_STREAM_CHUNK, check_stream_chunk, phase3_stream_join,
+    phase3_server_aggregate, phase4_threshold_blind, phase4_threshold_compare,
+    phase4_dp_combine, sample_discrete_laplace, check_epsilon,
+    SetOps, SetOpsAgg, phase3_server_setops,
+    MultisetSemantics,
 };
+use paillier_crypto::cf_stats::CfSndSum;
 use paillier_crypto::paillier::p_keygen::PublicKey;
+use paillier_crypto::parallel::{default_pool, set_default_threads};
 use paillier_crypto::{KeyPair, SecretKey};
 use paillier_crypto::net_protocol::{
     BandwidthMeter,
-    MsgPubKey, MsgDualBundle, MsgTriplets,
+    MsgPubKey, MsgHashKeyShare, MsgCuckooSize, MsgSetSize, MsgClkParams, MsgClkScores, MsgDualBundle, MsgPosTriplets, MsgMaskPairs, MsgSndSum, MsgThresholdResult, MsgThresholdCmp, MsgDpShare, MsgDpResult, MsgMkShare, MsgFtBundle, MsgTriplets,
+    MsgMkQuads,
+    MsgDeltaSession, MsgDelta, MsgCtList, MsgDeltaResult,
+    MsgResume, MsgResumePlan,
+    MsgCardinalCommit, MsgCardinal,
+    MsgSetOps, MsgSetOpsResult,
+    MsgMultiset,
+    MsgKeaDelta, MsgKeaDualBundle, MsgKeaTriplets,
     send_tracked, recv_tracked,
 };
 
@@ -39,10 +181,18 @@ struct BdData {
     pk:     Option<PublicKey>,
     stream: Option<TcpStream>,   // conservé pour Phase 0b
     bundle: Option<DualFtBundle>,
+    mk_bundle: Option<FtBundle>,  // mode --multikey : Ft sous pk_self uniquement
+    kea_delta:  Option<(num_bigint::BigUint, num_bigint::BigUint)>,  // mode --kea
+    kea_bundle: Option<DualKeaFtBundle>,                             // mode --kea
     table:  Option<SparseTable>,
 }
 impl BdData {
-    fn new() -> Self { BdData { pk: None, stream: None, bundle: None, table: None } }
+    fn new() -> Self {
+        BdData {
+            pk: None, stream: None, bundle: None, mk_bundle: None,
+            kea_delta: None, kea_bundle: None, table: None,
+        }
+    }
 }
 
 // ─────────────────────────────────────────────────────────
@@ -84,6 +234,50 @@ fn send_pk_other(
     Ok(())
 }
 
+// ─────────────────────────────────────────────────────────
+// Phase 0d : relais des parts de clé de hachage
+//
+// Le serveur ne peut pas lire les parts (chiffrées sous la pk
+// de l'autre BD) ; il vérifie seulement que les deux BD
+// annoncent la même taille de table et la même psk.
+// Retourne table_bits pour l'estimation des collisions.
+// ─────────────────────────────────────────────────────────
+fn relay_hash_key_shares(
+    d1: &mut BdData,
+    d2: &mut BdData,
+    m1: &mut BandwidthMeter,
+    m2: &mut BandwidthMeter,
+) -> io::Result<u32> {
+    let s1 = d1.stream.as_mut().expect("stream BD1 manquant");
+    let s2 = d2.stream.as_mut().expect("stream BD2 manquant");
+
+    m1.begin("Phase0d recv key share BD1");
+    let buf1 = recv_tracked(s1, m1)?;
+    m1.end();
+    m2.begin("Phase0d recv key share BD2");
+    let buf2 = recv_tracked(s2, m2)?;
+    m2.end();
+
+    let (msg1, msg2) = (MsgHashKeyShare::decode(&buf1)?, MsgHashKeyShare::decode(&buf2)?);
+    if msg1.table_bits != msg2.table_bits || msg1.psk_id != msg2.psk_id {
+        eprintln!(
+            "[Serveur] Phase 0d : configurations différentes (table_bits {} / {}, psk identique : {})",
+            msg1.table_bits, msg2.table_bits, msg1.psk_id == msg2.psk_id
+        );
+    }
+
+    // Relais tel quel : chaque BD détecte elle-même une incohérence
+    m1.begin("Phase0d send key share to BD1");
+    send_tracked(s1, &buf2, m1)?;
+    m1.end();
+    m2.begin("Phase0d send key share to BD2");
+    send_tracked(s2, &buf1, m2)?;
+    m2.end();
+
+    println!("[Serveur] Phase 0d terminée — parts de clé de hachage relayées (2^{} positions).", msg1.table_bits);
+    Ok(msg1.table_bits as u32)
+}
+
 // ─────────────────────────────────────────────────────────
 // Phase 2 : lire le DualFtBundle sur la connexion existante
 // ─────────────────────────────────────────────────────────
@@ -127,14 +321,1094 @@ fn send_triplets(
     Ok(())
 }
 
+// ─────────────────────────────────────────────────────────
+// Phase 5 : relais des engagements, puis des ouvertures
+//
+// Les ouvertures sont chiffrées sous la pk de l'autre BD : le
+// serveur ne lit pas les cardinaux. Chaque BD n'ouvre qu'après
+// avoir reçu l'engagement de l'autre, et décide seule de la
+// cohérence.
+// ─────────────────────────────────────────────────────────
+fn relay_cardinals(
+    d1: &mut BdData,
+    d2: &mut BdData,
+    m1: &mut BandwidthMeter,
+    m2: &mut BandwidthMeter,
+) -> io::Result<()> {
+    let s1 = d1.stream.as_mut().expect("stream BD1 manquant");
+    let s2 = d2.stream.as_mut().expect("stream BD2 manquant");
+
+    for step in ["engagement", "ouverture"] {
+        m1.begin(&format!("Phase5 recv {} BD1", step));
+        let buf1 = recv_tracked(s1, m1)?;
+        m1.end();
+        m2.begin(&format!("Phase5 recv {} BD2", step));
+        let buf2 = recv_tracked(s2, m2)?;
+        m2.end();
+        if step == "engagement" {
+            MsgCardinalCommit::decode(&buf1)?;
+            MsgCardinalCommit::decode(&buf2)?;
+        } else {
+            MsgCardinal::decode(&buf1)?;
+            MsgCardinal::decode(&buf2)?;
+        }
+        m1.begin(&format!("Phase5 send {} to BD1", step));
+        send_tracked(s1, &buf2, m1)?;
+        m1.end();
+        m2.begin(&format!("Phase5 send {} to BD2", step));
+        send_tracked(s2, &buf1, m2)?;
+        m2.end();
+    }
+    println!("[Serveur] Phase 5 terminée — engagements et ouvertures des cardinaux relayés.");
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Connexion sortante vers le port retour d'un BD (réessaie
+// tant que le BD n'a pas ouvert son listener)
+// ─────────────────────────────────────────────────────────
+fn connect_retry(addr: &str) -> TcpStream {
+    loop {
+        match TcpStream::connect(addr) {
+            Ok(s)  => return s,
+            Err(_) => thread::sleep(std::time::Duration::from_millis(100)),
+        }
+    }
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 2 (multi-clés) : lire le FtBundle sous pk_self
+// ─────────────────────────────────────────────────────────
+fn recv_mk_bundle(
+    stream: &mut TcpStream,
+    label:  &str,
+    meter:  &mut BandwidthMeter,
+) -> io::Result<FtBundle> {
+    meter.begin(&format!("Phase2 recv {}", label));
+    let buf = recv_tracked(stream, meter)?;
+    meter.end();
+    let msg    = MsgFtBundle::decode(&buf)?;
+    let bundle = FtBundle { ft_by_pos: msg.entries.into_iter().collect() };
+    println!(
+        "[Serveur] {} Phase 2 : {} positions reçues (multi-clés)",
+        label, bundle.ft_by_pos.len()
+    );
+    Ok(bundle)
+}
+
+// ─────────────────────────────────────────────────────────
+// Phases 2 → 4 en mode multi-clés
+// ─────────────────────────────────────────────────────────
+fn run_multikey(
+    data1:      &Arc<Mutex<BdData>>,
+    data2:      &Arc<Mutex<BdData>>,
+    meter1:     &Arc<Mutex<BandwidthMeter>>,
+    meter2:     &Arc<Mutex<BandwidthMeter>>,
+    table_bits: u32,
+) -> io::Result<()> {
+    // ── Phase 2 : réception des FtBundles ────────────────────────────
+    println!("[Serveur] Phase 2 : réception des bundles multi-clés...");
+    let mut handles = Vec::new();
+    for (data, meter, label) in [(data1, meter1, "BD1"), (data2, meter2, "BD2")] {
+        let (d, m) = (Arc::clone(data), Arc::clone(meter));
+        handles.push(thread::spawn(move || {
+            let mut d = d.lock().unwrap();
+            let stream = d.stream.as_mut().expect("stream BD manquant");
+            let bundle = recv_mk_bundle(stream, label, &mut m.lock().unwrap())
+                .expect("recv_mk_bundle échoué");
+            let positions: HashSet<usize> = bundle.ft_by_pos.keys().copied().collect();
+            d.table     = Some(SparseTable { active: positions });
+            d.mk_bundle = Some(bundle);
+        }));
+    }
+    for h in handles {
+        h.join().expect("thread Phase2 multi-clés panique");
+    }
+    println!("[Serveur] Phase 2 terminée.");
+
+    // ── Phase 3 : CF.Mul multi-clés ──────────────────────────────────
+    println!("[Serveur] Phase 3 : CF.Mul multi-clés...");
+    let t_p3 = Instant::now();
+    let quads = {
+        let d1 = data1.lock().unwrap();
+        let d2 = data2.lock().unwrap();
+        report_cross_collisions(
+            d1.table.as_ref().expect("table1 manquante"),
+            d2.table.as_ref().expect("table2 manquante"),
+            table_bits,
+        );
+        phase3_server_compute_mk(
+            d1.table.as_ref().expect("table1 manquante"),
+            d2.table.as_ref().expect("table2 manquante"),
+            d1.mk_bundle.as_ref().expect("bundle1 manquant"),
+            d2.mk_bundle.as_ref().expect("bundle2 manquant"),
+            d1.pk.as_ref().expect("pk1 manquante"),
+            d2.pk.as_ref().expect("pk2 manquante"),
+        )
+    };
+    println!("[Serveur] Phase 3 en {:.3?} — {} produits", t_p3.elapsed(), quads.len());
+
+    // ── Phase 3 : envoi à BD1 ────────────────────────────────────────
+    let mut m1 = meter1.lock().unwrap();
+    let mut m2 = meter2.lock().unwrap();
+
+    let mut s1 = connect_retry("127.0.0.1:7003");
+    m1.begin("Phase3 send BD1");
+    let payload = MsgMkQuads { quads }.encode();
+    send_tracked(&mut s1, &payload, &mut m1)?;
+    m1.end();
+    println!("[Serveur] BD1 Phase 3 : {:.1} Ko envoyés", payload.len() as f64 / 1024.0);
+
+    // ── Phase 4 : relais des parts chiffrées ─────────────────────────
+    // Le serveur ne voit que des chiffrés sous la clé du destinataire.
+    m1.begin("Phase4 relay BD1→BD2");
+    let relay = recv_tracked(&mut s1, &mut m1)?;
+    m1.end();
+
+    let mut s2 = connect_retry("127.0.0.1:7004");
+    m2.begin("Phase4 relay BD1→BD2");
+    send_tracked(&mut s2, &relay, &mut m2)?;
+    let share = recv_tracked(&mut s2, &mut m2)?;
+    m2.end();
+
+    m1.begin("Phase4 relay BD2→BD1");
+    send_tracked(&mut s1, &share, &mut m1)?;
+    m1.end();
+    println!("[Serveur] Phase 4 : parts relayées.");
+
+    println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+    m1.report();
+    println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+    m2.report();
+
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phases 0c → 3 en mode KEA
+// ─────────────────────────────────────────────────────────
+fn run_kea(
+    data1:  &Arc<Mutex<BdData>>,
+    data2:  &Arc<Mutex<BdData>>,
+    meter1: &Arc<Mutex<BandwidthMeter>>,
+    meter2: &Arc<Mutex<BandwidthMeter>>,
+) -> io::Result<()> {
+    let mut d1 = data1.lock().unwrap();
+    let mut d2 = data2.lock().unwrap();
+    let mut m1 = meter1.lock().unwrap();
+    let mut m2 = meter2.lock().unwrap();
+
+    // ── Phase 0c : échange croisé des ct_delta ───────────────────────
+    for (d, m, label) in [(&mut d1, &mut m1, "BD1"), (&mut d2, &mut m2, "BD2")] {
+        m.begin(&format!("Phase0c recv ct_delta {}", label));
+        let buf = recv_tracked(d.stream.as_mut().expect("stream BD manquant"), m)?;
+        m.end();
+        d.kea_delta = Some(MsgKeaDelta::decode(&buf)?.ct_delta);
+    }
+    let delta1 = d1.kea_delta.clone().expect("ct_delta1 manquant");
+    let delta2 = d2.kea_delta.clone().expect("ct_delta2 manquant");
+    for (d, m, label, delta_other) in [
+        (&mut d1, &mut m1, "BD1", &delta2),
+        (&mut d2, &mut m2, "BD2", &delta1),
+    ] {
+        m.begin(&format!("Phase0c send ct_delta_other to {}", label));
+        let payload = MsgKeaDelta { ct_delta: delta_other.clone() }.encode();
+        send_tracked(d.stream.as_mut().expect("stream BD manquant"), &payload, m)?;
+        m.end();
+    }
+    println!("[Serveur] Phase 0c terminée — ct_delta croisés envoyés.");
+
+    // ── Phase 0d : parts de clé de hachage ───────────────────────────
+    let table_bits = relay_hash_key_shares(&mut d1, &mut d2, &mut m1, &mut m2)?;
+
+    // ── Phase 2 : réception des DualKeaFtBundles ─────────────────────
+    for (d, m, label) in [(&mut d1, &mut m1, "BD1"), (&mut d2, &mut m2, "BD2")] {
+        m.begin(&format!("Phase2 recv {}", label));
+        let buf = recv_tracked(d.stream.as_mut().expect("stream BD manquant"), m)?;
+        m.end();
+        let msg = MsgKeaDualBundle::decode(&buf)?;
+        let bundle = DualKeaFtBundle {
+            under_pk1: KeaFtBundle { ft_by_pos: msg.under_pk1.entries.into_iter().collect() },
+            under_pk2: KeaFtBundle { ft_by_pos: msg.under_pk2.entries.into_iter().collect() },
+        };
+        println!(
+            "[Serveur] {} Phase 2 : {} positions KEA reçues",
+            label, bundle.under_pk1.ft_by_pos.len()
+        );
+        let positions: HashSet<usize> = bundle.under_pk1.ft_by_pos.keys().copied().collect();
+        d.table      = Some(SparseTable { active: positions });
+        d.kea_bundle = Some(bundle);
+    }
+    println!("[Serveur] Phase 2 terminée.");
+
+    // ── Phase 3 : CF.Mul KEA ─────────────────────────────────────────
+    report_cross_collisions(
+        d1.table.as_ref().expect("table1 manquante"),
+        d2.table.as_ref().expect("table2 manquante"),
+        table_bits,
+    );
+    let t_p3 = Instant::now();
+    let (agg1, agg2) = phase3_server_compute_kea(
+        d1.table.as_ref().expect("table1 manquante"),
+        d2.table.as_ref().expect("table2 manquante"),
+        d1.kea_bundle.as_ref().expect("bundle1 manquant"),
+        d2.kea_bundle.as_ref().expect("bundle2 manquant"),
+        d1.pk.as_ref().expect("pk1 manquante"), &delta1,
+        d2.pk.as_ref().expect("pk2 manquante"), &delta2,
+    );
+    println!(
+        "[Serveur] Phase 3 en {:.3?} — {} triplets KEA pk1, {} triplets KEA pk2",
+        t_p3.elapsed(), agg1.len(), agg2.len()
+    );
+
+    for (addr, m, label, triplets) in [
+        ("127.0.0.1:7003", &mut m1, "BD1", agg1),
+        ("127.0.0.1:7004", &mut m2, "BD2", agg2),
+    ] {
+        let mut s = connect_retry(addr);
+        m.begin(&format!("Phase3 send {}", label));
+        let payload = MsgKeaTriplets { triplets }.encode();
+        send_tracked(&mut s, &payload, m)?;
+        m.end();
+        println!("[Serveur] {} Phase 3 : {:.1} Ko envoyés", label, payload.len() as f64 / 1024.0);
+    }
+    relay_cardinals(&mut d1, &mut d2, &mut m1, &mut m2)?;
+
+    println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+    m1.report();
+    println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+    m2.report();
+
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phases 0e → 3 en mode cuckoo
+// ─────────────────────────────────────────────────────────
+fn run_cuckoo(
+    data1:  &Arc<Mutex<BdData>>,
+    data2:  &Arc<Mutex<BdData>>,
+    meter1: &Arc<Mutex<BandwidthMeter>>,
+    meter2: &Arc<Mutex<BandwidthMeter>>,
+) -> io::Result<()> {
+    let mut d1 = data1.lock().unwrap();
+    let mut d2 = data2.lock().unwrap();
+    let mut m1 = meter1.lock().unwrap();
+    let mut m2 = meter2.lock().unwrap();
+
+    // ── Phase 0e : relais des tailles d'ensemble ─────────────────────
+    let mut sizes = Vec::with_capacity(2);
+    for (d, m, label) in [(&mut d1, &mut m1, "BD1"), (&mut d2, &mut m2, "BD2")] {
+        m.begin(&format!("Phase0e recv size {}", label));
+        let buf = recv_tracked(d.stream.as_mut().expect("stream BD manquant"), m)?;
+        m.end();
+        sizes.push((MsgCuckooSize::decode(&buf)?, buf));
+    }
+    let (size2, buf2) = sizes.pop().expect("taille BD2 manquante");
+    let (size1, buf1) = sizes.pop().expect("taille BD1 manquante");
+    for (d, m, label, buf) in [(&mut d1, &mut m1, "BD1", &buf2), (&mut d2, &mut m2, "BD2", &buf1)] {
+        m.begin(&format!("Phase0e send size to {}", label));
+        send_tracked(d.stream.as_mut().expect("stream BD manquant"), buf, m)?;
+        m.end();
+    }
+    // Chaque BD abandonne elle-même si k ou le stash diffèrent
+    if size1.hashes != size2.hashes || size1.stash != size2.stash {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Phase 0e : paramètres cuckoo différents (k {} / {}, stash {} / {})",
+            size1.hashes, size2.hashes, size1.stash, size2.stash
+        )));
+    }
+    let params = CuckooParams::new(
+        size1.size as usize, size2.size as usize, size1.hashes as usize, size1.stash as usize,
+    ).map_err(io::Error::other)?;
+    println!(
+        "[Serveur] Phase 0e terminée — n1 = {}, n2 = {} : {} bins x {}, stash {}.",
+        params.n1, params.n2, params.bins, params.bin_size, params.stash
+    );
+
+    // ── Phase 2 : réception des Ft des slots ─────────────────────────
+    for (d, m, label) in [(&mut d1, &mut m1, "BD1"), (&mut d2, &mut m2, "BD2")] {
+        let bundle = recv_bundle(d.stream.as_mut().expect("stream BD manquant"), label, m)?;
+        d.bundle = Some(bundle);
+    }
+    println!("[Serveur] Phase 2 terminée.");
+
+    // ── Phase 3 : comparaisons r·(Ft1 - Ft2) ─────────────────────────
+    let t_p3 = Instant::now();
+    let (agg1, agg2) = phase3_server_compute_cuckoo(
+        &params,
+        d1.bundle.as_ref().expect("bundle1 manquant"),
+        d2.bundle.as_ref().expect("bundle2 manquant"),
+        d1.pk.as_ref().expect("pk1 manquante"),
+        d2.pk.as_ref().expect("pk2 manquante"),
+    ).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    println!(
+        "[Serveur] Phase 3 en {:.3?} — {} comparaisons pk1, {} comparaisons pk2",
+        t_p3.elapsed(), agg1.len(), agg2.len()
+    );
+
+    for (addr, m, label, cts) in [
+        ("127.0.0.1:7003", &mut m1, "BD1", agg1),
+        ("127.0.0.1:7004", &mut m2, "BD2", agg2),
+    ] {
+        let mut s = connect_retry(addr);
+        m.begin(&format!("Phase3 send {}", label));
+        let payload = MsgFtBundle { entries: cts.into_iter().enumerate().collect() }.encode();
+        send_tracked(&mut s, &payload, m)?;
+        m.end();
+        println!("[Serveur] {} Phase 3 : {:.1} Ko envoyés", label, payload.len() as f64 / 1024.0);
+    }
+
+    println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+    m1.report();
+    println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+    m2.report();
+
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 0e (--similarity / --minhash) : relais des tailles
+//
+// Une taille cachée est chiffrée sous la pk du destinataire :
+// le serveur ne vérifie que la cohérence de k.
+// ─────────────────────────────────────────────────────────
+fn relay_set_sizes(
+    d1: &mut BdData,
+    d2: &mut BdData,
+    m1: &mut BandwidthMeter,
+    m2: &mut BandwidthMeter,
+) -> io::Result<u32> {
+    let mut sizes = Vec::with_capacity(2);
+    for (d, m, label) in [(&mut *d1, &mut *m1, "BD1"), (&mut *d2, &mut *m2, "BD2")] {
+        m.begin(&format!("Phase0e recv size {}", label));
+        let buf = recv_tracked(d.stream.as_mut().expect("stream BD manquant"), m)?;
+        m.end();
+        sizes.push((MsgSetSize::decode(&buf)?, buf));
+    }
+    let (size2, buf2) = sizes.pop().expect("taille BD2 manquante");
+    let (size1, buf1) = sizes.pop().expect("taille BD1 manquante");
+    for (d, m, label, buf) in [(d1, m1, "BD1", &buf2), (d2, m2, "BD2", &buf1)] {
+        m.begin(&format!("Phase0e send size to {}", label));
+        send_tracked(d.stream.as_mut().expect("stream BD manquant"), buf, m)?;
+        m.end();
+    }
+    // Chaque BD abandonne elle-même si k diffère
+    if size1.minhash_k != size2.minhash_k {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Phase 0e : k MinHash différent (k {} / {})", size1.minhash_k, size2.minhash_k
+        )));
+    }
+    let shown = |s: &MsgSetSize| if s.hidden { "cachée".to_string() } else { s.size.to_string() };
+    println!("[Serveur] Phase 0e terminée — tailles relayées (BD1 : {}, BD2 : {}).", shown(&size1), shown(&size2));
+    Ok(size1.minhash_k)
+}
+
+fn run_minhash(
+    data1:  &Arc<Mutex<BdData>>,
+    data2:  &Arc<Mutex<BdData>>,
+    meter1: &Arc<Mutex<BandwidthMeter>>,
+    meter2: &Arc<Mutex<BandwidthMeter>>,
+) -> io::Result<()> {
+    let mut d1 = data1.lock().unwrap();
+    let mut d2 = data2.lock().unwrap();
+    let mut m1 = meter1.lock().unwrap();
+    let mut m2 = meter2.lock().unwrap();
+
+    // ── Phase 0e : tailles et k ──────────────────────────────────────
+    let k = relay_set_sizes(&mut d1, &mut d2, &mut m1, &mut m2)? as usize;
+    check_minhash_k(k).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+    // ── Phase 2 : réception des Ft des composantes ───────────────────
+    for (d, m, label) in [(&mut d1, &mut m1, "BD1"), (&mut d2, &mut m2, "BD2")] {
+        let bundle = recv_bundle(d.stream.as_mut().expect("stream BD manquant"), label, m)?;
+        d.bundle = Some(bundle);
+    }
+    println!("[Serveur] Phase 2 terminée.");
+
+    // ── Phase 3 : comparaisons r·(Ft1_i - Ft2_i) ─────────────────────
+    let t_p3 = Instant::now();
+    let (agg1, agg2) = phase3_server_compute_minhash(
+        k,
+        d1.bundle.as_ref().expect("bundle1 manquant"),
+        d2.bundle.as_ref().expect("bundle2 manquant"),
+        d1.pk.as_ref().expect("pk1 manquante"),
+        d2.pk.as_ref().expect("pk2 manquante"),
+    ).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    println!("[Serveur] Phase 3 en {:.3?} — {} comparaisons par clé", t_p3.elapsed(), k);
+
+    for (addr, m, label, cts) in [
+        ("127.0.0.1:7003", &mut m1, "BD1", agg1),
+        ("127.0.0.1:7004", &mut m2, "BD2", agg2),
+    ] {
+        let mut s = connect_retry(addr);
+        m.begin(&format!("Phase3 send {}", label));
+        let payload = MsgFtBundle { entries: cts.into_iter().enumerate().collect() }.encode();
+        send_tracked(&mut s, &payload, m)?;
+        m.end();
+        println!("[Serveur] {} Phase 3 : {:.1} Ko envoyés", label, payload.len() as f64 / 1024.0);
+    }
+
+    println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+    m1.report();
+    println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+    m2.report();
+
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Mode --delta : une époque de la session persistante
+// ─────────────────────────────────────────────────────────
+fn run_delta(
+    data1:      &Arc<Mutex<BdData>>,
+    data2:      &Arc<Mutex<BdData>>,
+    meter1:     &Arc<Mutex<BandwidthMeter>>,
+    meter2:     &Arc<Mutex<BandwidthMeter>>,
+    state_path: &str,
+) -> io::Result<()> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let mut d1 = data1.lock().unwrap();
+    let mut d2 = data2.lock().unwrap();
+    let mut m1 = meter1.lock().unwrap();
+    let mut m2 = meter2.lock().unwrap();
+    let pk1 = d1.pk.clone().expect("pk1 manquante");
+    let pk2 = d2.pk.clone().expect("pk2 manquante");
+
+    // ── Phase 0c : session existante (pk vérifiées) ou nouvelle ──────
+    let existing = if std::path::Path::new(state_path).exists() {
+        let state = DeltaState::load(state_path).map_err(to_io)?;
+        if state.pk1.n != pk1.n || state.pk2.n != pk2.n {
+            return Err(invalid(format!(
+                "Phase 0c : pk différentes de celles de la session {:016x} (clés régénérées ?)", state.session_id
+            )));
+        }
+        Some(state)
+    } else {
+        None
+    };
+    let announce = MsgDeltaSession {
+        session_id: existing.as_ref().map_or_else(new_session_id, |s| s.session_id),
+        epoch:      existing.as_ref().map_or(0, |s| s.epoch),
+        fresh:      existing.is_none(),
+    };
+    for (d, m, label) in [(&mut d1, &mut m1, "BD1"), (&mut d2, &mut m2, "BD2")] {
+        m.begin(&format!("Phase0c send session {}", label));
+        send_tracked(d.stream.as_mut().expect("stream BD manquant"), &announce.encode(), m)?;
+        m.end();
+    }
+    let mut state = match existing {
+        Some(s) => s,
+        None => {
+            let table_bits = relay_hash_key_shares(&mut d1, &mut d2, &mut m1, &mut m2)?;
+            DeltaState::new(announce.session_id, pk1, pk2, table_bits)
+        }
+    };
+    println!(
+        "[Serveur] Phase 0c : session {:016x}, époque {} ({}).",
+        state.session_id, state.epoch, if announce.fresh { "création" } else { "reprise" }
+    );
+
+    // ── Phase 2 : différences des deux BD ────────────────────────────
+    for (bd, d, m, label) in [(1u8, &mut d1, &mut m1, "BD1"), (2, &mut d2, &mut m2, "BD2")] {
+        m.begin(&format!("Phase2 recv delta {}", label));
+        let msg = MsgDelta::decode(&recv_tracked(d.stream.as_mut().expect("stream BD manquant"), m)?)?;
+        m.end();
+        let added = DualFtBundle {
+            under_pk1: FtBundle { ft_by_pos: msg.added.under_pk1.entries.into_iter().collect() },
+            under_pk2: FtBundle { ft_by_pos: msg.added.under_pk2.entries.into_iter().collect() },
+        };
+        let stats = state.apply_delta(bd, &msg.removed, &added).map_err(to_io)?;
+        println!(
+            "[Serveur] {} Phase 2 : +{} / -{} positions ({} produit(s) retiré(s) de Enc(c)), {} actives.",
+            label, stats.added, stats.removed, stats.products_dropped, state.active(bd)
+        );
+    }
+
+    // ── Phase 3 : nouveaux produits, masques convertis par chaque BD ─
+    let pending = state.pending_products();
+    let (t1, t2) = state.compute_products(&pending).map_err(to_io)?;
+    let mut links = [
+        (connect_retry("127.0.0.1:7003"), "BD1", &mut m1, &t1),
+        (connect_retry("127.0.0.1:7004"), "BD2", &mut m2, &t2),
+    ];
+    let mut mask_products = Vec::with_capacity(2);
+    for (s, label, m, triplets) in links.iter_mut() {
+        let (order, pairs) = phase3_delta_mask_pairs(triplets);
+        m.begin(&format!("Phase3 masks {}", label));
+        send_tracked(s, &MsgMaskPairs { pairs }.encode(), m)?;
+        let shuffled = MsgCtList::decode(&recv_tracked(s, m)?)?.cts;
+        m.end();
+        mask_products.push(phase3_delta_unshuffle(&order, shuffled).map_err(to_io)?);
+    }
+    state.absorb_products(&pending, [&t1, &t2], [&mask_products[0], &mask_products[1]]).map_err(to_io)?;
+
+    // ── Phase 4 : état enregistré AVANT l'envoi des résultats ────────
+    state.epoch += 1;
+    state.save(state_path).map_err(to_io)?;
+    println!(
+        "[Serveur] Phase 4 : époque {} enregistrée dans {} ({} positions communes).",
+        state.epoch, state_path, state.common()
+    );
+    for (k, (s, label, m, _)) in (1u8..).zip(links.iter_mut()) {
+        let total = state.encrypted_total(k).map_err(to_io)?;
+        m.begin(&format!("Phase4 send {}", label));
+        send_tracked(s, &MsgDeltaResult { epoch: state.epoch, total }.encode(), m)?;
+        m.end();
+    }
+
+    println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+    m1.report();
+    println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+    m2.report();
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Mode --checkpoint : mode standard, étapes persistées dans `dir`
+// ─────────────────────────────────────────────────────────
+fn run_checkpoint(
+    data1:  &Arc<Mutex<BdData>>,
+    data2:  &Arc<Mutex<BdData>>,
+    meter1: &Arc<Mutex<BandwidthMeter>>,
+    meter2: &Arc<Mutex<BandwidthMeter>>,
+    dir:    &str,
+) -> io::Result<()> {
+    let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let mut d1 = data1.lock().unwrap();
+    let mut d2 = data2.lock().unwrap();
+    let mut m1 = meter1.lock().unwrap();
+    let mut m2 = meter2.lock().unwrap();
+    let pk1 = d1.pk.clone().expect("pk1 manquante");
+    let pk2 = d2.pk.clone().expect("pk2 manquante");
+
+    // ── Phase 0c : reprise ou nouvelle session ───────────────────────
+    let mut cp = ServerCheckpoint::open(dir, &pk1, &pk2).map_err(to_io)?;
+    let mut claimed = [0u64; 2];
+    for (c, d, m, label) in [(0, &mut d1, &mut m1, "BD1"), (1, &mut d2, &mut m2, "BD2")] {
+        m.begin(&format!("Phase0c recv session {}", label));
+        claimed[c] = MsgResume::decode(&recv_tracked(d.stream.as_mut().expect("stream BD manquant"), m)?)?.session_id;
+        m.end();
+    }
+    let resume = cp.session_id().filter(|&id| claimed == [id, id]);
+    let session_id = resume.unwrap_or_else(new_session_id);
+    for (bd, d, m) in [(1u8, &mut d1, &mut m1), (2, &mut d2, &mut m2)] {
+        let plan = MsgResumePlan {
+            session_id,
+            fresh:       resume.is_none(),
+            send_bundle: resume.is_none() || !cp.has_bundle(bd),
+        };
+        m.begin(&format!("Phase0c send plan BD{}", bd));
+        send_tracked(d.stream.as_mut().expect("stream BD manquant"), &plan.encode(), m)?;
+        m.end();
+    }
+    let table_bits = match resume {
+        Some(_) => {
+            let (done, total) = cp.phase3_progress().unwrap_or((0, 0));
+            println!(
+                "[Serveur] Phase 0c : reprise de la session {:016x} (bundles BD1/BD2 : {}/{}, Phase 3 : {}/{} lots).",
+                session_id, cp.has_bundle(1), cp.has_bundle(2), done, total
+            );
+            cp.table_bits().expect("session reprise")
+        }
+        None => {
+            println!(
+                "[Serveur] Phase 0c : nouvelle session {:016x} (BD : {:016x} / {:016x}, serveur : {}).",
+                session_id, claimed[0], claimed[1], cp.session_id().map_or("aucune".into(), |id| format!("{:016x}", id))
+            );
+            let table_bits = relay_hash_key_shares(&mut d1, &mut d2, &mut m1, &mut m2)?;
+            cp.start(session_id, &pk1, &pk2, table_bits, CHECKPOINT_BATCH).map_err(to_io)?;
+            table_bits
+        }
+    };
+
+    // ── Phase 2 : bundles manquants, enregistrés dès réception ───────
+    let mut bundles = Vec::with_capacity(2);
+    for (bd, d, m, label) in [(1u8, &mut d1, &mut m1, "BD1"), (2, &mut d2, &mut m2, "BD2")] {
+        if cp.has_bundle(bd) {
+            let b = cp.load_bundle(bd, &pk1, &pk2).map_err(to_io)?;
+            println!("[Serveur] {} Phase 2 : bundle relu depuis {} ({} positions).", label, dir, b.under_pk1.ft_by_pos.len());
+            bundles.push(b);
+        } else {
+            let b = recv_bundle(d.stream.as_mut().expect("stream BD manquant"), label, m)?;
+            cp.save_bundle(bd, &b, &pk1, &pk2).map_err(to_io)?;
+            bundles.push(b);
+        }
+    }
+    let tables: Vec<SparseTable> = bundles.iter()
+        .map(|b| SparseTable { active: b.under_pk1.ft_by_pos.keys().copied().collect() })
+        .collect();
+    report_cross_collisions(&tables[0], &tables[1], table_bits);
+
+    // ── Phase 3 : lots persistés, puis envoi ─────────────────────────
+    let t_p3 = Instant::now();
+    let (agg1, agg2) = cp.phase3(&bundles[0], &bundles[1], &pk1, &pk2).map_err(to_io)?;
+    println!("[Serveur] Phase 3 en {:.3?} — {} triplets par clé", t_p3.elapsed(), agg1.len());
+    send_triplets(connect_retry("127.0.0.1:7003"), "BD1", &agg1, &mut m1)?;
+    send_triplets(connect_retry("127.0.0.1:7004"), "BD2", &agg2, &mut m2)?;
+    relay_cardinals(&mut d1, &mut d2, &mut m1, &mut m2)?;
+
+    println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+    m1.report();
+    println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+    m2.report();
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Mode --stream : jointure des flux triés, mémoire bornée
+// ─────────────────────────────────────────────────────────
+fn recv_stream_lot(
+    stream: &mut TcpStream,
+    meter:  &mut BandwidthMeter,
+) -> Result<Option<Vec<StreamEntry>>, paillier_crypto::CryptoError> {
+    let net = |e: io::Error| paillier_crypto::CryptoError::InvalidInput(format!("réseau : {}", e));
+    let msg = MsgDualBundle::decode(&recv_tracked(stream, meter).map_err(net)?).map_err(net)?;
+    if msg.under_pk1.entries.is_empty() {
+        return Ok(None);
+    }
+    if msg.under_pk1.entries.len() != msg.under_pk2.entries.len()
+        || msg.under_pk1.entries.iter().zip(&msg.under_pk2.entries).any(|(a, b)| a.0 != b.0)
+    {
+        return Err(paillier_crypto::CryptoError::InvalidInput(
+            "lot : positions différentes sous pk1 et pk2".into(),
+        ));
+    }
+    Ok(Some(
+        msg.under_pk1.entries.into_iter()
+            .zip(msg.under_pk2.entries)
+            .map(|((pos, ft1), (_, ft2))| (pos, ft1, ft2))
+            .collect(),
+    ))
+}
+
+fn run_stream(
+    data1:  &Arc<Mutex<BdData>>,
+    data2:  &Arc<Mutex<BdData>>,
+    meter1: &Arc<Mutex<BandwidthMeter>>,
+    meter2: &Arc<Mutex<BandwidthMeter>>,
+    chunk:  usize,
+) -> io::Result<()> {
+    let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let mut d1 = data1.lock().unwrap();
+    let mut d2 = data2.lock().unwrap();
+    let mut m1 = meter1.lock().unwrap();
+    let mut m2 = meter2.lock().unwrap();
+    let pk1 = d1.pk.clone().expect("pk1 manquante");
+    let pk2 = d2.pk.clone().expect("pk2 manquante");
+
+    // ── Phases 2-3 : lots reçus et joints au fil de l'eau ────────────
+    println!("[Serveur] Phase 2 : réception des flux triés...");
+    let t_p3 = Instant::now();
+    m1.begin("Phase2 stream BD1");
+    m2.begin("Phase2 stream BD2");
+    let join = {
+        let (s1, s2) = (d1.stream.as_mut().expect("stream BD1 manquant"), d2.stream.as_mut().expect("stream BD2 manquant"));
+        let (mm1, mm2) = (&mut *m1, &mut *m2);
+        phase3_stream_join(|| recv_stream_lot(s1, mm1), || recv_stream_lot(s2, mm2), &pk1, &pk2, chunk)
+            .map_err(to_io)?
+    };
+    m1.end();
+    m2.end();
+    println!(
+        "[Serveur] Phase 3 en {:.3?} — {} / {} positions, {} commune(s)",
+        t_p3.elapsed(), join.active[0], join.active[1], join.common
+    );
+
+    // ── Phase 3 : masques par lots, puis Π C0 ────────────────────────
+    let [alpha1, alpha2] = join.alpha;
+    let [masks1, masks2] = join.masks;
+    for (addr, m, label, masks, alpha) in [
+        ("127.0.0.1:7003", &mut m1, "BD1", masks1, alpha1),
+        ("127.0.0.1:7004", &mut m2, "BD2", masks2, alpha2),
+    ] {
+        let mut s = connect_retry(addr);
+        m.begin(&format!("Phase3 send {}", label));
+        let total = masks.len();
+        for lot in masks.drain(chunk).map_err(to_io)? {
+            send_tracked(&mut s, &MsgMaskPairs { pairs: lot.map_err(to_io)? }.encode(), m)?;
+        }
+        send_tracked(&mut s, &MsgMaskPairs { pairs: Vec::new() }.encode(), m)?;
+        send_tracked(&mut s, &MsgMkShare { share: alpha }.encode(), m)?;
+        m.end();
+        println!("[Serveur] {} Phase 3 : {} paires de masques envoyées par lots de {}", label, total, chunk);
+    }
+
+    println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+    m1.report();
+    println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+    m2.report();
+    Ok(())
+}
+
+fn run_fuzzy(
+    data1:  &Arc<Mutex<BdData>>,
+    data2:  &Arc<Mutex<BdData>>,
+    meter1: &Arc<Mutex<BandwidthMeter>>,
+    meter2: &Arc<Mutex<BandwidthMeter>>,
+) -> io::Result<()> {
+    let mut d1 = data1.lock().unwrap();
+    let mut d2 = data2.lock().unwrap();
+    let mut m1 = meter1.lock().unwrap();
+    let mut m2 = meter2.lock().unwrap();
+
+    // ── Phase 0e : relais des paramètres CLK ─────────────────────────
+    let mut params = Vec::with_capacity(2);
+    for (d, m, label) in [(&mut d1, &mut m1, "BD1"), (&mut d2, &mut m2, "BD2")] {
+        m.begin(&format!("Phase0e recv CLK {}", label));
+        let buf = recv_tracked(d.stream.as_mut().expect("stream BD manquant"), m)?;
+        m.end();
+        params.push((MsgClkParams::decode(&buf)?, buf));
+    }
+    let (p2, buf2) = params.pop().expect("paramètres BD2 manquants");
+    let (p1, buf1) = params.pop().expect("paramètres BD1 manquants");
+    for (d, m, label, buf) in [(&mut d1, &mut m1, "BD1", &buf2), (&mut d2, &mut m2, "BD2", &buf1)] {
+        m.begin(&format!("Phase0e send CLK to {}", label));
+        send_tracked(d.stream.as_mut().expect("stream BD manquant"), buf, m)?;
+        m.end();
+    }
+    // Chaque BD abandonne elle-même si L ou k diffèrent
+    if p1.bits != p2.bits || p1.hashes != p2.hashes {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Phase 0e : paramètres CLK différents (L {} / {}, k {} / {})",
+            p1.bits, p2.bits, p1.hashes, p2.hashes
+        )));
+    }
+    let clk = ClkParams::new(p1.bits as usize, p1.hashes as usize).map_err(io::Error::other)?;
+    let records = (p1.records as usize, p2.records as usize);
+    println!(
+        "[Serveur] Phase 0e terminée — {} x {} enregistrements, CLK {} bits (k={}).",
+        records.0, records.1, clk.bits, clk.hashes
+    );
+
+    // ── Phase 2 : réception des Ft des bits CLK ──────────────────────
+    for (d, m, label) in [(&mut d1, &mut m1, "BD1"), (&mut d2, &mut m2, "BD2")] {
+        let bundle = recv_bundle(d.stream.as_mut().expect("stream BD manquant"), label, m)?;
+        d.bundle = Some(bundle);
+    }
+    println!("[Serveur] Phase 2 terminée.");
+
+    // ── Phase 3 : scores chiffrés de toutes les paires ───────────────
+    let t_p3 = Instant::now();
+    let (agg1, agg2) = phase3_server_compute_fuzzy(
+        clk,
+        records,
+        d1.bundle.as_ref().expect("bundle1 manquant"),
+        d2.bundle.as_ref().expect("bundle2 manquant"),
+        d1.pk.as_ref().expect("pk1 manquante"),
+        d2.pk.as_ref().expect("pk2 manquante"),
+    ).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    println!("[Serveur] Phase 3 en {:.3?} — {} scores par clé", t_p3.elapsed(), agg1.len());
+
+    for (addr, m, label, scores) in [
+        ("127.0.0.1:7003", &mut m1, "BD1", agg1),
+        ("127.0.0.1:7004", &mut m2, "BD2", agg2),
+    ] {
+        let mut s = connect_retry(addr);
+        m.begin(&format!("Phase3 send {}", label));
+        let payload = MsgClkScores { scores }.encode();
+        send_tracked(&mut s, &payload, m)?;
+        m.end();
+        println!("[Serveur] {} Phase 3 : {:.1} Ko envoyés", label, payload.len() as f64 / 1024.0);
+    }
+
+    println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+    m1.report();
+    println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+    m2.report();
+
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phases 3-4 à seuil pour un BD (connexion retour) :
+// masques → Enc(Σ b b') → Enc(z) → bits chiffrés → comparaison
+// ─────────────────────────────────────────────────────────
+fn run_threshold_round(
+    addr:      &str,
+    label:     &str,
+    agg:       &CfSndSum,
+    threshold: u64,
+    pk:        &PublicKey,
+    meter:     &mut BandwidthMeter,
+) -> io::Result<()> {
+    let mut s = connect_retry(addr);
+    meter.begin(&format!("Phase3 send masks {}", label));
+    let payload = MsgMaskPairs { pairs: agg.betas.clone() }.encode();
+    send_tracked(&mut s, &payload, meter)?;
+    meter.end();
+    println!("[Serveur] {} Phase 3 : {} paires de masques envoyées ({:.1} Ko)",
+        label, agg.betas.len(), payload.len() as f64 / 1024.0);
+
+    meter.begin(&format!("Phase4 threshold {}", label));
+    let enc_b = MsgMkShare::decode(&recv_tracked(&mut s, meter)?)?.share;
+    let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+    let (z, blind) = phase4_threshold_blind(&agg.alpha, &enc_b, threshold, pk).map_err(to_io)?;
+    send_tracked(&mut s, &MsgThresholdResult { threshold, z }.encode(), meter)?;
+    let enc_bits = MsgCtList::decode(&recv_tracked(&mut s, meter)?)?.cts;
+    let (cts, hint) = phase4_threshold_compare(&enc_bits, &blind, pk).map_err(to_io)?;
+    send_tracked(&mut s, &MsgThresholdCmp { hint, cts }.encode(), meter)?;
+    meter.end();
+    println!("[Serveur] {} Phase 4 : comparaison bit à bit au seuil {} envoyée.", label, threshold);
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 3 (--sum, multiset product) pour un BD : somme agrégée
+// ─────────────────────────────────────────────────────────
+fn send_snd_sum(addr: &str, label: &str, agg: &CfSndSum, meter: &mut BandwidthMeter) -> io::Result<()> {
+    let mut s = connect_retry(addr);
+    meter.begin(&format!("Phase3 send sum {}", label));
+    let payload = MsgSndSum { sum: agg.clone() }.encode();
+    send_tracked(&mut s, &payload, meter)?;
+    meter.end();
+    println!("[Serveur] {} Phase 3 : somme agrégée, {} paires de masques ({:.1} Ko)",
+        label, agg.betas.len(), payload.len() as f64 / 1024.0);
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 0e (--setops) : chaque BD annonce ses sorties, le serveur
+// renvoie les siennes ; chaque BD compare de son côté
+// ─────────────────────────────────────────────────────────
+fn relay_setops(
+    d1:  &mut BdData,
+    d2:  &mut BdData,
+    m1:  &mut BandwidthMeter,
+    m2:  &mut BandwidthMeter,
+    ops: SetOps,
+) -> io::Result<()> {
+    let mut claimed = Vec::with_capacity(2);
+    for (d, m, label) in [(&mut *d1, &mut *m1, "BD1"), (&mut *d2, &mut *m2, "BD2")] {
+        let s = d.stream.as_mut().expect("stream BD manquant");
+        m.begin(&format!("Phase0e setops {}", label));
+        let own = MsgSetOps::decode(&recv_tracked(s, m)?)?.ops;
+        send_tracked(s, &MsgSetOps { ops: ops.bits() }.encode(), m)?;
+        m.end();
+        claimed.push((label, own));
+    }
+    for (label, own) in claimed {
+        if own != ops.bits() {
+            let shown = SetOps::from_bits(own).map(|o| o.to_string()).unwrap_or_else(|_| format!("{:#04x}", own));
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+                "Phase 0e : {} demande --setops {}, le serveur applique {}", label, shown, ops
+            )));
+        }
+    }
+    println!("[Serveur] Phase 0e terminée — sorties ensemblistes convenues : {}.", ops);
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 0e (--multiset) : sémantique relayée d'un BD à l'autre,
+// chaque BD comparant de son côté
+// ─────────────────────────────────────────────────────────
+fn relay_multiset(
+    d1: &mut BdData,
+    d2: &mut BdData,
+    m1: &mut BandwidthMeter,
+    m2: &mut BandwidthMeter,
+) -> io::Result<MultisetSemantics> {
+    let mut bufs = Vec::with_capacity(2);
+    for (d, m, label) in [(&mut *d1, &mut *m1, "BD1"), (&mut *d2, &mut *m2, "BD2")] {
+        m.begin(&format!("Phase0e recv multiset {}", label));
+        bufs.push(recv_tracked(d.stream.as_mut().expect("stream BD manquant"), m)?);
+        m.end();
+    }
+    for (d, m, label, buf) in [(d1, m1, "BD1", &bufs[1]), (d2, m2, "BD2", &bufs[0])] {
+        m.begin(&format!("Phase0e send multiset to {}", label));
+        send_tracked(d.stream.as_mut().expect("stream BD manquant"), buf, m)?;
+        m.end();
+    }
+    let (ms1, ms2) = (MsgMultiset::decode(&bufs[0])?, MsgMultiset::decode(&bufs[1])?);
+    let shown = |m: &MsgMultiset| MultisetSemantics::from_code(m.semantics)
+        .map(|s| s.name().to_string())
+        .unwrap_or_else(|_| format!("code {}", m.semantics));
+    if (ms1.semantics, ms1.max_count) != (ms2.semantics, ms2.max_count) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!(
+            "Phase 0e : multiset différent (BD1 {} ≤ {}, BD2 {} ≤ {})",
+            shown(&ms1), ms1.max_count, shown(&ms2), ms2.max_count
+        )));
+    }
+    let semantics = MultisetSemantics::from_code(ms1.semantics)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    println!("[Serveur] Phase 0e terminée — multiensembles, sémantique {}.", semantics.name());
+    Ok(semantics)
+}
+
+// ─────────────────────────────────────────────────────────
+// Phase 3 (--setops) pour un BD : masques puis α_o
+// ─────────────────────────────────────────────────────────
+fn send_setops(addr: &str, label: &str, agg: &SetOpsAgg, meter: &mut BandwidthMeter) -> io::Result<()> {
+    let mut s = connect_retry(addr);
+    meter.begin(&format!("Phase3 send setops {}", label));
+    let masks = MsgMaskPairs { pairs: agg.betas.clone() }.encode();
+    send_tracked(&mut s, &masks, meter)?;
+    send_tracked(&mut s, &MsgSetOpsResult { ops: agg.ops.bits(), alphas: agg.alphas.clone() }.encode(), meter)?;
+    meter.end();
+    println!("[Serveur] {} Phase 3 : {} paires de masques + {} résultat(s) ({}) envoyés ({:.1} Ko)",
+        label, agg.betas.len(), agg.alphas.len(), agg.ops, masks.len() as f64 / 1024.0);
+    Ok(())
+}
+
+// ─────────────────────────────────────────────────────────
+// Phases 3-4 différentiellement privées (les deux BD à la fois) :
+// masques → (Enc(Σ b b'), parts de bruit) → Enc(c + bruit)
+// ─────────────────────────────────────────────────────────
+fn run_dp_rounds(
+    sums:    [&CfSndSum; 2],
+    pks:     [&PublicKey; 2],
+    epsilon: f64,
+    meters:  [&mut BandwidthMeter; 2],
+) -> io::Result<()> {
+    let invalid = |msg: String| io::Error::new(io::ErrorKind::InvalidData, msg);
+    let [m1, m2] = meters;
+    let mut links = [
+        (connect_retry("127.0.0.1:7003"), "BD1", m1),
+        (connect_retry("127.0.0.1:7004"), "BD2", m2),
+    ];
+    for ((s, label, meter), agg) in links.iter_mut().zip(sums) {
+        meter.begin(&format!("Phase3 send masks {}", label));
+        let payload = MsgMaskPairs { pairs: agg.betas.clone() }.encode();
+        send_tracked(s, &payload, meter)?;
+        meter.end();
+        println!("[Serveur] {} Phase 3 : {} paires de masques envoyées ({:.1} Ko)",
+            label, agg.betas.len(), payload.len() as f64 / 1024.0);
+    }
+
+    // Les parts de bruit des deux BD sont nécessaires aux deux résultats
+    let mut shares = Vec::with_capacity(2);
+    for (s, label, meter) in links.iter_mut() {
+        meter.begin(&format!("Phase4 dp shares {}", label));
+        let share = MsgDpShare::decode(&recv_tracked(s, meter)?)?;
+        meter.end();
+        if share.epsilon.to_bits() != epsilon.to_bits() {
+            return Err(invalid(format!(
+                "Phase 4 : {} annonce epsilon {} (serveur : --dp {})", label, share.epsilon, epsilon
+            )));
+        }
+        shares.push(share);
+    }
+
+    // Part du serveur : une seule valeur, chiffrée sous pk1 puis pk2
+    let server_noise = sample_discrete_laplace(epsilon, &mut OsRng);
+    for (i, (s, label, meter)) in links.iter_mut().enumerate() {
+        let noises: Vec<_> = shares.iter()
+            .map(|sh| if i == 0 { sh.noise1.clone() } else { sh.noise2.clone() })
+            .collect();
+        let noisy = phase4_dp_combine(&sums[i].alpha, &shares[i].mask_sum, &noises, server_noise, pks[i])
+            .map_err(|e| invalid(e.to_string()))?;
+        meter.begin(&format!("Phase4 dp result {}", label));
+        send_tracked(s, &MsgDpResult { epsilon, noisy }.encode(), meter)?;
+        meter.end();
+        println!("[Serveur] {} Phase 4 : cardinal bruité (epsilon = {}) envoyé.", label, epsilon);
+    }
+    Ok(())
+}
+
 // ─────────────────────────────────────────────────────────
 // main
 // ─────────────────────────────────────────────────────────
 fn main() -> io::Result<()> {
+    const USAGE: &str = "Usage : server [--multikey | --kea | --cuckoo | --fuzzy | --minhash | --delta <état.json> | --stream [--chunk <N>] | --checkpoint <dir>] [--size-hiding [--pad-to <P>]] [--similarity] [--threshold <t> | --dp <epsilon> | --reveal | --setops <a-b,b-a,union,inter> | --sum] [--multiset] [--threads <N>]
+  --kea : contrôle d'image KEA ; le serveur connaît ct_delta, ce n'est pas un calcul vérifiable
+  --dp : bruit face aux BD uniquement, le serveur voit le cardinal exact";
+    let args: Vec<String> = env::args().collect();
+    let multikey = args.iter().any(|a| a == "--multikey");
+    let kea      = args.iter().any(|a| a == "--kea");
+    let cuckoo    = args.iter().any(|a| a == "--cuckoo");
+    let fuzzy     = args.iter().any(|a| a == "--fuzzy");
+    let minhash   = args.iter().any(|a| a == "--minhash");
+    let similarity = args.iter().any(|a| a == "--similarity");
+    let delta: Option<&str> = match args.iter().position(|a| a == "--delta") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .map(String::as_str)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?,
+        ),
+        None => None,
+    };
+    let checkpoint: Option<&str> = match args.iter().position(|a| a == "--checkpoint") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .map(String::as_str)
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?,
+        ),
+        None => None,
+    };
+    let stream    = args.iter().any(|a| a == "--stream");
+    let chunk: usize = match args.iter().position(|a| a == "--chunk") {
+        Some(i) => args.get(i + 1)
+            .and_then(|v| v.parse().ok())
+            .and_then(|c| check_stream_chunk(c).ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?,
+        None => DEFAULT_STREAM_CHUNK,
+    };
+    let reveal    = args.iter().any(|a| a == "--reveal");
+    let size_hiding = args.iter().any(|a| a == "--size-hiding");
+    let pad_to: Option<usize> = match args.iter().position(|a| a == "--pad-to") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?,
+        ),
+        None => None,
+    };
+    let threshold: Option<u64> = match args.iter().position(|a| a == "--threshold") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .and_then(|v| v.parse().ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?,
+        ),
+        None => None,
+    };
+    let dp: Option<f64> = match args.iter().position(|a| a == "--dp") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .and_then(|v| v.parse().ok())
+                .and_then(|e| check_epsilon(e).ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?,
+        ),
+        None => None,
+    };
+    let setops: Option<SetOps> = match args.iter().position(|a| a == "--setops") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .and_then(|v| SetOps::parse(v).ok())
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?,
+        ),
+        None => None,
+    };
+    let multiset = args.iter().any(|a| a == "--multiset");
+    let sum      = args.iter().any(|a| a == "--sum");
+    if [multikey, kea, cuckoo, fuzzy, minhash, delta.is_some(), stream, checkpoint.is_some()].iter().filter(|&&f| f).count() > 1
+        || ((delta.is_some() || stream || checkpoint.is_some()) && (size_hiding || similarity || reveal || threshold.is_some() || dp.is_some()))
+        || (size_hiding && (multikey || kea || fuzzy || minhash))
+        || (pad_to.is_some() != (size_hiding && !cuckoo))
+        || ((threshold.is_some() || dp.is_some() || reveal) && (multikey || kea || cuckoo || fuzzy || minhash))
+        || (similarity && (multikey || kea || cuckoo || fuzzy || minhash || threshold.is_some() || dp.is_some()))
+        || [threshold.is_some(), dp.is_some(), reveal, setops.is_some()].iter().filter(|&&f| f).count() > 1
+        || (setops.is_some() && (!size_hiding || cuckoo || similarity))
+        || (multiset && (multikey || kea || cuckoo || fuzzy || minhash || delta.is_some() || stream || checkpoint.is_some()
+            || size_hiding || similarity || reveal || threshold.is_some() || dp.is_some() || setops.is_some()))
+        || (sum && (multikey || kea || cuckoo || fuzzy || minhash || delta.is_some() || stream || checkpoint.is_some()
+            || size_hiding || similarity || reveal || threshold.is_some() || dp.is_some() || setops.is_some() || multiset))
+    {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, USAGE));
+    }
+    if let Some(i) = args.iter().position(|a| a == "--threads") {
+        let n: usize = args.get(i + 1)
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, USAGE))?;
+        set_default_threads(n);
+    }
+
     println!("\n╔══════════════════════════════════════════════════════╗");
     println!("║   SERVEUR PSI — Moteur de Calculs                    ║");
     println!("║   BD1→:7001  BD2→:7002  retour→:7003/:7004           ║");
     println!("╚══════════════════════════════════════════════════════╝\n");
+    println!("[Serveur] Pool de calcul : {} thread(s).", default_pool().threads());
 
     let meter1: Arc<Mutex<BandwidthMeter>> = Arc::new(Mutex::new(BandwidthMeter::new()));
     let meter2: Arc<Mutex<BandwidthMeter>> = Arc::new(Mutex::new(BandwidthMeter::new()));
@@ -199,6 +1473,73 @@ fn main() -> io::Result<()> {
     }
     println!("[Serveur] Phase 0b terminée — pk croisées envoyées.");
 
+    if kea {
+        return run_kea(&data1, &data2, &meter1, &meter2);
+    }
+    if let Some(path) = delta {
+        return run_delta(&data1, &data2, &meter1, &meter2, path);
+    }
+    if let Some(dir) = checkpoint {
+        return run_checkpoint(&data1, &data2, &meter1, &meter2, dir);
+    }
+
+    // ── Phase 0d : parts de clé de hachage ───────────────────────────
+    let table_bits = relay_hash_key_shares(
+        &mut data1.lock().unwrap(),
+        &mut data2.lock().unwrap(),
+        &mut meter1.lock().unwrap(),
+        &mut meter2.lock().unwrap(),
+    )?;
+
+    if multikey {
+        return run_multikey(&data1, &data2, &meter1, &meter2, table_bits);
+    }
+    if cuckoo {
+        return run_cuckoo(&data1, &data2, &meter1, &meter2);
+    }
+    if fuzzy {
+        return run_fuzzy(&data1, &data2, &meter1, &meter2);
+    }
+    if minhash {
+        return run_minhash(&data1, &data2, &meter1, &meter2);
+    }
+    if stream {
+        return run_stream(&data1, &data2, &meter1, &meter2, chunk);
+    }
+
+    // ── Phase 0e (--similarity) : tailles d'ensemble ─────────────────
+    if similarity {
+        relay_set_sizes(
+            &mut data1.lock().unwrap(),
+            &mut data2.lock().unwrap(),
+            &mut meter1.lock().unwrap(),
+            &mut meter2.lock().unwrap(),
+        )?;
+    }
+
+    // ── Phase 0e (--multiset) : même sémantique des deux côtés ───────
+    let multiset_product = if multiset {
+        relay_multiset(
+            &mut data1.lock().unwrap(),
+            &mut data2.lock().unwrap(),
+            &mut meter1.lock().unwrap(),
+            &mut meter2.lock().unwrap(),
+        )? == MultisetSemantics::Product
+    } else {
+        false
+    };
+
+    // ── Phase 0e (--setops) : accord sur les sorties révélées ────────
+    if let Some(ops) = setops {
+        relay_setops(
+            &mut data1.lock().unwrap(),
+            &mut data2.lock().unwrap(),
+            &mut meter1.lock().unwrap(),
+            &mut meter2.lock().unwrap(),
+            ops,
+        )?;
+    }
+
     // ── Phase 2 : réception des DualFtBundles ────────────────────────
     println!("[Serveur] Phase 2 : réception des bundles...");
     {
@@ -231,9 +1572,22 @@ fn main() -> io::Result<()> {
     // ── Phase 3 : CF.Mul ─────────────────────────────────────────────
     println!("[Serveur] Phase 3 : CF.Mul...");
     let t_p3 = Instant::now();
-    let (agg1, agg2) = {
+    let (common, agg1, agg2) = {
         let d1 = data1.lock().unwrap();
         let d2 = data2.lock().unwrap();
+        if let Some(p) = pad_to {
+            // Un bundle plus court trahirait la taille de la base
+            for (label, d) in [("BD1", &d1), ("BD2", &d2)] {
+                check_padded_bundle(label, d.bundle.as_ref().expect("bundle manquant"), p)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            }
+            println!("[Serveur] Mode size-hiding : {} positions par BD, tailles masquées (positions communes visibles).", p);
+        }
+        report_cross_collisions(
+            d1.table.as_ref().expect("table1 manquante"),
+            d2.table.as_ref().expect("table2 manquante"),
+            table_bits,
+        );
         let dummy_sk = SecretKey {
             lambda: num_bigint::BigUint::zero(),
             mu:     num_bigint::BigUint::zero(),
@@ -246,7 +1600,7 @@ fn main() -> io::Result<()> {
             public_key: d2.pk.clone().expect("pk2 manquante"),
             secret_key: dummy_sk,
         };
-        phase3_server_compute(
+        phase3_server_compute_positions(
             d1.table.as_ref().expect("table1 manquante"),
             d2.table.as_ref().expect("table2 manquante"),
             d1.bundle.as_ref().expect("bundle1 manquant"),
@@ -259,6 +1613,133 @@ fn main() -> io::Result<()> {
         t_p3.elapsed(), agg1.len(), agg2.len()
     );
 
+    // ── Phase 3 (--setops) : α_o des seules sorties convenues ────────
+    if let Some(ops) = setops {
+        let (sum1, sum2) = {
+            let d1 = data1.lock().unwrap();
+            let d2 = data2.lock().unwrap();
+            let (b1, b2) = (d1.bundle.as_ref().expect("bundle1 manquant"), d2.bundle.as_ref().expect("bundle2 manquant"));
+            let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+            let p = pad_to.expect("--setops exige --pad-to");
+            (
+                phase3_server_setops(ops, &agg1, &b1.under_pk1, &b2.under_pk1, p, d1.pk.as_ref().expect("pk1 manquante")).map_err(to_io)?,
+                phase3_server_setops(ops, &agg2, &b1.under_pk2, &b2.under_pk2, p, d2.pk.as_ref().expect("pk2 manquante")).map_err(to_io)?,
+            )
+        };
+        let mut m1 = meter1.lock().unwrap();
+        let mut m2 = meter2.lock().unwrap();
+        send_setops("127.0.0.1:7003", "BD1", &sum1, &mut m1)?;
+        send_setops("127.0.0.1:7004", "BD2", &sum2, &mut m2)?;
+
+        println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+        m1.report();
+        println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+        m2.report();
+        return Ok(());
+    }
+
+    // ── Phases 3-4 à seuil : seul le bit de comparaison est révélé ───
+    if let Some(t) = threshold {
+        let (pk1, pk2, pad_to) = {
+            let d1 = data1.lock().unwrap();
+            let d2 = data2.lock().unwrap();
+            let n1 = d1.table.as_ref().expect("table1 manquante").len();
+            let n2 = d2.table.as_ref().expect("table2 manquante").len();
+            (d1.pk.clone().expect("pk1 manquante"), d2.pk.clone().expect("pk2 manquante"), n1.min(n2))
+        };
+        let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let sum1 = phase3_server_aggregate(&agg1, pad_to, &pk1).map_err(to_io)?;
+        let sum2 = phase3_server_aggregate(&agg2, pad_to, &pk2).map_err(to_io)?;
+
+        let mut m1 = meter1.lock().unwrap();
+        let mut m2 = meter2.lock().unwrap();
+        run_threshold_round("127.0.0.1:7003", "BD1", &sum1, t, &pk1, &mut m1)?;
+        run_threshold_round("127.0.0.1:7004", "BD2", &sum2, t, &pk2, &mut m2)?;
+
+        println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+        m1.report();
+        println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+        m2.report();
+        return Ok(());
+    }
+
+    // ── Phases 3-4 --dp : bruit ajouté sous chiffrement ──────────────
+    if let Some(eps) = dp {
+        let (pk1, pk2, pad_to) = {
+            let d1 = data1.lock().unwrap();
+            let d2 = data2.lock().unwrap();
+            let n1 = d1.table.as_ref().expect("table1 manquante").len();
+            let n2 = d2.table.as_ref().expect("table2 manquante").len();
+            (d1.pk.clone().expect("pk1 manquante"), d2.pk.clone().expect("pk2 manquante"), n1.min(n2))
+        };
+        let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let sum1 = phase3_server_aggregate(&agg1, pad_to, &pk1).map_err(to_io)?;
+        let sum2 = phase3_server_aggregate(&agg2, pad_to, &pk2).map_err(to_io)?;
+
+        let mut m1 = meter1.lock().unwrap();
+        let mut m2 = meter2.lock().unwrap();
+        run_dp_rounds([&sum1, &sum2], [&pk1, &pk2], eps, [&mut m1, &mut m2])?;
+
+        println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+        m1.report();
+        println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+        m2.report();
+        return Ok(());
+    }
+
+    // ── Phase 3 (--sum, multiset product) : seule la somme est ───────
+    //    déchiffrable ; en product, bourrage à min(|T1|, |T2|) pour
+    //    taire le nombre de clés communes
+    if sum || multiset_product {
+        let (pk1, pk2, pad_to) = {
+            let d1 = data1.lock().unwrap();
+            let d2 = data2.lock().unwrap();
+            let n1 = d1.table.as_ref().expect("table1 manquante").len();
+            let n2 = d2.table.as_ref().expect("table2 manquante").len();
+            let pad_to = if multiset_product { n1.min(n2) } else { 0 };
+            (d1.pk.clone().expect("pk1 manquante"), d2.pk.clone().expect("pk2 manquante"), pad_to)
+        };
+        let to_io = |e: paillier_crypto::CryptoError| io::Error::new(io::ErrorKind::InvalidData, e.to_string());
+        let sum1 = phase3_server_aggregate(&agg1, pad_to, &pk1).map_err(to_io)?;
+        let sum2 = phase3_server_aggregate(&agg2, pad_to, &pk2).map_err(to_io)?;
+        send_snd_sum("127.0.0.1:7003", "BD1", &sum1, &mut meter1.lock().unwrap())?;
+        send_snd_sum("127.0.0.1:7004", "BD2", &sum2, &mut meter2.lock().unwrap())?;
+        relay_cardinals(
+            &mut data1.lock().unwrap(), &mut data2.lock().unwrap(),
+            &mut meter1.lock().unwrap(), &mut meter2.lock().unwrap(),
+        )?;
+        println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+        meter1.lock().unwrap().report();
+        println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+        meter2.lock().unwrap().report();
+        return Ok(());
+    }
+
+    // ── Phase 3 (--reveal) : triplets positionnés ────────────────────
+    if reveal {
+        for (addr, meter, label, cts) in [
+            ("127.0.0.1:7003", &meter1, "BD1", agg1),
+            ("127.0.0.1:7004", &meter2, "BD2", agg2),
+        ] {
+            let mut m = meter.lock().unwrap();
+            let mut s = connect_retry(addr);
+            m.begin(&format!("Phase3 send {}", label));
+            let payload = MsgPosTriplets { entries: common.iter().copied().zip(cts).collect() }.encode();
+            send_tracked(&mut s, &payload, &mut m)?;
+            m.end();
+            println!("[Serveur] {} Phase 3 : {} triplets positionnés ({:.1} Ko)", label, common.len(), payload.len() as f64 / 1024.0);
+        }
+        relay_cardinals(
+            &mut data1.lock().unwrap(), &mut data2.lock().unwrap(),
+            &mut meter1.lock().unwrap(), &mut meter2.lock().unwrap(),
+        )?;
+        println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
+        meter1.lock().unwrap().report();
+        println!("[Serveur] ─── Rapport BD2 ↔ Serveur ───");
+        meter2.lock().unwrap().report();
+        return Ok(());
+    }
+
     // ── Phase 3 : envoi des résultats ─────────────────────────────────
     println!("[Serveur] Envoi → BD1:7003 | BD2:7004...");
     let agg1: Arc<Vec<CfSnd>> = Arc::new(agg1);
@@ -294,6 +1775,10 @@ fn main() -> io::Result<()> {
     });
     ts1.join().expect("thread send BD1 panique");
     ts2.join().expect("thread send BD2 panique");
+    relay_cardinals(
+        &mut data1.lock().unwrap(), &mut data2.lock().unwrap(),
+        &mut meter1.lock().unwrap(), &mut meter2.lock().unwrap(),
+    )?;
 
     println!("\n[Serveur] ─── Rapport BD1 ↔ Serveur ───");
     meter1.lock().unwrap().report();
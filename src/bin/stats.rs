@@ -0,0 +1,196 @@
+// =========================================================
+// stats.rs — Démonstration des statistiques homomorphes
+//
+// Usage :
+//   cargo run --bin stats -- [--csv-a <A.csv>] [--csv-b <B.csv>]
+//                            [--col-a NSS] [--col-b NSS] [--key NSS]
+//                            [--decimals 0] [--rows 100] [--bits 1024]
+//
+// BD1 chiffre la colonne col-a de csv-a, BD2 la colonne col-b
+// de csv-b. Les lignes sont appariées sur la colonne --key,
+// présente dans les deux fichiers (jointure interne en clair,
+// première occurrence de chaque clé) : la covariance porte sur
+// les mêmes individus. Deux colonnes des mêmes enregistrements :
+// même fichier pour csv-a et csv-b, --key identification.
+// Le Serveur agrège sous CF, le détenteur de clé déchiffre
+// uniquement les agrégats. Les statistiques en clair sont
+// affichées pour comparaison.
+// =========================================================
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::time::Instant;
+
+use paillier_crypto::cf_stats::{
+    FixedPoint, ColumnStats,
+    load_keyed_column_from_csv, stats_encrypt_column,
+    stats_server_aggregate_column, stats_server_cross, stats_decrypt_pair,
+};
+use paillier_crypto::exactmatch::phase0_keygen;
+
+const USAGE: &str = "Usage : stats [--csv-a <A.csv>] [--csv-b <B.csv>] [--col-a <col>] [--col-b <col>] [--key <col>] \
+                     [--decimals <d>] [--rows <N>] [--bits <bits>]";
+
+const DEFAULT_CSV_A: &str = "src/base de donnes/base_A_1000.csv";
+const DEFAULT_CSV_B: &str = "src/base de donnes/base_B_1000.csv";
+
+struct Args {
+    csv_a:    String,
+    csv_b:    String,
+    col_a:    String,
+    col_b:    String,
+    key:      String,
+    decimals: u32,
+    rows:     usize,
+    bits:     u64,
+}
+
+fn parse_args() -> Result<Args, String> {
+    let mut a = Args {
+        csv_a:    DEFAULT_CSV_A.into(),
+        csv_b:    DEFAULT_CSV_B.into(),
+        col_a:    "NSS".into(),
+        col_b:    "NSS".into(),
+        key:      "NSS".into(),
+        decimals: 0,
+        rows:     100,
+        bits:     1024,
+    };
+
+    let argv: Vec<String> = env::args().skip(1).collect();
+    let mut it = argv.iter();
+    while let Some(flag) = it.next() {
+        let val = it.next().ok_or_else(|| format!("{} : valeur manquante\n{}", flag, USAGE))?;
+        let bad = |_| format!("{} : valeur invalide '{}'\n{}", flag, val, USAGE);
+        match flag.as_str() {
+            "--csv-a"    => a.csv_a    = val.clone(),
+            "--csv-b"    => a.csv_b    = val.clone(),
+            "--col-a"    => a.col_a    = val.clone(),
+            "--col-b"    => a.col_b    = val.clone(),
+            "--key"      => a.key      = val.clone(),
+            "--decimals" => a.decimals = val.parse().map_err(bad)?,
+            "--rows"     => a.rows     = val.parse().map_err(bad)?,
+            "--bits"     => a.bits     = val.parse().map_err(bad)?,
+            _            => return Err(format!("option inconnue '{}'\n{}", flag, USAGE)),
+        }
+    }
+    Ok(a)
+}
+
+// ─────────────────────────────────────────────────────────
+// Jointure sur la clé — (x, y) des individus présents des deux
+// côtés, dans l'ordre de A ; une clé répétée n'est prise qu'une fois
+// ─────────────────────────────────────────────────────────
+
+fn join_on_key(a: &[(String, String)], b: &[(String, String)]) -> (Vec<String>, Vec<String>) {
+    let mut by_key: HashMap<&str, &str> = HashMap::new();
+    for (k, v) in b {
+        by_key.entry(k.as_str()).or_insert(v.as_str());
+    }
+    let mut seen = HashSet::new();
+    a.iter()
+        .filter(|(k, _)| seen.insert(k.as_str()))
+        .filter_map(|(k, x)| by_key.get(k.as_str()).map(|y| (x.clone(), y.to_string())))
+        .unzip()
+}
+
+// ─────────────────────────────────────────────────────────
+// Référence en clair (f64) — uniquement pour la comparaison
+// ─────────────────────────────────────────────────────────
+
+fn plain_stats(xs: &[f64], ys: &[f64]) -> (f64, f64, f64, f64, f64, f64) {
+    let n  = xs.len() as f64;
+    let mx = xs.iter().sum::<f64>() / n;
+    let my = ys.iter().sum::<f64>() / n;
+    let vx = xs.iter().map(|x| (x - mx).powi(2)).sum::<f64>() / (n - 1.0);
+    let vy = ys.iter().map(|y| (y - my).powi(2)).sum::<f64>() / (n - 1.0);
+    let cv = xs.iter().zip(ys).map(|(x, y)| (x - mx) * (y - my)).sum::<f64>() / (n - 1.0);
+    (mx, my, vx, vy, cv, cv / (vx.sqrt() * vy.sqrt()))
+}
+
+fn print_column(label: &str, s: &ColumnStats, plain_mean: f64, plain_var: f64) {
+    println!("  {} (N = {})", label, s.count);
+    println!("    somme      : {:.6e}", s.sum);
+    println!("    moyenne    : {:.6e}   (clair : {:.6e})", s.mean, plain_mean);
+    println!("    variance   : {:.6e}   (clair : {:.6e})", s.variance, plain_var);
+    println!("    écart-type : {:.6e}", s.std_dev);
+}
+
+fn main() {
+    let args = match parse_args() {
+        Ok(a)  => a,
+        Err(e) => { eprintln!("{}", e); std::process::exit(2); }
+    };
+
+    println!("=================================================");
+    println!("  Statistiques homomorphes — Catalano-Fiore");
+    println!("=================================================");
+
+    // ── Chargement des colonnes ──────────────────────────
+    let load = |path: &str, col: &str| match load_keyed_column_from_csv(path, &args.key, col) {
+        Ok(v)  => v,
+        Err(e) => { eprintln!("[ERREUR] {}", e); std::process::exit(1); }
+    };
+    let (mut xs, mut ys) = join_on_key(&load(&args.csv_a, &args.col_a), &load(&args.csv_b, &args.col_b));
+
+    let rows = args.rows.min(xs.len()).min(ys.len());
+    if rows < 2 {
+        eprintln!("[ERREUR] au moins 2 lignes appariées sur '{}' sont nécessaires (trouvé {})", args.key, rows);
+        std::process::exit(1);
+    }
+    xs.truncate(rows);
+    ys.truncate(rows);
+    println!("  BD1 : {}[{}]  |  BD2 : {}[{}]  |  {} lignes appariées sur {}",
+        args.csv_a, args.col_a, args.csv_b, args.col_b, rows, args.key);
+
+    let fp = FixedPoint::new(args.decimals);
+    let t  = Instant::now();
+
+    let result = (|| {
+        // ── Détenteur de clé ─────────────────────────────
+        let kp = phase0_keygen("Détenteur", args.bits);
+        let pk = &kp.public_key;
+
+        // ── BD1 / BD2 ────────────────────────────────────
+        let ex = stats_encrypt_column("BD1", &xs, fp, pk)?;
+        let ey = stats_encrypt_column("BD2", &ys, fp, pk)?;
+
+        // ── Serveur ──────────────────────────────────────
+        let ax    = stats_server_aggregate_column("BD1", &ex, pk)?;
+        let ay    = stats_server_aggregate_column("BD2", &ey, pk)?;
+        let cross = stats_server_cross(&ex, &ey, pk)?;
+
+        // ── Détenteur de clé ─────────────────────────────
+        stats_decrypt_pair(&ax, &ay, &cross, fp, &kp)
+    })();
+
+    let st = match result {
+        Ok(s)  => s,
+        Err(e) => { eprintln!("[ERREUR] {}", e); std::process::exit(1); }
+    };
+
+    // Même exigence que stats_encrypt_column : une valeur illisible est une erreur
+    let parse = |label: &str, vs: &[String]| -> Vec<f64> {
+        vs.iter()
+            .map(|v| v.parse().unwrap_or_else(|_| {
+                eprintln!("[ERREUR] {} : valeur non numérique '{}'", label, v);
+                std::process::exit(1);
+            }))
+            .collect()
+    };
+    let (fx, fy) = (parse("BD1", &xs), parse("BD2", &ys));
+    let (mx, my, vx, vy, cv, r) = plain_stats(&fx, &fy);
+
+    println!("\n=================================================");
+    println!("  RÉSULTATS (déchiffrés par le détenteur de clé)");
+    println!("=================================================");
+    print_column(&format!("BD1.{}", args.col_a), &st.x, mx, vx);
+    print_column(&format!("BD2.{}", args.col_b), &st.y, my, vy);
+    println!("  Covariance   : {:.6e}   (clair : {:.6e})", st.covariance, cv);
+    match st.pearson {
+        Some(p) => println!("  Pearson r    : {:.6}   (clair : {:.6})", p, r),
+        None    => println!("  Pearson r    : indéfini (colonne constante)"),
+    }
+    println!("  Durée totale : {:.3?}", t.elapsed());
+    println!("=================================================");
+}
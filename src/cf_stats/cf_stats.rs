@@ -0,0 +1,495 @@
+// =========================================================
+// cf_stats — Statistiques homomorphes sur Catalano-Fiore
+//
+// Deux bases (BD1, BD2) détiennent chacune une colonne numérique.
+// On calcule, sans révéler les valeurs individuelles :
+//   - somme, moyenne, variance de chaque colonne
+//   - covariance et corrélation de Pearson entre les deux colonnes
+//
+// Rôles :
+//   Détenteur de clé (K) : génère (pk, sk), déchiffre les agrégats
+//   BD1 / BD2            : encodent (virgule fixe) et chiffrent sous pk
+//   Évaluateur (Serveur) : cf_add pour Σx, cf_mul pour Σx², Σy², Σxy
+//
+// Hypothèse : K et l'Évaluateur ne collusionnent pas. K ne voit que les
+// agrégats et les masques b_i (aléatoires, indépendants des données) ;
+// l'Évaluateur ne voit que a_i = m_i - b_i (uniformes dans Z_n).
+//
+// ─── Somme de produits en Seconde Forme ──────────────────
+// cf_mul renvoie (C0, C1, C2) avec
+//   C0 = Enc(m·m' - b·b'),  C1 = Enc(b),  C2 = Enc(b')
+// Une somme de N produits conserve :
+//   α     = Π C0_i = Enc(Σ m_i·m'_i - Σ b_i·b'_i)
+//   betas = [(C1_i, C2_i)]
+// et se déchiffre en Dec(α) + Σ Dec(C1_i)·Dec(C2_i) mod n.
+// K n'apprend donc que la somme, jamais un produit isolé.
+//
+// ─── Virgule fixe signée ─────────────────────────────────
+// v ↦ round(v · 10^d) mod n  (négatifs : n - |x|)
+// Un produit porte l'échelle 10^{2d}. Au déchiffrement,
+// m > n/2 est interprété comme négatif. Contrainte : toute
+// somme réelle doit rester dans ]-n/2, n/2[.
+//
+// Les variances et covariances sont calculées en ENTIERS
+// (N·Σx² − (Σx)²) avant la conversion en f64 : pas
+// d'annulation catastrophique sur des valeurs comme les NSS.
+// =========================================================
+
+use std::collections::HashMap;
+use std::io;
+use std::time::Instant;
+use num_bigint::{BigInt, BigUint, RandBigInt, Sign};
+use num_traits::{One, Zero, ToPrimitive};
+use rand_core::OsRng;
+
+use crate::fiore_catalano::cf_encrypt::cf_encrypt::cf_encrypt;
+use crate::fiore_catalano::cf_add::cf_add::cf_add;
+use crate::fiore_catalano::cf_add::cf_add_dec::cf_add_dec;
+use crate::fiore_catalano::cf_mul::cf_mul::cf_mul;
+use crate::karatsuba_mul::karatsuba_mul::fast_mul;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_keygen::PublicKey;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ---------------------------------------------------------
+// Types
+// ---------------------------------------------------------
+
+/// Première Forme CF : (m - b mod n, Enc(b))
+pub type CfFst = (BigUint, BigUint);
+
+/// Somme de produits CF en Seconde Forme (cf. en-tête)
+#[derive(Clone, Debug)]
+pub struct CfSndSum {
+    pub alpha: BigUint,
+    pub betas: Vec<(BigUint, BigUint)>,
+}
+
+/// Agrégats chiffrés d'une colonne, calculés par l'Évaluateur
+#[derive(Clone, Debug)]
+pub struct EncColumnAgg {
+    pub count:  usize,
+    pub sum:    CfFst,
+    pub sum_sq: CfSndSum,
+}
+
+/// Statistiques déchiffrées d'une colonne
+#[derive(Clone, Debug)]
+pub struct ColumnStats {
+    pub count:    usize,
+    pub sum:      f64,
+    pub mean:     f64,
+    /// Variance d'échantillon (dénominateur N - 1)
+    pub variance: f64,
+    pub std_dev:  f64,
+}
+
+/// Statistiques croisées déchiffrées
+#[derive(Clone, Debug)]
+pub struct PairStats {
+    pub x:          ColumnStats,
+    pub y:          ColumnStats,
+    /// Covariance d'échantillon (dénominateur N - 1)
+    pub covariance: f64,
+    /// None si l'une des colonnes est constante
+    pub pearson:    Option<f64>,
+}
+
+// ---------------------------------------------------------
+// Encodage virgule fixe
+// ---------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FixedPoint {
+    pub decimals: u32,
+}
+
+impl FixedPoint {
+    pub fn new(decimals: u32) -> Self {
+        FixedPoint { decimals }
+    }
+
+    fn scale(&self) -> BigInt {
+        BigInt::from(10u32).pow(self.decimals)
+    }
+
+    // -----------------------------------------------------
+    // Parse une valeur décimale ("-12.345", "622494716482810")
+    // sans passer par f64 : les NSS (15 chiffres) et leurs
+    // carrés ne tiennent pas exactement dans un f64.
+    // Les décimales en excès sont arrondies (demi vers le haut).
+    // -----------------------------------------------------
+    pub fn parse(&self, s: &str) -> Result<BigInt, CryptoError> {
+        let s = s.trim();
+        let (neg, digits) = match s.strip_prefix('-') {
+            Some(rest) => (true, rest),
+            None       => (false, s.strip_prefix('+').unwrap_or(s)),
+        };
+        let (int_part, frac_part) = digits.split_once('.').unwrap_or((digits, ""));
+
+        let is_digits = |p: &str| p.bytes().all(|c| c.is_ascii_digit());
+        if (int_part.is_empty() && frac_part.is_empty()) || !is_digits(int_part) || !is_digits(frac_part) {
+            return Err(CryptoError::InvalidInput(format!("valeur numérique invalide : '{}'", s)));
+        }
+
+        let d = self.decimals as usize;
+        let mut frac: String = frac_part.chars().take(d).collect();
+        while frac.len() < d {
+            frac.push('0');
+        }
+        let round_up = frac_part.as_bytes().get(d).is_some_and(|&c| c >= b'5');
+
+        let mut v: BigInt = format!("{}{}", int_part, frac)
+            .trim_start_matches('0')
+            .parse()
+            .unwrap_or_else(|_| BigInt::zero());
+        if round_up {
+            v += 1;
+        }
+        Ok(if neg { -v } else { v })
+    }
+
+    /// Entier signé → représentant dans Z_n
+    pub fn to_zn(x: &BigInt, n: &BigUint) -> Result<BigUint, CryptoError> {
+        let half = n >> 1;
+        if x.magnitude() >= &half {
+            return Err(CryptoError::MessageOutOfRange);
+        }
+        Ok(match x.sign() {
+            Sign::Minus => n - x.magnitude(),
+            _           => x.magnitude().clone(),
+        })
+    }
+
+    /// Représentant dans Z_n → entier signé (m > n/2 ⇒ négatif)
+    pub fn from_zn(m: &BigUint, n: &BigUint) -> BigInt {
+        if m > &(n >> 1) {
+            -BigInt::from(n - m)
+        } else {
+            BigInt::from(m.clone())
+        }
+    }
+
+    /// Entier à l'échelle 10^d → chaîne décimale exacte ("-12.340")
+    pub fn format(&self, x: &BigInt) -> String {
+        let digits = x.magnitude().to_string();
+        let d = self.decimals as usize;
+        let sign = if x.sign() == Sign::Minus { "-" } else { "" };
+        if d == 0 {
+            return format!("{}{}", sign, digits);
+        }
+        let padded = format!("{:0>width$}", digits, width = d + 1);
+        let (int_part, frac_part) = padded.split_at(padded.len() - d);
+        format!("{}{}.{}", sign, int_part, frac_part)
+    }
+
+    /// Entier à l'échelle 10^{power·d} → f64
+    pub fn to_f64(&self, x: &BigInt, power: u32) -> f64 {
+        ratio_f64(x, &self.scale().pow(power))
+    }
+}
+
+/// num / den en f64, sans dépassement sur les grands entiers
+fn ratio_f64(num: &BigInt, den: &BigInt) -> f64 {
+    let q = num / den;
+    let r = num % den;
+    q.to_f64().unwrap_or(f64::NAN)
+        + r.to_f64().unwrap_or(0.0) / den.to_f64().unwrap_or(f64::INFINITY)
+}
+
+// ---------------------------------------------------------
+// BD — Encodage et chiffrement d'une colonne
+// ---------------------------------------------------------
+
+pub fn stats_encrypt_column(
+    label:  &str,
+    values: &[String],
+    fp:     FixedPoint,
+    pk:     &PublicKey,
+) -> Result<Vec<CfFst>, CryptoError> {
+    println!("  [Stats] {} : chiffrement CF de {} valeurs (10^-{})...", label, values.len(), fp.decimals);
+    let t = Instant::now();
+    let mut rng = OsRng;
+
+    let cts = values
+        .iter()
+        .map(|v| {
+            let m = FixedPoint::to_zn(&fp.parse(v)?, &pk.n)?;
+            let b = rng.gen_biguint_below(&pk.n);
+            cf_encrypt(&m, &b, pk)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    println!("  [Stats] {} : colonne chiffrée en {:.3?}", label, t.elapsed());
+    Ok(cts)
+}
+
+// ---------------------------------------------------------
+// Évaluateur — Sommes homomorphes
+// ---------------------------------------------------------
+
+/// Σ m_i en Première Forme (cf_add successifs, départ CF(0, 0) = (0, 1))
+pub fn cf_sum(cts: &[CfFst], pk: &PublicKey) -> Result<CfFst, CryptoError> {
+    let mut acc = (BigUint::zero(), BigUint::one());
+    for ct in cts {
+        acc = cf_add(&acc, ct, &pk.n, &pk.n_squared)?;
+    }
+    Ok(acc)
+}
+
+/// Σ x_i·y_i en Seconde Forme (cf_mul position par position)
+pub fn cf_sum_products(xs: &[CfFst], ys: &[CfFst], pk: &PublicKey) -> Result<CfSndSum, CryptoError> {
+    if xs.len() != ys.len() {
+        return Err(CryptoError::InvalidInput(format!(
+            "cf_sum_products : colonnes de tailles différentes ({} / {})",
+            xs.len(), ys.len()
+        )));
+    }
+
+    let mut alpha = BigUint::one();
+    let mut betas = Vec::with_capacity(xs.len());
+    for (x, y) in xs.iter().zip(ys) {
+        let (c0, c1, c2) = cf_mul(x, y, pk)?;
+        alpha = fast_mul(&alpha, &c0, &pk.n_squared)?;
+        betas.push((c1, c2));
+    }
+    Ok(CfSndSum { alpha, betas })
+}
+
+pub fn stats_server_aggregate_column(
+    label: &str,
+    cts:   &[CfFst],
+    pk:    &PublicKey,
+) -> Result<EncColumnAgg, CryptoError> {
+    println!("  [Stats] Serveur : agrégation de {} ({} valeurs)...", label, cts.len());
+    let t = Instant::now();
+    let agg = EncColumnAgg {
+        count:  cts.len(),
+        sum:    cf_sum(cts, pk)?,
+        sum_sq: cf_sum_products(cts, cts, pk)?,
+    };
+    println!("  [Stats] Serveur : Σ et Σ² de {} en {:.3?}", label, t.elapsed());
+    Ok(agg)
+}
+
+pub fn stats_server_cross(xs: &[CfFst], ys: &[CfFst], pk: &PublicKey) -> Result<CfSndSum, CryptoError> {
+    println!("  [Stats] Serveur : produit croisé Σxy ({} paires)...", xs.len());
+    let t = Instant::now();
+    let cross = cf_sum_products(xs, ys, pk)?;
+    println!("  [Stats] Serveur : Σxy en {:.3?}", t.elapsed());
+    Ok(cross)
+}
+
+// ---------------------------------------------------------
+// Détenteur de clé — Déchiffrement des agrégats
+// ---------------------------------------------------------
+
+/// Dec(α) + Σ Dec(C1_i)·Dec(C2_i) mod n.
+/// Les masques sont mis en cache : pour Σx², C1_i = C2_i.
+pub fn cf_sum_products_dec(s: &CfSndSum, kp: &KeyPair) -> Result<BigUint, CryptoError> {
+    let (pk, sk) = (&kp.public_key, &kp.secret_key);
+    let mut cache: HashMap<&BigUint, BigUint> = HashMap::new();
+    for c in s.betas.iter().flat_map(|(c1, c2)| [c1, c2]) {
+        if !cache.contains_key(c) {
+            cache.insert(c, p_decrypt(c, pk, sk)?);
+        }
+    }
+
+    let mut acc = p_decrypt(&s.alpha, pk, sk)?;
+    for (c1, c2) in &s.betas {
+        acc = (acc + fast_mul(&cache[c1], &cache[c2], &pk.n)?) % &pk.n;
+    }
+    Ok(acc)
+}
+
+/// Sommes exactes (Σx à l'échelle 10^d, Σx² à l'échelle 10^{2d})
+fn decrypt_sums(agg: &EncColumnAgg, kp: &KeyPair) -> Result<(BigInt, BigInt), CryptoError> {
+    let n = &kp.public_key.n;
+    let sum    = cf_add_dec(&agg.sum, &kp.public_key, &kp.secret_key)?;
+    let sum_sq = cf_sum_products_dec(&agg.sum_sq, kp)?;
+    Ok((FixedPoint::from_zn(&sum, n), FixedPoint::from_zn(&sum_sq, n)))
+}
+
+/// (N·Σxy − Σx·Σy) / (N·(N−1)), numérateur calculé en entiers
+fn sample_moment(count: usize, sxy: &BigInt, sx: &BigInt, sy: &BigInt, fp: FixedPoint) -> (BigInt, f64) {
+    let n   = BigInt::from(count);
+    let num = &n * sxy - sx * sy;
+    let den = &n * (&n - 1) * fp.scale().pow(2);
+    let val = if count < 2 { f64::NAN } else { ratio_f64(&num, &den) };
+    (num, val)
+}
+
+fn column_stats(count: usize, sx: &BigInt, sxx: &BigInt, fp: FixedPoint) -> ColumnStats {
+    let sum  = fp.to_f64(sx, 1);
+    let mean = if count == 0 { f64::NAN } else { ratio_f64(sx, &(BigInt::from(count) * fp.scale())) };
+    let (_, variance) = sample_moment(count, sxx, sx, sx, fp);
+    ColumnStats { count, sum, mean, variance, std_dev: variance.sqrt() }
+}
+
+pub fn stats_decrypt_column(
+    label: &str,
+    agg:   &EncColumnAgg,
+    fp:    FixedPoint,
+    kp:    &KeyPair,
+) -> Result<ColumnStats, CryptoError> {
+    println!("  [Stats] Détenteur : déchiffrement des agrégats de {}...", label);
+    let (sx, sxx) = decrypt_sums(agg, kp)?;
+    Ok(column_stats(agg.count, &sx, &sxx, fp))
+}
+
+pub fn stats_decrypt_pair(
+    x_agg: &EncColumnAgg,
+    y_agg: &EncColumnAgg,
+    cross: &CfSndSum,
+    fp:    FixedPoint,
+    kp:    &KeyPair,
+) -> Result<PairStats, CryptoError> {
+    if x_agg.count != y_agg.count || cross.betas.len() != x_agg.count {
+        return Err(CryptoError::InvalidInput("stats_decrypt_pair : effectifs incohérents".into()));
+    }
+    println!("  [Stats] Détenteur : déchiffrement des agrégats croisés...");
+    let count      = x_agg.count;
+    let (sx, sxx)  = decrypt_sums(x_agg, kp)?;
+    let (sy, syy)  = decrypt_sums(y_agg, kp)?;
+    let sxy        = FixedPoint::from_zn(&cf_sum_products_dec(cross, kp)?, &kp.public_key.n);
+
+    let (num_xy, covariance) = sample_moment(count, &sxy, &sx, &sy, fp);
+    let (num_xx, _)          = sample_moment(count, &sxx, &sx, &sx, fp);
+    let (num_yy, _)          = sample_moment(count, &syy, &sy, &sy, fp);
+
+    // r = num_xy / √(num_xx·num_yy) : les dénominateurs communs s'annulent.
+    // Racine entière calculée sur le produit décalé de 2·64 bits pour
+    // conserver 64 bits de précision après troncature.
+    let pearson = if num_xx.is_zero() || num_yy.is_zero() {
+        None
+    } else {
+        let prod: BigUint = (&num_xx * &num_yy).to_biguint().unwrap_or_default() << 128;
+        let root = BigInt::from(prod.sqrt());
+        Some(ratio_f64(&(num_xy << 64), &root).clamp(-1.0, 1.0))
+    };
+
+    Ok(PairStats {
+        x: column_stats(count, &sx, &sxx, fp),
+        y: column_stats(count, &sy, &syy, fp),
+        covariance,
+        pearson,
+    })
+}
+
+// ---------------------------------------------------------
+// Chargement d'une colonne numérique depuis un CSV
+// (même format que load_nss_from_csv, colonne au choix)
+// ---------------------------------------------------------
+
+pub fn load_column_from_csv(path: &str, column: &str) -> io::Result<Vec<String>> {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = reader.lines();
+
+    let header = lines
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} : fichier vide", path)))??;
+    let col = header
+        .split(',')
+        .position(|c| c.trim() == column)
+        .ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} : colonne '{}' introuvable", path, column),
+        ))?;
+
+    let mut out = Vec::new();
+    for line in lines {
+        let line = line?;
+        if let Some(v) = line.split(',').nth(col).map(str::trim) {
+            if !v.is_empty() {
+                out.push(v.to_string());
+            }
+        }
+    }
+    Ok(out)
+}
+
+/// (clé, valeur) de chaque ligne où les deux colonnes sont renseignées,
+/// pour apparier deux bases sur une clé plutôt que par rang
+pub fn load_keyed_column_from_csv(path: &str, key: &str, column: &str) -> io::Result<Vec<(String, String)>> {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    let reader = BufReader::new(File::open(path)?);
+    let mut lines = reader.lines();
+
+    let header = lines
+        .next()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("{} : fichier vide", path)))??;
+    let find = |name: &str| header
+        .split(',')
+        .position(|c| c.trim() == name)
+        .ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("{} : colonne '{}' introuvable", path, name),
+        ));
+    let (kcol, vcol) = (find(key)?, find(column)?);
+
+    let mut out = Vec::new();
+    for line in lines {
+        let line = line?;
+        let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+        if let (Some(k), Some(v)) = (fields.get(kcol), fields.get(vcol)) {
+            if !k.is_empty() && !v.is_empty() {
+                out.push((k.to_string(), v.to_string()));
+            }
+        }
+    }
+    Ok(out)
+}
+
+// ---------------------------------------------------------
+// Tests — comparaison avec le calcul en clair
+// ---------------------------------------------------------
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    #[test]
+    fn test_fixed_point_parse() {
+        let fp = FixedPoint::new(2);
+        assert_eq!(fp.parse("12.345").unwrap(), BigInt::from(1235));
+        assert_eq!(fp.parse("-0.5").unwrap(), BigInt::from(-50));
+        assert_eq!(fp.parse("7").unwrap(), BigInt::from(700));
+        assert!(fp.parse("1e3").is_err());
+        assert!(fp.parse("-").is_err());
+    }
+
+    #[test]
+    fn test_encrypted_stats_match_plaintext() {
+        let kp = p_keygen(128).unwrap();
+        let fp = FixedPoint::new(1);
+        let xs: Vec<String> = ["1.5", "2", "-3.5", "4", "10"].iter().map(|s| s.to_string()).collect();
+        let ys: Vec<String> = ["2", "4.5", "-6", "8", "19.5"].iter().map(|s| s.to_string()).collect();
+
+        let ex = stats_encrypt_column("X", &xs, fp, &kp.public_key).unwrap();
+        let ey = stats_encrypt_column("Y", &ys, fp, &kp.public_key).unwrap();
+        let ax = stats_server_aggregate_column("X", &ex, &kp.public_key).unwrap();
+        let ay = stats_server_aggregate_column("Y", &ey, &kp.public_key).unwrap();
+        let cross = stats_server_cross(&ex, &ey, &kp.public_key).unwrap();
+        let st = stats_decrypt_pair(&ax, &ay, &cross, fp, &kp).unwrap();
+
+        let fx: Vec<f64> = xs.iter().map(|s| s.parse().unwrap()).collect();
+        let fy: Vec<f64> = ys.iter().map(|s| s.parse().unwrap()).collect();
+        let n = fx.len() as f64;
+        let (mx, my) = (fx.iter().sum::<f64>() / n, fy.iter().sum::<f64>() / n);
+        let vx  = fx.iter().map(|x| (x - mx).powi(2)).sum::<f64>() / (n - 1.0);
+        let vy  = fy.iter().map(|y| (y - my).powi(2)).sum::<f64>() / (n - 1.0);
+        let cov = fx.iter().zip(&fy).map(|(x, y)| (x - mx) * (y - my)).sum::<f64>() / (n - 1.0);
+
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-9;
+        assert!(close(st.x.mean, mx) && close(st.y.mean, my));
+        assert!(close(st.x.variance, vx) && close(st.y.variance, vy));
+        assert!(close(st.covariance, cov));
+        assert!(close(st.pearson.unwrap(), cov / (vx.sqrt() * vy.sqrt())));
+    }
+}
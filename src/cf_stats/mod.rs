@@ -0,0 +1,12 @@
+pub mod cf_stats;
+
+pub use cf_stats::{
+    // Types
+    CfSndSum, EncColumnAgg, ColumnStats, PairStats, FixedPoint,
+    // BD
+    stats_encrypt_column, load_column_from_csv, load_keyed_column_from_csv,
+    // Évaluateur
+    cf_sum, cf_sum_products, stats_server_aggregate_column, stats_server_cross,
+    // Détenteur de clé
+    cf_sum_products_dec, stats_decrypt_column, stats_decrypt_pair,
+};
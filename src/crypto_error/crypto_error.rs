@@ -36,6 +36,16 @@ pub enum CryptoError {
     /// La vérification d'image KEA a échoué (chiffré invalide ou falsifié)
     KeaImVerFailed,
 
+    // --- Erreurs de format des chiffrés CF sérialisés ---
+    /// Le chiffré a été produit sous une autre clé publique
+    KeyFingerprintMismatch,
+    /// Niveau CF inattendu (1 = Première Forme, 2 = Seconde Forme)
+    CfLevelMismatch { expected: u8, found: u8 },
+    /// Version d'encodage non supportée
+    UnsupportedVersion { found: u8, supported: u8 },
+    /// Encodage binaire ou JSON invalide (en-tête, longueur, champ)
+    MalformedCiphertext(String),
+
     InvalidInput(String), // Erreur générique pour les entrées invalides (ex: base zéro dans la fonction de représentation en base)
 
 
@@ -62,6 +72,14 @@ impl fmt::Display for CryptoError {
                 write!(f, "Fichier de clés incohérent : n_squared != n*n (corrompu ou falsifié)"),
             CryptoError::KeaImVerFailed =>
                 write!(f, "Vérification d'image KEA échouée : chiffré invalide ou falsifié"),
+            CryptoError::KeyFingerprintMismatch =>
+                write!(f, "Empreinte de clé différente : chiffré produit sous une autre clé publique"),
+            CryptoError::CfLevelMismatch { expected, found } =>
+                write!(f, "Niveau CF incohérent : attendu {expected}, trouvé {found}"),
+            CryptoError::UnsupportedVersion { found, supported } =>
+                write!(f, "Version d'encodage {found} non supportée (version supportée : {supported})"),
+            CryptoError::MalformedCiphertext(msg) =>
+                write!(f, "Chiffré CF mal formé : {msg}"),
             
             CryptoError::InvalidInput(msg) =>
                 write!(f, "Entrée invalide : {msg}"),
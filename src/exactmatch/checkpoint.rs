@@ -0,0 +1,499 @@
+use std::fs;
+use std::path::Path;
+use std::time::Instant;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use crate::exactmatch::exactmatch::{CfFst, CfSnd, DualFtBundle, FtBundle};
+use crate::exactmatch::position_hash::PositionHasher;
+use crate::exactmatch::delta::{read_state, write_state, parse_json};
+use crate::fiore_catalano::cf_batch::cf_batch::cf_mul_batch;
+use crate::fiore_catalano::cf_codec::CfCiphertext;
+use crate::key_management::key_fingerprint::{key_fingerprint, fingerprint_to_hex, hex_to_fingerprint};
+use crate::key_management::key_storage::{load_keypair_json, save_keypair_json};
+use crate::paillier::p_keygen::PublicKey;
+use crate::paillier::p_keygen::p_keygen::p_keygen;
+use crate::parallel::default_pool;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// Reprise sur incident (--checkpoint <dir>) — mode standard
+//
+// Une session porte un identifiant de 64 bits tiré par le serveur et les
+// empreintes SHA-256 de pk1 et pk2 (key_fingerprint). Chaque étape
+// terminée est écrite sur disque (écriture atomique) :
+//
+//   BD      <dir>/keypair.json   paire Paillier, conservée entre sessions
+//           <dir>/session.json   id, empreintes, empreinte des clés CSV, K
+//           <dir>/bundle.bin     DualFtBundle chiffré (Phase 2)
+//   Serveur <dir>/session.json   id, empreintes, bundles reçus, positions
+//                                communes, lots de Phase 3 terminés
+//           <dir>/bundle_bd{1,2}.bin
+//           <dir>/p3_{1,2}_{i}.bin  lot i des triplets sous pk1 / pk2
+//
+// Phase 0c : chaque BD annonce l'id de sa session locale (0 si aucune,
+// si pk_other ou son CSV a changé). Le serveur reprend si les deux id
+// égalent le sien et que pk1, pk2 ont les empreintes enregistrées ;
+// sinon il ouvre une nouvelle session (Phase 0d refaite, fichiers
+// précédents effacés). À la reprise, un BD ne renvoie son bundle que si
+// le serveur ne l'a pas, et la Phase 3 repart du premier lot non écrit.
+//
+// Les chiffrés CF sont stockés au format cf_codec : l'empreinte de la clé
+// y est vérifiée au chargement (pas de mélange entre sessions).
+// ============================================================================
+
+/// Positions communes par lot de Phase 3 (un point de reprise par lot)
+pub const CHECKPOINT_BATCH: usize = 1024;
+
+/// Taille maximale d'un fichier de reprise (1 Go)
+const MAX_CHECKPOINT_BYTES: u64 = 1 << 30;
+
+/// Empreinte des clés dérivées du CSV (ordre et doublons compris)
+pub fn input_digest(keys: &[String]) -> [u8; 32] {
+    let mut h = Sha256::new();
+    for k in keys {
+        h.update((k.len() as u64).to_be_bytes());
+        h.update(k.as_bytes());
+    }
+    h.finalize().into()
+}
+
+fn io_err(path: &Path) -> impl Fn(std::io::Error) -> CryptoError + '_ {
+    move |e| CryptoError::InvalidInput(format!("{} : {}", path.display(), e))
+}
+
+fn path_str(path: &Path) -> Result<&str, CryptoError> {
+    path.to_str().ok_or_else(|| CryptoError::InvalidInput(format!("chemin non UTF-8 : {}", path.display())))
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), CryptoError> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).and_then(|_| fs::rename(&tmp, path)).map_err(io_err(path))
+}
+
+// ---------------------------------------------------------------------------
+// Encodage binaire : [u32 BE longueur][CfCiphertext v1] par chiffré
+// ---------------------------------------------------------------------------
+
+fn push_ct(out: &mut Vec<u8>, ct: &CfCiphertext) {
+    let bytes = ct.to_bytes();
+    out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+    out.extend_from_slice(&bytes);
+}
+
+fn take<'a>(buf: &mut &'a [u8], n: usize) -> Result<&'a [u8], CryptoError> {
+    if buf.len() < n {
+        return Err(CryptoError::MalformedCiphertext("point de reprise tronqué".into()));
+    }
+    let (head, tail) = buf.split_at(n);
+    *buf = tail;
+    Ok(head)
+}
+
+fn take_ct(buf: &mut &[u8]) -> Result<CfCiphertext, CryptoError> {
+    let len = u32::from_be_bytes(take(buf, 4)?.try_into().expect("4 octets")) as usize;
+    CfCiphertext::from_bytes(take(buf, len)?)
+}
+
+/// [u64 position][Ft sous pk1][Ft sous pk2] par position, triées
+fn encode_bundle(b: &DualFtBundle, pk1: &PublicKey, pk2: &PublicKey) -> Result<Vec<u8>, CryptoError> {
+    let mut positions: Vec<usize> = b.under_pk1.ft_by_pos.keys().copied().collect();
+    positions.sort_unstable();
+    let mut out = Vec::new();
+    for pos in positions {
+        let ft2 = b.under_pk2.ft_by_pos.get(&pos)
+            .ok_or_else(|| CryptoError::InvalidInput(format!("bundle : position {} absente sous pk2", pos)))?;
+        out.extend_from_slice(&(pos as u64).to_be_bytes());
+        push_ct(&mut out, &CfCiphertext::from_fst(&b.under_pk1.ft_by_pos[&pos], pk1)?);
+        push_ct(&mut out, &CfCiphertext::from_fst(ft2, pk2)?);
+    }
+    Ok(out)
+}
+
+fn decode_bundle(mut buf: &[u8], pk1: &PublicKey, pk2: &PublicKey) -> Result<DualFtBundle, CryptoError> {
+    let mut b = DualFtBundle {
+        under_pk1: FtBundle { ft_by_pos: Default::default() },
+        under_pk2: FtBundle { ft_by_pos: Default::default() },
+    };
+    while !buf.is_empty() {
+        let pos = u64::from_be_bytes(take(&mut buf, 8)?.try_into().expect("8 octets")) as usize;
+        let ft1: CfFst = take_ct(&mut buf)?.to_fst(pk1)?;
+        let ft2: CfFst = take_ct(&mut buf)?.to_fst(pk2)?;
+        b.under_pk1.ft_by_pos.insert(pos, ft1);
+        b.under_pk2.ft_by_pos.insert(pos, ft2);
+    }
+    Ok(b)
+}
+
+fn read_file(path: &Path) -> Result<Vec<u8>, CryptoError> {
+    let len = fs::metadata(path).map_err(io_err(path))?.len();
+    if len > MAX_CHECKPOINT_BYTES {
+        return Err(CryptoError::InvalidInput(format!("{} dépasse {} octets", path.display(), MAX_CHECKPOINT_BYTES)));
+    }
+    fs::read(path).map_err(io_err(path))
+}
+
+// ============================================================================
+// Côté BD
+// ============================================================================
+
+#[derive(Serialize, Deserialize)]
+struct ClientSession {
+    session_id: u64,
+    bd_id:      u8,
+    fp_self:    String,
+    fp_other:   String,
+    input:      String,
+    table_bits: u32,
+    /// Clé de hachage K (hex) : secrète, comme sk
+    hash_key:   String,
+}
+
+pub struct ClientCheckpoint {
+    dir:     String,
+    session: Option<ClientSession>,
+}
+
+impl ClientCheckpoint {
+    /// Ouvre (ou crée) le répertoire ; session.json est relu s'il existe
+    pub fn open(dir: &str) -> Result<Self, CryptoError> {
+        fs::create_dir_all(dir).map_err(io_err(Path::new(dir)))?;
+        let path = Path::new(dir).join("session.json");
+        let session = if path.exists() {
+            let p = path_str(&path)?;
+            Some(parse_json(p, &read_state(p)?)?)
+        } else {
+            None
+        };
+        Ok(ClientCheckpoint { dir: dir.to_string(), session })
+    }
+
+    fn file(&self, name: &str) -> std::path::PathBuf {
+        Path::new(&self.dir).join(name)
+    }
+
+    /// Paire Paillier du BD : relue, ou générée puis enregistrée
+    pub fn keypair(&self, bits: u64) -> Result<KeyPair, CryptoError> {
+        let path = self.file("keypair.json");
+        let p = path_str(&path)?;
+        if path.exists() {
+            return load_keypair_json(p).map_err(io_err(&path));
+        }
+        let kp = p_keygen(bits)?;
+        save_keypair_json(&kp, p).map_err(io_err(&path))?;
+        Ok(kp)
+    }
+
+    /// Identifiant de la session locale si elle peut reprendre : même BD,
+    /// mêmes empreintes de pk, mêmes clés CSV
+    pub fn resumable(&self, bd_id: u8, pk_self: &PublicKey, pk_other: &PublicKey, input: &[u8; 32]) -> Option<u64> {
+        self.session.as_ref()
+            .filter(|s| {
+                s.bd_id == bd_id
+                    && s.fp_self == fingerprint_to_hex(&key_fingerprint(pk_self))
+                    && s.fp_other == fingerprint_to_hex(&key_fingerprint(pk_other))
+                    && s.input == fingerprint_to_hex(input)
+            })
+            .map(|s| s.session_id)
+    }
+
+    /// Nouvelle session : l'ancien bundle est effacé
+    pub fn start(
+        &mut self,
+        session_id: u64,
+        bd_id:      u8,
+        pk_self:    &PublicKey,
+        pk_other:   &PublicKey,
+        input:      &[u8; 32],
+        hasher:     &PositionHasher,
+    ) -> Result<(), CryptoError> {
+        let bundle = self.file("bundle.bin");
+        if bundle.exists() {
+            fs::remove_file(&bundle).map_err(io_err(&bundle))?;
+        }
+        let session = ClientSession {
+            session_id,
+            bd_id,
+            fp_self:    fingerprint_to_hex(&key_fingerprint(pk_self)),
+            fp_other:   fingerprint_to_hex(&key_fingerprint(pk_other)),
+            input:      fingerprint_to_hex(input),
+            table_bits: hasher.table_bits(),
+            hash_key:   fingerprint_to_hex(hasher.key()),
+        };
+        write_state(path_str(&self.file("session.json"))?, &session)?;
+        self.session = Some(session);
+        Ok(())
+    }
+
+    fn current(&self) -> Result<&ClientSession, CryptoError> {
+        self.session.as_ref().ok_or_else(|| CryptoError::InvalidInput("aucune session enregistrée".into()))
+    }
+
+    pub fn session_id(&self) -> Option<u64> {
+        self.session.as_ref().map(|s| s.session_id)
+    }
+
+    pub fn hasher(&self) -> Result<PositionHasher, CryptoError> {
+        let s = self.current()?;
+        PositionHasher::new(hex_to_fingerprint(&s.hash_key)?, s.table_bits)
+    }
+
+    pub fn load_bundle(&self, pk1: &PublicKey, pk2: &PublicKey) -> Result<Option<DualFtBundle>, CryptoError> {
+        self.current()?;
+        let path = self.file("bundle.bin");
+        if !path.exists() {
+            return Ok(None);
+        }
+        decode_bundle(&read_file(&path)?, pk1, pk2).map(Some)
+    }
+
+    pub fn save_bundle(&self, b: &DualFtBundle, pk1: &PublicKey, pk2: &PublicKey) -> Result<(), CryptoError> {
+        self.current()?;
+        write_atomic(&self.file("bundle.bin"), &encode_bundle(b, pk1, pk2)?)
+    }
+}
+
+// ============================================================================
+// Côté serveur
+// ============================================================================
+
+#[derive(Serialize, Deserialize)]
+struct ServerSession {
+    session_id:   u64,
+    fp1:          String,
+    fp2:          String,
+    table_bits:   u32,
+    bundles:      [bool; 2],
+    /// Positions communes, triées (fixées au premier passage en Phase 3)
+    common:       Option<Vec<usize>>,
+    batch:        usize,
+    batches_done: usize,
+}
+
+pub struct ServerCheckpoint {
+    dir:     String,
+    session: Option<ServerSession>,
+}
+
+impl ServerCheckpoint {
+    /// Ouvre le répertoire ; la session enregistrée n'est retenue que si
+    /// pk1 et pk2 ont les empreintes attendues
+    pub fn open(dir: &str, pk1: &PublicKey, pk2: &PublicKey) -> Result<Self, CryptoError> {
+        fs::create_dir_all(dir).map_err(io_err(Path::new(dir)))?;
+        let path = Path::new(dir).join("session.json");
+        let session: Option<ServerSession> = if path.exists() {
+            let p = path_str(&path)?;
+            Some(parse_json(p, &read_state(p)?)?)
+        } else {
+            None
+        };
+        let (fp1, fp2) = (fingerprint_to_hex(&key_fingerprint(pk1)), fingerprint_to_hex(&key_fingerprint(pk2)));
+        Ok(ServerCheckpoint {
+            dir:     dir.to_string(),
+            session: session.filter(|s| s.fp1 == fp1 && s.fp2 == fp2),
+        })
+    }
+
+    fn file(&self, name: &str) -> std::path::PathBuf {
+        Path::new(&self.dir).join(name)
+    }
+
+    fn current(&self) -> Result<&ServerSession, CryptoError> {
+        self.session.as_ref().ok_or_else(|| CryptoError::InvalidInput("aucune session enregistrée".into()))
+    }
+
+    fn save(&self) -> Result<(), CryptoError> {
+        write_state(path_str(&self.file("session.json"))?, self.current()?)
+    }
+
+    /// Session reprenable (empreintes vérifiées à l'ouverture)
+    pub fn session_id(&self) -> Option<u64> {
+        self.session.as_ref().map(|s| s.session_id)
+    }
+
+    pub fn table_bits(&self) -> Option<u32> {
+        self.session.as_ref().map(|s| s.table_bits)
+    }
+
+    pub fn has_bundle(&self, bd: u8) -> bool {
+        self.session.as_ref().is_some_and(|s| s.bundles[bd as usize - 1])
+    }
+
+    /// Lots de Phase 3 déjà écrits / total (None avant la Phase 3)
+    pub fn phase3_progress(&self) -> Option<(usize, usize)> {
+        let s = self.session.as_ref()?;
+        s.common.as_ref().map(|c| (s.batches_done, c.len().div_ceil(s.batch)))
+    }
+
+    /// Nouvelle session : les fichiers de la précédente sont effacés
+    pub fn start(
+        &mut self,
+        session_id: u64,
+        pk1:        &PublicKey,
+        pk2:        &PublicKey,
+        table_bits: u32,
+        batch:      usize,
+    ) -> Result<(), CryptoError> {
+        if batch == 0 {
+            return Err(CryptoError::InvalidInput("checkpoint : lot de Phase 3 vide".into()));
+        }
+        let dir = Path::new(&self.dir);
+        for entry in fs::read_dir(dir).map_err(io_err(dir))? {
+            let path = entry.map_err(io_err(dir))?.path();
+            let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            if name.starts_with("bundle_bd") || name.starts_with("p3_") {
+                fs::remove_file(&path).map_err(io_err(&path))?;
+            }
+        }
+        self.session = Some(ServerSession {
+            session_id,
+            fp1: fingerprint_to_hex(&key_fingerprint(pk1)),
+            fp2: fingerprint_to_hex(&key_fingerprint(pk2)),
+            table_bits,
+            bundles: [false, false],
+            common: None,
+            batch,
+            batches_done: 0,
+        });
+        self.save()
+    }
+
+    pub fn save_bundle(&mut self, bd: u8, b: &DualFtBundle, pk1: &PublicKey, pk2: &PublicKey) -> Result<(), CryptoError> {
+        self.current()?;
+        write_atomic(&self.file(&format!("bundle_bd{}.bin", bd)), &encode_bundle(b, pk1, pk2)?)?;
+        if let Some(s) = self.session.as_mut() {
+            s.bundles[bd as usize - 1] = true;
+        }
+        self.save()
+    }
+
+    pub fn load_bundle(&self, bd: u8, pk1: &PublicKey, pk2: &PublicKey) -> Result<DualFtBundle, CryptoError> {
+        if !self.has_bundle(bd) {
+            return Err(CryptoError::InvalidInput(format!("checkpoint : bundle BD{} absent", bd)));
+        }
+        decode_bundle(&read_file(&self.file(&format!("bundle_bd{}.bin", bd)))?, pk1, pk2)
+    }
+
+    /// Phase 3 par lots de positions communes ; chaque lot est écrit
+    /// avant d'être compté. Retourne tous les triplets (sous pk1, sous pk2).
+    pub fn phase3(
+        &mut self,
+        bd1: &DualFtBundle,
+        bd2: &DualFtBundle,
+        pk1: &PublicKey,
+        pk2: &PublicKey,
+    ) -> Result<(Vec<CfSnd>, Vec<CfSnd>), CryptoError> {
+        if self.current()?.common.is_none() {
+            let other = &bd2.under_pk1.ft_by_pos;
+            let mut common: Vec<usize> = bd1.under_pk1.ft_by_pos.keys().filter(|p| other.contains_key(p)).copied().collect();
+            common.sort_unstable();
+            if let Some(s) = self.session.as_mut() {
+                s.common = Some(common);
+            }
+            self.save()?;
+        }
+        let (done, total) = self.phase3_progress().expect("positions communes fixées");
+        println!(
+            "  [Phase 3] Serveur : {} lot(s) de {} positions communes, {} deja ecrit(s)...",
+            total, self.current()?.batch, done
+        );
+        let t_start = Instant::now();
+
+        for i in done..total {
+            let lot: Vec<usize> = {
+                let s = self.current()?;
+                let common = s.common.as_ref().expect("positions communes fixées");
+                common[i * s.batch..((i + 1) * s.batch).min(common.len())].to_vec()
+            };
+            for (k, pk) in [(1u8, pk1), (2, pk2)] {
+                let pick = |b: &DualFtBundle, pos: usize| -> Result<CfFst, CryptoError> {
+                    let ft = if k == 1 { &b.under_pk1 } else { &b.under_pk2 };
+                    ft.ft_by_pos.get(&pos).cloned()
+                        .ok_or_else(|| CryptoError::InvalidInput(format!("Ft manquant pour la position commune {}", pos)))
+                };
+                let operands: Vec<(CfFst, CfFst)> = lot.iter()
+                    .map(|&p| Ok((pick(bd1, p)?, pick(bd2, p)?)))
+                    .collect::<Result<_, CryptoError>>()?;
+                let pairs: Vec<(&CfFst, &CfFst)> = operands.iter().map(|(a, b)| (a, b)).collect();
+                let mut out = Vec::new();
+                for t in cf_mul_batch(&pairs, pk, default_pool())? {
+                    push_ct(&mut out, &CfCiphertext::from_snd(&t, pk)?);
+                }
+                write_atomic(&self.file(&format!("p3_{}_{}.bin", k, i)), &out)?;
+            }
+            if let Some(s) = self.session.as_mut() {
+                s.batches_done = i + 1;
+            }
+            self.save()?;
+        }
+        println!("  [Phase 3] termine en {:.3?} ({} lot(s) calcule(s)).", t_start.elapsed(), total - done);
+
+        let mut out = (Vec::new(), Vec::new());
+        for i in 0..total {
+            for (k, pk, dst) in [(1u8, pk1, &mut out.0), (2, pk2, &mut out.1)] {
+                let bytes = read_file(&self.file(&format!("p3_{}_{}.bin", k, i)))?;
+                let mut buf = bytes.as_slice();
+                while !buf.is_empty() {
+                    dst.push(take_ct(&mut buf)?.to_snd(pk)?);
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exactmatch::exactmatch::{SparseTable, phase2_prepare_dual_ft, phase4_decrypt_and_count};
+
+    #[test]
+    fn test_checkpoint_resumes_phase3_and_checks_fingerprints() {
+        let dir = std::env::temp_dir().join(format!("psi-test-checkpoint-{}", std::process::id()));
+        let (cdir, sdir) = (dir.join("bd1"), dir.join("serveur"));
+        let (cdir, sdir) = (cdir.to_str().unwrap(), sdir.to_str().unwrap());
+
+        let mut client = ClientCheckpoint::open(cdir).unwrap();
+        let kp1 = client.keypair(256).unwrap();
+        assert_eq!(ClientCheckpoint::open(cdir).unwrap().keypair(256).unwrap().public_key.n, kp1.public_key.n);
+        let kp2 = p_keygen(256).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+
+        // BD : session, bundle persisté puis relu ; CSV modifié = pas de reprise
+        let hasher = PositionHasher::new([9u8; 32], 16).unwrap();
+        let a: Vec<String> = (0..30).map(|i| format!("k{}", i)).collect();
+        let b: Vec<String> = (20..45).map(|i| format!("k{}", i)).collect();
+        client.start(77, 1, pk1, pk2, &input_digest(&a), &hasher).unwrap();
+        let b1 = phase2_prepare_dual_ft("A", &SparseTable::build(&a, &hasher), pk1, pk2);
+        client.save_bundle(&b1, pk1, pk2).unwrap();
+        let client = ClientCheckpoint::open(cdir).unwrap();
+        assert_eq!(client.resumable(1, pk1, pk2, &input_digest(&a)), Some(77));
+        assert_eq!(client.resumable(1, pk1, pk2, &input_digest(&b)), None);
+        let b1 = client.load_bundle(pk1, pk2).unwrap().unwrap();
+        assert_eq!(client.load_bundle(pk2, pk1).err(), Some(CryptoError::KeyFingerprintMismatch));
+        let b2 = phase2_prepare_dual_ft("B", &SparseTable::build(&b, &client.hasher().unwrap()), pk1, pk2);
+
+        // Serveur : Phase 3 interrompue après le premier lot, puis reprise
+        let mut server = ServerCheckpoint::open(sdir, pk1, pk2).unwrap();
+        server.start(77, pk1, pk2, 16, 4).unwrap();
+        server.save_bundle(1, &b1, pk1, pk2).unwrap();
+        server.save_bundle(2, &b2, pk1, pk2).unwrap();
+        let (t1, _) = server.phase3(&b1, &b2, pk1, pk2).unwrap();
+        assert_eq!(phase4_decrypt_and_count("A", &t1, &kp1).unwrap(), 10);
+        let mut state: ServerSession = parse_json("s", &read_state(&format!("{}/session.json", sdir)).unwrap()).unwrap();
+        state.batches_done = 1;
+        write_state(&format!("{}/session.json", sdir), &state).unwrap();
+        fs::remove_file(Path::new(sdir).join("p3_2_2.bin")).unwrap();
+
+        assert!(ServerCheckpoint::open(sdir, pk2, pk1).unwrap().session_id().is_none());
+        let mut server = ServerCheckpoint::open(sdir, pk1, pk2).unwrap();
+        assert_eq!((server.session_id(), server.phase3_progress()), (Some(77), Some((1, 3))));
+        let (l1, l2) = (server.load_bundle(1, pk1, pk2).unwrap(), server.load_bundle(2, pk1, pk2).unwrap());
+        let (_, t2) = server.phase3(&l1, &l2, pk1, pk2).unwrap();
+        assert_eq!(phase4_decrypt_and_count("B", &t2, &kp2).unwrap(), 10);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
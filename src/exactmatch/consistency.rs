@@ -0,0 +1,151 @@
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use crate::key_management::key_fingerprint::key_fingerprint;
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_keygen::PublicKey;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// Phase 5 — cohérence des cardinaux entre BD1 et BD2
+//
+// BD1 compte sous pk1, BD2 sous pk2 : un serveur qui calcule
+// différemment sous les deux clés donne deux résultats distincts,
+// chacun étant localement valide. Phase 5 :
+//
+//   (1) chaque BD s'engage : h = SHA-256(domaine ‖ BD ‖ fp(pk1) ‖ fp(pk2)
+//       ‖ c ‖ r), r aléatoire de 32 octets ; le serveur relaie h
+//   (2) après réception de l'engagement de l'autre, chaque BD ouvre :
+//       Enc_other(c ‖ r), relayé par le serveur sans lecture (il voit
+//       de toute façon les positions communes, --size-hiding compris)
+//   (3) chaque BD vérifie l'ouverture contre h, puis c_self = c_other ;
+//       tout écart interrompt avec un diagnostic
+//
+// L'engagement précède l'ouverture : aucun BD ne peut adapter son
+// cardinal à celui de l'autre. Les empreintes de pk lient l'engagement
+// à la session.
+// ============================================================================
+
+const CARDINAL_DOMAIN: &[u8] = b"psi-ca/phase5/cardinal/v1";
+
+/// Cardinal et aléa d'engagement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardinalOpening {
+    pub count: u64,
+    pub nonce: [u8; 32],
+}
+
+impl CardinalOpening {
+    pub fn new(count: u64) -> Self {
+        let mut nonce = [0u8; 32];
+        OsRng.fill_bytes(&mut nonce);
+        CardinalOpening { count, nonce }
+    }
+
+    pub fn commitment(&self, bd_id: u8, pk1: &PublicKey, pk2: &PublicKey) -> [u8; 32] {
+        let mut h = Sha256::new();
+        h.update(CARDINAL_DOMAIN);
+        h.update([bd_id]);
+        h.update(key_fingerprint(pk1));
+        h.update(key_fingerprint(pk2));
+        h.update(self.count.to_be_bytes());
+        h.update(self.nonce);
+        h.finalize().into()
+    }
+
+    /// c ‖ r en un entier de 40 octets (< n pour toute clé ≥ 512 bits)
+    fn to_plain(&self) -> BigUint {
+        let mut bytes = self.count.to_be_bytes().to_vec();
+        bytes.extend_from_slice(&self.nonce);
+        BigUint::from_bytes_be(&bytes)
+    }
+
+    fn from_plain(m: &BigUint) -> Result<Self, CryptoError> {
+        if m.bits() > 320 {
+            return Err(CryptoError::InvalidInput("Phase 5 : ouverture hors format".into()));
+        }
+        let nonce_part = m & ((BigUint::from(1u32) << 256u32) - 1u32);
+        let mut nonce = [0u8; 32];
+        let nb = nonce_part.to_bytes_be();
+        nonce[32 - nb.len()..].copy_from_slice(&nb);
+        let count = (m >> 256u32).to_u64().expect("64 bits au plus");
+        Ok(CardinalOpening { count, nonce })
+    }
+}
+
+/// (1) engagement sur le cardinal local
+pub fn phase5_commit(count: usize, bd_id: u8, pk1: &PublicKey, pk2: &PublicKey) -> (CardinalOpening, [u8; 32]) {
+    let opening = CardinalOpening::new(count as u64);
+    let commitment = opening.commitment(bd_id, pk1, pk2);
+    (opening, commitment)
+}
+
+/// (2) ouverture chiffrée pour l'autre BD
+pub fn phase5_seal(opening: &CardinalOpening, pk_other: &PublicKey) -> Result<BigUint, CryptoError> {
+    p_encrypt(&opening.to_plain(), pk_other)
+}
+
+/// (3) ouverture de l'autre BD vérifiée contre son engagement, puis
+/// comparée au cardinal local
+#[allow(clippy::too_many_arguments)]
+pub fn phase5_verify(
+    label:            &str,
+    own_count:        usize,
+    other_bd:         u8,
+    other_commitment: &[u8; 32],
+    other_sealed:     &BigUint,
+    kp:               &KeyPair,
+    pk1:              &PublicKey,
+    pk2:              &PublicKey,
+) -> Result<u64, CryptoError> {
+    let opening = CardinalOpening::from_plain(&p_decrypt(other_sealed, &kp.public_key, &kp.secret_key)?)?;
+    if opening.commitment(other_bd, pk1, pk2) != *other_commitment {
+        return Err(CryptoError::InvalidInput(format!(
+            "Phase 5 : l'ouverture de BD{} ne correspond pas à son engagement", other_bd
+        )));
+    }
+    let own = own_count as u64;
+    let (c1, c2) = if other_bd == 2 { (own, opening.count) } else { (opening.count, own) };
+    if c1 != c2 {
+        return Err(CryptoError::InvalidInput(format!(
+            "Phase 5 : cardinaux incohérents, BD1 = {} sous pk1, BD2 = {} sous pk2 \
+             (le serveur a calculé différemment sous les deux clés)",
+            c1, c2
+        )));
+    }
+    println!("  [Phase 5] {} : cardinal confirmé par BD{} ({}).", label, other_bd, opening.count);
+    Ok(opening.count)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    #[test]
+    fn test_cardinal_commit_reveal_detects_mismatch() {
+        let kp1 = p_keygen(512).unwrap();
+        let kp2 = p_keygen(512).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+
+        let (o1, h1) = phase5_commit(13, 1, pk1, pk2);
+        let (o2, h2) = phase5_commit(13, 2, pk1, pk2);
+        let s1 = phase5_seal(&o1, pk2).unwrap();
+        let s2 = phase5_seal(&o2, pk1).unwrap();
+        assert_eq!(phase5_verify("BD1", 13, 2, &h2, &s2, &kp1, pk1, pk2).unwrap(), 13);
+        assert_eq!(phase5_verify("BD2", 13, 1, &h1, &s1, &kp2, pk1, pk2).unwrap(), 13);
+
+        // cardinal différent sous pk2
+        let (o3, h3) = phase5_commit(12, 2, pk1, pk2);
+        let s3 = phase5_seal(&o3, pk1).unwrap();
+        assert!(phase5_verify("BD1", 13, 2, &h3, &s3, &kp1, pk1, pk2).is_err());
+        // ouverture qui ne correspond pas à l'engagement
+        assert!(phase5_verify("BD1", 12, 2, &h2, &s3, &kp1, pk1, pk2).is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use std::collections::HashSet;
+use num_bigint::{BigUint, RandBigInt};
+use rand::Rng;
+use rand_core::OsRng;
+use crate::exactmatch::position_hash::PositionHasher;
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ============================================================================
+// Tables cuckoo / bins simples pour ExactMatch sans collision
+//
+// SparseTable confond deux NSS distincts qui tombent sur la même position.
+// Ici, la comparaison porte sur une ÉTIQUETTE de 128 bits et la position ne
+// sert plus qu'à réduire le nombre de comparaisons :
+//
+//   BD1 (côté cuckoo) : chaque NSS x est placé dans UN bin parmi
+//       h_1(x), …, h_k(x)  (k fonctions, éviction aléatoire), ou dans le
+//       stash si l'insertion échoue. Au plus un élément par bin.
+//
+//   BD2 (côté simple) : chaque NSS y est copié dans TOUS ses bins
+//       h_1(y), …, h_k(y), chaque bin étant complété à bin_size entrées.
+//       Ses étiquettes sont aussi listées à part (stash_list, complétée à
+//       n2 entrées) pour être comparées au stash de BD1.
+//
+// Si x = y, x se trouve dans exactement un bin de BD1 (ou dans le stash), et
+// y figure une seule fois dans ce bin chez BD2 (ou dans stash_list) : le
+// couple est compté UNE fois. Deux NSS distincts ne coïncident que si leurs
+// étiquettes 128 bits sont égales (probabilité 2^-128 par paire).
+//
+// Bourrage (valeurs hors de l'espace des étiquettes, donc jamais égales) :
+//   étiquettes réelles  ∈ [0, 2^128)
+//   bourrage BD1        ∈ [2^128, 2^129)
+//   bourrage BD2        ∈ [2^129, 2^130)
+//
+// Taille des bins : la plus petite valeur B telle que
+//   nb_bins · P(Binomiale(k·n2, 1/nb_bins) > B) ≤ 2^-STAT_SECURITY_BITS
+// Elle ne dépend que de paramètres publics (n1, n2, k) : le serveur ne
+// déduit rien du remplissage réel des bins.
+// ============================================================================
+
+/// Nombre de fonctions de hachage cuckoo par défaut
+pub const DEFAULT_CUCKOO_HASHES: usize = 3;
+
+/// Taille du stash par défaut
+pub const DEFAULT_STASH_SIZE: usize = 2;
+
+/// Facteur d'expansion : nb_bins = ⌈1.27 · n1⌉ (seuil de charge de k = 3 : 0.91)
+const CUCKOO_EXPANSION: f64 = 1.27;
+
+/// Paramètre de sécurité statistique (débordement de bin, échec d'insertion)
+pub const STAT_SECURITY_BITS: i32 = 40;
+
+/// Nombre maximal d'évictions avant de recourir au stash
+const MAX_EVICTIONS: usize = 500;
+
+/// Taille des étiquettes comparées sous CF
+pub const TAG_BITS: u64 = 128;
+
+// ---------------------------------------------------------------------------
+// Paramètres publics, calculés à l'identique par BD1, BD2 et le serveur
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct CuckooParams {
+    pub n1:       usize,
+    pub n2:       usize,
+    pub hashes:   usize,
+    pub bins:     usize,
+    pub bin_size: usize,
+    pub stash:    usize,
+}
+
+impl CuckooParams {
+    pub fn new(n1: usize, n2: usize, hashes: usize, stash: usize) -> Result<Self, CryptoError> {
+        if !(2..=8).contains(&hashes) {
+            return Err(CryptoError::InvalidInput(format!("cuckoo : k = {} hors de [2, 8]", hashes)));
+        }
+        if n1 == 0 || n2 == 0 {
+            return Err(CryptoError::InvalidInput("cuckoo : ensemble vide".into()));
+        }
+        let bins     = ((n1 as f64 * CUCKOO_EXPANSION).ceil() as usize).max(hashes + 1);
+        let bin_size = max_bin_load(hashes * n2, bins);
+        Ok(CuckooParams { n1, n2, hashes, bins, bin_size, stash })
+    }
+
+    /// Longueur du vecteur de Ft envoyé par BD1
+    pub fn cuckoo_slots(&self) -> usize {
+        self.bins + self.stash
+    }
+
+    /// Longueur du vecteur de Ft envoyé par BD2
+    pub fn simple_slots(&self) -> usize {
+        self.bins * self.bin_size + self.n2
+    }
+
+    /// Nombre de comparaisons (= chiffrés renvoyés à chaque BD)
+    pub fn comparisons(&self) -> usize {
+        self.bins * self.bin_size + self.stash * self.n2
+    }
+}
+
+/// Plus petit B tel que bins · P(X > B) ≤ 2^-λ, X ~ Binomiale(balls, 1/bins)
+fn max_bin_load(balls: usize, bins: usize) -> usize {
+    let p = 1.0 / bins as f64;
+    let target = (-(STAT_SECURITY_BITS as f64)).exp2() / bins as f64;
+
+    // pmf(t) par récurrence, puis queue P(X > B) en partant du haut
+    let mut pmf = Vec::with_capacity(balls + 1);
+    let mut cur = (balls as f64) * (1.0 - p).ln();
+    for t in 0..=balls {
+        pmf.push(cur.exp());
+        cur += ((balls - t) as f64).ln() - ((t + 1) as f64).ln() + p.ln() - (1.0 - p).ln();
+    }
+    let mut tail = 0.0;
+    for b in (0..=balls).rev() {
+        if tail > target {
+            return (b + 1).min(balls);
+        }
+        tail += pmf[b];
+    }
+    0
+}
+
+// ---------------------------------------------------------------------------
+// Fonctions dérivées de la clé HMAC
+// ---------------------------------------------------------------------------
+
+fn bin_of(hasher: &PositionHasher, i: usize, nss: &str, bins: usize) -> usize {
+    let d = hasher.prf(&[b'c', b'k', i as u8, 0], nss);
+    let mut head = [0u8; 8];
+    head.copy_from_slice(&d[..8]);
+    (u64::from_be_bytes(head) % bins as u64) as usize
+}
+
+/// Étiquette 128 bits d'un NSS
+pub fn cuckoo_tag(hasher: &PositionHasher, nss: &str) -> BigUint {
+    BigUint::from_bytes_be(&hasher.prf(b"tag\0", nss)[..(TAG_BITS / 8) as usize])
+}
+
+/// Bins distincts d'un NSS (un NSS n'apparaît qu'une fois par bin)
+fn distinct_bins(hasher: &PositionHasher, nss: &str, p: &CuckooParams) -> Vec<usize> {
+    let mut out: Vec<usize> = (0..p.hashes).map(|i| bin_of(hasher, i, nss, p.bins)).collect();
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+fn dummy(offset_bits: u64) -> BigUint {
+    let base = BigUint::from(1u32) << offset_bits;
+    &base + OsRng.gen_biguint_below(&base)
+}
+
+// ---------------------------------------------------------------------------
+// BD1 — Table cuckoo
+// ---------------------------------------------------------------------------
+
+pub struct CuckooTable {
+    pub params: CuckooParams,
+    /// slots[0..bins] : un élément par bin, slots[bins..] : stash
+    pub slots:  Vec<BigUint>,
+    /// Nombre d'éléments réels placés dans le stash
+    pub stashed: usize,
+}
+
+impl CuckooTable {
+    pub fn build(nss_list: &[String], hasher: &PositionHasher, params: CuckooParams) -> Result<Self, CryptoError> {
+        let p = params;
+        let mut table: Vec<Option<&str>> = vec![None; p.bins];
+        let mut stash: Vec<&str> = Vec::new();
+        let mut rng = OsRng;
+
+        let distinct: HashSet<&str> = nss_list.iter().map(String::as_str).collect();
+        if distinct.len() > p.n1 {
+            return Err(CryptoError::InvalidInput("cuckoo : plus d'éléments que n1".into()));
+        }
+
+        for &x in &distinct {
+            let mut cur = x;
+            let mut placed = false;
+            for _ in 0..MAX_EVICTIONS {
+                let choices = distinct_bins(hasher, cur, &p);
+                if let Some(&b) = choices.iter().find(|&&b| table[b].is_none()) {
+                    table[b] = Some(cur);
+                    placed = true;
+                    break;
+                }
+                let b = choices[rng.gen_range(0..choices.len())];
+                cur = table[b].replace(cur).expect("bin occupé");
+            }
+            if !placed {
+                if stash.len() == p.stash {
+                    return Err(CryptoError::InvalidInput(format!(
+                        "cuckoo : stash plein ({} entrées) — augmenter --stash", p.stash
+                    )));
+                }
+                stash.push(cur);
+            }
+        }
+
+        let stashed = stash.len();
+        let mut slots: Vec<BigUint> = table
+            .iter()
+            .map(|e| e.map_or_else(|| dummy(TAG_BITS), |x| cuckoo_tag(hasher, x)))
+            .collect();
+        slots.extend(stash.iter().map(|x| cuckoo_tag(hasher, x)));
+        slots.extend((stashed..p.stash).map(|_| dummy(TAG_BITS)));
+
+        Ok(CuckooTable { params, slots, stashed })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// BD2 — Bins simples complétés
+// ---------------------------------------------------------------------------
+
+pub struct SimpleBins {
+    pub params:   CuckooParams,
+    /// slots[j·bin_size + l] : l-ième entrée du bin j, puis stash_list (n2)
+    pub slots:    Vec<BigUint>,
+    /// Charge maximale réelle (≤ bin_size)
+    pub max_load: usize,
+}
+
+impl SimpleBins {
+    pub fn build(nss_list: &[String], hasher: &PositionHasher, params: CuckooParams) -> Result<Self, CryptoError> {
+        let p = params;
+        let mut bins: Vec<Vec<BigUint>> = vec![Vec::new(); p.bins];
+
+        let distinct: HashSet<&str> = nss_list.iter().map(String::as_str).collect();
+        if distinct.len() > p.n2 {
+            return Err(CryptoError::InvalidInput("cuckoo : plus d'éléments que n2".into()));
+        }
+
+        let mut tags = Vec::with_capacity(p.n2);
+        for &y in &distinct {
+            let tag = cuckoo_tag(hasher, y);
+            for b in distinct_bins(hasher, y, &p) {
+                bins[b].push(tag.clone());
+            }
+            tags.push(tag);
+        }
+
+        let max_load = bins.iter().map(Vec::len).max().unwrap_or(0);
+        if max_load > p.bin_size {
+            return Err(CryptoError::InvalidInput(format!(
+                "cuckoo : bin de {} entrées > bin_size = {}", max_load, p.bin_size
+            )));
+        }
+
+        let mut slots = Vec::with_capacity(p.simple_slots());
+        for bin in bins {
+            let real = bin.len();
+            slots.extend(bin);
+            slots.extend((real..p.bin_size).map(|_| dummy(TAG_BITS + 1)));
+        }
+        let real = tags.len();
+        slots.extend(tags);
+        slots.extend((real..p.n2).map(|_| dummy(TAG_BITS + 1)));
+
+        Ok(SimpleBins { params, slots, max_load })
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Serveur — paires (slot BD1, slot BD2) à comparer, dans l'ordre canonique
+// ---------------------------------------------------------------------------
+
+pub fn comparison_pairs(p: &CuckooParams) -> Vec<(usize, usize)> {
+    let mut out = Vec::with_capacity(p.comparisons());
+    for j in 0..p.bins {
+        for l in 0..p.bin_size {
+            out.push((j, j * p.bin_size + l));
+        }
+    }
+    for s in 0..p.stash {
+        for y in 0..p.n2 {
+            out.push((p.bins + s, p.bins * p.bin_size + y));
+        }
+    }
+    out
+}
+
+// ============================================================================
+// Tests — cardinal exact en clair sur la disposition cuckoo / bins
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exactmatch::position_hash::{derive_hash_key, random_key_share};
+
+    #[test]
+    fn test_cuckoo_layout_counts_exactly() {
+        let key    = derive_hash_key(&random_key_share(), &random_key_share(), None);
+        let hasher = PositionHasher::new(key, 8).unwrap();
+
+        let a: Vec<String> = (0..300).map(|i| format!("{}", 100_000 + i)).collect();
+        let b: Vec<String> = (200..450).map(|i| format!("{}", 100_000 + i)).collect();
+        let p  = CuckooParams::new(a.len(), b.len(), DEFAULT_CUCKOO_HASHES, DEFAULT_STASH_SIZE).unwrap();
+        let t1 = CuckooTable::build(&a, &hasher, p).unwrap();
+        let t2 = SimpleBins::build(&b, &hasher, p).unwrap();
+
+        assert_eq!(t1.slots.len(), p.cuckoo_slots());
+        assert_eq!(t2.slots.len(), p.simple_slots());
+        let matches = comparison_pairs(&p)
+            .iter()
+            .filter(|&&(i, j)| t1.slots[i] == t2.slots[j])
+            .count();
+        // Avec table_bits = 8, SparseTable aurait des centaines de collisions
+        assert_eq!(matches, 100);
+    }
+}
@@ -0,0 +1,500 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::time::Instant;
+use num_bigint::BigUint;
+use num_traits::{One, ToPrimitive, Zero};
+use rand::seq::SliceRandom;
+use rand_core::{OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use crate::exactmatch::exactmatch::{CfFst, CfSnd, DualFtBundle, SparseTable};
+use crate::exactmatch::position_hash::PositionHasher;
+use crate::fiore_catalano::cf_batch::cf_batch::cf_mul_batch;
+use crate::key_management::key_storage::{
+    PublicKeyJson, biguint_to_hex, hex_to_biguint, public_key_to_json, json_to_public_key,
+};
+use crate::key_management::key_fingerprint::{fingerprint_to_hex, hex_to_fingerprint};
+use crate::paillier::math::mod_inverse;
+use crate::paillier::p_batch::p_batch::p_decrypt_batch;
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_keygen::PublicKey;
+use crate::parallel::default_pool;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// PSI incrémentale (--delta) — session persistante
+//
+// Les clés Paillier (key_storage) et la clé de hachage K sont conservées
+// d'une exécution à l'autre : une position reste la même tant que la
+// session vit. Chaque BD n'envoie que la différence avec l'époque
+// précédente :
+//
+//   Phase 0   : pk relues, le serveur annonce (session, époque, fresh) ;
+//               la Phase 0d (accord sur K) n'a lieu qu'à la création
+//   Phase 2   : BD j -> Serveur : positions retirées + Ft des ajoutées
+//   Phase 3   : Serveur : retraits appliqués, puis CF.Mul sur les
+//               positions NOUVELLEMENT communes uniquement
+//   Phase 3b  : les masques (C1, C2) des nouveaux produits, mélangés,
+//               partent vers le BD k qui renvoie Enc_k(b·b') par paire ;
+//               E_p = C0 · Enc(b·b') = Enc_k(x1·x2) est conservé
+//   Phase 4   : Enc_k(c) = Π E_p tenu à jour par le serveur :
+//               ajout  : total · E_p
+//               retrait: total · E_p^-1
+//               le BD déchiffre un seul chiffré
+//
+// Coût par époque : O(|Δ|) Ft, CF.Mul et déchiffrements au lieu de
+// O(|table|). Le serveur conserve les Ft des deux BD sous pk1 et pk2
+// (état JSON) ; chaque BD conserve K, pk_other et ses positions.
+//
+// Fuite : le serveur voit, comme en mode standard, les positions actives,
+// et en plus leur évolution. Chaque BD apprend c à chaque époque, donc
+// l'effet net de sa propre différence sur l'intersection.
+//
+// Désynchronisation (un participant n'a pas enregistré la dernière
+// époque) : l'époque annoncée ne correspond plus, l'exécution est
+// refusée ; supprimer les trois états recrée une session.
+// ============================================================================
+
+/// Taille maximale d'un état de session (1 Go)
+const MAX_STATE_BYTES: u64 = 1 << 30;
+
+pub(crate) fn read_state(path: &str) -> Result<String, CryptoError> {
+    let io_err = |e: std::io::Error| CryptoError::InvalidInput(format!("{} : {}", path, e));
+    if fs::metadata(path).map_err(io_err)?.len() > MAX_STATE_BYTES {
+        return Err(CryptoError::InvalidInput(format!("{} dépasse {} octets", path, MAX_STATE_BYTES)));
+    }
+    fs::read_to_string(path).map_err(io_err)
+}
+
+/// Écriture atomique (fichier temporaire puis renommage)
+pub(crate) fn write_state<T: Serialize>(path: &str, value: &T) -> Result<(), CryptoError> {
+    let json = serde_json::to_string(value).map_err(|e| CryptoError::InvalidInput(e.to_string()))?;
+    let tmp = format!("{}.tmp", path);
+    fs::write(&tmp, json)
+        .and_then(|_| fs::rename(&tmp, path))
+        .map_err(|e| CryptoError::InvalidInput(format!("{} : {}", path, e)))
+}
+
+pub(crate) fn parse_json<'a, T: Deserialize<'a>>(path: &str, text: &'a str) -> Result<T, CryptoError> {
+    serde_json::from_str(text).map_err(|e| CryptoError::InvalidInput(format!("{} : {}", path, e)))
+}
+
+// ============================================================================
+// État du serveur
+// ============================================================================
+
+#[derive(Serialize, Deserialize)]
+struct FtJson {
+    pos: usize,
+    /// Ft sous pk1 (a, Enc(b)) puis sous pk2
+    a1:  String,
+    b1:  String,
+    a2:  String,
+    b2:  String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct ProductJson {
+    pos: usize,
+    e1:  String,
+    e2:  String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct DeltaStateJson {
+    session_id: u64,
+    epoch:      u64,
+    table_bits: u32,
+    pk1:        PublicKeyJson,
+    pk2:        PublicKeyJson,
+    ft1:        Vec<FtJson>,
+    ft2:        Vec<FtJson>,
+    products:   Vec<ProductJson>,
+    total1:     String,
+    total2:     String,
+}
+
+/// Effet d'une différence sur l'état (affiché par le serveur)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DeltaStats {
+    pub added:            usize,
+    pub removed:          usize,
+    /// Produits retirés de Enc(c) (position commune qui disparaît)
+    pub products_dropped: usize,
+}
+
+pub struct DeltaState {
+    pub session_id: u64,
+    pub epoch:      u64,
+    pub table_bits: u32,
+    pub pk1:        PublicKey,
+    pub pk2:        PublicKey,
+    /// ft[j][pos] = (Ft sous pk1, Ft sous pk2) de la BD j+1
+    ft:             [BTreeMap<usize, (CfFst, CfFst)>; 2],
+    /// products[pos] = (Enc1(x1·x2), Enc2(x1·x2))
+    products:       BTreeMap<usize, (BigUint, BigUint)>,
+    /// Π products, sous pk1 et pk2
+    total:          [BigUint; 2],
+}
+
+pub fn new_session_id() -> u64 {
+    OsRng.next_u64()
+}
+
+impl DeltaState {
+    /// Total initial : 1 = Enc(0) (aléa trivial, rerandomisé à l'envoi)
+    pub fn new(session_id: u64, pk1: PublicKey, pk2: PublicKey, table_bits: u32) -> Self {
+        DeltaState {
+            session_id,
+            epoch: 0,
+            table_bits,
+            pk1,
+            pk2,
+            ft:       [BTreeMap::new(), BTreeMap::new()],
+            products: BTreeMap::new(),
+            total:    [BigUint::one(), BigUint::one()],
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, CryptoError> {
+        let text = read_state(path)?;
+        let j: DeltaStateJson = parse_json(path, &text)?;
+        let ft_map = |v: &[FtJson]| -> Result<BTreeMap<usize, (CfFst, CfFst)>, CryptoError> {
+            v.iter()
+                .map(|f| Ok((f.pos, (
+                    (hex_to_biguint(&f.a1)?, hex_to_biguint(&f.b1)?),
+                    (hex_to_biguint(&f.a2)?, hex_to_biguint(&f.b2)?),
+                ))))
+                .collect()
+        };
+        let products = j.products.iter()
+            .map(|p| Ok((p.pos, (hex_to_biguint(&p.e1)?, hex_to_biguint(&p.e2)?))))
+            .collect::<Result<_, CryptoError>>()?;
+        Ok(DeltaState {
+            session_id: j.session_id,
+            epoch:      j.epoch,
+            table_bits: j.table_bits,
+            pk1:        json_to_public_key(&j.pk1)?,
+            pk2:        json_to_public_key(&j.pk2)?,
+            ft:         [ft_map(&j.ft1)?, ft_map(&j.ft2)?],
+            products,
+            total:      [hex_to_biguint(&j.total1)?, hex_to_biguint(&j.total2)?],
+        })
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), CryptoError> {
+        let ft_json = |m: &BTreeMap<usize, (CfFst, CfFst)>| -> Vec<FtJson> {
+            m.iter()
+                .map(|(&pos, ((a1, b1), (a2, b2)))| FtJson {
+                    pos,
+                    a1: biguint_to_hex(a1),
+                    b1: biguint_to_hex(b1),
+                    a2: biguint_to_hex(a2),
+                    b2: biguint_to_hex(b2),
+                })
+                .collect()
+        };
+        write_state(path, &DeltaStateJson {
+            session_id: self.session_id,
+            epoch:      self.epoch,
+            table_bits: self.table_bits,
+            pk1:        public_key_to_json(&self.pk1),
+            pk2:        public_key_to_json(&self.pk2),
+            ft1:        ft_json(&self.ft[0]),
+            ft2:        ft_json(&self.ft[1]),
+            products:   self.products.iter()
+                .map(|(&pos, (e1, e2))| ProductJson { pos, e1: biguint_to_hex(e1), e2: biguint_to_hex(e2) })
+                .collect(),
+            total1:     biguint_to_hex(&self.total[0]),
+            total2:     biguint_to_hex(&self.total[1]),
+        })
+    }
+
+    /// Positions actives de la BD `bd` (1 ou 2)
+    pub fn active(&self, bd: u8) -> usize {
+        self.ft[bd as usize - 1].len()
+    }
+
+    pub fn common(&self) -> usize {
+        self.products.len()
+    }
+
+    /// Phase 3 — applique la différence de la BD `bd` : retraits (et
+    /// produits correspondants retirés de Enc(c)) puis ajouts
+    pub fn apply_delta(&mut self, bd: u8, removed: &[usize], added: &DualFtBundle) -> Result<DeltaStats, CryptoError> {
+        if bd != 1 && bd != 2 {
+            return Err(CryptoError::InvalidInput(format!("delta : BD{} inconnue", bd)));
+        }
+        let j = bd as usize - 1;
+        let size = 1usize << self.table_bits;
+        let (u1, u2) = (&added.under_pk1.ft_by_pos, &added.under_pk2.ft_by_pos);
+        if u1.len() != u2.len() || u1.keys().any(|p| !u2.contains_key(p)) {
+            return Err(CryptoError::InvalidInput(format!("delta BD{} : Ft sous pk1 et pk2 incohérents", bd)));
+        }
+
+        let mut stats = DeltaStats::default();
+        for &pos in removed {
+            if self.ft[j].remove(&pos).is_none() {
+                return Err(CryptoError::InvalidInput(format!("delta BD{} : position {} retirée mais absente", bd, pos)));
+            }
+            if let Some((e1, e2)) = self.products.remove(&pos) {
+                let [t1, t2] = &mut self.total;
+                for (total, e, pk) in [(t1, e1, &self.pk1), (t2, e2, &self.pk2)] {
+                    *total = (&*total * mod_inverse(&e, &pk.n_squared)?) % &pk.n_squared;
+                }
+                stats.products_dropped += 1;
+            }
+            stats.removed += 1;
+        }
+        for (&pos, ft1) in u1 {
+            if pos >= size {
+                return Err(CryptoError::InvalidInput(format!("delta BD{} : position {} hors table", bd, pos)));
+            }
+            if self.ft[j].contains_key(&pos) {
+                return Err(CryptoError::InvalidInput(format!("delta BD{} : position {} ajoutée deux fois", bd, pos)));
+            }
+            self.ft[j].insert(pos, (ft1.clone(), u2[&pos].clone()));
+            stats.added += 1;
+        }
+        Ok(stats)
+    }
+
+    /// Positions actives des deux côtés sans produit (nouvellement communes)
+    pub fn pending_products(&self) -> Vec<usize> {
+        self.ft[0].keys()
+            .filter(|p| self.ft[1].contains_key(p) && !self.products.contains_key(p))
+            .copied()
+            .collect()
+    }
+
+    /// CF.Mul des positions `pending`, sous pk1 puis sous pk2
+    pub fn compute_products(&self, pending: &[usize]) -> Result<(Vec<CfSnd>, Vec<CfSnd>), CryptoError> {
+        let t_start = Instant::now();
+        let pool = default_pool();
+        let pairs = |k: usize| -> Vec<(&CfFst, &CfFst)> {
+            pending.iter()
+                .map(|p| {
+                    let (a, b) = (&self.ft[0][p], &self.ft[1][p]);
+                    if k == 0 { (&a.0, &b.0) } else { (&a.1, &b.1) }
+                })
+                .collect()
+        };
+        let t1 = cf_mul_batch(&pairs(0), &self.pk1, pool)?;
+        let t2 = cf_mul_batch(&pairs(1), &self.pk2, pool)?;
+        println!(
+            "  [Phase 3] delta : {} nouveaux produits x 2 cles en {:.3?}.",
+            pending.len(), t_start.elapsed()
+        );
+        Ok((t1, t2))
+    }
+
+    /// E_p = C0 · Enc(b·b') sous chaque clé, puis Enc(c) · Π E_p
+    pub fn absorb_products(
+        &mut self,
+        pending: &[usize],
+        triplets: [&[CfSnd]; 2],
+        mask_products: [&[BigUint]; 2],
+    ) -> Result<(), CryptoError> {
+        for k in 0..2 {
+            if triplets[k].len() != pending.len() || mask_products[k].len() != pending.len() {
+                return Err(CryptoError::InvalidInput(format!(
+                    "delta : {} produits de masques recus sous pk{}, {} attendus",
+                    mask_products[k].len(), k + 1, pending.len()
+                )));
+            }
+        }
+        let (n2_1, n2_2) = (self.pk1.n_squared.clone(), self.pk2.n_squared.clone());
+        for (i, &pos) in pending.iter().enumerate() {
+            let e1 = (&triplets[0][i].0 * &mask_products[0][i]) % &n2_1;
+            let e2 = (&triplets[1][i].0 * &mask_products[1][i]) % &n2_2;
+            self.total[0] = (&self.total[0] * &e1) % &n2_1;
+            self.total[1] = (&self.total[1] * &e2) % &n2_2;
+            self.products.insert(pos, (e1, e2));
+        }
+        Ok(())
+    }
+
+    /// Enc_k(c) rerandomisé (k = 1 ou 2)
+    pub fn encrypted_total(&self, k: u8) -> Result<BigUint, CryptoError> {
+        let (total, pk) = if k == 1 { (&self.total[0], &self.pk1) } else { (&self.total[1], &self.pk2) };
+        Ok((total * p_encrypt(&BigUint::zero(), pk)?) % &pk.n_squared)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Phase 3b — Serveur : masques mélangés, puis remise dans l'ordre
+// ---------------------------------------------------------------------------
+
+/// Retourne (ordre, paires (C1, C2) dans cet ordre)
+pub fn phase3_delta_mask_pairs(triplets: &[CfSnd]) -> (Vec<usize>, Vec<(BigUint, BigUint)>) {
+    let mut order: Vec<usize> = (0..triplets.len()).collect();
+    order.shuffle(&mut rand::thread_rng());
+    let pairs = order.iter().map(|&i| (triplets[i].1.clone(), triplets[i].2.clone())).collect();
+    (order, pairs)
+}
+
+pub fn phase3_delta_unshuffle(order: &[usize], shuffled: Vec<BigUint>) -> Result<Vec<BigUint>, CryptoError> {
+    if shuffled.len() != order.len() {
+        return Err(CryptoError::InvalidInput(format!(
+            "delta : {} produits de masques recus, {} attendus", shuffled.len(), order.len()
+        )));
+    }
+    let mut out = vec![BigUint::zero(); order.len()];
+    for (&i, v) in order.iter().zip(shuffled) {
+        out[i] = v;
+    }
+    Ok(out)
+}
+
+// ---------------------------------------------------------------------------
+// Phase 3b / 4 — BD
+// ---------------------------------------------------------------------------
+
+/// Enc(b·b') pour chaque paire de masques ; les masques sont uniformes
+pub fn phase4_delta_mask_products(
+    label: &str,
+    pairs: &[(BigUint, BigUint)],
+    kp:    &KeyPair,
+) -> Result<Vec<BigUint>, CryptoError> {
+    let pk = &kp.public_key;
+    let flat: Vec<BigUint> = pairs.iter().flat_map(|(c1, c2)| [c1.clone(), c2.clone()]).collect();
+    let ms = p_decrypt_batch(&flat, pk, &kp.secret_key, default_pool())?;
+    let out = ms.chunks(2)
+        .map(|m| p_encrypt(&((&m[0] * &m[1]) % &pk.n), pk))
+        .collect::<Result<Vec<_>, _>>()?;
+    println!("  [Phase 3] {} : {} produits de masques rechiffres.", label, out.len());
+    Ok(out)
+}
+
+pub fn phase4_delta_decrypt_total(label: &str, ct: &BigUint, kp: &KeyPair) -> Result<usize, CryptoError> {
+    let m = p_decrypt(ct, &kp.public_key, &kp.secret_key)?;
+    let c = m.to_usize()
+        .ok_or_else(|| CryptoError::InvalidInput("delta : cardinal hors bornes (etat corrompu ?)".into()))?;
+    println!("  [Phase 4] {} : cardinal courant dechiffre -> {}", label, c);
+    Ok(c)
+}
+
+// ============================================================================
+// Session côté BD
+// ============================================================================
+
+#[derive(Serialize, Deserialize)]
+pub struct DeltaSession {
+    pub session_id: u64,
+    pub epoch:      u64,
+    pub bd_id:      u8,
+    pub table_bits: u32,
+    /// Clé de hachage K (hex) : secrète, comme sk
+    hash_key:       String,
+    pk_other:       PublicKeyJson,
+    /// Positions envoyées à l'époque `epoch`, triées
+    pub positions:  Vec<usize>,
+}
+
+impl DeltaSession {
+    pub fn new(session_id: u64, bd_id: u8, hasher: &PositionHasher, pk_other: &PublicKey) -> Self {
+        DeltaSession {
+            session_id,
+            epoch:      0,
+            bd_id,
+            table_bits: hasher.table_bits(),
+            hash_key:   fingerprint_to_hex(hasher.key()),
+            pk_other:   public_key_to_json(pk_other),
+            positions:  Vec::new(),
+        }
+    }
+
+    pub fn load(path: &str) -> Result<Self, CryptoError> {
+        let text = read_state(path)?;
+        parse_json(path, &text)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), CryptoError> {
+        write_state(path, self)
+    }
+
+    pub fn hasher(&self) -> Result<PositionHasher, CryptoError> {
+        PositionHasher::new(hex_to_fingerprint(&self.hash_key)?, self.table_bits)
+    }
+
+    pub fn pk_other(&self) -> Result<PublicKey, CryptoError> {
+        json_to_public_key(&self.pk_other)
+    }
+
+    /// (ajoutées, retirées) par rapport à l'époque enregistrée, triées
+    pub fn diff(&self, table: &SparseTable) -> (Vec<usize>, Vec<usize>) {
+        let prev: HashSet<usize> = self.positions.iter().copied().collect();
+        let mut added: Vec<usize> = table.active.difference(&prev).copied().collect();
+        let mut removed: Vec<usize> = prev.difference(&table.active).copied().collect();
+        added.sort_unstable();
+        removed.sort_unstable();
+        (added, removed)
+    }
+
+    pub fn advance(&mut self, epoch: u64, table: &SparseTable) {
+        self.epoch = epoch;
+        self.positions = table.active.iter().copied().collect();
+        self.positions.sort_unstable();
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exactmatch::exactmatch::phase2_prepare_dual_ft;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    fn table(positions: &[usize]) -> SparseTable {
+        SparseTable { active: positions.iter().copied().collect() }
+    }
+
+    /// Une époque complète : différences, nouveaux produits, masques
+    fn epoch(state: &mut DeltaState, kps: [&KeyPair; 2], deltas: [(&[usize], &[usize]); 2]) -> [usize; 2] {
+        let (pk1, pk2) = (state.pk1.clone(), state.pk2.clone());
+        for (bd, (add, rem)) in [(1u8, deltas[0]), (2, deltas[1])] {
+            let bundle = phase2_prepare_dual_ft("BD", &table(add), &pk1, &pk2);
+            state.apply_delta(bd, rem, &bundle).unwrap();
+        }
+        let pending = state.pending_products();
+        let (t1, t2) = state.compute_products(&pending).unwrap();
+        let mut bb = Vec::new();
+        for (t, kp) in [(&t1, kps[0]), (&t2, kps[1])] {
+            let (order, pairs) = phase3_delta_mask_pairs(t);
+            let shuffled = phase4_delta_mask_products("BD", &pairs, kp).unwrap();
+            bb.push(phase3_delta_unshuffle(&order, shuffled).unwrap());
+        }
+        state.absorb_products(&pending, [&t1, &t2], [&bb[0], &bb[1]]).unwrap();
+        [1u8, 2].map(|k| {
+            let ct = state.encrypted_total(k).unwrap();
+            phase4_delta_decrypt_total("BD", &ct, kps[k as usize - 1]).unwrap()
+        })
+    }
+
+    #[test]
+    fn test_delta_updates_cardinal_incrementally() {
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let mut state = DeltaState::new(new_session_id(), kp1.public_key.clone(), kp2.public_key.clone(), 12);
+        let kps = [&kp1, &kp2];
+
+        // Époque 1 : {1..5} ∩ {3..8} = {3, 4, 5}
+        assert_eq!(epoch(&mut state, kps, [(&[1, 2, 3, 4, 5], &[]), (&[3, 4, 5, 6, 7, 8], &[])]), [3, 3]);
+        // Époque 2 : BD1 retire 4, ajoute 6 et 9 ; BD2 ajoute 9 -> {3, 5, 6, 9}
+        assert_eq!(epoch(&mut state, kps, [(&[6, 9], &[4]), (&[9], &[])]), [4, 4]);
+        assert_eq!(state.pending_products(), Vec::<usize>::new());
+
+        // Persistance : même cardinal après rechargement
+        let path = std::env::temp_dir().join(format!("delta_state_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        state.save(path).unwrap();
+        let mut state = DeltaState::load(path).unwrap();
+        fs::remove_file(path).unwrap();
+        // Époque 3 : BD2 retire 3 et 9 -> {5, 6}
+        assert_eq!(epoch(&mut state, kps, [(&[], &[]), (&[], &[3, 9])]), [2, 2]);
+        assert!(state.apply_delta(1, &[42], &phase2_prepare_dual_ft("BD1", &table(&[]), &kp1.public_key, &kp2.public_key)).is_err());
+    }
+}
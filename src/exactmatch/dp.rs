@@ -0,0 +1,313 @@
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use num_bigint::{BigInt, BigUint};
+use rand::Rng;
+use rand_core::OsRng;
+use serde::{Deserialize, Serialize};
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_keygen::PublicKey;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::cf_stats::FixedPoint;
+use crate::KeyPair;
+
+// ============================================================================
+// Publication différentiellement privée du cardinal (--dp ε)
+//
+// Le cardinal exact |BD1 ∩ BD2| trahit la présence d'une personne quand
+// deux bases ne diffèrent que d'un enregistrement (sensibilité Δ = 1).
+// On publie c + N, où N est un bruit de Laplace discret (géométrique
+// bilatéral) de paramètre α = e^-ε, ajouté sous chiffrement :
+//
+//   Phase 3  (Serveur) : agrégation bourrée, comme --threshold :
+//            α_agg = Π C0_i = Enc(c - Σ b_i b'_i) reste chez le serveur,
+//            les masques, re-randomisés (phase3_rerandomize_masks),
+//            partent vers le BD. Sans cette re-randomisation, un BD
+//            reconnaîtrait ses masques de Phase 2, compterait les vrais
+//            triplets (c exact) et retrancherait c de la sortie : il
+//            connaîtrait alors le bruit total.
+//   Phase 4a (BD j)    : Enc_j(B_j) et une part de bruit z_j tirée
+//            localement, chiffrée sous pk1 ET pk2
+//   Phase 4b (Serveur) : Enc_i(c + z_s + z_1 + z_2)
+//            = α_agg · Enc_i(B_i) · Enc_i(z_s) · Enc_i(z_1) · Enc_i(z_2)
+//   Phase 4c (BD i)    : Dec signé -> c + N
+//
+// Les trois parts sont des Laplace discrets complets et indépendants :
+// chaque participant connaît au plus la sienne, il reste au moins deux
+// parts inconnues (somme ε-DP par post-traitement). Les deux BD reçoivent
+// la même valeur bruitée : aucune moyenne possible entre les deux sorties.
+// Coût : variance 3 · 2α / (1 - α)², trois fois le minimum centralisé.
+//
+// Portée : le serveur voit les positions communes (--size-hiding ne lui
+// cache que les tailles), donc c exact. Le bruit ne protège c que face
+// à l'autre BD et aux destinataires de la publication, pas face au
+// serveur.
+//
+// Chaque BD tient un registre de budget (--dp-ledger) : ε est débité
+// AVANT l'exécution, une publication interrompue compte aussi.
+// ============================================================================
+
+/// Nombre de parts de bruit (serveur, BD1, BD2)
+pub const DP_NOISE_SHARES: u32 = 3;
+
+/// Tolérance des comparaisons de budget (sommes de flottants)
+const BUDGET_EPS: f64 = 1e-9;
+
+/// Taille maximale d'un registre (1 Mo)
+const MAX_LEDGER_BYTES: u64 = 1 << 20;
+
+/// ε doit être fini et strictement positif
+pub fn check_epsilon(epsilon: f64) -> Result<f64, CryptoError> {
+    if epsilon.is_finite() && epsilon > 0.0 {
+        Ok(epsilon)
+    } else {
+        Err(CryptoError::InvalidInput(format!("epsilon {} invalide (> 0 requis)", epsilon)))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Laplace discret : P(Z = z) ∝ α^|z|, α = e^-ε
+// ---------------------------------------------------------------------------
+
+/// Z = G1 - G2, G géométrique (nombre d'échecs, succès 1 - α),
+/// tiré par inversion : G = ⌊ln U / ln α⌋, U ∈ (0, 1]
+///
+/// Inversion en flottant : U n'a que 53 bits, donc les probabilités
+/// sont arrondies à ~2^-53 près et la queue est tronquée (G ≤ 37/ε
+/// environ). La sortie étant entière, la mantisse ne fuit pas (pas
+/// d'attaque de Mironov sur les bits de poids faible), mais la loi
+/// n'est qu'approchée : la garantie est ε-DP à un écart de l'ordre
+/// de 2^-53 près, pas ε-DP exacte.
+pub fn sample_discrete_laplace<R: Rng + ?Sized>(epsilon: f64, rng: &mut R) -> i64 {
+    let ln_alpha = -epsilon;
+    let mut geometric = || {
+        let u = 1.0 - rng.gen::<f64>();
+        (u.ln() / ln_alpha).floor() as i64
+    };
+    geometric() - geometric()
+}
+
+/// Variance d'une part : 2α / (1 - α)²
+pub fn discrete_laplace_variance(epsilon: f64) -> f64 {
+    let alpha = (-epsilon).exp();
+    2.0 * alpha / ((1.0 - alpha) * (1.0 - alpha))
+}
+
+// ---------------------------------------------------------------------------
+// Phase 4a — BD : part de bruit chiffrée sous les deux clés
+// ---------------------------------------------------------------------------
+
+/// Retourne (Enc_pk1(z), Enc_pk2(z)) ; z ne quitte jamais le BD en clair
+pub fn phase4_dp_noise_share(
+    label:   &str,
+    epsilon: f64,
+    pk1:     &PublicKey,
+    pk2:     &PublicKey,
+) -> Result<(BigUint, BigUint), CryptoError> {
+    let z = BigInt::from(sample_discrete_laplace(check_epsilon(epsilon)?, &mut OsRng));
+    let c1 = p_encrypt(&FixedPoint::to_zn(&z, &pk1.n)?, pk1)?;
+    let c2 = p_encrypt(&FixedPoint::to_zn(&z, &pk2.n)?, pk2)?;
+    println!("  [Phase 4] {} : part de bruit (epsilon = {}) chiffree sous pk1 et pk2.", label, epsilon);
+    Ok((c1, c2))
+}
+
+// ---------------------------------------------------------------------------
+// Phase 4b — Serveur : Enc(c + Σ bruits)
+// ---------------------------------------------------------------------------
+
+/// `shares` : parts des BD sous pk ; `server_noise` : part du serveur,
+/// tirée UNE fois pour les deux clés (même valeur publiée aux deux BD)
+pub fn phase4_dp_combine(
+    alpha:        &BigUint,
+    enc_b:        &BigUint,
+    shares:       &[BigUint],
+    server_noise: i64,
+    pk:           &PublicKey,
+) -> Result<BigUint, CryptoError> {
+    for ct in std::iter::once(enc_b).chain(shares) {
+        if ct >= &pk.n_squared {
+            return Err(CryptoError::CiphertextOutOfRange);
+        }
+    }
+    let own = p_encrypt(&FixedPoint::to_zn(&BigInt::from(server_noise), &pk.n)?, pk)?;
+    Ok(shares.iter()
+        .chain([enc_b, &own])
+        .fold(alpha % &pk.n_squared, |acc, ct| (acc * ct) % &pk.n_squared))
+}
+
+// ---------------------------------------------------------------------------
+// Phase 4c — BD : cardinal bruité (signé, peut être négatif)
+// ---------------------------------------------------------------------------
+
+pub fn phase4_dp_decrypt(label: &str, ct: &BigUint, kp: &KeyPair) -> Result<BigInt, CryptoError> {
+    let m = p_decrypt(ct, &kp.public_key, &kp.secret_key)?;
+    println!("  [Phase 4] {} : cardinal bruite dechiffre.", label);
+    Ok(FixedPoint::from_zn(&m, &kp.public_key.n))
+}
+
+// ============================================================================
+// Registre de budget (JSON, un fichier par base de données)
+//
+//   { "budget": 2.0,
+//     "releases": [ { "epsilon": 0.5, "unix_time": 1700000000, "dataset": "bd1.csv" } ] }
+//
+// Le budget total est fixé à la création (--dp-budget) ; une base
+// existante le relit et refuse un budget différent.
+// ============================================================================
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DpRelease {
+    pub epsilon:   f64,
+    pub unix_time: u64,
+    pub dataset:   String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DpLedger {
+    pub budget:   f64,
+    #[serde(default)]
+    pub releases: Vec<DpRelease>,
+}
+
+impl DpLedger {
+    pub fn new(budget: f64) -> Result<Self, CryptoError> {
+        Ok(DpLedger { budget: check_epsilon(budget)?, releases: Vec::new() })
+    }
+
+    /// Ouvre `path` ; s'il n'existe pas, `budget` est requis pour le créer
+    pub fn open(path: &str, budget: Option<f64>) -> Result<Self, CryptoError> {
+        if !Path::new(path).exists() {
+            return match budget {
+                Some(b) => Self::new(b),
+                None    => Err(CryptoError::InvalidInput(format!(
+                    "registre {} absent : --dp-budget requis pour le créer", path
+                ))),
+            };
+        }
+        let io_err = |e: std::io::Error| CryptoError::InvalidInput(format!("{} : {}", path, e));
+        if fs::metadata(path).map_err(io_err)?.len() > MAX_LEDGER_BYTES {
+            return Err(CryptoError::InvalidInput(format!("{} dépasse {} octets", path, MAX_LEDGER_BYTES)));
+        }
+        let ledger: DpLedger = serde_json::from_str(&fs::read_to_string(path).map_err(io_err)?)
+            .map_err(|e| CryptoError::InvalidInput(format!("{} : {}", path, e)))?;
+        check_epsilon(ledger.budget)?;
+        if let Some(b) = budget {
+            if (b - ledger.budget).abs() > BUDGET_EPS {
+                return Err(CryptoError::InvalidInput(format!(
+                    "{} : budget {} enregistré, --dp-budget {} refusé", path, ledger.budget, b
+                )));
+            }
+        }
+        Ok(ledger)
+    }
+
+    pub fn spent(&self) -> f64 {
+        self.releases.iter().map(|r| r.epsilon).sum()
+    }
+
+    pub fn remaining(&self) -> f64 {
+        (self.budget - self.spent()).max(0.0)
+    }
+
+    /// Débite ε (composition séquentielle) ; refuse si le budget est dépassé
+    pub fn charge(&mut self, epsilon: f64, dataset: &str) -> Result<(), CryptoError> {
+        check_epsilon(epsilon)?;
+        if self.spent() + epsilon > self.budget + BUDGET_EPS {
+            return Err(CryptoError::InvalidInput(format!(
+                "budget de confidentialité épuisé : {:.4} dépensé sur {:.4}, epsilon {} demandé",
+                self.spent(), self.budget, epsilon
+            )));
+        }
+        let unix_time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        self.releases.push(DpRelease { epsilon, unix_time, dataset: dataset.to_string() });
+        Ok(())
+    }
+
+    /// Écriture atomique (fichier temporaire puis renommage)
+    pub fn save(&self, path: &str) -> Result<(), CryptoError> {
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| CryptoError::InvalidInput(e.to_string()))?;
+        let tmp = format!("{}.tmp", path);
+        fs::write(&tmp, json)
+            .and_then(|_| fs::rename(&tmp, path))
+            .map_err(|e| CryptoError::InvalidInput(format!("{} : {}", path, e)))
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    #[test]
+    fn test_dp_noise_and_ledger() {
+        // Bruit centré, variance conforme à 2α / (1 - α)²
+        let eps = 1.0;
+        let draws: Vec<i64> = (0..20_000).map(|_| sample_discrete_laplace(eps, &mut OsRng)).collect();
+        let mean = draws.iter().sum::<i64>() as f64 / draws.len() as f64;
+        let var = draws.iter().map(|&z| (z as f64 - mean).powi(2)).sum::<f64>() / draws.len() as f64;
+        assert!(mean.abs() < 0.1, "moyenne {}", mean);
+        assert!((var / discrete_laplace_variance(eps) - 1.0).abs() < 0.15, "variance {}", var);
+
+        // Bruit homomorphe : Enc(c - B) · Enc(B) · parts -> c + bruit
+        let kp = p_keygen(256).unwrap();
+        let pk = &kp.public_key;
+        let c = 7u32;
+        let b = BigUint::from(1000u32);
+        let alpha = p_encrypt(&((BigUint::from(c) + &pk.n - &b) % &pk.n), pk).unwrap();
+        let enc_b = p_encrypt(&b, pk).unwrap();
+        let (s1, _) = phase4_dp_noise_share("BD1", 50.0, pk, pk).unwrap();
+        let (_, s2) = phase4_dp_noise_share("BD2", 50.0, pk, pk).unwrap();
+        let ct = phase4_dp_combine(&alpha, &enc_b, &[s1, s2], sample_discrete_laplace(50.0, &mut OsRng), pk).unwrap();
+        // ε = 50 : bruit nul sauf probabilité ~e^-50
+        assert_eq!(phase4_dp_decrypt("BD1", &ct, &kp).unwrap(), BigInt::from(c));
+
+        // Registre : composition séquentielle, refus au-delà du budget
+        let mut ledger = DpLedger::new(1.0).unwrap();
+        ledger.charge(0.6, "bd1.csv").unwrap();
+        assert!(ledger.charge(0.5, "bd1.csv").is_err());
+        ledger.charge(0.4, "bd1.csv").unwrap();
+        assert!(ledger.remaining() < 1e-6);
+        let path = std::env::temp_dir().join(format!("dp_ledger_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        ledger.save(path).unwrap();
+        assert_eq!(DpLedger::open(path, None).unwrap(), ledger);
+        assert!(DpLedger::open(path, Some(2.0)).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_dp_masks_do_not_reveal_cardinal() {
+        use num_bigint::RandBigInt;
+        use crate::exactmatch::exactmatch::{phase3_server_aggregate, phase4_threshold_mask_sum};
+        use crate::fiore_catalano::cf_batch::cf_batch::{cf_encrypt_batch, cf_mul_batch};
+        use crate::parallel::default_pool;
+
+        let kp = p_keygen(256).unwrap();
+        let pk = &kp.public_key;
+
+        // 2 produits 1·1, un produit 1·0, bourrés à 6
+        let bits: Vec<BigUint> = [1u32, 1, 1, 1, 1, 0].iter().map(|&b| BigUint::from(b)).collect();
+        let masks: Vec<BigUint> = bits.iter().map(|_| OsRng.gen_biguint_below(&pk.n)).collect();
+        let fts = cf_encrypt_batch(&bits, &masks, pk, default_pool()).unwrap();
+        let pairs: Vec<_> = fts.chunks(2).map(|c| (&c[0], &c[1])).collect();
+        let triplets = cf_mul_batch(&pairs, pk, default_pool()).unwrap();
+        let agg = phase3_server_aggregate(&triplets, 6, pk).unwrap();
+
+        // Le BD qui a gardé ses masques n'en retrouve aucun : il ne peut
+        // ni compter les vrais triplets ni isoler le bruit
+        let seen: Vec<BigUint> = agg.betas.iter()
+            .flat_map(|(c1, c2)| [c1, c2])
+            .map(|c| p_decrypt(c, pk, &kp.secret_key).unwrap())
+            .collect();
+        assert!(masks.iter().all(|b| !seen.contains(b)));
+
+        let enc_b = phase4_threshold_mask_sum("BD1", &agg.betas, &kp).unwrap();
+        let ct = phase4_dp_combine(&agg.alpha, &enc_b, &[], 0, pk).unwrap();
+        assert_eq!(phase4_dp_decrypt("BD1", &ct, &kp).unwrap(), BigInt::from(2));
+    }
+}
@@ -3,7 +3,8 @@
 // via Catalano-Fiore (1 niveau de Mul)
 //
 
-//   (1) Hash 30 bits, table creuse.
+//   (1) Hash a cle HMAC-SHA-256 (position_hash.rs), table creuse
+//       de 2^table_bits positions (defaut 30).
 //   (2) Serveur ne reçoit jamais les masques en clair.
 //   (3) n1 != n2 : phase2 prend kp1 ET kp2, génère des
 //        masques distincts dans Z_{n1} et Z_{n2}.
@@ -11,27 +12,45 @@
 //   (5) Phase 4 : cf_mul_dec (Dec2) + somme.
 // =========================================================
 
-use num_bigint::{BigUint, RandBigInt};
+use num_bigint::{BigInt, BigUint, RandBigInt};
+use num_traits::{One, ToPrimitive, Zero};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use rayon::prelude::*;
 use rand_core::OsRng;
-use crate::paillier::p_keygen::PublicKey;
 use std::collections::{HashMap, HashSet};
 use std::time::Instant;
 
 
 // ---------------------------------------------------------------------------
-use crate::fiore_catalano::cf_mul::cf_mul::cf_mul;
-use crate::fiore_catalano::cf_mul_dec::cf_mul_dec::cf_mul_dec;
+use crate::fiore_catalano::cf_batch::cf_batch::{cf_encrypt_batch, cf_mul_batch, cf_mul_dec_batch};
+use crate::parallel::default_pool;
+use crate::fiore_catalano::cf_sub::cf_sub::cf_sub;
+use crate::fiore_catalano::cf_sub::cf_scal::cf_scal;
+use crate::paillier::p_batch::p_batch::{p_decrypt_batch, p_encrypt_batch};
+use crate::fiore_catalano::cf_mul_mk::cf_mul_mk::{cf_encrypt_mk, cf_mul_mk, MkCfSnd};
+use crate::fiore_catalano::cf_mul_mk::cf_mul_mk_dec::{
+    cf_mul_mk_linearize, cf_mul_mk_aggregate, cf_mul_mk_partial_dec, cf_mul_mk_combine, MkCfLin,
+};
+use crate::fiore_catalano::cf_kea::{CfKeaFst, CfKeaSnd};
+use crate::fiore_catalano::cf_kea::cf_kea_encrypt::cf_kea_encrypt;
+use crate::fiore_catalano::cf_kea::cf_kea_mul::cf_kea_mul;
+use crate::fiore_catalano::cf_kea::cf_kea_mul_dec::cf_kea_mul_dec;
+use crate::paillier_kea::paillier_kea_keygen::{paillier_kea_keygen, KeyPairKEA};
 use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::exactmatch::position_hash::{
+    PositionHasher, HashKey, random_key_share, derive_hash_key,
+    share_to_biguint, biguint_to_share,
+    intra_collision_probability, expected_cross_collisions, cross_collision_probability,
+};
+use crate::exactmatch::multiset::DuplicateStats;
+use crate::exactmatch::cuckoo::{CuckooParams, CuckooTable, SimpleBins, comparison_pairs};
+use crate::cf_stats::{CfSndSum, FixedPoint, cf_sum_products_dec};
 use crate::paillier::p_keygen::p_keygen::p_keygen;
 use crate::KeyPair;
 
-// ---------------------------------------------------------
-// Constantes
-// ---------------------------------------------------------
-
-pub const HASH_BITS:  usize = 30;
-pub const TABLE_SIZE: usize = 1 << HASH_BITS;
-
 // ---------------------------------------------------------
 // Types alias
 // ---------------------------------------------------------
@@ -52,10 +71,10 @@ pub struct SparseTable {
 }
 
 impl SparseTable {
-    pub fn build(nss_list: &[String]) -> Self {
+    pub fn build(nss_list: &[String], hasher: &PositionHasher) -> Self {
         let mut active = HashSet::new();
         for nss in nss_list {
-            active.insert(simple_hash(nss));
+            active.insert(hasher.position(nss));
         }
         SparseTable { active }
     }
@@ -64,6 +83,10 @@ impl SparseTable {
         self.active.len()
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.active.is_empty()
+    }
+
     pub fn common_positions(&self, other: &SparseTable) -> Vec<usize> {
         let (small, big) = if self.active.len() <= other.active.len() {
             (&self.active, &other.active)
@@ -92,22 +115,25 @@ pub struct DualFtBundle {
 }
 
 // ---------------------------------------------------------
-// Hash (remplacer par SHA-256/BLAKE3 en production)
+// Chargement CSV — colonne "NSS"
+// Découpage naïf, panique sur erreur : les binaires passent par
+// records::load_records (RFC 4180, Result, lignes rejetées).
 // ---------------------------------------------------------
 
-pub fn simple_hash(s: &str) -> usize {
-    let mut h: u32 = 0;
-    for ch in s.chars() {
-        h = h.wrapping_shl(7).wrapping_sub(h).wrapping_add(ch as u32);
-    }
-    (h as usize) & (TABLE_SIZE - 1)
+pub fn load_nss_from_csv(path: &str) -> Vec<String> {
+    load_fields_from_csv(path, &["NSS"])
+        .into_iter()
+        .filter_map(|mut row| row.pop())
+        .collect()
 }
 
 // ---------------------------------------------------------
-// Chargement CSV — colonne "NSS"
+// Chargement CSV — plusieurs colonnes (appariement --fuzzy)
+// Une ligne est gardée si au moins un champ est non vide ;
+// les lignes trop courtes sont ignorées.
 // ---------------------------------------------------------
 
-pub fn load_nss_from_csv(path: &str) -> Vec<String> {
+pub fn load_fields_from_csv(path: &str, columns: &[&str]) -> Vec<Vec<String>> {
     use std::fs::File;
     use std::io::{BufRead, BufReader};
 
@@ -118,16 +144,24 @@ pub fn load_nss_from_csv(path: &str) -> Vec<String> {
 
     let header = lines.next().expect("Fichier vide").expect("Erreur lecture");
     let cols: Vec<&str> = header.split(',').collect();
-    let nss_col = cols
+    let idx: Vec<usize> = columns
         .iter()
-        .position(|c| c.trim() == "NSS")
-        .expect("Colonne 'NSS' introuvable");
+        .map(|name| {
+            cols.iter()
+                .position(|c| c.trim() == *name)
+                .unwrap_or_else(|| panic!("Colonne '{}' introuvable", name))
+        })
+        .collect();
 
     lines
         .filter_map(|line| {
             let line = line.ok()?;
-            let val = line.split(',').nth(nss_col)?.trim().to_string();
-            if val.is_empty() { None } else { Some(val) }
+            let fields: Vec<&str> = line.split(',').collect();
+            let row: Vec<String> = idx
+                .iter()
+                .map(|&i| fields.get(i).map(|v| v.trim().to_string()))
+                .collect::<Option<_>>()?;
+            if row.iter().all(String::is_empty) { None } else { Some(row) }
         })
         .collect()
 }
@@ -148,30 +182,90 @@ pub fn phase0_keygen(label: &str, bits: u64) -> KeyPair {
 // Phase 1 — Table creuse
 // ---------------------------------------------------------
 
-pub fn phase1_build_table(label: &str, nss_list: &[String]) -> SparseTable {
+pub fn phase1_build_table(label: &str, nss_list: &[String], hasher: &PositionHasher) -> SparseTable {
     println!(
-        "  [Phase 1] {} : {} NSS, TABLE_SIZE=2^{}...",
-        label, nss_list.len(), HASH_BITS
+        "  [Phase 1] {} : {} NSS, TABLE_SIZE=2^{} (HMAC-SHA-256)...",
+        label, nss_list.len(), hasher.table_bits()
     );
-    let table = SparseTable::build(nss_list);
+    let table    = SparseTable::build(nss_list, hasher);
+    let distinct = nss_list.iter().collect::<HashSet<_>>().len();
+    // Les doublons sont fusionnés ici (cf. --multiset pour les compter)
+    DuplicateStats::from_keys(nss_list).report(label);
     println!("  [Phase 1] {} : {} position(s) active(s).", label, table.len());
+    println!(
+        "  [Phase 1] {} : P(collision interne) = {:.3e}, collisions observees = {}",
+        label,
+        intra_collision_probability(distinct, hasher.table_bits()),
+        distinct - table.len()
+    );
     table
 }
 
 // ---------------------------------------------------------
-// Helper : CF.Enc(1, b) = ( (1 - b) mod n, Enc_pk(b) )
+// Phase 0d — Accord sur la cle de hachage des positions
+//
+// Chaque BD tire une part, l'envoie chiffree sous pk_other
+// (le serveur relaie sans pouvoir dechiffrer), puis derive
+// K a partir des deux parts (cf. position_hash.rs).
 // ---------------------------------------------------------
 
+/// Tire la part locale et la chiffre sous pk_other
+pub fn phase0_hash_key_share(
+    label:    &str,
+    pk_other: &crate::paillier::p_keygen::PublicKey,
+) -> (HashKey, BigUint) {
+    let share = random_key_share();
+    let ct    = p_encrypt(&share_to_biguint(&share), pk_other)
+        .expect("p_encrypt(part de cle) a echoue");
+    println!("  [Phase 0d] {} : part de cle de hachage chiffree sous pk_other.", label);
+    (share, ct)
+}
+
+/// Dechiffre la part de l'autre BD et construit le hacheur.
+/// bd_id fixe l'ordre des parts : K ne depend pas du role local.
+pub fn phase0_derive_hasher(
+    label:       &str,
+    bd_id:       u8,
+    own_share:   &HashKey,
+    other_ct:    &BigUint,
+    kp_self:     &KeyPair,
+    psk:         Option<&HashKey>,
+    table_bits:  u32,
+) -> Result<PositionHasher, CryptoError> {
+    let other = biguint_to_share(&p_decrypt(other_ct, &kp_self.public_key, &kp_self.secret_key)?)?;
+    let key = if bd_id == 1 {
+        derive_hash_key(own_share, &other, psk)
+    } else {
+        derive_hash_key(&other, own_share, psk)
+    };
+    println!(
+        "  [Phase 0d] {} : cle de hachage derivee (psk : {}).",
+        label, if psk.is_some() { "oui" } else { "non" }
+    );
+    PositionHasher::new(key, table_bits)
+}
+
 // ---------------------------------------------------------
-// Helper : CF.Enc(1, b) sous une PublicKey donnée
-// Raison : le chiffrement ne nécessite que n, g, n_squared.
+// Estimation des faux positifs (cote serveur, apres Phase 2)
 // ---------------------------------------------------------
-fn make_ft_for_one(b: &BigUint, pk: &crate::paillier::p_keygen::PublicKey) -> CfFst {
-    let n     = &pk.n;
-    let b_mod = b % n;
-    let c0    = (BigUint::from(1u32) + n - &b_mod) % n;
-    let c1    = p_encrypt(&b_mod, pk).expect("p_encrypt(b) a echoue");
-    (c0, c1)
+
+pub fn report_cross_collisions(table1: &SparseTable, table2: &SparseTable, table_bits: u32) {
+    println!(
+        "  [Collisions] 2^{} positions, |T1|={}, |T2|={} : faux positifs attendus <= {:.3e}, P(>=1) = {:.3e}",
+        table_bits, table1.len(), table2.len(),
+        expected_cross_collisions(table1.len(), table2.len(), table_bits),
+        cross_collision_probability(table1.len(), table2.len(), table_bits),
+    );
+}
+
+// ---------------------------------------------------------
+// Helper : CF.Enc(1, b) = ( (1 - b) mod n, Enc_pk(b) ) pour
+// chaque masque, en parallèle sur le pool par défaut.
+// ft[i] correspond à masques[i] (ordre conservé).
+// ---------------------------------------------------------
+pub(crate) fn make_ft_for_ones(masques: &[BigUint], pk: &crate::paillier::p_keygen::PublicKey) -> Vec<CfFst> {
+    let ones = vec![BigUint::one(); masques.len()];
+    cf_encrypt_batch(&ones, masques, pk, default_pool()).expect("cf_encrypt_batch(1, b) a echoue")
 }
 
 // ---------------------------------------------------------
@@ -207,19 +301,15 @@ pub fn phase2_prepare_dual_ft(
     );
 
     let mut rng = OsRng;
-    let mut ft_pk1: HashMap<usize, CfFst> = HashMap::with_capacity(table.len());
-    let mut ft_pk2: HashMap<usize, CfFst> = HashMap::with_capacity(table.len());
+    let positions: Vec<usize> = table.active.iter().copied().collect();
 
-    for &pos in table.active.iter() {
-        // Masque b1 tiré dans Z_{n1} → Ft chiffré sous pk1
-        let b1 = rng.gen_biguint_below(&pk1.n);
-        ft_pk1.insert(pos, make_ft_for_one(&b1, pk1));
+    // Masques b1 tirés dans Z_{n1}, b2 dans Z_{n2} (indépendants : n1 ≠ n2
+    // en général). Tirage séquentiel, chiffrement parallèle.
+    let b1s: Vec<BigUint> = positions.iter().map(|_| rng.gen_biguint_below(&pk1.n)).collect();
+    let b2s: Vec<BigUint> = positions.iter().map(|_| rng.gen_biguint_below(&pk2.n)).collect();
 
-        // Masque b2 tiré dans Z_{n2} → Ft chiffré sous pk2
-        // b2 est indépendant de b1 : n1 ≠ n2 en général
-        let b2 = rng.gen_biguint_below(&pk2.n);
-        ft_pk2.insert(pos, make_ft_for_one(&b2, pk2));
-    }
+    let ft_pk1: HashMap<usize, CfFst> = positions.iter().copied().zip(make_ft_for_ones(&b1s, pk1)).collect();
+    let ft_pk2: HashMap<usize, CfFst> = positions.iter().copied().zip(make_ft_for_ones(&b2s, pk2)).collect();
 
     println!("  [Phase 2] {} : Ft prets (le serveur ne voit jamais b en clair).", label);
 
@@ -229,6 +319,81 @@ pub fn phase2_prepare_dual_ft(
     }
 }
 
+// ---------------------------------------------------------
+// Phase 2 (--size-hiding --pad-to P) — bundle de taille fixe
+//
+// Les positions actives sont complétées par des positions
+// factices tirées uniformément dans [0, 2^table_bits), hors de
+// la table : Ft(1) pour une position active, Ft(0) sinon. Le
+// serveur reçoit P positions de chaque BD quelle que soit |BD| ;
+// les positions étant des sorties HMAC sous K, il ne distingue
+// pas une position factice d'une réelle. Phase 3 et 4 sont
+// inchangées (Ft(0)·x = 0).
+//
+// table_bits reste grand (défaut 30) : le taux de collisions est
+// celui du mode standard pour deux tables de P positions. Le
+// serveur voit encore les positions communes, donc le cardinal à
+// quelques collisions factices près ; seules les tailles lui sont
+// cachées.
+//
+// Coût : P Ft par clé au lieu de |table|.
+// ---------------------------------------------------------
+
+pub fn phase2_prepare_dual_ft_padded(
+    label:  &str,
+    table:  &SparseTable,
+    hasher: &PositionHasher,
+    pad_to: usize,
+    pk1:    &crate::paillier::p_keygen::PublicKey,
+    pk2:    &crate::paillier::p_keygen::PublicKey,
+) -> Result<DualFtBundle, CryptoError> {
+    if table.len() > pad_to || pad_to as u64 > hasher.table_size() {
+        return Err(CryptoError::InvalidInput(format!(
+            "mode size-hiding : --pad-to {} hors de [{}, 2^{}]", pad_to, table.len(), hasher.table_bits()
+        )));
+    }
+    println!(
+        "  [Phase 2] {} : bundle bourré — {} positions actives completees a {} (sous pk1 et pk2)...",
+        label, table.len(), pad_to
+    );
+
+    let mut rng = OsRng;
+    let mut positions: Vec<usize> = table.active.iter().copied().collect();
+    let mut taken = table.active.clone();
+    while positions.len() < pad_to {
+        let pos = rng.gen_range(0..hasher.table_size()) as usize;
+        if taken.insert(pos) {
+            positions.push(pos);
+        }
+    }
+    let bits: Vec<BigUint> = (0..pad_to)
+        .map(|i| if i < table.len() { BigUint::one() } else { BigUint::zero() })
+        .collect();
+    let b1s: Vec<BigUint> = bits.iter().map(|_| rng.gen_biguint_below(&pk1.n)).collect();
+    let b2s: Vec<BigUint> = bits.iter().map(|_| rng.gen_biguint_below(&pk2.n)).collect();
+
+    let pool   = default_pool();
+    let ft_pk1 = cf_encrypt_batch(&bits, &b1s, pk1, pool)?;
+    let ft_pk2 = cf_encrypt_batch(&bits, &b2s, pk2, pool)?;
+
+    Ok(DualFtBundle {
+        under_pk1: FtBundle { ft_by_pos: positions.iter().copied().zip(ft_pk1).collect() },
+        under_pk2: FtBundle { ft_by_pos: positions.iter().copied().zip(ft_pk2).collect() },
+    })
+}
+
+/// Serveur : un bundle bourré porte exactement P positions, les
+/// mêmes sous pk1 et pk2 ; un bundle plus court trahirait |BD|
+pub fn check_padded_bundle(label: &str, bundle: &DualFtBundle, pad_to: usize) -> Result<(), CryptoError> {
+    let (u1, u2) = (&bundle.under_pk1.ft_by_pos, &bundle.under_pk2.ft_by_pos);
+    if u1.len() != pad_to || u2.len() != pad_to || !u1.keys().all(|p| u2.contains_key(p)) {
+        return Err(CryptoError::InvalidInput(format!(
+            "{} : bundle de {} / {} positions, {} attendues en mode size-hiding", label, u1.len(), u2.len(), pad_to
+        )));
+    }
+    Ok(())
+}
+
 // ---------------------------------------------------------
 // Phase 3 — Serveur : CF.Mul sur les positions communes
 // ---------------------------------------------------------
@@ -241,37 +406,511 @@ pub fn phase3_server_compute(
     kp1:    &KeyPair,
     kp2:    &KeyPair,
 ) -> (Vec<CfSnd>, Vec<CfSnd>) {
+    let (_, out_pk1, out_pk2) = phase3_server_compute_positions(table1, table2, bd1, bd2, kp1, kp2);
+    (out_pk1, out_pk2)
+}
+
+/// Comme phase3_server_compute, avec la position de chaque triplet
+/// (mode --reveal : les BD retrouvent leurs enregistrements)
+pub fn phase3_server_compute_positions(
+    table1: &SparseTable,
+    table2: &SparseTable,
+    bd1:    &DualFtBundle,
+    bd2:    &DualFtBundle,
+    kp1:    &KeyPair,
+    kp2:    &KeyPair,
+) -> (Vec<usize>, Vec<CfSnd>, Vec<CfSnd>) {
     println!("  [Phase 3] Serveur : CF.Mul sur les positions communes...");
     let t_start = Instant::now();
 
     let common = table1.common_positions(table2);
     println!("  [Phase 3] {} position(s) commune(s).", common.len());
 
-    let mut out_pk1: Vec<CfSnd> = Vec::with_capacity(common.len());
-    let mut out_pk2: Vec<CfSnd> = Vec::with_capacity(common.len());
+    let mut pairs_pk1 = Vec::with_capacity(common.len());
+    let mut pairs_pk2 = Vec::with_capacity(common.len());
+
+    for &pos in common.iter() {
+        // Opérandes sous pk1
+        let ft1   = bd1.under_pk1.ft_by_pos.get(&pos)
+            .expect("BD1 Ft(pk1) manquant pour une position commune");
+        let ft1_p = bd2.under_pk1.ft_by_pos.get(&pos)
+            .expect("BD2 Ft(pk1) manquant pour une position commune");
+        pairs_pk1.push((ft1, ft1_p));
+
+        // Opérandes sous pk2
+        let ft2   = bd1.under_pk2.ft_by_pos.get(&pos)
+            .expect("BD1 Ft(pk2) manquant pour une position commune");
+        let ft2_p = bd2.under_pk2.ft_by_pos.get(&pos)
+            .expect("BD2 Ft(pk2) manquant pour une position commune");
+        pairs_pk2.push((ft2, ft2_p));
+    }
+
+    // CF.Mul en parallèle ; out[i] correspond à common[i]
+    let pool    = default_pool();
+    let out_pk1 = cf_mul_batch(&pairs_pk1, &kp1.public_key, pool).expect("cf_mul(pk1) a echoue");
+    let out_pk2 = cf_mul_batch(&pairs_pk2, &kp2.public_key, pool).expect("cf_mul(pk2) a echoue");
+
+    println!(
+        "  [Phase 3] termine en {:.3?} ({} CF.Mul x 2 cles).",
+        t_start.elapsed(), out_pk1.len()
+    );
+
+    (common, out_pk1, out_pk2)
+}
+
+// ---------------------------------------------------------
+// Phase 4 — BD : Dec2 sur chaque triplet + somme
+//
+// Chaque produit déchiffré vaut 0 ou 1 pour un serveur honnête.
+// La somme est gardée en BigUint (aucun repli modulo 2^64) et
+// les triplets hors {0, 1} sont relevés : ils prouvent que le
+// serveur a dévié, et le cardinal est alors refusé.
+// ---------------------------------------------------------
+
+/// Triplets invalides détaillés dans le rapport
+pub const INVALID_TRIPLETS_SHOWN: usize = 10;
+
+/// Triplet dont le produit déchiffré n'est ni 0 ni 1
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidTriplet {
+    pub index: usize,
+    pub value: BigUint,
+}
+
+/// Somme exacte des produits déchiffrés
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CardinalTally {
+    pub sum:     BigUint,
+    pub total:   usize,
+    pub invalid: Vec<InvalidTriplet>,
+}
+
+impl CardinalTally {
+    pub fn from_values(values: &[BigUint]) -> Self {
+        let invalid = values.iter()
+            .enumerate()
+            .filter(|(_, m)| !m.is_zero() && !m.is_one())
+            .map(|(index, m)| InvalidTriplet { index, value: m.clone() })
+            .collect();
+        CardinalTally { sum: values.iter().sum(), total: values.len(), invalid }
+    }
+
+    pub fn is_valid(&self) -> bool {
+        self.invalid.is_empty()
+    }
+
+    /// Cardinal, refusé si un triplet est hors {0, 1} ou si la somme
+    /// dépasse u64
+    pub fn to_u64(&self) -> Result<u64, CryptoError> {
+        if let Some(first) = self.invalid.first() {
+            return Err(CryptoError::InvalidInput(format!(
+                "{} triplet(s) sur {} hors de {{0, 1}} (premier : #{}), resultat du serveur refuse",
+                self.invalid.len(), self.total, first.index
+            )));
+        }
+        self.sum.to_u64().ok_or_else(|| CryptoError::InvalidInput(format!(
+            "cardinal de {} bits, hors de u64", self.sum.bits()
+        )))
+    }
+
+    /// Liste des triplets invalides (les INVALID_TRIPLETS_SHOWN premiers)
+    pub fn report(&self, label: &str) {
+        if self.is_valid() {
+            return;
+        }
+        println!(
+            "  [Phase 4] {} : {} triplet(s) sur {} hors de {{0, 1}} — serveur deviant :",
+            label, self.invalid.len(), self.total
+        );
+        for t in self.invalid.iter().take(INVALID_TRIPLETS_SHOWN) {
+            if t.value.bits() <= 64 {
+                println!("    triplet #{:<8} valeur {}", t.index, t.value);
+            } else {
+                println!("    triplet #{:<8} valeur de {} bits", t.index, t.value.bits());
+            }
+        }
+        if self.invalid.len() > INVALID_TRIPLETS_SHOWN {
+            println!("    ... et {} autre(s).", self.invalid.len() - INVALID_TRIPLETS_SHOWN);
+        }
+    }
+}
+
+/// Dec2 de chaque triplet, sans conclure sur le cardinal
+pub fn phase4_decrypt_tally(cts: &[CfSnd], kp: &KeyPair) -> Result<CardinalTally, CryptoError> {
+    let ms = cf_mul_dec_batch(cts, &kp.public_key, &kp.secret_key, default_pool())?;
+    Ok(CardinalTally::from_values(&ms))
+}
+
+pub fn phase4_decrypt_and_count(label: &str, cts: &[CfSnd], kp: &KeyPair) -> Result<usize, CryptoError> {
+    println!(
+        "  [Phase 4] {} : Dec2 ({} triplets)...",
+        label, cts.len()
+    );
+    let t_start = Instant::now();
+
+    let tally = phase4_decrypt_tally(cts, kp)?;
+    tally.report(label);
+    let count = usize::try_from(tally.to_u64()?)
+        .map_err(|_| CryptoError::InvalidInput("cardinal hors de usize".into()))?;
+
+    println!(
+        "  [Phase 4] {} : termine en {:.3?}  ->  cardinal = {}",
+        label, t_start.elapsed(), count
+    );
+
+    Ok(count)
+}
+
+// =========================================================
+// Variante PSI complète (--reveal)
+//
+// Le serveur joint à chaque triplet sa position (index du bundle,
+// déjà connu de lui). Chaque BD déchiffre les triplets un par un
+// au lieu de les sommer, garde les positions qui valent 1 et
+// retrouve SES enregistrements qui y sont hachés. L'autre BD ne
+// reçoit rien de plus ; les positions non communes ne quittent
+// pas le serveur (mode --size-hiding : elles valent 0).
+// =========================================================
+
+/// Positions (index du bundle) dont le produit se déchiffre en 1.
+/// Toute autre valeur que 0 ou 1 trahit un serveur déviant.
+pub fn phase4_decrypt_positions(
+    label:   &str,
+    entries: &[(usize, CfSnd)],
+    kp:      &KeyPair,
+) -> Result<Vec<usize>, CryptoError> {
+    println!("  [Phase 4] {} : Dec2 de {} triplets positionnes...", label, entries.len());
+    let t_start = Instant::now();
+
+    let cts: Vec<CfSnd> = entries.iter().map(|(_, ct)| ct.clone()).collect();
+    let ms = cf_mul_dec_batch(&cts, &kp.public_key, &kp.secret_key, default_pool())?;
+    let mut matched = Vec::new();
+    for ((pos, _), m) in entries.iter().zip(&ms) {
+        if m.is_one() {
+            matched.push(*pos);
+        } else if !m.is_zero() {
+            return Err(CryptoError::InvalidInput(format!(
+                "position {} : produit {} hors de {{0, 1}}", pos, m
+            )));
+        }
+    }
+    matched.sort_unstable();
+    matched.dedup();
+
+    println!(
+        "  [Phase 4] {} : termine en {:.3?}  ->  {} position(s) commune(s)",
+        label, t_start.elapsed(), matched.len()
+    );
+    Ok(matched)
+}
+
+/// Indices des clés hachées sur une position de `matched`
+/// (les positions factices du mode size-hiding valent 0 : jamais rapportées).
+///
+/// Une position commune ne dit pas QUELLE clé y est commune : deux clés
+/// locales distinctes hachées sur la même position sont toutes deux
+/// rapportées, même si une seule figure dans l'autre base. Plus de clés
+/// distinctes que de positions dans le résultat signale ce cas.
+pub fn reveal_matching_records(
+    keys:    &[String],
+    matched: &[usize],
+    hasher:  &PositionHasher,
+) -> Vec<usize> {
+    let matched: HashSet<usize> = matched.iter().copied().collect();
+    keys.iter()
+        .enumerate()
+        .filter(|(_, k)| matched.contains(&hasher.position(k)))
+        .map(|(i, _)| i)
+        .collect()
+}
+
+// =========================================================
+// Variante multi-cles — CF pk1 x pk2
+//
+// Chaque BD chiffre sa table UNE seule fois, sous sa propre
+// cle. Le serveur multiplie Ft(BD1, pk1) par Ft(BD2, pk2) avec
+// cf_mul_mk ; le resultat est dechiffre conjointement :
+//
+//   Phase 4a  BD1 : linearise + agrege  -> (A1 sous pk1, A2 sous pk2)
+//                   part1 = Dec1(A1) mod M
+//                   envoie A2 et Enc2(part1) a BD2 (via serveur)
+//   Phase 4b  BD2 : part2 = Dec2(A2) mod M, part1 = Dec2(Enc2(part1))
+//                   renvoie Enc1(part2) a BD1 (via serveur)
+//   Phase 4c  BD1 : part2 = Dec1(Enc1(part2))
+//
+// Les parts transitent chiffrees sous la cle du destinataire :
+// le serveur, qui connait les rho, n'apprend pas le cardinal.
+// =========================================================
+
+/// |M| : espace des messages commun aux deux cles (cardinal < 2^64)
+pub const MK_MODULUS_BITS: u64 = 64;
+
+/// M = 2^MK_MODULUS_BITS
+pub fn mk_plain_modulus() -> BigUint {
+    BigUint::one() << MK_MODULUS_BITS
+}
+
+// ---------------------------------------------------------
+// Phase 2 (multi-cles) — Ft sous la seule cle de la BD
+// ---------------------------------------------------------
+
+pub fn phase2_prepare_mk_ft(
+    label:   &str,
+    table:   &SparseTable,
+    pk_self: &crate::paillier::p_keygen::PublicKey,
+) -> FtBundle {
+    println!(
+        "  [Phase 2] {} : preparation Ft multi-cles pour {} positions (sous pk_self)...",
+        label, table.len()
+    );
+
+    let modulus = mk_plain_modulus();
+    let one     = BigUint::one();
+    let mut rng = OsRng;
+    let mut ft: HashMap<usize, CfFst> = HashMap::with_capacity(table.len());
+
+    for &pos in table.active.iter() {
+        let b = rng.gen_biguint_below(&modulus);
+        ft.insert(
+            pos,
+            cf_encrypt_mk(&one, &b, &modulus, pk_self).expect("cf_encrypt_mk a echoue"),
+        );
+    }
+
+    println!("  [Phase 2] {} : Ft multi-cles prets (1 chiffrement par position).", label);
+
+    FtBundle { ft_by_pos: ft }
+}
+
+// ---------------------------------------------------------
+// Phase 3 (multi-cles) — Serveur : CF.Mul pk1 x pk2
+// ---------------------------------------------------------
+
+pub fn phase3_server_compute_mk(
+    table1: &SparseTable,
+    table2: &SparseTable,
+    bd1:    &FtBundle,
+    bd2:    &FtBundle,
+    pk1:    &crate::paillier::p_keygen::PublicKey,
+    pk2:    &crate::paillier::p_keygen::PublicKey,
+) -> Vec<MkCfSnd> {
+    println!("  [Phase 3] Serveur : CF.Mul multi-cles sur les positions communes...");
+    let t_start = Instant::now();
+
+    let common = table1.common_positions(table2);
+    println!("  [Phase 3] {} position(s) commune(s).", common.len());
+
+    let modulus = mk_plain_modulus();
+    let mut out: Vec<MkCfSnd> = Vec::with_capacity(common.len());
+
+    for &pos in common.iter() {
+        let ft1 = bd1.ft_by_pos.get(&pos)
+            .expect("BD1 Ft(pk1) manquant pour une position commune");
+        let ft2 = bd2.ft_by_pos.get(&pos)
+            .expect("BD2 Ft(pk2) manquant pour une position commune");
+        out.push(
+            cf_mul_mk(ft1, ft2, &modulus, pk1, pk2).expect("cf_mul_mk a echoue")
+        );
+    }
+
+    println!(
+        "  [Phase 3] termine en {:.3?} ({} CF.Mul multi-cles).",
+        t_start.elapsed(), out.len()
+    );
+
+    out
+}
+
+// ---------------------------------------------------------
+// Phase 4a (multi-cles) — BD1 : linearisation + agregation
+// ---------------------------------------------------------
+
+pub fn phase4_mk_linearize(
+    label: &str,
+    cts:   &[MkCfSnd],
+    kp1:   &KeyPair,
+    pk2:   &crate::paillier::p_keygen::PublicKey,
+) -> Result<MkCfLin, CryptoError> {
+    println!(
+        "  [Phase 4] {} : linearisation de {} produits multi-cles...",
+        label, cts.len()
+    );
+    let t_start = Instant::now();
+
+    let lin: Vec<MkCfLin> = cts
+        .iter()
+        .map(|ct| cf_mul_mk_linearize(ct, &kp1.public_key, &kp1.secret_key, pk2))
+        .collect::<Result<_, _>>()?;
+
+    let agg = cf_mul_mk_aggregate(&lin, &kp1.public_key, pk2)?;
+
+    println!("  [Phase 4] {} : agregation terminee en {:.3?}.", label, t_start.elapsed());
+
+    Ok(agg)
+}
+
+// ---------------------------------------------------------
+// Phase 4b (multi-cles) — part locale d'une BD
+// ---------------------------------------------------------
+
+/// `ct` vient du réseau (agrégat ou part relayée) : un chiffré hors de
+/// Z_{n^2} est une erreur, pas une panique
+pub fn phase4_mk_partial_dec(label: &str, ct: &BigUint, kp: &KeyPair) -> Result<BigUint, CryptoError> {
+    let part = cf_mul_mk_partial_dec(ct, &mk_plain_modulus(), &kp.public_key, &kp.secret_key)?;
+    println!("  [Phase 4] {} : part locale dechiffree.", label);
+    Ok(part)
+}
+
+// ---------------------------------------------------------
+// Phase 4c (multi-cles) — recombinaison des deux parts
+// ---------------------------------------------------------
+
+/// `max` : borne connue du BD (produits reçus, ou sa propre taille) ;
+/// une somme au-delà trahit un serveur ou une part déviants
+pub fn phase4_mk_combine(
+    label:      &str,
+    part_self:  &BigUint,
+    part_other: &BigUint,
+    max:        usize,
+) -> Result<usize, CryptoError> {
+    let sum   = cf_mul_mk_combine(part_self, part_other, &mk_plain_modulus());
+    let count = sum.to_usize().filter(|&c| c <= max).ok_or_else(|| CryptoError::InvalidInput(format!(
+        "multi-cles : cardinal recombine {} hors de [0, {}] (serveur ou part deviants)", sum, max
+    )))?;
+    println!("  [Phase 4] {} : parts recombinees  ->  cardinal = {}", label, count);
+    Ok(count)
+}
+
+// =========================================================
+// Variante KEA (--kea) — controle d'image sur les CF (schema P^(2))
+//
+// Chaque detenteur de cle publie ct_delta = (Enc(1), Enc(xi)).
+// Les Ft sont chiffres avec cf_kea_encrypt, le serveur applique
+// cf_kea_mul sur les deux composantes, et la BD verifie l'image
+// de chaque triplet avant de l'accepter (cf_kea_mul_dec).
+//
+// Ce n'est pas un calcul verifiable face au serveur.
+// Portee exacte : ct_delta est transmis au serveur (Phase 0c),
+// qui peut donc forger des paires valides (Enc(x), Enc(xi*x))
+// pour tout x, et supprimer, dupliquer ou remplacer des triplets
+// sans etre detecte. Seule une composante alteree independamment
+// de l'autre fait echouer la verification :
+// Phase 4 retourne alors Err(CryptoError::KeaImVerFailed).
+// =========================================================
+
+pub struct KeaFtBundle {
+    pub ft_by_pos: HashMap<usize, CfKeaFst>,
+}
+
+pub struct DualKeaFtBundle {
+    pub under_pk1: KeaFtBundle,
+    pub under_pk2: KeaFtBundle,
+}
+
+// ---------------------------------------------------------
+// Phase 0 (KEA) — cle KEA associee a la cle Paillier locale
+// ---------------------------------------------------------
+
+pub fn phase0_kea_keygen(label: &str, kp: &KeyPair) -> KeyPairKEA {
+    println!("  [Phase 0] {} : generation de la cle KEA (xi, ct_delta)...", label);
+    let t = Instant::now();
+    let kea = paillier_kea_keygen(&kp.public_key).expect("paillier_kea_keygen a echoue");
+    println!("  [Phase 0] {} : cle KEA generee en {:.3?}", label, t.elapsed());
+    kea
+}
+
+// ---------------------------------------------------------
+// Helper : CF_kea.Enc(1, b) sous (pk, ct_delta)
+// ---------------------------------------------------------
+fn make_kea_ft_for_one(
+    b:        &BigUint,
+    pk:       &crate::paillier::p_keygen::PublicKey,
+    ct_delta: &(BigUint, BigUint),
+) -> CfKeaFst {
+    cf_kea_encrypt(&BigUint::one(), b, pk, ct_delta).expect("cf_kea_encrypt a echoue")
+}
+
+// ---------------------------------------------------------
+// Phase 2 (KEA) — Ft sous (pk1, delta1) et (pk2, delta2)
+// ---------------------------------------------------------
+
+pub fn phase2_prepare_dual_ft_kea(
+    label:  &str,
+    table:  &SparseTable,
+    pk1:    &crate::paillier::p_keygen::PublicKey,
+    delta1: &(BigUint, BigUint),
+    pk2:    &crate::paillier::p_keygen::PublicKey,
+    delta2: &(BigUint, BigUint),
+) -> DualKeaFtBundle {
+    println!(
+        "  [Phase 2] {} : preparation Ft KEA pour {} positions (sous pk1 et pk2)...",
+        label, table.len()
+    );
+
+    let mut rng = OsRng;
+    let mut ft_pk1: HashMap<usize, CfKeaFst> = HashMap::with_capacity(table.len());
+    let mut ft_pk2: HashMap<usize, CfKeaFst> = HashMap::with_capacity(table.len());
+
+    for &pos in table.active.iter() {
+        let b1 = rng.gen_biguint_below(&pk1.n);
+        ft_pk1.insert(pos, make_kea_ft_for_one(&b1, pk1, delta1));
+
+        let b2 = rng.gen_biguint_below(&pk2.n);
+        ft_pk2.insert(pos, make_kea_ft_for_one(&b2, pk2, delta2));
+    }
+
+    println!("  [Phase 2] {} : Ft KEA prets.", label);
+
+    DualKeaFtBundle {
+        under_pk1: KeaFtBundle { ft_by_pos: ft_pk1 },
+        under_pk2: KeaFtBundle { ft_by_pos: ft_pk2 },
+    }
+}
+
+// ---------------------------------------------------------
+// Phase 3 (KEA) — Serveur : cf_kea_mul sur les positions communes
+// ---------------------------------------------------------
+
+#[allow(clippy::too_many_arguments)]
+pub fn phase3_server_compute_kea(
+    table1: &SparseTable,
+    table2: &SparseTable,
+    bd1:    &DualKeaFtBundle,
+    bd2:    &DualKeaFtBundle,
+    pk1:    &crate::paillier::p_keygen::PublicKey,
+    delta1: &(BigUint, BigUint),
+    pk2:    &crate::paillier::p_keygen::PublicKey,
+    delta2: &(BigUint, BigUint),
+) -> (Vec<CfKeaSnd>, Vec<CfKeaSnd>) {
+    println!("  [Phase 3] Serveur : CF.Mul KEA sur les positions communes...");
+    let t_start = Instant::now();
+
+    let common = table1.common_positions(table2);
+    println!("  [Phase 3] {} position(s) commune(s).", common.len());
+
+    let mut out_pk1: Vec<CfKeaSnd> = Vec::with_capacity(common.len());
+    let mut out_pk2: Vec<CfKeaSnd> = Vec::with_capacity(common.len());
 
-    for pos in common.iter().copied() {
-        // CF.Mul sous pk1
+    for &pos in common.iter() {
         let ft1   = bd1.under_pk1.ft_by_pos.get(&pos)
             .expect("BD1 Ft(pk1) manquant pour une position commune");
         let ft1_p = bd2.under_pk1.ft_by_pos.get(&pos)
             .expect("BD2 Ft(pk1) manquant pour une position commune");
         out_pk1.push(
-            cf_mul(ft1, ft1_p, &kp1.public_key).expect("cf_mul(pk1) a echoue")
+            cf_kea_mul(ft1, ft1_p, pk1, delta1).expect("cf_kea_mul(pk1) a echoue")
         );
 
-        // CF.Mul sous pk2
         let ft2   = bd1.under_pk2.ft_by_pos.get(&pos)
             .expect("BD1 Ft(pk2) manquant pour une position commune");
         let ft2_p = bd2.under_pk2.ft_by_pos.get(&pos)
             .expect("BD2 Ft(pk2) manquant pour une position commune");
         out_pk2.push(
-            cf_mul(ft2, ft2_p, &kp2.public_key).expect("cf_mul(pk2) a echoue")
+            cf_kea_mul(ft2, ft2_p, pk2, delta2).expect("cf_kea_mul(pk2) a echoue")
         );
     }
 
     println!(
-        "  [Phase 3] termine en {:.3?} ({} CF.Mul x 2 cles).",
+        "  [Phase 3] termine en {:.3?} ({} CF.Mul KEA x 2 cles).",
         t_start.elapsed(), out_pk1.len()
     );
 
@@ -279,29 +918,795 @@ pub fn phase3_server_compute(
 }
 
 // ---------------------------------------------------------
-// Phase 4 — BD : Dec2 sur chaque triplet + somme
+// Phase 4 (KEA) — verification d'image + Dec2 + somme
+//
+// Le premier triplet dont l'image est invalide interrompt le
+// comptage ; les produits hors {0, 1} sont relevés comme en
+// mode standard. Un triplet forgé de façon cohérente (paires
+// valides construites avec ct_delta) ou omis passe inaperçu.
 // ---------------------------------------------------------
 
-pub fn phase4_decrypt_and_count(label: &str, cts: &[CfSnd], kp: &KeyPair) -> usize {
+pub fn phase4_decrypt_and_count_kea(
+    label: &str,
+    cts:   &[CfKeaSnd],
+    kp:    &KeyPair,
+    kea:   &KeyPairKEA,
+) -> Result<usize, CryptoError> {
     println!(
-        "  [Phase 4] {} : Dec2 ({} triplets)...",
+        "  [Phase 4] {} : verification KEA + Dec2 ({} triplets)...",
         label, cts.len()
     );
     let t_start = Instant::now();
 
-    let mut sum = BigUint::from(0u32);
-    for ct in cts {
-        let m = cf_mul_dec(ct, &kp.public_key, &kp.secret_key)
-            .expect("cf_mul_dec a echoue");
-        sum += m;
+    let mut ms = Vec::with_capacity(cts.len());
+    for (i, ct) in cts.iter().enumerate() {
+        match cf_kea_mul_dec(ct, &kp.public_key, &kp.secret_key, &kea.psy) {
+            Ok(m) => ms.push(m),
+            Err(e) => {
+                println!("  [Phase 4] {} : triplet #{} rejete ({}).", label, i, e);
+                return Err(e);
+            }
+        }
     }
 
-    let count = sum.to_u64_digits().last().copied().unwrap_or(0) as usize;
+    let tally = CardinalTally::from_values(&ms);
+    tally.report(label);
+    let count = usize::try_from(tally.to_u64()?)
+        .map_err(|_| CryptoError::InvalidInput("cardinal hors de usize".into()))?;
+
+    println!(
+        "  [Phase 4] {} : {} triplets verifies en {:.3?}  ->  cardinal = {}",
+        label, cts.len(), t_start.elapsed(), count
+    );
+
+    Ok(count)
+}
+
+// =========================================================
+// Variante cuckoo (--cuckoo) — cardinal exact, sans collision
+//
+//   BD1 : table cuckoo (un élément par bin + stash)
+//   BD2 : bins simples complétés à bin_size + liste du stash
+//   Les Ft portent l'étiquette 128 bits (et non plus 1) ; le
+//   bundle est indexé par numéro de slot.
+//
+//   Serveur : pour chaque paire (slot BD1, slot BD2) de
+//   comparison_pairs, Ft1 - Ft2 puis multiplication par un
+//   scalaire aléatoire r : le BD déchiffre 0 si les étiquettes
+//   sont égales, une valeur uniforme sinon. Les résultats sont
+//   mélangés avant l'envoi (la position d'un zéro ne révèle
+//   pas le bin).
+// =========================================================
+
+// ---------------------------------------------------------
+// Phase 1 (cuckoo) — BD1 : table cuckoo, BD2 : bins simples
+// ---------------------------------------------------------
+
+pub fn phase1_build_cuckoo(
+    label:    &str,
+    nss_list: &[String],
+    hasher:   &PositionHasher,
+    params:   CuckooParams,
+) -> Result<CuckooTable, CryptoError> {
+    println!(
+        "  [Phase 1] {} : {} NSS -> table cuckoo ({} bins, k={}, stash={})...",
+        label, nss_list.len(), params.bins, params.hashes, params.stash
+    );
+    let table = CuckooTable::build(nss_list, hasher, params)?;
+    println!(
+        "  [Phase 1] {} : {} slot(s), {} element(s) dans le stash.",
+        label, table.slots.len(), table.stashed
+    );
+    Ok(table)
+}
+
+pub fn phase1_build_simple_bins(
+    label:    &str,
+    nss_list: &[String],
+    hasher:   &PositionHasher,
+    params:   CuckooParams,
+) -> Result<SimpleBins, CryptoError> {
+    println!(
+        "  [Phase 1] {} : {} NSS -> bins simples ({} bins x {}, k={})...",
+        label, nss_list.len(), params.bins, params.bin_size, params.hashes
+    );
+    let bins = SimpleBins::build(nss_list, hasher, params)?;
+    println!(
+        "  [Phase 1] {} : {} slot(s), charge maximale reelle = {} / {}.",
+        label, bins.slots.len(), bins.max_load, params.bin_size
+    );
+    Ok(bins)
+}
+
+// ---------------------------------------------------------
+// Phase 2 (cuckoo) — CF.Enc(étiquette) sous pk1 et pk2
+// ---------------------------------------------------------
+
+pub fn phase2_prepare_slot_ft(
+    label: &str,
+    slots: &[BigUint],
+    pk1:   &crate::paillier::p_keygen::PublicKey,
+    pk2:   &crate::paillier::p_keygen::PublicKey,
+) -> DualFtBundle {
+    println!(
+        "  [Phase 2] {} : preparation Ft pour {} slots (sous pk1 et pk2)...",
+        label, slots.len()
+    );
+
+    let mut rng = OsRng;
+    let b1s: Vec<BigUint> = slots.iter().map(|_| rng.gen_biguint_below(&pk1.n)).collect();
+    let b2s: Vec<BigUint> = slots.iter().map(|_| rng.gen_biguint_below(&pk2.n)).collect();
+
+    let pool   = default_pool();
+    let ft_pk1 = cf_encrypt_batch(slots, &b1s, pk1, pool).expect("cf_encrypt(slot, pk1) a echoue");
+    let ft_pk2 = cf_encrypt_batch(slots, &b2s, pk2, pool).expect("cf_encrypt(slot, pk2) a echoue");
+
+    DualFtBundle {
+        under_pk1: FtBundle { ft_by_pos: ft_pk1.into_iter().enumerate().collect() },
+        under_pk2: FtBundle { ft_by_pos: ft_pk2.into_iter().enumerate().collect() },
+    }
+}
+
+// ---------------------------------------------------------
+// Phase 3 (cuckoo) — Serveur : r · (Ft1 - Ft2) par paire
+// ---------------------------------------------------------
+
+pub(crate) fn compare_slots(
+    pairs: &[(usize, usize)],
+    bd1:   &FtBundle,
+    bd2:   &FtBundle,
+    pk:    &crate::paillier::p_keygen::PublicKey,
+) -> Result<Vec<CfFst>, CryptoError> {
+    let mut rng = OsRng;
+    let rs: Vec<BigUint> = pairs.iter().map(|_| rng.gen_biguint_range(&BigUint::one(), &pk.n)).collect();
+
+    let mut out = default_pool().install(|| {
+        pairs.par_iter().zip(rs.par_iter())
+            .map(|(&(i, j), r)| {
+                let a = bd1.ft_by_pos.get(&i)
+                    .ok_or_else(|| CryptoError::InvalidInput(format!("slot BD1 #{} manquant", i)))?;
+                let b = bd2.ft_by_pos.get(&j)
+                    .ok_or_else(|| CryptoError::InvalidInput(format!("slot BD2 #{} manquant", j)))?;
+                let d = cf_sub(a, b, &pk.n, &pk.n_squared)?;
+                cf_scal(&d, r, &pk.n, &pk.n_squared)
+            })
+            .collect::<Result<Vec<CfFst>, CryptoError>>()
+    })?;
+    out.shuffle(&mut rand::thread_rng());
+    Ok(out)
+}
+
+pub fn phase3_server_compute_cuckoo(
+    params: &CuckooParams,
+    bd1:    &DualFtBundle,
+    bd2:    &DualFtBundle,
+    pk1:    &crate::paillier::p_keygen::PublicKey,
+    pk2:    &crate::paillier::p_keygen::PublicKey,
+) -> Result<(Vec<CfFst>, Vec<CfFst>), CryptoError> {
+    println!(
+        "  [Phase 3] Serveur : {} comparaisons cuckoo ({} bins x {} + stash {} x {})...",
+        params.comparisons(), params.bins, params.bin_size, params.stash, params.n2
+    );
+    let t_start = Instant::now();
+
+    for (label, b, expected) in [
+        ("BD1", bd1, params.cuckoo_slots()),
+        ("BD2", bd2, params.simple_slots()),
+    ] {
+        if b.under_pk1.ft_by_pos.len() != expected || b.under_pk2.ft_by_pos.len() != expected {
+            return Err(CryptoError::InvalidInput(format!(
+                "{} : {} / {} slots recus, {} attendus",
+                label, b.under_pk1.ft_by_pos.len(), b.under_pk2.ft_by_pos.len(), expected
+            )));
+        }
+    }
+
+    let pairs   = comparison_pairs(params);
+    let out_pk1 = compare_slots(&pairs, &bd1.under_pk1, &bd2.under_pk1, pk1)?;
+    let out_pk2 = compare_slots(&pairs, &bd1.under_pk2, &bd2.under_pk2, pk2)?;
+
+    println!(
+        "  [Phase 3] termine en {:.3?} ({} comparaisons x 2 cles, resultats melanges).",
+        t_start.elapsed(), out_pk1.len()
+    );
+    Ok((out_pk1, out_pk2))
+}
+
+// ---------------------------------------------------------
+// Phase 4 (cuckoo) — BD : déchiffrement + comptage des zéros
+// ---------------------------------------------------------
+
+pub fn phase4_count_zeros(label: &str, cts: &[CfFst], kp: &KeyPair) -> Result<usize, CryptoError> {
+    println!("  [Phase 4] {} : dechiffrement de {} comparaisons...", label, cts.len());
+    let t_start = Instant::now();
+
+    let pk  = &kp.public_key;
+    let c1s: Vec<BigUint> = cts.iter().map(|(_, c1)| c1.clone()).collect();
+    let bs  = p_decrypt_batch(&c1s, pk, &kp.secret_key, default_pool())?;
+    let count = cts.iter().zip(&bs)
+        .filter(|((c0, _), b)| ((c0 + *b) % &pk.n).is_zero())
+        .count();
 
     println!(
         "  [Phase 4] {} : termine en {:.3?}  ->  cardinal = {}",
         label, t_start.elapsed(), count
     );
+    Ok(count)
+}
+
+// =========================================================
+// Variante PSI-Sum (--sum) — Σ valeur sur l'intersection
+//
+//   BD1 : Ft(1) sur ses positions (indicateur d'appartenance)
+//   BD2 : Ft(v) sur ses positions, v = valeur de la colonne
+//         choisie, encodée en virgule fixe (FixedPoint)
+//   Serveur : cf_mul(Ft(1), Ft(v)) = v sur chaque position
+//         commune, puis agrégation (phase3_server_aggregate) :
+//         α = Π C0_i et masques re-randomisés, mélangés
+//   BD : Dec(α) + Σ Dec(C1')·Dec(C2') = Σ v, décodage signé.
+//         Aucun triplet n'est déchiffré isolément : BD1 n'apprend
+//         que la somme (et le nombre de positions communes), jamais
+//         la valeur d'un individu.
+//
+// Le serveur ne distingue pas un bundle de valeurs d'un bundle
+// d'indicateurs. Deux NSS de BD2 sur la même position voient
+// leurs valeurs additionnées (même NSS répété, ou collision).
+// =========================================================
+
+pub struct ValueTable {
+    pub table:  SparseTable,
+    pub values: HashMap<usize, BigInt>,
+}
+
+// ---------------------------------------------------------
+// Phase 1 (PSI-Sum) — BD2 : table creuse + valeur par position
+// ---------------------------------------------------------
+
+pub fn phase1_build_value_table(
+    label:  &str,
+    rows:   &[(String, String)],
+    hasher: &PositionHasher,
+    fp:     FixedPoint,
+) -> Result<ValueTable, CryptoError> {
+    println!(
+        "  [Phase 1] {} : {} lignes (NSS, valeur), {} decimale(s)...",
+        label, rows.len(), fp.decimals
+    );
+    let mut values: HashMap<usize, BigInt> = HashMap::new();
+    for (nss, raw) in rows {
+        let v = fp.parse(raw)?;
+        *values.entry(hasher.position(nss)).or_insert_with(BigInt::zero) += v;
+    }
+    let table = SparseTable { active: values.keys().copied().collect() };
+    println!(
+        "  [Phase 1] {} : {} position(s) active(s), {} ligne(s) fusionnee(s).",
+        label, table.len(), rows.len() - table.len()
+    );
+    Ok(ValueTable { table, values })
+}
+
+// ---------------------------------------------------------
+// Phase 2 (PSI-Sum) — BD2 : CF.Enc(v) sous pk1 et pk2
+// ---------------------------------------------------------
 
-    count
-}
\ No newline at end of file
+pub fn phase2_prepare_dual_ft_values(
+    label: &str,
+    vt:    &ValueTable,
+    pk1:   &crate::paillier::p_keygen::PublicKey,
+    pk2:   &crate::paillier::p_keygen::PublicKey,
+) -> Result<DualFtBundle, CryptoError> {
+    println!(
+        "  [Phase 2] {} : preparation Ft(valeur) pour {} positions (sous pk1 et pk2)...",
+        label, vt.table.len()
+    );
+
+    let mut rng = OsRng;
+    let positions: Vec<usize> = vt.values.keys().copied().collect();
+    let encode = |n: &BigUint| -> Result<Vec<BigUint>, CryptoError> {
+        positions.iter().map(|p| FixedPoint::to_zn(&vt.values[p], n)).collect()
+    };
+    let (m1s, m2s) = (encode(&pk1.n)?, encode(&pk2.n)?);
+    let b1s: Vec<BigUint> = positions.iter().map(|_| rng.gen_biguint_below(&pk1.n)).collect();
+    let b2s: Vec<BigUint> = positions.iter().map(|_| rng.gen_biguint_below(&pk2.n)).collect();
+
+    let pool   = default_pool();
+    let ft_pk1 = cf_encrypt_batch(&m1s, &b1s, pk1, pool)?;
+    let ft_pk2 = cf_encrypt_batch(&m2s, &b2s, pk2, pool)?;
+
+    Ok(DualFtBundle {
+        under_pk1: FtBundle { ft_by_pos: positions.iter().copied().zip(ft_pk1).collect() },
+        under_pk2: FtBundle { ft_by_pos: positions.iter().copied().zip(ft_pk2).collect() },
+    })
+}
+
+// ---------------------------------------------------------
+// Phase 4 (PSI-Sum) — BD : déchiffrement de la somme agrégée
+//
+// La somme est exacte tant que |Σ v| < n/2 (sinon elle est
+// repliée modulo n, comme toute somme homomorphe).
+// ---------------------------------------------------------
+
+pub fn phase4_decrypt_sum(label: &str, agg: &CfSndSum, kp: &KeyPair) -> Result<BigInt, CryptoError> {
+    println!("  [Phase 4] {} : Dec(alpha) + {} paires de masques...", label, agg.betas.len());
+    let t_start = Instant::now();
+
+    let total = cf_sum_products_dec(agg, kp)?;
+    let sum   = FixedPoint::from_zn(&total, &kp.public_key.n);
+
+    println!("  [Phase 4] {} : termine en {:.3?}.", label, t_start.elapsed());
+    Ok(sum)
+}
+
+// =========================================================
+// Variante à seuil (--threshold t) — le détenteur de clé
+// n'apprend que le bit [ |BD1 ∩ BD2| >= t ]
+//
+//   Phase 3  (Serveur) : triplets complétés par des produits
+//            factices CF(0)·CF(0) jusqu'à min(|T1|, |T2|) (le
+//            nombre de triplets ne révèle plus le cardinal),
+//            agrégés : α = Π C0_i = Enc(c - Σ b_i b'_i), puis
+//            masques re-randomisés (phase3_rerandomize_masks).
+//            Seuls les masques, mélangés, partent vers le BD ;
+//            α reste chez le serveur.
+//   Phase 4a (BD) : Dec des masques -> B = Σ b_i b'_i, renvoie
+//            Enc(B). Après re-randomisation, les masques sont
+//            uniformes et sans lien avec ceux de Phase 2 : un BD
+//            ne reconnaît pas les siens, rien sur c.
+//   Phase 4b (Serveur) : x = c - t + 2^ℓ, dont le bit ℓ vaut
+//            [c >= t] ; envoie Enc(z), z = x + r, r < 2^(ℓ+1+σ).
+//            z masque x statistiquement (distance <= 2^-σ).
+//   Phase 4c (BD) : Dec(z), α = z mod 2^ℓ ; renvoie Enc de chacun
+//            des ℓ + 1 bits de 2α + 1.
+//   Phase 4d (Serveur) : comparaison DGK de 2α + 1 et 2β, avec
+//            β = r mod 2^ℓ : pour chaque bit i (poids fort d'abord)
+//            Enc(s + a_i - b_i + 3·Σ_{j>i} a_j ⊕ b_j), s = ±1 tiré
+//            au hasard ; chaque chiffré est élevé à une puissance
+//            aléatoire, puis l'ensemble est mélangé. Indice joint :
+//            h = (bit ℓ de r) ⊕ [s = -1].
+//   Phase 4e (BD) : δ' = [un chiffré vaut 0] = [α < β] ⊕ [s = -1]
+//            et [c >= t] = (bit ℓ de z) ⊕ δ' ⊕ h.
+//
+// Le BD voit z (statistiquement indépendant de c), δ' et h
+// (uniformes, leur XOR fixé par le résultat) : il n'apprend que
+// le bit. Le serveur ne voit que des chiffrés sous la clé du BD.
+// Modèle semi-honnête : un serveur déviant peut fausser le bit.
+// =========================================================
+
+/// ℓ : |c - t| < 2^ℓ pour tous c, t de u64
+pub const THRESHOLD_CMP_BITS: u32 = 64;
+
+/// σ : bits de masquage statistique de z
+pub const THRESHOLD_STAT_BITS: u32 = 80;
+
+/// Secret du serveur entre les Phases 4b et 4d
+pub struct ThresholdBlind {
+    /// β = r mod 2^ℓ
+    low:    BigUint,
+    /// bit ℓ de r
+    parity: bool,
+}
+
+/// Secret du BD entre les Phases 4c et 4e : bit ℓ de z
+pub struct ThresholdShare {
+    parity: bool,
+}
+
+// ---------------------------------------------------------
+// Phase 3 (agrégats) — Serveur : re-randomisation des masques
+//
+//   C1' = C1 · Enc(ρ1),  C2' = C2 · Enc(ρ2)
+//   α'  = α · C2^(-ρ1) · C1^(-ρ2) · Enc(-Σ ρ1·ρ2)
+//
+// (b + ρ1)(b' + ρ2) = b·b' + ρ1·b' + ρ2·b + ρ1·ρ2 : la somme
+// Dec(α') + Σ Dec(C1')·Dec(C2') est inchangée, mais chaque
+// masque déchiffré est uniforme et indépendant de Phase 2.
+// ---------------------------------------------------------
+
+pub fn phase3_rerandomize_masks(
+    sum: &CfSndSum,
+    pk:  &crate::paillier::p_keygen::PublicKey,
+) -> Result<CfSndSum, CryptoError> {
+    let mut rng = OsRng;
+    let n2 = &pk.n_squared;
+    let rhos: Vec<(BigUint, BigUint)> = sum.betas.iter()
+        .map(|_| (rng.gen_biguint_below(&pk.n), rng.gen_biguint_below(&pk.n)))
+        .collect();
+
+    // ((C1', C2'), C2^(n - ρ1) · C1^(n - ρ2))
+    let fresh: Vec<((BigUint, BigUint), BigUint)> = default_pool().install(|| {
+        sum.betas.par_iter().zip(rhos.par_iter())
+            .map(|((c1, c2), (r1, r2))| {
+                let c1_p = (c1 * p_encrypt(r1, pk)?) % n2;
+                let c2_p = (c2 * p_encrypt(r2, pk)?) % n2;
+                let fix  = (c2.modpow(&(&pk.n - r1), n2) * c1.modpow(&(&pk.n - r2), n2)) % n2;
+                Ok(((c1_p, c2_p), fix))
+            })
+            .collect::<Result<_, CryptoError>>()
+    })?;
+
+    let cross = rhos.iter().fold(BigUint::zero(), |acc, (r1, r2)| (acc + r1 * r2) % &pk.n);
+    let mut alpha = (&sum.alpha * p_encrypt(&((&pk.n - cross) % &pk.n), pk)?) % n2;
+    let mut betas = Vec::with_capacity(fresh.len());
+    for (pair, fix) in fresh {
+        alpha = (alpha * fix) % n2;
+        betas.push(pair);
+    }
+    Ok(CfSndSum { alpha, betas })
+}
+
+// ---------------------------------------------------------
+// Phase 3 (seuil, --dp, --sum, multiset product) — Serveur :
+// bourrage + agrégation + re-randomisation des masques
+// ---------------------------------------------------------
+
+pub fn phase3_server_aggregate(
+    triplets: &[CfSnd],
+    pad_to:   usize,
+    pk:       &crate::paillier::p_keygen::PublicKey,
+) -> Result<CfSndSum, CryptoError> {
+    let mut rng = OsRng;
+    let dummies = pad_to.saturating_sub(triplets.len());
+
+    // Produits factices : CF(0, b) · CF(0, b'), masques frais
+    let zeros = vec![BigUint::zero(); 2 * dummies];
+    let masks: Vec<BigUint> = zeros.iter().map(|_| rng.gen_biguint_below(&pk.n)).collect();
+    let fts   = cf_encrypt_batch(&zeros, &masks, pk, default_pool())?;
+    let pairs: Vec<(&CfFst, &CfFst)> = fts.chunks(2).map(|c| (&c[0], &c[1])).collect();
+    let fakes = cf_mul_batch(&pairs, pk, default_pool())?;
+
+    let mut alpha = BigUint::one();
+    let mut betas = Vec::with_capacity(triplets.len() + dummies);
+    for (c0, c1, c2) in triplets.iter().chain(fakes.iter()) {
+        alpha = (alpha * c0) % &pk.n_squared;
+        betas.push((c1.clone(), c2.clone()));
+    }
+    let mut agg = phase3_rerandomize_masks(&CfSndSum { alpha, betas }, pk)?;
+    agg.betas.shuffle(&mut rand::thread_rng());
+
+    println!(
+        "  [Phase 3] agregation : {} triplets + {} factices, masques re-randomises.",
+        triplets.len(), dummies
+    );
+    Ok(agg)
+}
+
+// ---------------------------------------------------------
+// Phase 4a (seuil) — BD : Enc(Σ b_i b'_i)
+// ---------------------------------------------------------
+
+pub fn phase4_threshold_mask_sum(
+    label: &str,
+    betas: &[(BigUint, BigUint)],
+    kp:    &KeyPair,
+) -> Result<BigUint, CryptoError> {
+    println!("  [Phase 4] {} : dechiffrement de {} paires de masques...", label, betas.len());
+    let pk = &kp.public_key;
+    let flat: Vec<BigUint> = betas.iter().flat_map(|(c1, c2)| [c1.clone(), c2.clone()]).collect();
+    let ms = p_decrypt_batch(&flat, pk, &kp.secret_key, default_pool())?;
+    let b_sum = ms.chunks(2).fold(BigUint::zero(), |acc, m| (acc + &m[0] * &m[1]) % &pk.n);
+    p_encrypt(&b_sum, pk)
+}
+
+// ---------------------------------------------------------
+// Phase 4b (seuil) — Serveur : Enc(c - t + 2^ℓ + r)
+// ---------------------------------------------------------
+
+pub fn phase4_threshold_blind(
+    alpha:     &BigUint,
+    enc_b:     &BigUint,
+    threshold: u64,
+    pk:        &crate::paillier::p_keygen::PublicKey,
+) -> Result<(BigUint, ThresholdBlind), CryptoError> {
+    let l = u64::from(THRESHOLD_CMP_BITS);
+    let width = l + 1 + u64::from(THRESHOLD_STAT_BITS);
+    // z < 2^(width + 1) ne doit pas se replier modulo n
+    if pk.n.bits() <= width + 1 {
+        return Err(CryptoError::InvalidInput(format!(
+            "seuil : module de {} bits, {} requis au minimum", pk.n.bits(), width + 2
+        )));
+    }
+    let r = OsRng.gen_biguint(width);
+    let two_l = BigUint::one() << l;
+
+    // Enc(c) · Enc(2^ℓ - t + r)
+    let enc_c  = (alpha * enc_b) % &pk.n_squared;
+    let offset = &two_l - BigUint::from(threshold) + &r;
+    let z = (enc_c * p_encrypt(&offset, pk)?) % &pk.n_squared;
+    Ok((z, ThresholdBlind { low: &r % &two_l, parity: r.bit(l) }))
+}
+
+// ---------------------------------------------------------
+// Phase 4c (seuil) — BD : Enc des bits de 2α + 1
+// ---------------------------------------------------------
+
+pub fn phase4_threshold_bits(
+    label: &str,
+    z:     &BigUint,
+    kp:    &KeyPair,
+) -> Result<(Vec<BigUint>, ThresholdShare), CryptoError> {
+    let pk = &kp.public_key;
+    let l  = u64::from(THRESHOLD_CMP_BITS);
+    let z  = p_decrypt(z, pk, &kp.secret_key)?;
+    let a  = ((&z % (BigUint::one() << l)) << 1u32) + 1u32;
+
+    // Poids fort d'abord : ℓ + 1 bits
+    let bits: Vec<BigUint> = (0..=l).rev().map(|i| BigUint::from(a.bit(i) as u8)).collect();
+    let cts = p_encrypt_batch(&bits, pk, default_pool())?;
+    println!("  [Phase 4] {} : {} bits chiffres pour la comparaison.", label, cts.len());
+    Ok((cts, ThresholdShare { parity: z.bit(l) }))
+}
+
+// ---------------------------------------------------------
+// Phase 4d (seuil) — Serveur : comparaison DGK aveuglée
+// ---------------------------------------------------------
+
+pub fn phase4_threshold_compare(
+    enc_bits: &[BigUint],
+    blind:    &ThresholdBlind,
+    pk:       &crate::paillier::p_keygen::PublicKey,
+) -> Result<(Vec<BigUint>, bool), CryptoError> {
+    let l = THRESHOLD_CMP_BITS as usize;
+    if enc_bits.len() != l + 1 {
+        return Err(CryptoError::InvalidInput(format!(
+            "seuil : {} bits chiffres recus, {} attendus", enc_bits.len(), l + 1
+        )));
+    }
+    let mut rng = OsRng;
+    let (n, n2) = (&pk.n, &pk.n_squared);
+    let negative = rng.gen_biguint(1).is_one();
+    let s = if negative { n - 1u32 } else { BigUint::one() };
+    let b = &blind.low << 1u32;
+    let enc_one = p_encrypt(&BigUint::one(), pk)?;
+    let three = BigUint::from(3u32);
+
+    // acc = Enc(Σ_{j>i} a_j ⊕ b_j), chiffré trivial de 0 au départ
+    let mut acc = BigUint::one();
+    let mut cs  = Vec::with_capacity(l + 1);
+    for (k, ea) in enc_bits.iter().enumerate() {
+        let bi = b.bit((l - k) as u64);
+        let offset = if bi { (&s + n - 1u32) % n } else { s.clone() };
+        let c = (ea * p_encrypt(&offset, pk)? % n2) * acc.modpow(&three, n2) % n2;
+        cs.push(c);
+        // a_i ⊕ b_i : a_i si b_i = 0, 1 - a_i sinon
+        let w = if bi { &enc_one * ea.modpow(&(n - 1u32), n2) % n2 } else { ea.clone() };
+        acc = acc * w % n2;
+    }
+
+    // Puissance aléatoire non nulle + rechiffrement, puis mélange
+    let zero = BigUint::zero();
+    let mut out = cs.iter()
+        .map(|c| {
+            let e = rng.gen_biguint_range(&BigUint::one(), n);
+            Ok(c.modpow(&e, n2) * p_encrypt(&zero, pk)? % n2)
+        })
+        .collect::<Result<Vec<_>, CryptoError>>()?;
+    out.shuffle(&mut rand::thread_rng());
+    Ok((out, blind.parity ^ negative))
+}
+
+// ---------------------------------------------------------
+// Phase 4e (seuil) — BD : un seul bit
+// ---------------------------------------------------------
+
+pub fn phase4_threshold_decide(
+    label: &str,
+    cts:   &[BigUint],
+    hint:  bool,
+    share: &ThresholdShare,
+    kp:    &KeyPair,
+) -> Result<bool, CryptoError> {
+    let expected = THRESHOLD_CMP_BITS as usize + 1;
+    if cts.len() != expected {
+        return Err(CryptoError::InvalidInput(format!(
+            "seuil : {} chiffres de comparaison recus, {} attendus", cts.len(), expected
+        )));
+    }
+    let ms = p_decrypt_batch(cts, &kp.public_key, &kp.secret_key, default_pool())?;
+    let zeros = ms.iter().filter(|m| m.is_zero()).count();
+    if zeros > 1 {
+        return Err(CryptoError::InvalidInput(format!(
+            "seuil : {} chiffres nuls (au plus 1), le serveur a devie", zeros
+        )));
+    }
+    println!("  [Phase 4] {} : comparaison au seuil dechiffree.", label);
+    Ok(share.parity ^ (zeros == 1) ^ hint)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_threshold_reveals_only_comparison() {
+        let kp = p_keygen(256).unwrap();
+        let pk = &kp.public_key;
+
+        // 3 positions communes (1·1) et 2 produits 1·0
+        let bits: Vec<BigUint> = [1u32, 1, 1, 1, 1, 1, 1, 0, 1, 0].iter().map(|&b| BigUint::from(b)).collect();
+        let masks: Vec<BigUint> = bits.iter().map(|_| OsRng.gen_biguint_below(&pk.n)).collect();
+        let fts = cf_encrypt_batch(&bits, &masks, pk, default_pool()).unwrap();
+        let pairs: Vec<(&CfFst, &CfFst)> = fts.chunks(2).map(|c| (&c[0], &c[1])).collect();
+        let triplets = cf_mul_batch(&pairs, pk, default_pool()).unwrap();
+
+        let agg = phase3_server_aggregate(&triplets, 8, pk).unwrap();
+        assert_eq!(agg.betas.len(), 8);
+
+        // Un BD qui a gardé ses masques de Phase 2 ne les retrouve pas
+        let seen: HashSet<BigUint> = agg.betas.iter()
+            .flat_map(|(c1, c2)| [c1, c2])
+            .map(|c| p_decrypt(c, pk, &kp.secret_key).unwrap())
+            .collect();
+        assert!(masks.iter().all(|b| !seen.contains(b)));
+
+        let enc_b = phase4_threshold_mask_sum("T", &agg.betas, &kp).unwrap();
+        for (t, expected) in [(0, true), (2, true), (3, true), (4, false), (100, false), (u64::MAX, false)] {
+            let (z, blind) = phase4_threshold_blind(&agg.alpha, &enc_b, t, pk).unwrap();
+            let (enc_bits, share) = phase4_threshold_bits("T", &z, &kp).unwrap();
+            let (cts, hint) = phase4_threshold_compare(&enc_bits, &blind, pk).unwrap();
+            assert_eq!(phase4_threshold_decide("T", &cts, hint, &share, &kp).unwrap(), expected, "t = {}", t);
+        }
+        let (z, blind) = phase4_threshold_blind(&agg.alpha, &enc_b, 3, pk).unwrap();
+        let (mut enc_bits, _) = phase4_threshold_bits("T", &z, &kp).unwrap();
+        enc_bits.pop();
+        assert!(phase4_threshold_compare(&enc_bits, &blind, pk).is_err());
+    }
+
+    #[test]
+    fn test_psi_sum_decrypts_only_the_total() {
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let hasher = PositionHasher::new([3u8; 32], 24).unwrap();
+        let fp = FixedPoint::new(2);
+
+        let keys1: Vec<String> = ["a", "b", "c", "d"].iter().map(|s| s.to_string()).collect();
+        let rows: Vec<(String, String)> = [("b", "10.50"), ("c", "-3.25"), ("d", "7"), ("e", "1000")]
+            .iter().map(|&(k, v)| (k.to_string(), v.to_string())).collect();
+        let t1 = phase1_build_table("A", &keys1, &hasher);
+        let vt = phase1_build_value_table("B", &rows, &hasher, fp).unwrap();
+        let b1 = phase2_prepare_dual_ft("A", &t1, &kp1.public_key, &kp2.public_key);
+        let b2 = phase2_prepare_dual_ft_values("B", &vt, &kp1.public_key, &kp2.public_key).unwrap();
+        let (cts1, cts2) = phase3_server_compute(&t1, &vt.table, &b1, &b2, &kp1, &kp2);
+
+        // Le BD ne reçoit que α et les masques : ni triplet, ni C0 isolé
+        for (cts, kp) in [(&cts1, &kp1), (&cts2, &kp2)] {
+            let agg = phase3_server_aggregate(cts, 0, &kp.public_key).unwrap();
+            assert_eq!(agg.betas.len(), 3);
+            let total = phase4_decrypt_sum("A", &agg, kp).unwrap();
+            assert_eq!(fp.format(&total), "14.25");
+        }
+    }
+
+    #[test]
+    fn test_reveal_maps_positions_to_own_records() {
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+        let hasher = PositionHasher::new([5u8; 32], 10).unwrap();
+
+        let keys1: Vec<String> = ["a", "b", "c", "d", "b"].iter().map(|s| s.to_string()).collect();
+        let keys2: Vec<String> = ["b", "d", "e"].iter().map(|s| s.to_string()).collect();
+        let t1 = phase1_build_table("A", &keys1, &hasher);
+        let t2 = phase1_build_table("B", &keys2, &hasher);
+        let common: HashSet<usize> = ["b", "d"].iter().map(|k| hasher.position(k)).collect();
+
+        for size_hiding in [false, true] {
+            let (b1, b2) = if size_hiding {
+                (
+                    phase2_prepare_dual_ft_padded("A", &t1, &hasher, 8, pk1, pk2).unwrap(),
+                    phase2_prepare_dual_ft_padded("B", &t2, &hasher, 8, pk1, pk2).unwrap(),
+                )
+            } else {
+                (phase2_prepare_dual_ft("A", &t1, pk1, pk2), phase2_prepare_dual_ft("B", &t2, pk1, pk2))
+            };
+            // Le serveur ne voit que les positions des bundles
+            let s1 = SparseTable { active: b1.under_pk1.ft_by_pos.keys().copied().collect() };
+            let s2 = SparseTable { active: b2.under_pk1.ft_by_pos.keys().copied().collect() };
+            let (pos, cts1, cts2) = phase3_server_compute_positions(&s1, &s2, &b1, &b2, &kp1, &kp2);
+
+            let m1 = phase4_decrypt_positions("A", &pos.iter().copied().zip(cts1).collect::<Vec<_>>(), &kp1).unwrap();
+            let m2 = phase4_decrypt_positions("B", &pos.iter().copied().zip(cts2).collect::<Vec<_>>(), &kp2).unwrap();
+            assert_eq!(m1, m2);
+            assert_eq!(m1.iter().copied().collect::<HashSet<_>>(), common, "size_hiding = {}", size_hiding);
+            assert_eq!(reveal_matching_records(&keys1, &m1, &hasher), vec![1, 3, 4]);
+            assert_eq!(reveal_matching_records(&keys2, &m2, &hasher), vec![0, 1]);
+        }
+
+        // Produit 2·1 injecté par un serveur déviant
+        let bits: Vec<BigUint> = [1u32, 1, 2, 1].iter().map(|&b| BigUint::from(b)).collect();
+        let masks: Vec<BigUint> = bits.iter().map(|_| OsRng.gen_biguint_below(&pk1.n)).collect();
+        let fts = cf_encrypt_batch(&bits, &masks, pk1, default_pool()).unwrap();
+        let pairs: Vec<(&CfFst, &CfFst)> = fts.chunks(2).map(|c| (&c[0], &c[1])).collect();
+        let triplets = cf_mul_batch(&pairs, pk1, default_pool()).unwrap();
+        let entries: Vec<(usize, CfSnd)> = [7usize, 9].into_iter().zip(triplets).collect();
+        assert_eq!(phase4_decrypt_positions("A", &entries[..1], &kp1).unwrap(), vec![7]);
+        assert!(phase4_decrypt_positions("A", &entries, &kp1).is_err());
+    }
+
+    #[test]
+    fn test_size_hiding_padded_cardinal() {
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+        let hasher = PositionHasher::new([7u8; 32], 30).unwrap();
+
+        // 8 clés communes, |A| = 20, |B| = 15, P = 32
+        let keys1: Vec<String> = (0..20).map(|i| format!("nss{}", i)).collect();
+        let keys2: Vec<String> = (12..27).map(|i| format!("nss{}", i)).collect();
+        let t1 = phase1_build_table("A", &keys1, &hasher);
+        let t2 = phase1_build_table("B", &keys2, &hasher);
+        let b1 = phase2_prepare_dual_ft_padded("A", &t1, &hasher, 32, pk1, pk2).unwrap();
+        let b2 = phase2_prepare_dual_ft_padded("B", &t2, &hasher, 32, pk1, pk2).unwrap();
+        assert!(phase2_prepare_dual_ft_padded("A", &t1, &hasher, 19, pk1, pk2).is_err());
+
+        // Le serveur ne voit que P positions par BD
+        check_padded_bundle("BD1", &b1, 32).unwrap();
+        check_padded_bundle("BD2", &b2, 32).unwrap();
+        let s1 = SparseTable { active: b1.under_pk1.ft_by_pos.keys().copied().collect() };
+        let s2 = SparseTable { active: b2.under_pk1.ft_by_pos.keys().copied().collect() };
+        assert_eq!((s1.len(), s2.len()), (32, 32));
+        let (cts1, cts2) = phase3_server_compute(&s1, &s2, &b1, &b2, &kp1, &kp2);
+        assert_eq!(phase4_decrypt_and_count("A", &cts1, &kp1).unwrap(), 8);
+        assert_eq!(phase4_decrypt_and_count("B", &cts2, &kp2).unwrap(), 8);
+
+        // Bundle non bourré, ou amputé d'une position : rejeté
+        let plain = phase2_prepare_dual_ft("B", &t2, pk1, pk2);
+        assert!(check_padded_bundle("BD2", &plain, 32).is_err());
+        let mut short = b1;
+        let pos = *short.under_pk1.ft_by_pos.keys().next().unwrap();
+        short.under_pk1.ft_by_pos.remove(&pos);
+        short.under_pk2.ft_by_pos.remove(&pos);
+        assert!(check_padded_bundle("BD1", &short, 32).is_err());
+    }
+
+    #[test]
+    fn test_mk_joint_decryption_rejects_bad_input() {
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+        let hasher = PositionHasher::new([4u8; 32], 24).unwrap();
+        let t1 = phase1_build_table("A", &["a", "b", "c"].map(String::from), &hasher);
+        let t2 = phase1_build_table("B", &["b", "c", "d"].map(String::from), &hasher);
+        let b1 = phase2_prepare_mk_ft("A", &t1, pk1);
+        let b2 = phase2_prepare_mk_ft("B", &t2, pk2);
+        let quads = phase3_server_compute_mk(&t1, &t2, &b1, &b2, pk1, pk2);
+
+        // BD1 : A1 sous pk1, A2 sous pk2 ; BD2 déchiffre A2
+        let (a1, a2) = phase4_mk_linearize("A", &quads, &kp1, pk2).unwrap();
+        let part1 = phase4_mk_partial_dec("A", &a1, &kp1).unwrap();
+        let part2 = phase4_mk_partial_dec("B", &a2, &kp2).unwrap();
+        assert_eq!(phase4_mk_combine("A", &part1, &part2, quads.len()).unwrap(), 2);
+
+        // Part décalée : cardinal au-delà de la borne, refusé au lieu d'être tronqué
+        assert!(phase4_mk_combine("A", &(&part1 + 5u32), &part2, quads.len()).is_err());
+        assert!(phase4_mk_combine("A", &part1, &(&part2 + (BigUint::one() << 40u32)), 3).is_err());
+        // Chiffré relayé hors de Z_{n^2} : erreur, pas de panique
+        assert!(phase4_mk_partial_dec("B", &pk2.n_squared, &kp2).is_err());
+    }
+
+    #[test]
+    fn test_count_rejects_values_outside_zero_one() {
+        let kp = p_keygen(256).unwrap();
+        let pk = &kp.public_key;
+
+        // produits 1, 0, 1, puis 2·3 = 6 injecté par un serveur déviant
+        let bits: Vec<BigUint> = [1u32, 1, 1, 0, 1, 1, 2, 3].iter().map(|&b| BigUint::from(b)).collect();
+        let masks: Vec<BigUint> = bits.iter().map(|_| OsRng.gen_biguint_below(&pk.n)).collect();
+        let fts = cf_encrypt_batch(&bits, &masks, pk, default_pool()).unwrap();
+        let pairs: Vec<(&CfFst, &CfFst)> = fts.chunks(2).map(|c| (&c[0], &c[1])).collect();
+        let triplets = cf_mul_batch(&pairs, pk, default_pool()).unwrap();
+
+        assert_eq!(phase4_decrypt_and_count("T", &triplets[..3], &kp).unwrap(), 2);
+        let tally = phase4_decrypt_tally(&triplets, &kp).unwrap();
+        assert_eq!(tally.sum, BigUint::from(8u32));
+        assert_eq!(tally.invalid, vec![InvalidTriplet { index: 3, value: BigUint::from(6u32) }]);
+        assert!(phase4_decrypt_and_count("T", &triplets, &kp).is_err());
+
+        // 2^64 : l'ancien `.last()` du vecteur de chiffres donnait 1
+        let big = CardinalTally::from_values(&[BigUint::one() << 64u32]);
+        assert!(big.to_u64().is_err());
+        assert_eq!(big.invalid.len(), 1);
+    }
+}
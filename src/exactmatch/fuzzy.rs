@@ -0,0 +1,381 @@
+use std::time::Instant;
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{ToPrimitive, Zero, One};
+use rand::seq::SliceRandom;
+use rand_core::OsRng;
+use rayon::prelude::*;
+use crate::exactmatch::exactmatch::{phase3_rerandomize_masks, CfFst, FtBundle, DualFtBundle};
+use crate::exactmatch::position_hash::PositionHasher;
+use crate::fiore_catalano::cf_batch::cf_batch::cf_encrypt_batch;
+use crate::fiore_catalano::cf_add::cf_add::cf_add;
+use crate::fiore_catalano::cf_add::cf_add_dec::cf_add_dec;
+use crate::cf_stats::{CfSndSum, cf_sum, cf_sum_products_dec};
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::karatsuba_mul::karatsuba_mul::fast_mul;
+use crate::paillier::p_keygen::PublicKey;
+use crate::parallel::default_pool;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// Appariement approximatif (--fuzzy) — encodages CLK (filtres de Bloom)
+//
+// Chaque enregistrement devient un vecteur de L bits : pour chaque champ
+// (nom, prénom, date…) normalisé, chaque bigramme active k positions
+// HMAC(K, "clk" || champ || i || bigramme) mod L, K étant la clé de la
+// Phase 0d. Deux encodages a, b se comparent par
+//
+//   c = <a, b> = Σ a_l·b_l        s = |a| + |b|
+//   Dice(a, b)    = 2c / s
+//   Hamming(a, b) = s - 2c
+//
+// Serveur, pour chaque paire (i, j) et sous chaque clé :
+//   c_ij : Σ_l cf_mul(Ft(a_il), Ft(b_jl))     (CfSndSum, Seconde Forme,
+//          un seul Enc frais par paire au lieu de L)
+//   s_ij : Σ_l Ft(a_il) + Σ_l Ft(b_jl)         (Première Forme)
+// re-randomise les masques de chaque score (phase3_rerandomize_masks
+// pour c_ij, un décalage ρ pour s_ij), puis mélange les |BD1|·|BD2|
+// scores. Sans cela, un BD reconnaîtrait ses masques de Phase 2 dans
+// chaque score et saurait à quel enregistrement il se rapporte.
+//
+// BD : déchiffre (c, s) de chaque paire et compte celles qui passent le
+// seuil. Elle apprend la distribution des scores, pas quelles paires
+// s'apparient. Coût : |BD1|·|BD2|·L cf_mul et 2L Enc de re-randomisation
+// par clé — réserver ce mode à des blocs de taille modeste.
+// ============================================================================
+
+/// Longueur par défaut de l'encodage (bits)
+pub const DEFAULT_CLK_BITS: usize = 256;
+
+/// Nombre par défaut de positions activées par bigramme
+pub const DEFAULT_CLK_HASHES: usize = 4;
+
+/// Paramètres d'encodage — identiques des deux côtés (vérifiés en Phase 0e)
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ClkParams {
+    pub bits:   usize,
+    pub hashes: usize,
+}
+
+impl ClkParams {
+    pub fn new(bits: usize, hashes: usize) -> Result<Self, CryptoError> {
+        if !(8..=4096).contains(&bits) {
+            return Err(CryptoError::InvalidInput(format!("CLK : {} bits hors de [8, 4096]", bits)));
+        }
+        if !(1..=32).contains(&hashes) {
+            return Err(CryptoError::InvalidInput(format!("CLK : k = {} hors de [1, 32]", hashes)));
+        }
+        Ok(ClkParams { bits, hashes })
+    }
+}
+
+/// Critère d'appariement d'une paire
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Similarity {
+    /// Dice >= seuil (dans [0, 1])
+    Dice(f64),
+    /// Hamming <= distance maximale
+    Hamming(u64),
+}
+
+impl Similarity {
+    /// c = produit scalaire, s = |a| + |b|
+    pub fn matches(&self, c: u64, s: u64) -> bool {
+        match *self {
+            Similarity::Dice(t)    => s > 0 && (2 * c) as f64 >= t * s as f64,
+            Similarity::Hamming(h) => s.saturating_sub(2 * c) <= h,
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Encodage
+// ---------------------------------------------------------------------------
+
+/// Minuscules, alphanumériques uniquement (« Le Gall » = « le-gall » = « LEGALL »)
+pub fn normalize_field(s: &str) -> String {
+    s.chars().filter(|c| c.is_alphanumeric()).flat_map(char::to_lowercase).collect()
+}
+
+/// Bigrammes de la valeur bordée par '_' : « ana » -> _a an na a_
+pub fn bigrams(s: &str) -> Vec<String> {
+    let padded: Vec<char> = std::iter::once('_').chain(s.chars()).chain(std::iter::once('_')).collect();
+    padded.windows(2).map(|w| w.iter().collect()).collect()
+}
+
+/// Encodage CLK d'un enregistrement (un champ vide n'active aucun bit)
+pub fn clk_encode(fields: &[String], params: ClkParams, hasher: &PositionHasher) -> Vec<bool> {
+    let mut clk = vec![false; params.bits];
+    for (f, value) in fields.iter().enumerate() {
+        let norm = normalize_field(value);
+        if norm.is_empty() {
+            continue;
+        }
+        for bg in bigrams(&norm) {
+            for i in 0..params.hashes {
+                let tag = hasher.prf(b"clk\0", &format!("{}\0{}\0{}", f, i, bg));
+                let mut head = [0u8; 8];
+                head.copy_from_slice(&tag[..8]);
+                clk[(u64::from_be_bytes(head) % params.bits as u64) as usize] = true;
+            }
+        }
+    }
+    clk
+}
+
+pub fn phase1_build_clks(
+    label:   &str,
+    records: &[Vec<String>],
+    params:  ClkParams,
+    hasher:  &PositionHasher,
+) -> Vec<Vec<bool>> {
+    println!(
+        "  [Phase 1] {} : {} enregistrements -> CLK {} bits, k={}...",
+        label, records.len(), params.bits, params.hashes
+    );
+    let clks: Vec<Vec<bool>> = records.iter().map(|r| clk_encode(r, params, hasher)).collect();
+    let ones: usize = clks.iter().map(|c| c.iter().filter(|&&b| b).count()).sum();
+    println!(
+        "  [Phase 1] {} : taux de remplissage moyen {:.1} %.",
+        label, 100.0 * ones as f64 / (clks.len().max(1) * params.bits) as f64
+    );
+    clks
+}
+
+// ---------------------------------------------------------------------------
+// BD — Phase 2 : Ft de chaque bit (index = enregistrement·L + bit)
+// ---------------------------------------------------------------------------
+
+pub fn phase2_prepare_clk_ft(
+    label: &str,
+    clks:  &[Vec<bool>],
+    pk1:   &PublicKey,
+    pk2:   &PublicKey,
+) -> Result<DualFtBundle, CryptoError> {
+    let bits: Vec<BigUint> = clks.iter().flatten()
+        .map(|&b| if b { BigUint::one() } else { BigUint::zero() })
+        .collect();
+    println!("  [Phase 2] {} : preparation Ft pour {} bits (sous pk1 et pk2)...", label, bits.len());
+
+    let mut rng = OsRng;
+    let b1s: Vec<BigUint> = bits.iter().map(|_| rng.gen_biguint_below(&pk1.n)).collect();
+    let b2s: Vec<BigUint> = bits.iter().map(|_| rng.gen_biguint_below(&pk2.n)).collect();
+
+    let pool   = default_pool();
+    let ft_pk1 = cf_encrypt_batch(&bits, &b1s, pk1, pool)?;
+    let ft_pk2 = cf_encrypt_batch(&bits, &b2s, pk2, pool)?;
+    Ok(DualFtBundle {
+        under_pk1: FtBundle { ft_by_pos: ft_pk1.into_iter().enumerate().collect() },
+        under_pk2: FtBundle { ft_by_pos: ft_pk2.into_iter().enumerate().collect() },
+    })
+}
+
+// ---------------------------------------------------------------------------
+// Serveur — Phase 3 : (c_ij, s_ij) pour toutes les paires, mélangés
+// ---------------------------------------------------------------------------
+
+/// Score chiffré d'une paire : (produit scalaire, |a| + |b|)
+pub type ClkScore = (CfSndSum, CfFst);
+
+/// Découpe un bundle en `records` vecteurs de L Ft
+fn clk_rows(label: &str, b: &FtBundle, records: usize, bits: usize) -> Result<Vec<Vec<CfFst>>, CryptoError> {
+    if b.ft_by_pos.len() != records * bits {
+        return Err(CryptoError::InvalidInput(format!(
+            "{} : {} Ft recus, {} x {} attendus", label, b.ft_by_pos.len(), records, bits
+        )));
+    }
+    (0..records)
+        .map(|r| {
+            (0..bits)
+                .map(|l| b.ft_by_pos.get(&(r * bits + l)).cloned().ok_or_else(|| {
+                    CryptoError::InvalidInput(format!("{} : bit #{} manquant", label, r * bits + l))
+                }))
+                .collect()
+        })
+        .collect()
+}
+
+/// Σ_l cf_mul(x_l, y_l) avec un seul chiffrement frais :
+///   α = Enc(Σ c0·c0') · Π c1'^{c0} · Π c1^{c0'}
+/// (cf_sum_products chiffre chaque c0·c0' séparément)
+fn cf_inner_product(xs: &[CfFst], ys: &[CfFst], pk: &PublicKey) -> Result<CfSndSum, CryptoError> {
+    let mut plain = BigUint::zero();
+    let mut alpha = BigUint::one();
+    let mut betas = Vec::with_capacity(xs.len());
+    for ((c0, c1), (c0p, c1p)) in xs.iter().zip(ys) {
+        plain = (plain + fast_mul(c0, c0p, &pk.n)?) % &pk.n;
+        alpha = fast_mul(&alpha, &c1p.modpow(c0, &pk.n_squared), &pk.n_squared)?;
+        alpha = fast_mul(&alpha, &c1.modpow(c0p, &pk.n_squared), &pk.n_squared)?;
+        betas.push((c1.clone(), c1p.clone()));
+    }
+    let alpha = fast_mul(&alpha, &p_encrypt(&plain, pk)?, &pk.n_squared)?;
+    Ok(CfSndSum { alpha, betas })
+}
+
+/// (c0, Enc(b)) -> (c0 - ρ, Enc(b + ρ)) : même valeur, masque uniforme
+fn rerandomize_weight((c0, c1): &CfFst, pk: &PublicKey) -> Result<CfFst, CryptoError> {
+    let rho = OsRng.gen_biguint_below(&pk.n);
+    let c0p = (c0 + &pk.n - &rho) % &pk.n;
+    let c1p = fast_mul(c1, &p_encrypt(&rho, pk)?, &pk.n_squared)?;
+    Ok((c0p, c1p))
+}
+
+fn score_pairs(a: &[Vec<CfFst>], b: &[Vec<CfFst>], pk: &PublicKey) -> Result<Vec<ClkScore>, CryptoError> {
+    let weights_a = a.iter().map(|r| cf_sum(r, pk)).collect::<Result<Vec<_>, _>>()?;
+    let weights_b = b.iter().map(|r| cf_sum(r, pk)).collect::<Result<Vec<_>, _>>()?;
+    let pairs: Vec<(usize, usize)> = (0..a.len()).flat_map(|i| (0..b.len()).map(move |j| (i, j))).collect();
+
+    let mut out = default_pool().install(|| {
+        pairs.par_iter()
+            .map(|&(i, j)| {
+                let inner  = phase3_rerandomize_masks(&cf_inner_product(&a[i], &b[j], pk)?, pk)?;
+                let weight = rerandomize_weight(&cf_add(&weights_a[i], &weights_b[j], &pk.n, &pk.n_squared)?, pk)?;
+                Ok((inner, weight))
+            })
+            .collect::<Result<Vec<ClkScore>, CryptoError>>()
+    })?;
+    out.shuffle(&mut rand::thread_rng());
+    Ok(out)
+}
+
+/// `records` : (|BD1|, |BD2|) annoncés en Phase 0e
+pub fn phase3_server_compute_fuzzy(
+    params:  ClkParams,
+    records: (usize, usize),
+    bd1:     &DualFtBundle,
+    bd2:     &DualFtBundle,
+    pk1:     &PublicKey,
+    pk2:     &PublicKey,
+) -> Result<(Vec<ClkScore>, Vec<ClkScore>), CryptoError> {
+    println!(
+        "  [Phase 3] Serveur : {} x {} paires, produits scalaires sur {} bits...",
+        records.0, records.1, params.bits
+    );
+    let t_start = Instant::now();
+
+    let mut out = Vec::with_capacity(2);
+    for (pk, b1, b2) in [(pk1, &bd1.under_pk1, &bd2.under_pk1), (pk2, &bd1.under_pk2, &bd2.under_pk2)] {
+        let a = clk_rows("BD1", b1, records.0, params.bits)?;
+        let b = clk_rows("BD2", b2, records.1, params.bits)?;
+        out.push(score_pairs(&a, &b, pk)?);
+    }
+    let out_pk2 = out.pop().expect("scores pk2");
+    let out_pk1 = out.pop().expect("scores pk1");
+
+    println!(
+        "  [Phase 3] termine en {:.3?} ({} scores x 2 cles, melanges).",
+        t_start.elapsed(), out_pk1.len()
+    );
+    Ok((out_pk1, out_pk2))
+}
+
+// ---------------------------------------------------------------------------
+// BD — Phase 4 : déchiffrement des scores et comptage
+// ---------------------------------------------------------------------------
+
+/// `bits` borne les valeurs légitimes : c <= L, s <= 2L
+pub fn phase4_count_similar(
+    label:      &str,
+    scores:     &[ClkScore],
+    bits:       usize,
+    similarity: Similarity,
+    kp:         &KeyPair,
+) -> Result<usize, CryptoError> {
+    println!("  [Phase 4] {} : dechiffrement de {} scores...", label, scores.len());
+    let t_start = Instant::now();
+
+    let decoded = default_pool().install(|| {
+        scores.par_iter()
+            .map(|(inner, weight)| {
+                let c = cf_sum_products_dec(inner, kp)?.to_u64().filter(|&c| c <= bits as u64);
+                let s = cf_add_dec(weight, &kp.public_key, &kp.secret_key)?.to_u64().filter(|&s| s <= 2 * bits as u64);
+                match (c, s) {
+                    (Some(c), Some(s)) if 2 * c <= s => Ok((c, s)),
+                    _ => Err(CryptoError::InvalidInput("score CLK hors bornes (serveur devie ?)".into())),
+                }
+            })
+            .collect::<Result<Vec<(u64, u64)>, CryptoError>>()
+    })?;
+    let count = decoded.iter().filter(|&&(c, s)| similarity.matches(c, s)).count();
+
+    println!(
+        "  [Phase 4] {} : termine en {:.3?}  ->  {} paire(s) similaire(s) ({:?})",
+        label, t_start.elapsed(), count, similarity
+    );
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    fn record(fields: &[&str]) -> Vec<String> {
+        fields.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_fuzzy_scores_tolerate_typos() {
+        let hasher = PositionHasher::new([7u8; 32], 20).unwrap();
+        let params = ClkParams::new(64, 2).unwrap();
+        let a = vec![record(&["Dupont", "Marie", "1980-04-12"])];
+        let b = vec![
+            record(&["Dupond", "Marie", "1980-04-12"]),
+            record(&["Martin", "Paul", "1975-11-02"]),
+        ];
+        let (ca, cb) = (phase1_build_clks("A", &a, params, &hasher), phase1_build_clks("B", &b, params, &hasher));
+
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+        let b1 = phase2_prepare_clk_ft("A", &ca, pk1, pk2).unwrap();
+        let b2 = phase2_prepare_clk_ft("B", &cb, pk1, pk2).unwrap();
+        let (s1, s2) = phase3_server_compute_fuzzy(params, (1, 2), &b1, &b2, pk1, pk2).unwrap();
+
+        let dice = Similarity::Dice(0.6);
+        assert_eq!(phase4_count_similar("A", &s1, params.bits, dice, &kp1).unwrap(), 1);
+        assert_eq!(phase4_count_similar("B", &s2, params.bits, dice, &kp2).unwrap(), 1);
+        assert_eq!(phase4_count_similar("A", &s1, params.bits, Similarity::Hamming(128), &kp1).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_fuzzy_scores_hide_phase2_masks() {
+        let hasher = PositionHasher::new([9u8; 32], 20).unwrap();
+        let params = ClkParams::new(16, 2).unwrap();
+        let a = vec![record(&["Dupont", "Marie"]), record(&["Martin", "Paul"])];
+        let b = vec![record(&["Dupond", "Marie"]), record(&["Durand", "Jeanne"])];
+        let (ca, cb) = (phase1_build_clks("A", &a, params, &hasher), phase1_build_clks("B", &b, params, &hasher));
+
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+        let b1 = phase2_prepare_clk_ft("A", &ca, pk1, pk2).unwrap();
+        let b2 = phase2_prepare_clk_ft("B", &cb, pk1, pk2).unwrap();
+        let (s1, _) = phase3_server_compute_fuzzy(params, (2, 2), &b1, &b2, pk1, pk2).unwrap();
+
+        // Masques de Phase 2 sous pk1, et leurs sommes par enregistrement
+        let dec = |c: &BigUint| p_decrypt(c, pk1, &kp1.secret_key).unwrap();
+        let masks = |bundle: &FtBundle| -> Vec<BigUint> {
+            (0..bundle.ft_by_pos.len()).map(|p| dec(&bundle.ft_by_pos[&p].1)).collect()
+        };
+        let (ma, mb) = (masks(&b1.under_pk1), masks(&b2.under_pk1));
+        let own: HashSet<&BigUint> = ma.iter().chain(&mb).collect();
+        let row_sums = |m: &[BigUint]| -> Vec<BigUint> {
+            m.chunks(params.bits).map(|r| r.iter().fold(BigUint::zero(), |acc, x| (acc + x) % &pk1.n)).collect()
+        };
+        let (ra, rb) = (row_sums(&ma), row_sums(&mb));
+        let weight_masks: HashSet<BigUint> = ra.iter()
+            .flat_map(|x| rb.iter().map(move |y| (x + y) % &pk1.n))
+            .collect();
+
+        for (inner, weight) in &s1 {
+            for (c1, c2) in &inner.betas {
+                assert!(!own.contains(&dec(c1)) && !own.contains(&dec(c2)));
+            }
+            assert!(!weight_masks.contains(&dec(&weight.1)));
+        }
+        assert_eq!(phase4_count_similar("A", &s1, params.bits, Similarity::Hamming(32), &kp1).unwrap(), 4);
+    }
+}
@@ -1,16 +1,94 @@
 pub mod exactmatch;
+pub mod position_hash;
+pub mod cuckoo;
+pub mod multiparty;
+pub mod fuzzy;
+pub mod dp;
+pub mod similarity;
+pub mod delta;
+pub mod stream;
+pub mod checkpoint;
+pub mod consistency;
+pub mod setops;
+pub mod multiset;
 
 pub use exactmatch::CfFst;
 pub use exactmatch::CfSnd;
 pub use exactmatch::SparseTable;
 pub use exactmatch::FtBundle;
 pub use exactmatch::DualFtBundle;
-pub use exactmatch::HASH_BITS;
-pub use exactmatch::TABLE_SIZE;
-pub use exactmatch::simple_hash;
 pub use exactmatch::load_nss_from_csv;
+pub use exactmatch::load_fields_from_csv;
 pub use exactmatch::phase0_keygen;
 pub use exactmatch::phase1_build_table;
+pub use exactmatch::phase0_hash_key_share;
+pub use exactmatch::phase0_derive_hasher;
+pub use exactmatch::report_cross_collisions;
+pub use position_hash::PositionHasher;
+pub use position_hash::HashKey;
+pub use position_hash::derive_hash_key_multi;
+pub use position_hash::HASH_KEY_LEN;
+pub use position_hash::DEFAULT_TABLE_BITS;
+pub use position_hash::check_table_bits;
 pub use exactmatch::phase2_prepare_dual_ft;
+pub use exactmatch::{phase2_prepare_dual_ft_padded, check_padded_bundle};
 pub use exactmatch::phase3_server_compute;
-pub use exactmatch::phase4_decrypt_and_count;
\ No newline at end of file
+pub use exactmatch::phase3_server_compute_positions;
+pub use exactmatch::{phase4_decrypt_positions, reveal_matching_records};
+pub use exactmatch::phase4_decrypt_and_count;
+pub use exactmatch::{phase4_decrypt_tally, CardinalTally, InvalidTriplet, INVALID_TRIPLETS_SHOWN};
+pub use exactmatch::MK_MODULUS_BITS;
+pub use exactmatch::mk_plain_modulus;
+pub use exactmatch::phase2_prepare_mk_ft;
+pub use exactmatch::phase3_server_compute_mk;
+pub use exactmatch::phase4_mk_linearize;
+pub use exactmatch::phase4_mk_partial_dec;
+pub use exactmatch::phase4_mk_combine;
+pub use exactmatch::KeaFtBundle;
+pub use exactmatch::DualKeaFtBundle;
+pub use exactmatch::phase0_kea_keygen;
+pub use exactmatch::phase2_prepare_dual_ft_kea;
+pub use exactmatch::phase3_server_compute_kea;
+pub use exactmatch::phase4_decrypt_and_count_kea;
+pub use exactmatch::phase1_build_cuckoo;
+pub use exactmatch::phase1_build_simple_bins;
+pub use exactmatch::phase2_prepare_slot_ft;
+pub use exactmatch::phase3_server_compute_cuckoo;
+pub use exactmatch::phase4_count_zeros;
+pub use cuckoo::CuckooParams;
+pub use cuckoo::CuckooTable;
+pub use cuckoo::SimpleBins;
+pub use cuckoo::DEFAULT_CUCKOO_HASHES;
+pub use cuckoo::DEFAULT_STASH_SIZE;
+pub use exactmatch::ValueTable;
+pub use exactmatch::phase1_build_value_table;
+pub use exactmatch::phase2_prepare_dual_ft_values;
+pub use exactmatch::phase4_decrypt_sum;
+pub use exactmatch::{THRESHOLD_CMP_BITS, THRESHOLD_STAT_BITS, ThresholdBlind, ThresholdShare};
+pub use exactmatch::phase3_rerandomize_masks;
+pub use exactmatch::phase3_server_aggregate;
+pub use exactmatch::phase4_threshold_mask_sum;
+pub use exactmatch::phase4_threshold_blind;
+pub use exactmatch::phase4_threshold_bits;
+pub use exactmatch::phase4_threshold_compare;
+pub use exactmatch::phase4_threshold_decide;
+pub use multiparty::MultiFtBundle;
+pub use multiparty::MAX_PARTIES;
+pub use multiparty::{mp_prepare_ft, mp_common_positions, mp_column, mp_multiply};
+pub use multiparty::{mp_hash_key_shares, mp_derive_hasher};
+pub use multiparty::{mp_blind, mp_reencrypt, mp_fresh_in_order, mp_unblind, mp_table_of, check_party_count};
+pub use fuzzy::{ClkParams, ClkScore, Similarity, DEFAULT_CLK_BITS, DEFAULT_CLK_HASHES};
+pub use fuzzy::{clk_encode, phase1_build_clks, phase2_prepare_clk_ft, phase3_server_compute_fuzzy, phase4_count_similar};
+pub use dp::{DpLedger, DpRelease, DP_NOISE_SHARES, check_epsilon, sample_discrete_laplace, discrete_laplace_variance};
+pub use dp::{phase4_dp_noise_share, phase4_dp_combine, phase4_dp_decrypt};
+pub use similarity::{SetOverlap, DEFAULT_MINHASH_K, MAX_MINHASH_K, check_minhash_k};
+pub use similarity::{phase0_size_share, phase0_open_size, minhash_signature, phase3_server_compute_minhash, phase4_estimate_jaccard};
+pub use delta::{DeltaState, DeltaStats, DeltaSession, new_session_id};
+pub use delta::{phase3_delta_mask_pairs, phase3_delta_unshuffle, phase4_delta_mask_products, phase4_delta_decrypt_total};
+pub use stream::{PositionSorter, SortedPositions, MaskSpool, MaskChunks, StreamJoin, StreamTally, StreamEntry, StreamLot};
+pub use stream::{DEFAULT_STREAM_CHUNK, MAX_STREAM_CHUNK, check_stream_chunk, phase2_stream_chunk, phase3_stream_join};
+pub use checkpoint::{ClientCheckpoint, ServerCheckpoint, CHECKPOINT_BATCH, input_digest};
+pub use consistency::{CardinalOpening, phase5_commit, phase5_seal, phase5_verify};
+pub use setops::{SetOp, SetOps, SetOpsAgg, phase3_server_setops, phase4_setops_decrypt};
+pub use multiset::{MultisetSemantics, DuplicateStats, MULTISET_MAX_COUNT};
+pub use multiset::{occurrence_position, phase1_build_multiset, phase4_multiset_product};
@@ -0,0 +1,306 @@
+use std::collections::HashSet;
+use std::time::Instant;
+use num_bigint::{BigUint, RandBigInt};
+use rand_core::OsRng;
+use crate::exactmatch::exactmatch::{CfFst, CfSnd, FtBundle, SparseTable, make_ft_for_ones};
+use crate::fiore_catalano::cf_batch::cf_batch::{cf_encrypt_batch, cf_mul_batch, cf_mul_dec_batch};
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::exactmatch::position_hash::{
+    PositionHasher, HashKey, random_key_share, derive_hash_key_multi, share_to_biguint, biguint_to_share,
+};
+use crate::paillier::p_keygen::PublicKey;
+use crate::parallel::default_pool;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// ExactMatch à N parties (N >= 2)
+//
+// CF n'évalue qu'un produit de degré 2. Pour x_1·x_2·…·x_N on enchaîne
+// N-1 cf_mul, en ramenant le produit intermédiaire en Première Forme entre
+// deux niveaux par un tour de rechiffrement avec le détenteur de la clé :
+//
+//   Serveur : P = cf_mul(acc, Ft_i)                       (Seconde Forme)
+//             P' = P + s  (s uniforme dans Z_n, C0 · Enc(s))
+//   BD k    : m' = Dec2(P') = acc·x_i + s  (uniforme : rien n'est révélé)
+//             renvoie CF.Enc(m') frais
+//   Serveur : acc = CF.Enc(m') - s = (c0 - s mod n, c1)   (Première Forme)
+//
+// Chaque BD k reçoit le produit final sous SA clé pk_k : les Ft de chaque
+// BD sont chiffrés sous les N clés (MultiFtBundle), comme DualFtBundle le
+// fait pour N = 2. Coût : N clés × |positions communes| × (N-1) cf_mul et
+// N-2 tours de rechiffrement.
+// ============================================================================
+
+/// Nombre maximal de parties accepté par le serveur
+pub const MAX_PARTIES: usize = 16;
+
+/// Ft d'une BD sous chacune des N clés (under[k] : sous pk_k)
+pub struct MultiFtBundle {
+    pub under: Vec<FtBundle>,
+}
+
+// ---------------------------------------------------------------------------
+// BD — Phase 0d : clé HMAC commune aux N parties
+//
+// Chaque BD chiffre sa part sous la pk de chacune des N-1 autres ;
+// K = SHA-256(domaine || s_1 || … || s_N || psk).
+// ---------------------------------------------------------------------------
+
+/// Part locale + ses N-1 chiffrés, étiquetés par le destinataire
+pub fn mp_hash_key_shares(
+    label:  &str,
+    own_id: usize,
+    pks:    &[PublicKey],
+) -> Result<(HashKey, Vec<(u32, BigUint)>), CryptoError> {
+    let share = random_key_share();
+    let m     = share_to_biguint(&share);
+    let mut out = Vec::with_capacity(pks.len().saturating_sub(1));
+    for (j, pk) in pks.iter().enumerate() {
+        if j != own_id {
+            out.push((j as u32, p_encrypt(&m, pk)?));
+        }
+    }
+    println!("  [Phase 0d] {} : part de cle chiffree sous {} pk.", label, out.len());
+    Ok((share, out))
+}
+
+/// Déchiffre les N-1 parts reçues (étiquetées par l'émetteur) et dérive K
+pub fn mp_derive_hasher(
+    label:      &str,
+    own_id:     usize,
+    own_share:  &HashKey,
+    received:   &[(u32, BigUint)],
+    kp_self:    &KeyPair,
+    psk:        Option<&HashKey>,
+    table_bits: u32,
+) -> Result<PositionHasher, CryptoError> {
+    let n_parties = received.len() + 1;
+    if own_id >= n_parties {
+        return Err(CryptoError::InvalidInput(format!("identifiant BD{} pour {} parties", own_id + 1, n_parties)));
+    }
+    let mut shares: Vec<Option<HashKey>> = vec![None; n_parties];
+    shares[own_id] = Some(*own_share);
+    for (from, ct) in received {
+        let slot = shares
+            .get_mut(*from as usize)
+            .filter(|s| s.is_none())
+            .ok_or_else(|| CryptoError::InvalidInput(format!("part de cle inattendue de BD{}", from + 1)))?;
+        *slot = Some(biguint_to_share(&p_decrypt(ct, &kp_self.public_key, &kp_self.secret_key)?)?);
+    }
+    let shares: Vec<HashKey> = shares
+        .into_iter()
+        .enumerate()
+        .map(|(i, s)| s.ok_or_else(|| CryptoError::InvalidInput(format!("part de cle de BD{} manquante", i + 1))))
+        .collect::<Result<_, _>>()?;
+    println!(
+        "  [Phase 0d] {} : cle de hachage derivee de {} parts (psk : {}).",
+        label, n_parties, if psk.is_some() { "oui" } else { "non" }
+    );
+    PositionHasher::new(derive_hash_key_multi(&shares, psk), table_bits)
+}
+
+// ---------------------------------------------------------------------------
+// BD — Phase 2 : Ft(1) sous les N clés
+// ---------------------------------------------------------------------------
+
+pub fn mp_prepare_ft(label: &str, table: &SparseTable, pks: &[PublicKey]) -> MultiFtBundle {
+    println!(
+        "  [Phase 2] {} : preparation Ft pour {} positions (sous {} cles)...",
+        label, table.len(), pks.len()
+    );
+    let mut rng = OsRng;
+    let positions: Vec<usize> = table.active.iter().copied().collect();
+
+    let under = pks
+        .iter()
+        .map(|pk| {
+            let masques: Vec<BigUint> = positions.iter().map(|_| rng.gen_biguint_below(&pk.n)).collect();
+            FtBundle { ft_by_pos: positions.iter().copied().zip(make_ft_for_ones(&masques, pk)).collect() }
+        })
+        .collect();
+    MultiFtBundle { under }
+}
+
+// ---------------------------------------------------------------------------
+// Serveur — positions présentes chez les N parties (triées)
+// ---------------------------------------------------------------------------
+
+pub fn mp_common_positions(tables: &[SparseTable]) -> Vec<usize> {
+    let Some((first, rest)) = tables.split_first() else {
+        return Vec::new();
+    };
+    let mut common: Vec<usize> = first
+        .active
+        .iter()
+        .copied()
+        .filter(|p| rest.iter().all(|t| t.active.contains(p)))
+        .collect();
+    common.sort_unstable();
+    common
+}
+
+/// Ft de la partie `party` sous la clé `key`, pour chaque position commune
+pub fn mp_column<'a>(
+    bundles: &'a [MultiFtBundle],
+    party:   usize,
+    key:     usize,
+    common:  &[usize],
+) -> Result<Vec<&'a CfFst>, CryptoError> {
+    let b = bundles
+        .get(party)
+        .and_then(|b| b.under.get(key))
+        .ok_or_else(|| CryptoError::InvalidInput(format!("bundle BD{} sous pk{} manquant", party + 1, key + 1)))?;
+    common
+        .iter()
+        .map(|p| {
+            b.ft_by_pos.get(p).ok_or_else(|| {
+                CryptoError::InvalidInput(format!("BD{} : Ft(pk{}) manquant en position {}", party + 1, key + 1, p))
+            })
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Serveur — un niveau de produit : acc_j · Ft_j
+// ---------------------------------------------------------------------------
+
+pub fn mp_multiply(acc: &[CfFst], column: &[&CfFst], pk: &PublicKey) -> Result<Vec<CfSnd>, CryptoError> {
+    if acc.len() != column.len() {
+        return Err(CryptoError::InvalidInput(format!(
+            "mp_multiply : {} accumulateurs / {} Ft", acc.len(), column.len()
+        )));
+    }
+    let pairs: Vec<(&CfFst, &CfFst)> = acc.iter().zip(column.iter().copied()).collect();
+    cf_mul_batch(&pairs, pk, default_pool())
+}
+
+// ---------------------------------------------------------------------------
+// Tour de rechiffrement (Seconde Forme → Première Forme)
+// ---------------------------------------------------------------------------
+
+/// Serveur : P + s pour chaque produit ; retourne les s à retirer ensuite
+pub fn mp_blind(products: &[CfSnd], pk: &PublicKey) -> Result<(Vec<CfSnd>, Vec<BigUint>), CryptoError> {
+    let mut rng = OsRng;
+    let mut blinded = Vec::with_capacity(products.len());
+    let mut offsets = Vec::with_capacity(products.len());
+    for (c0, c1, c2) in products {
+        let s  = rng.gen_biguint_below(&pk.n);
+        let c0 = (c0 * p_encrypt(&s, pk)?) % &pk.n_squared;
+        blinded.push((c0, c1.clone(), c2.clone()));
+        offsets.push(s);
+    }
+    Ok((blinded, offsets))
+}
+
+/// BD k : Dec2 des produits aveuglés, puis CF.Enc frais (masques neufs)
+pub fn mp_reencrypt(label: &str, blinded: &[CfSnd], kp: &KeyPair) -> Result<Vec<CfFst>, CryptoError> {
+    println!("  [Phase 3] {} : rechiffrement de {} produits aveugles...", label, blinded.len());
+    let t  = Instant::now();
+    let pk = &kp.public_key;
+    let ms = cf_mul_dec_batch(blinded, pk, &kp.secret_key, default_pool())?;
+    let mut rng = OsRng;
+    let masques: Vec<BigUint> = ms.iter().map(|_| rng.gen_biguint_below(&pk.n)).collect();
+    let fresh = cf_encrypt_batch(&ms, &masques, pk, default_pool())?;
+    println!("  [Phase 3] {} : rechiffrement termine en {:.3?}.", label, t.elapsed());
+    Ok(fresh)
+}
+
+/// Serveur : remet dans l'ordre les Ft rechiffrés renvoyés par un BD.
+/// Les indices doivent être exactement 0..attendus : un doublon ou un trou
+/// décalerait silencieusement les offsets de `mp_unblind`.
+pub fn mp_fresh_in_order(mut entries: Vec<(usize, CfFst)>, attendus: usize) -> Result<Vec<CfFst>, CryptoError> {
+    if entries.len() != attendus {
+        return Err(CryptoError::InvalidInput(format!(
+            "rechiffrement : {} Ft recus, {} attendus", entries.len(), attendus
+        )));
+    }
+    entries.sort_unstable_by_key(|(i, _)| *i);
+    if let Some((rang, (i, _))) = entries.iter().enumerate().find(|(rang, (i, _))| rang != i) {
+        return Err(CryptoError::InvalidInput(format!(
+            "rechiffrement : indice {} au rang {} (attendus 0..{})", i, rang, attendus
+        )));
+    }
+    Ok(entries.into_iter().map(|(_, ft)| ft).collect())
+}
+
+/// Serveur : CF.Enc(m + s) - s = CF.Enc(m)
+pub fn mp_unblind(fresh: &[CfFst], offsets: &[BigUint], pk: &PublicKey) -> Result<Vec<CfFst>, CryptoError> {
+    if fresh.len() != offsets.len() {
+        return Err(CryptoError::InvalidInput(format!(
+            "rechiffrement : {} Ft recus, {} attendus", fresh.len(), offsets.len()
+        )));
+    }
+    fresh
+        .iter()
+        .zip(offsets)
+        .map(|((c0, c1), s)| {
+            if c0 >= &pk.n || c1 >= &pk.n_squared {
+                return Err(CryptoError::CiphertextOutOfRange);
+            }
+            Ok(((c0 + &pk.n - s) % &pk.n, c1.clone()))
+        })
+        .collect()
+}
+
+// ---------------------------------------------------------------------------
+// Vérifications de configuration
+// ---------------------------------------------------------------------------
+
+pub fn check_party_count(n: usize) -> Result<(), CryptoError> {
+    if !(2..=MAX_PARTIES).contains(&n) {
+        return Err(CryptoError::InvalidInput(format!(
+            "nombre de parties = {} hors de [2, {}]", n, MAX_PARTIES
+        )));
+    }
+    Ok(())
+}
+
+/// Positions distinctes d'un bundle (reconstruction de la table côté serveur)
+pub fn mp_table_of(bundle: &MultiFtBundle) -> SparseTable {
+    let active: HashSet<usize> = bundle.under.first().map(|b| b.ft_by_pos.keys().copied().collect()).unwrap_or_default();
+    SparseTable { active }
+}
+
+// ============================================================================
+// Tests — trois parties, clés de test courtes, flux serveur simulé
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exactmatch::exactmatch::phase4_decrypt_and_count;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    #[test]
+    fn test_three_party_product() {
+        let kps: Vec<KeyPair> = (0..3).map(|_| p_keygen(256).unwrap()).collect();
+        let pks: Vec<PublicKey> = kps.iter().map(|k| k.public_key.clone()).collect();
+        let tables = [
+            SparseTable { active: [1, 2, 3, 4, 5].into_iter().collect() },
+            SparseTable { active: [2, 3, 4, 9].into_iter().collect() },
+            SparseTable { active: [3, 4, 5, 9].into_iter().collect() },
+        ];
+        let bundles: Vec<MultiFtBundle> = tables.iter().map(|t| mp_prepare_ft("T", t, &pks)).collect();
+        let common = mp_common_positions(&tables);
+        assert_eq!(common, vec![3, 4]);
+
+        for (k, kp) in kps.iter().enumerate() {
+            let pk  = &pks[k];
+            let mut acc: Vec<CfFst> = mp_column(&bundles, 0, k, &common).unwrap().into_iter().cloned().collect();
+            let p1  = mp_multiply(&acc, &mp_column(&bundles, 1, k, &common).unwrap(), pk).unwrap();
+            let (blinded, s) = mp_blind(&p1, pk).unwrap();
+            acc = mp_unblind(&mp_reencrypt("T", &blinded, kp).unwrap(), &s, pk).unwrap();
+            let p2  = mp_multiply(&acc, &mp_column(&bundles, 2, k, &common).unwrap(), pk).unwrap();
+            assert_eq!(phase4_decrypt_and_count("T", &p2, kp).unwrap(), 2);
+        }
+
+        // Indices du bundle rechiffré : permutés acceptés, doublon ou trou rejetés
+        let ft = |v: u32| (BigUint::from(v), BigUint::from(v));
+        let ok = mp_fresh_in_order(vec![(1, ft(1)), (0, ft(0))], 2).unwrap();
+        assert_eq!(ok, vec![ft(0), ft(1)]);
+        assert!(mp_fresh_in_order(vec![(0, ft(0)), (0, ft(1))], 2).is_err());
+        assert!(mp_fresh_in_order(vec![(0, ft(0)), (2, ft(2))], 2).is_err());
+        assert!(mp_fresh_in_order(vec![(0, ft(0))], 2).is_err());
+    }
+}
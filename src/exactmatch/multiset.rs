@@ -0,0 +1,292 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Instant;
+use num_bigint::{BigInt, BigUint};
+use num_traits::{ToPrimitive, Zero};
+use crate::exactmatch::exactmatch::{SparseTable, ValueTable};
+use crate::exactmatch::position_hash::PositionHasher;
+use crate::cf_stats::{CfSndSum, cf_sum_products_dec};
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// PSI-CA sur multiensembles (--multiset <min|product>)
+//
+// SparseTable fusionne les doublons : une clé présente a fois dans BD1 et
+// b fois dans BD2 compte 1. En mode multiset, chaque clé porte sa
+// multiplicité, et le serveur calcule par CF.Mul, sans rien changer :
+//
+//   min     : Σ min(a_i, b_i). La k-ième occurrence d'une clé occupe sa
+//             propre position (k = 1 : position habituelle, k ≥ 2 :
+//             HMAC(K, "occ" ‖ k ‖ clé)) ; min(a, b) = Σ_k [a ≥ k]·[b ≥ k]
+//             est alors le cardinal ordinaire des positions, triplets 0/1.
+//             Sans doublon, le résultat est celui du mode standard.
+//   product : Σ a_i·b_i. Ft(multiplicité) à la position de la clé (comme
+//             --sum) ; le serveur bourre les triplets à min(|T1|, |T2|),
+//             les agrège et re-randomise les masques
+//             (phase3_server_aggregate) : le BD ne déchiffre que la somme,
+//             jamais un produit a_i·b_i. Le total est borné par
+//             MULTISET_MAX_COUNT² par paire reçue, sinon le serveur a dévié.
+//             Deux clés sur une même position sont refusées en Phase 1 :
+//             leurs multiplicités s'additionneraient au-delà de cette borne.
+//
+// Fuite : en min, le serveur voit Σ a_i positions au lieu du nombre de clés
+// distinctes ; en product, la taille est inchangée. La sémantique et la
+// borne sont échangées en Phase 0e : les deux BD doivent annoncer la même.
+// ============================================================================
+
+/// Multiplicité maximale d'une clé (au-delà : erreur en Phase 1)
+pub const MULTISET_MAX_COUNT: u32 = 255;
+
+/// Classes de multiplicité détaillées dans le rapport de Phase 1
+const DUPLICATE_CLASSES_SHOWN: usize = 5;
+
+const OCCURRENCE_DOMAIN: &[u8] = b"occ\0";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MultisetSemantics {
+    Min,
+    Product,
+}
+
+impl MultisetSemantics {
+    pub fn parse(s: &str) -> Result<Self, CryptoError> {
+        match s {
+            "min"     => Ok(MultisetSemantics::Min),
+            "product" => Ok(MultisetSemantics::Product),
+            _ => Err(CryptoError::InvalidInput(format!("--multiset : « {} » inconnu (min, product)", s))),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MultisetSemantics::Min     => "min",
+            MultisetSemantics::Product => "product",
+        }
+    }
+
+    /// Code de Phase 0e
+    pub fn code(self) -> u8 {
+        match self {
+            MultisetSemantics::Min     => 1,
+            MultisetSemantics::Product => 2,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Result<Self, CryptoError> {
+        match code {
+            1 => Ok(MultisetSemantics::Min),
+            2 => Ok(MultisetSemantics::Product),
+            _ => Err(CryptoError::InvalidInput(format!("sémantique multiset inconnue ({})", code))),
+        }
+    }
+
+    /// Libellé du résultat
+    pub fn label(self) -> &'static str {
+        match self {
+            MultisetSemantics::Min     => "Σ min(a, b)",
+            MultisetSemantics::Product => "Σ a·b",
+        }
+    }
+}
+
+/// Doublons d'une base, avant toute fusion
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateStats {
+    pub records:    usize,
+    pub distinct:   usize,
+    /// Clés présentes au moins deux fois
+    pub duplicated: usize,
+    pub max_count:  u32,
+    /// multiplicité -> nombre de clés (multiplicités ≥ 2)
+    pub classes:    BTreeMap<u32, usize>,
+}
+
+impl DuplicateStats {
+    pub fn from_keys(keys: &[String]) -> Self {
+        let counts = multiplicities(keys);
+        let mut classes = BTreeMap::new();
+        for &c in counts.values().filter(|&&c| c >= 2) {
+            *classes.entry(c).or_insert(0) += 1;
+        }
+        DuplicateStats {
+            records:    keys.len(),
+            distinct:   counts.len(),
+            duplicated: classes.values().sum(),
+            max_count:  counts.values().copied().max().unwrap_or(0),
+            classes,
+        }
+    }
+
+    /// Lignes fusionnées par le mode standard
+    pub fn merged(&self) -> usize {
+        self.records - self.distinct
+    }
+
+    pub fn report(&self, label: &str) {
+        if self.duplicated == 0 {
+            println!("  [Phase 1] {} : aucun doublon ({} clés distinctes).", label, self.distinct);
+            return;
+        }
+        println!(
+            "  [Phase 1] {} : {} ligne(s), {} clé(s) distincte(s), {} clé(s) répétée(s) ({} ligne(s) en double), multiplicité max {}",
+            label, self.records, self.distinct, self.duplicated, self.merged(), self.max_count
+        );
+        let shown: Vec<String> = self.classes.iter()
+            .take(DUPLICATE_CLASSES_SHOWN)
+            .map(|(c, n)| format!("x{} : {}", c, n))
+            .collect();
+        let more = if self.classes.len() > DUPLICATE_CLASSES_SHOWN { ", …" } else { "" };
+        println!("  [Phase 1] {} : multiplicités {}{}", label, shown.join(", "), more);
+    }
+}
+
+fn multiplicities(keys: &[String]) -> HashMap<&str, u32> {
+    let mut counts: HashMap<&str, u32> = HashMap::new();
+    for k in keys {
+        *counts.entry(k.as_str()).or_insert(0) += 1;
+    }
+    counts
+}
+
+// ---------------------------------------------------------
+// Phase 1 — table du multiensemble
+// ---------------------------------------------------------
+
+/// Position de la k-ième occurrence (k ≥ 1) d'une clé
+pub fn occurrence_position(hasher: &PositionHasher, key: &str, k: u32) -> usize {
+    if k == 1 {
+        hasher.position(key)
+    } else {
+        hasher.position_in(OCCURRENCE_DOMAIN, &format!("{}\0{}", k, key))
+    }
+}
+
+/// min : une position par occurrence, valeur 1 ; product : multiplicité à
+/// la position de la clé. Les collisions fusionnent en min, comme en mode
+/// standard ; en product, elles sont une erreur (borne de Phase 4).
+pub fn phase1_build_multiset(
+    label:     &str,
+    keys:      &[String],
+    hasher:    &PositionHasher,
+    semantics: MultisetSemantics,
+) -> Result<ValueTable, CryptoError> {
+    println!(
+        "  [Phase 1] {} : multiensemble ({}) de {} ligne(s), TABLE_SIZE=2^{}...",
+        label, semantics.name(), keys.len(), hasher.table_bits()
+    );
+    let stats = DuplicateStats::from_keys(keys);
+    stats.report(label);
+    if stats.max_count > MULTISET_MAX_COUNT {
+        return Err(CryptoError::InvalidInput(format!(
+            "{} : multiplicité {} > {} (MULTISET_MAX_COUNT)", label, stats.max_count, MULTISET_MAX_COUNT
+        )));
+    }
+
+    let mut values: HashMap<usize, BigInt> = HashMap::new();
+    for (key, &count) in &multiplicities(keys) {
+        match semantics {
+            MultisetSemantics::Min => {
+                for k in 1..=count {
+                    values.insert(occurrence_position(hasher, key, k), BigInt::from(1u32));
+                }
+            }
+            MultisetSemantics::Product => {
+                *values.entry(hasher.position(key)).or_insert_with(BigInt::zero) += count;
+            }
+        }
+    }
+    let expected = match semantics {
+        MultisetSemantics::Min     => stats.records,
+        MultisetSemantics::Product => stats.distinct,
+    };
+    let table = SparseTable { active: values.keys().copied().collect::<HashSet<usize>>() };
+    if semantics == MultisetSemantics::Product && table.len() < expected {
+        return Err(CryptoError::InvalidInput(format!(
+            "{} : {} collision(s) de position en product (multiplicités additionnées), augmenter --table-bits",
+            label, expected - table.len()
+        )));
+    }
+    println!(
+        "  [Phase 1] {} : {} position(s) active(s), collisions observees = {}",
+        label, table.len(), expected - table.len()
+    );
+    Ok(ValueTable { table, values })
+}
+
+// ---------------------------------------------------------
+// Phase 4 (product) — BD : Σ a·b agrégée, bornée
+// ---------------------------------------------------------
+
+pub fn phase4_multiset_product(label: &str, agg: &CfSndSum, kp: &KeyPair) -> Result<u64, CryptoError> {
+    println!("  [Phase 4] {} : Dec(alpha) + {} paires de masques -> Σ a·b...", label, agg.betas.len());
+    let t_start = Instant::now();
+
+    let bound = BigUint::from(MULTISET_MAX_COUNT) * MULTISET_MAX_COUNT * agg.betas.len();
+    let total = cf_sum_products_dec(agg, kp)?;
+    if total > bound {
+        return Err(CryptoError::InvalidInput(format!(
+            "Σ a·b = {} > {} (MULTISET_MAX_COUNT² × {} paires), le serveur a dévié",
+            total, bound, agg.betas.len()
+        )));
+    }
+
+    println!("  [Phase 4] {} : termine en {:.3?}.", label, t_start.elapsed());
+    total.to_u64().ok_or_else(|| CryptoError::InvalidInput(format!("Σ a·b = {} hors de u64", total)))
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exactmatch::exactmatch::{
+        phase2_prepare_dual_ft_values, phase3_server_aggregate, phase3_server_compute, phase4_decrypt_and_count,
+    };
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    fn keys(spec: &[(&str, usize)]) -> Vec<String> {
+        spec.iter().flat_map(|&(k, n)| std::iter::repeat_n(k.to_string(), n)).collect()
+    }
+
+    #[test]
+    fn test_multiset_min_and_product() {
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let hasher = PositionHasher::new([7u8; 32], 24).unwrap();
+
+        // x : 3 / 2, y : 1 / 4, z : 2 / 0, w : 0 / 1
+        let a = keys(&[("x", 3), ("y", 1), ("z", 2)]);
+        let b = keys(&[("x", 2), ("y", 4), ("w", 1)]);
+        let stats = DuplicateStats::from_keys(&a);
+        assert_eq!((stats.records, stats.distinct, stats.duplicated, stats.max_count), (6, 3, 2, 3));
+        assert_eq!(stats.classes, BTreeMap::from([(2, 1), (3, 1)]));
+
+        for (semantics, expected) in [(MultisetSemantics::Min, 3u64), (MultisetSemantics::Product, 10)] {
+            let t1 = phase1_build_multiset("A", &a, &hasher, semantics).unwrap();
+            let t2 = phase1_build_multiset("B", &b, &hasher, semantics).unwrap();
+            let b1 = phase2_prepare_dual_ft_values("A", &t1, &kp1.public_key, &kp2.public_key).unwrap();
+            let b2 = phase2_prepare_dual_ft_values("B", &t2, &kp1.public_key, &kp2.public_key).unwrap();
+            let (cts, _) = phase3_server_compute(&t1.table, &t2.table, &b1, &b2, &kp1, &kp2);
+            let got = match semantics {
+                MultisetSemantics::Min     => phase4_decrypt_and_count("A", &cts, &kp1).unwrap() as u64,
+                MultisetSemantics::Product => {
+                    // Bourrage à min(|T1|, |T2|) = 3 : le BD ne voit ni les produits ni leur nombre
+                    let agg = phase3_server_aggregate(&cts, 3, &kp1.public_key).unwrap();
+                    assert_eq!(agg.betas.len(), 3);
+                    phase4_multiset_product("A", &agg, &kp1).unwrap()
+                }
+            };
+            assert_eq!(got, expected, "{:?}", semantics);
+        }
+
+        let too_many = keys(&[("x", MULTISET_MAX_COUNT as usize + 1)]);
+        assert!(phase1_build_multiset("A", &too_many, &hasher, MultisetSemantics::Min).is_err());
+
+        // 64 clés sur 2^8 positions : collisions certaines en pratique
+        let tiny = PositionHasher::new([7u8; 32], 8).unwrap();
+        let crowded: Vec<String> = (0..64).map(|i| format!("k{}", i)).collect();
+        assert!(phase1_build_multiset("A", &crowded, &tiny, MultisetSemantics::Min).is_ok());
+        assert!(phase1_build_multiset("A", &crowded, &tiny, MultisetSemantics::Product).is_err());
+    }
+}
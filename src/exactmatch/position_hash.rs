@@ -0,0 +1,256 @@
+use hmac::{Hmac, Mac};
+use num_bigint::BigUint;
+use rand_core::{OsRng, RngCore};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroize;
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ============================================================================
+// Hachage à clé des positions ExactMatch
+//
+//   pos(nss) = HMAC-SHA-256(K, len(d) || d || nss)[0..8]  >> (64 - table_bits)
+//
+// avec d = "" pour la position d'une clé. Les fonctions dérivées (cuckoo,
+// étiquettes, occurrences…) utilisent leur propre domaine d : la longueur
+// (u64 big-endian) le sépare de la donnée, si bien qu'aucune clé brute ne
+// peut reproduire la sortie d'un domaine dérivé.
+//
+// L'ancien simple_hash (polynôme 32 bits tronqué à 30 bits) s'inversait par
+// force brute sur le domaine des NSS (15 chiffres) : quiconque voyait une
+// position retrouvait le NSS. Sans K, une position HMAC ne révèle rien.
+//
+// Accord de clé (Phase 0d, après l'échange des pk) :
+//   - chaque BD tire une part s_i de 32 octets, l'envoie chiffrée sous
+//     pk_other (Paillier) via le serveur, qui ne peut pas la déchiffrer ;
+//   - K = SHA-256("psi-position-key-v1" || s_1 || s_2 || psk?)
+//
+// psk (optionnelle) : clé pré-partagée hors bande. Mélangée à K, elle
+// protège aussi contre un serveur actif qui substituerait les pk en Phase 0b.
+// Les deux BD doivent fournir la même psk : son identifiant psk_id circule
+// en clair pour détecter une configuration incohérente.
+//
+// Collisions : deux NSS distincts d'une même base peuvent tomber sur la même
+// position (la table perd une entrée), et deux NSS distincts des deux bases
+// peuvent coïncider (faux positif, le cardinal augmente). Les probabilités
+// sont estimées pour chaque exécution (intra_collision_probability,
+// expected_cross_collisions).
+// ============================================================================
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Taille de la clé de hachage et des parts échangées
+pub const HASH_KEY_LEN: usize = 32;
+
+/// Taille de table par défaut : 2^30 positions
+pub const DEFAULT_TABLE_BITS: u32 = 30;
+
+/// Bornes acceptées pour table_bits (positions stockées dans un usize)
+pub const MIN_TABLE_BITS: u32 = 8;
+pub const MAX_TABLE_BITS: u32 = 48;
+
+/// Séparateurs de domaine — à incrémenter si la dérivation change
+const KEY_DOMAIN: &[u8] = b"psi-position-key-v1";
+const PSK_ID_DOMAIN: &[u8] = b"psi-psk-id-v1";
+
+pub type HashKey = [u8; HASH_KEY_LEN];
+
+// ---------------------------------------------------------------------------
+// Hacheur de positions
+// ---------------------------------------------------------------------------
+
+#[derive(Clone)]
+pub struct PositionHasher {
+    key:        HashKey,
+    table_bits: u32,
+}
+
+impl PositionHasher {
+    pub fn new(key: HashKey, table_bits: u32) -> Result<Self, CryptoError> {
+        check_table_bits(table_bits)?;
+        Ok(PositionHasher { key, table_bits })
+    }
+
+    pub fn table_bits(&self) -> u32 {
+        self.table_bits
+    }
+
+    /// Clé K — uniquement pour la persistance d'une session (--delta)
+    pub(crate) fn key(&self) -> &HashKey {
+        &self.key
+    }
+
+    pub fn table_size(&self) -> u64 {
+        1u64 << self.table_bits
+    }
+
+    pub fn position(&self, s: &str) -> usize {
+        self.position_in(&[], s)
+    }
+
+    /// Position tirée de prf(domain, s) — positions dérivées (occurrences
+    /// d'un multiensemble) ; domain = [] redonne position()
+    pub fn position_in(&self, domain: &[u8], s: &str) -> usize {
+        let tag = self.prf(domain, s);
+
+        let mut head = [0u8; 8];
+        head.copy_from_slice(&tag[..8]);
+        (u64::from_be_bytes(head) >> (64 - self.table_bits)) as usize
+    }
+
+    /// HMAC-SHA-256(K, len(domaine) || domaine || s) — fonctions dérivées
+    /// (cuckoo, étiquettes). Le domaine vide est réservé à position().
+    pub fn prf(&self, domain: &[u8], s: &str) -> [u8; 32] {
+        let mut mac = HmacSha256::new_from_slice(&self.key)
+            .expect("HMAC accepte une clé de toute taille");
+        mac.update(&(domain.len() as u64).to_be_bytes());
+        mac.update(domain);
+        mac.update(s.as_bytes());
+        mac.finalize().into_bytes().into()
+    }
+}
+
+// La clé ne doit pas apparaître dans les logs
+impl std::fmt::Debug for PositionHasher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PositionHasher")
+            .field("key", &"<secret>")
+            .field("table_bits", &self.table_bits)
+            .finish()
+    }
+}
+
+impl Drop for PositionHasher {
+    fn drop(&mut self) {
+        self.key.zeroize();
+    }
+}
+
+pub fn check_table_bits(table_bits: u32) -> Result<(), CryptoError> {
+    if !(MIN_TABLE_BITS..=MAX_TABLE_BITS).contains(&table_bits) {
+        return Err(CryptoError::InvalidInput(format!(
+            "table_bits = {} hors de [{}, {}]",
+            table_bits, MIN_TABLE_BITS, MAX_TABLE_BITS
+        )));
+    }
+    Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// Accord de clé
+// ---------------------------------------------------------------------------
+
+/// Part aléatoire d'une BD
+pub fn random_key_share() -> HashKey {
+    let mut s = [0u8; HASH_KEY_LEN];
+    OsRng.fill_bytes(&mut s);
+    s
+}
+
+/// K = SHA-256(domaine || s_1 || s_2 || psk) — s_1 est TOUJOURS la part de BD1
+pub fn derive_hash_key(share_bd1: &HashKey, share_bd2: &HashKey, psk: Option<&HashKey>) -> HashKey {
+    derive_hash_key_multi(&[*share_bd1, *share_bd2], psk)
+}
+
+/// K = SHA-256(domaine || s_1 || … || s_N || psk), parts dans l'ordre des
+/// identifiants de BD (mode N parties ; N = 2 redonne derive_hash_key)
+pub fn derive_hash_key_multi(shares: &[HashKey], psk: Option<&HashKey>) -> HashKey {
+    let mut h = Sha256::new();
+    h.update(KEY_DOMAIN);
+    for s in shares {
+        h.update(s);
+    }
+    if let Some(psk) = psk {
+        h.update(psk);
+    }
+    h.finalize().into()
+}
+
+/// Identifiant public d'une psk (0 si absente) : détecte une psk différente
+/// d'un côté et de l'autre sans la révéler.
+pub fn psk_id(psk: Option<&HashKey>) -> u64 {
+    match psk {
+        None => 0,
+        Some(psk) => {
+            let mut h = Sha256::new();
+            h.update(PSK_ID_DOMAIN);
+            h.update(psk);
+            let d = h.finalize();
+            let mut head = [0u8; 8];
+            head.copy_from_slice(&d[..8]);
+            u64::from_be_bytes(head).max(1)
+        }
+    }
+}
+
+/// Part → message Paillier (entier < 2^256, toujours < n)
+pub fn share_to_biguint(share: &HashKey) -> BigUint {
+    BigUint::from_bytes_be(share)
+}
+
+/// Message Paillier déchiffré → part (complétée à gauche par des zéros)
+pub fn biguint_to_share(m: &BigUint) -> Result<HashKey, CryptoError> {
+    let bytes = m.to_bytes_be();
+    if bytes.len() > HASH_KEY_LEN {
+        return Err(CryptoError::InvalidInput("part de clé de hachage trop longue".into()));
+    }
+    let mut s = [0u8; HASH_KEY_LEN];
+    s[HASH_KEY_LEN - bytes.len()..].copy_from_slice(&bytes);
+    Ok(s)
+}
+
+// ---------------------------------------------------------------------------
+// Probabilités de collision (approximation de Poisson, T = 2^table_bits)
+// ---------------------------------------------------------------------------
+
+/// P(au moins une collision entre k éléments distincts d'une même base)
+///   ≈ 1 - exp(-k(k-1) / 2T)
+pub fn intra_collision_probability(k: usize, table_bits: u32) -> f64 {
+    let k = k as f64;
+    let t = (table_bits as f64).exp2();
+    -(-(k * (k - 1.0)) / (2.0 * t)).exp_m1()
+}
+
+/// Nombre attendu de faux positifs entre deux bases de n1 et n2 positions,
+/// majoré en supposant les bases disjointes : n1·n2 / T
+pub fn expected_cross_collisions(n1: usize, n2: usize, table_bits: u32) -> f64 {
+    (n1 as f64) * (n2 as f64) / (table_bits as f64).exp2()
+}
+
+/// P(au moins un faux positif) ≈ 1 - exp(-n1·n2 / T)
+pub fn cross_collision_probability(n1: usize, n2: usize, table_bits: u32) -> f64 {
+    -(-expected_cross_collisions(n1, n2, table_bits)).exp_m1()
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyed_positions() {
+        let (s1, s2) = (random_key_share(), random_key_share());
+        let k  = derive_hash_key(&s1, &s2, None);
+        let h  = PositionHasher::new(k, 20).unwrap();
+        let h2 = PositionHasher::new(derive_hash_key(&s1, &s2, Some(&[7u8; 32])), 20).unwrap();
+
+        let p = h.position("622494716482810");
+        assert_eq!(p, h.position("622494716482810"));
+        assert!(p < 1 << 20);
+        // Clé différente → positions indépendantes (égalité à 2^-20 près)
+        assert_ne!(
+            (0..8).map(|i| h.position(&i.to_string())).collect::<Vec<_>>(),
+            (0..8).map(|i| h2.position(&i.to_string())).collect::<Vec<_>>()
+        );
+
+        // Domaine préfixé par sa longueur : pas de collision avec une clé brute
+        assert_ne!(h.prf(b"tag\0", "x"), h.prf(&[], "tag\0x"));
+        assert_ne!(h.prf(b"ab", "c"), h.prf(b"a", "bc"));
+
+        assert_eq!(biguint_to_share(&share_to_biguint(&s1)).unwrap(), s1);
+        assert!(PositionHasher::new(k, 64).is_err());
+        assert_eq!(psk_id(None), 0);
+        assert_ne!(psk_id(Some(&[1u8; 32])), psk_id(Some(&[2u8; 32])));
+    }
+}
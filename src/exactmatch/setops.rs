@@ -0,0 +1,329 @@
+use std::fmt;
+use num_bigint::BigUint;
+use num_traits::{ToPrimitive, Zero};
+use crate::cf_stats::{cf_sum, CfSndSum};
+use crate::exactmatch::exactmatch::{phase3_server_aggregate, CfFst, CfSnd, FtBundle};
+use crate::paillier::math::mod_inverse;
+use crate::paillier::p_batch::p_batch::p_decrypt_batch;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_keygen::PublicKey;
+use crate::parallel::default_pool;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// Opérations ensemblistes privées — |A ∩ B|, |A \ B|, |B \ A|, |A ∪ B|
+//
+// Sur les bundles bourrés à P (--size-hiding --pad-to P), x_p = [p ∈ A]
+// pour chaque position p du bundle de BD1 (0 pour une factice), y_p de
+// même chez BD2 ; Σx = |A|, Σy = |B|, et Σxy porte sur les positions
+// communes. Chaque sortie est une forme L − Σ x·y :
+//
+//   A \ B = Σ x (1 − y)         = Σx − Σxy
+//   B \ A = Σ (1 − x) y         = Σy − Σxy
+//   A ∪ B = Σ 1 − (1 − x)(1 − y) = Σx + Σy − Σxy
+//   A ∩ B = Σ xy
+//
+// Serveur, sous la clé du BD destinataire :
+//   α = Π C0 = Enc(Σxy − M), M = Σ b·b', les triplets complétés à P
+//   par des produits factices et les masques re-randomisés comme pour
+//   --threshold (phase3_server_aggregate) : le BD ne reconnaît pas ses
+//   masques de Phase 2 et ne compte pas les triplets.
+//   Enc(Σx), Enc(Σy) par cf_add des Ft, puis pour chaque sortie choisie
+//   α_o = Enc(L_o) · α^-1 = Enc(L_o − Σxy + M)   (∩ : α_o = α)
+//   Le BD reçoit les P paires (C1, C2) mélangées et les seuls α_o
+//   choisis : sortie = Dec(α_o) − M (∩ : + M), bornée par 2P.
+//
+// Les sorties sont choisies par --setops, identique pour le serveur et les
+// deux BD (vérifié en Phase 0e). Un BD connaît sa propre taille : deux
+// sorties révélées ensemble (ou une seule et |self|) donnent les autres —
+// A \ B révélé à BD1 livre |A ∩ B| = |A| − |A \ B|.
+//
+// Le serveur est, en l'état, une partie qui apprend le résultat : il
+// voit les positions communes (--size-hiding ne cache que les tailles),
+// donc |A ∩ B| = nombre de triplets avant bourrage, et avec P les
+// autres cardinaux à |A| et |B| près. La re-randomisation et le choix
+// des sorties ne protègent que face aux BD.
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOp {
+    Inter,
+    AMinusB,
+    BMinusA,
+    Union,
+}
+
+impl SetOp {
+    pub const ALL: [SetOp; 4] = [SetOp::Inter, SetOp::AMinusB, SetOp::BMinusA, SetOp::Union];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SetOp::Inter   => "inter",
+            SetOp::AMinusB => "a-b",
+            SetOp::BMinusA => "b-a",
+            SetOp::Union   => "union",
+        }
+    }
+
+    /// Libellé du résultat (BD1 = A, BD2 = B)
+    pub fn label(self) -> &'static str {
+        match self {
+            SetOp::Inter   => "|BD1 ^ BD2|",
+            SetOp::AMinusB => "|BD1 \\ BD2|",
+            SetOp::BMinusA => "|BD2 \\ BD1|",
+            SetOp::Union   => "|BD1 U BD2|",
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << SetOp::ALL.iter().position(|&o| o == self).expect("SetOp::ALL complet")
+    }
+}
+
+/// Sorties choisies (masque de bits, ordre de SetOp::ALL)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SetOps(u8);
+
+impl SetOps {
+    /// « a-b,b-a,union » ; au moins une sortie
+    pub fn parse(list: &str) -> Result<Self, CryptoError> {
+        let mut bits = 0u8;
+        for name in list.split(',').map(str::trim) {
+            let op = SetOp::ALL.iter()
+                .find(|o| o.name() == name)
+                .ok_or_else(|| CryptoError::InvalidInput(format!(
+                    "--setops : opération « {} » inconnue (inter, a-b, b-a, union)", name
+                )))?;
+            bits |= op.bit();
+        }
+        Self::from_bits(bits)
+    }
+
+    pub fn from_bits(bits: u8) -> Result<Self, CryptoError> {
+        if bits == 0 || bits >> SetOp::ALL.len() != 0 {
+            return Err(CryptoError::InvalidInput(format!("--setops : masque de sorties invalide ({:#04x})", bits)));
+        }
+        Ok(SetOps(bits))
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, op: SetOp) -> bool {
+        self.0 & op.bit() != 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = SetOp> {
+        SetOp::ALL.into_iter().filter(move |&o| self.contains(o))
+    }
+
+    pub fn len(self) -> usize {
+        self.0.count_ones() as usize
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+}
+
+impl fmt::Display for SetOps {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names: Vec<&str> = self.iter().map(SetOp::name).collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+/// Agrégat destiné à un BD : un α_o par sortie choisie, masques mélangés
+#[derive(Clone, Debug)]
+pub struct SetOpsAgg {
+    pub ops:    SetOps,
+    pub alphas: Vec<BigUint>,
+    pub betas:  Vec<(BigUint, BigUint)>,
+}
+
+/// Enc(m) à partir de la Première Forme (a, Enc(b)) : Enc(a) · Enc(b)
+fn fst_to_paillier(ct: &CfFst, pk: &PublicKey) -> Result<BigUint, CryptoError> {
+    Ok((p_encrypt(&ct.0, pk)? * &ct.1) % &pk.n_squared)
+}
+
+// ---------------------------------------------------------
+// Phase 3 — Serveur : α_o des sorties choisies, sous une clé
+// ---------------------------------------------------------
+
+/// triplets : CF.Mul des positions communes ; xs / ys : Ft de BD1 / BD2
+/// (bundles bourrés à pad_to) sous la même clé que triplets
+pub fn phase3_server_setops(
+    ops:      SetOps,
+    triplets: &[CfSnd],
+    xs:       &FtBundle,
+    ys:       &FtBundle,
+    pad_to:   usize,
+    pk:       &PublicKey,
+) -> Result<SetOpsAgg, CryptoError> {
+    let n2 = &pk.n_squared;
+    let CfSndSum { alpha, betas } = phase3_server_aggregate(triplets, pad_to, pk)?;
+
+    let column = |b: &FtBundle| -> Result<BigUint, CryptoError> {
+        let cts: Vec<CfFst> = b.ft_by_pos.values().cloned().collect();
+        fst_to_paillier(&cf_sum(&cts, pk)?, pk)
+    };
+    let (sum_x, sum_y) = (column(xs)?, column(ys)?);
+    let alpha_inv = mod_inverse(&alpha, n2)?;
+
+    let mut alphas = Vec::with_capacity(ops.len());
+    for op in ops.iter() {
+        let linear = match op {
+            SetOp::Inter   => None,
+            SetOp::AMinusB => Some(sum_x.clone()),
+            SetOp::BMinusA => Some(sum_y.clone()),
+            SetOp::Union   => Some((&sum_x * &sum_y) % n2),
+        };
+        alphas.push(match linear {
+            None    => alpha.clone(),
+            Some(l) => (l * &alpha_inv * p_encrypt(&BigUint::zero(), pk)?) % n2,
+        });
+    }
+    println!(
+        "  [Phase 3] opérations ensemblistes {} : {} triplets agrégés, {} résultat(s) chiffré(s).",
+        ops, triplets.len(), alphas.len()
+    );
+    Ok(SetOpsAgg { ops, alphas, betas })
+}
+
+// ---------------------------------------------------------
+// Phase 4 — BD : M = Σ b·b', puis chaque sortie
+// ---------------------------------------------------------
+
+pub fn phase4_setops_decrypt(
+    label:  &str,
+    ops:    SetOps,
+    alphas: &[BigUint],
+    betas:  &[(BigUint, BigUint)],
+    bound:  usize,
+    kp:     &KeyPair,
+) -> Result<Vec<(SetOp, u64)>, CryptoError> {
+    if alphas.len() != ops.len() {
+        return Err(CryptoError::InvalidInput(format!(
+            "Phase 4 : {} résultat(s) reçu(s) pour {} sortie(s) ({})", alphas.len(), ops.len(), ops
+        )));
+    }
+    println!("  [Phase 4] {} : dechiffrement de {} paires de masques...", label, betas.len());
+    let (pk, sk) = (&kp.public_key, &kp.secret_key);
+    let flat: Vec<BigUint> = betas.iter().flat_map(|(c1, c2)| [c1.clone(), c2.clone()]).collect();
+    let ms = p_decrypt_batch(&flat, pk, sk, default_pool())?;
+    let m = ms.chunks(2).fold(BigUint::zero(), |acc, b| (acc + &b[0] * &b[1]) % &pk.n);
+
+    let mut out = Vec::with_capacity(ops.len());
+    for (op, alpha) in ops.iter().zip(alphas) {
+        let d = p_decrypt(alpha, pk, sk)?;
+        let v = match op {
+            SetOp::Inter => (d + &m) % &pk.n,
+            _            => (d + &pk.n - &m) % &pk.n,
+        };
+        // Un serveur honnête reste dans [0, 2P]
+        let count = v.to_u64().filter(|&c| c <= bound as u64).ok_or_else(|| CryptoError::InvalidInput(format!(
+            "Phase 4 : {} = {} hors de [0, {}] (le serveur a dévié)", op.label(), v, bound
+        )))?;
+        out.push((op, count));
+    }
+    Ok(out)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_bigint::RandBigInt;
+    use rand_core::OsRng;
+    use crate::fiore_catalano::cf_batch::cf_batch::{cf_encrypt_batch, cf_mul_batch};
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+    use crate::exactmatch::exactmatch::{
+        phase1_build_table, phase2_prepare_dual_ft_padded, phase3_server_compute, SparseTable,
+    };
+    use crate::exactmatch::position_hash::PositionHasher;
+    use crate::records::{generate, SynthConfig, SynthRow};
+
+    fn bundle(bits: &[u32], pk: &PublicKey) -> FtBundle {
+        let ms: Vec<BigUint> = bits.iter().map(|&b| BigUint::from(b)).collect();
+        let masks: Vec<BigUint> = ms.iter().map(|_| OsRng.gen_biguint_below(&pk.n)).collect();
+        let fts = cf_encrypt_batch(&ms, &masks, pk, default_pool()).unwrap();
+        FtBundle { ft_by_pos: fts.into_iter().enumerate().collect() }
+    }
+
+    #[test]
+    fn test_setops_over_padded_domain() {
+        let kp = p_keygen(256).unwrap();
+        let pk = &kp.public_key;
+
+        // A = {0, 1, 3, 6}, B = {0, 2, 3} sur 8 positions
+        let xs = bundle(&[1, 1, 0, 1, 0, 0, 1, 0], pk);
+        let ys = bundle(&[1, 0, 1, 1, 0, 0, 0, 0], pk);
+        let pairs: Vec<(&CfFst, &CfFst)> = (0..8).map(|p| (&xs.ft_by_pos[&p], &ys.ft_by_pos[&p])).collect();
+        let triplets = cf_mul_batch(&pairs, pk, default_pool()).unwrap();
+
+        let all = SetOps::parse("union,inter,b-a,a-b").unwrap();
+        let agg = phase3_server_setops(all, &triplets, &xs, &ys, 8, pk).unwrap();
+        let res = phase4_setops_decrypt("T", all, &agg.alphas, &agg.betas, 16, &kp).unwrap();
+        assert_eq!(res, vec![(SetOp::Inter, 2), (SetOp::AMinusB, 2), (SetOp::BMinusA, 1), (SetOp::Union, 5)]);
+
+        // Masques re-randomisés : aucun masque de Phase 2 n'est reconnaissable
+        let own: Vec<BigUint> = xs.ft_by_pos.values().chain(ys.ft_by_pos.values())
+            .map(|ft| p_decrypt(&ft.1, pk, &kp.secret_key).unwrap())
+            .collect();
+        for (c1, c2) in &agg.betas {
+            for c in [c1, c2] {
+                assert!(!own.contains(&p_decrypt(c, pk, &kp.secret_key).unwrap()));
+            }
+        }
+
+        // une seule sortie : un seul α transmis
+        let only = SetOps::parse("b-a").unwrap();
+        let agg = phase3_server_setops(only, &triplets, &xs, &ys, 8, pk).unwrap();
+        assert_eq!(agg.alphas.len(), 1);
+        assert_eq!(phase4_setops_decrypt("T", only, &agg.alphas, &agg.betas, 16, &kp).unwrap(), vec![(SetOp::BMinusA, 1)]);
+        assert!(phase4_setops_decrypt("T", all, &agg.alphas, &agg.betas, 16, &kp).is_err());
+
+        assert_eq!(only.to_string(), "b-a");
+        assert!(SetOps::parse("a-b,xor").is_err());
+        assert!(SetOps::from_bits(0).is_err() && SetOps::from_bits(0x10).is_err());
+    }
+
+    #[test]
+    fn test_setops_end_to_end_realistic_sizes() {
+        let kp = p_keygen(256).unwrap();
+        let pk = &kp.public_key;
+        let hasher = PositionHasher::new([9u8; 32], 30).unwrap();
+
+        // 3 000 / 2 000 NSS distincts, 1 200 communs, doublons ; P = 2^12.
+        // Le plein domaine à 20 bits y aurait ~6 collisions croisées.
+        let cfg = SynthConfig { size_a: 3_000, size_b: 2_000, overlap: 1_200, dup_rate: 0.05, typo_rate: 0.0, seed: 11, identity: false };
+        let data = generate(&cfg).unwrap();
+        let keys = |rows: &[SynthRow]| -> Vec<String> { rows.iter().map(|r| r.nss.clone()).collect() };
+        let t1 = phase1_build_table("A", &keys(&data.a), &hasher);
+        let t2 = phase1_build_table("B", &keys(&data.b), &hasher);
+        let p = 1 << 12;
+        let b1 = phase2_prepare_dual_ft_padded("A", &t1, &hasher, p, pk, pk).unwrap();
+        let b2 = phase2_prepare_dual_ft_padded("B", &t2, &hasher, p, pk, pk).unwrap();
+
+        // Serveur : ne voit que P positions par BD
+        let s1 = SparseTable { active: b1.under_pk1.ft_by_pos.keys().copied().collect() };
+        let s2 = SparseTable { active: b2.under_pk1.ft_by_pos.keys().copied().collect() };
+        let (triplets, _) = phase3_server_compute(&s1, &s2, &b1, &b2, &kp, &kp);
+        let all = SetOps::parse("inter,a-b,b-a,union").unwrap();
+        let agg = phase3_server_setops(all, &triplets, &b1.under_pk1, &b2.under_pk1, p, pk).unwrap();
+        assert_eq!(agg.betas.len(), p);
+
+        // 2^30 positions : aucune collision, les cardinaux sont exacts
+        let (a, b, inter) = (3_000, 2_000, data.exact_intersection() as u64);
+        assert_eq!((t1.len() as u64, t2.len() as u64, inter), (a, b, 1_200));
+        let res = phase4_setops_decrypt("A", all, &agg.alphas, &agg.betas, 2 * p, &kp).unwrap();
+        assert_eq!(res, vec![
+            (SetOp::Inter, inter), (SetOp::AMinusB, a - inter), (SetOp::BMinusA, b - inter), (SetOp::Union, a + b - inter),
+        ]);
+    }
+}
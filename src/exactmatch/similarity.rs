@@ -0,0 +1,210 @@
+use std::collections::HashSet;
+use std::time::Instant;
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+use crate::exactmatch::exactmatch::{CfFst, DualFtBundle, compare_slots, phase4_count_zeros};
+use crate::exactmatch::position_hash::PositionHasher;
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_keygen::PublicKey;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// Métriques de recouvrement entre deux bases (--similarity, --minhash)
+//
+// À partir de |A|, |B| et |A ∩ B| :
+//   |A ∪ B|       = |A| + |B| - |A ∩ B|
+//   Jaccard       = |A ∩ B| / |A ∪ B|
+//   inclusion A⊂B = |A ∩ B| / |A|     (part de A retrouvée dans B)
+//
+// Phase 0e : chaque BD annonce sa taille (positions actives de sa table,
+// l'unité du cardinal) via le serveur. --hide-sizes : la taille part
+// chiffrée sous la pk de l'autre BD, le serveur relaie sans la lire
+// (avec --size-hiding, il n'apprend alors aucune taille, mais voit
+// toujours |A ∩ B| par les positions communes). L'autre BD
+// apprend de toute façon |B| = |A ∪ B| - |A| + |A ∩ B|.
+//
+// Mode --minhash (grands ensembles) : chaque BD calcule une signature
+// MinHash de k composantes, h_i(A) = min_{x ∈ A} PRF_K(i, x). Pour une
+// PRF idéale, P[h_i(A) = h_i(B)] = J(A, B). Le serveur compare les
+// signatures composante par composante, r·(Ft1_i - Ft2_i) comme le mode
+// cuckoo, et le BD compte les égalités m : Ĵ = m / k, écart-type
+// √(J(1-J)/k). |A ∩ B| et |A ∪ B| s'en déduisent avec |A| et |B|.
+// Coût : k comparaisons au lieu de 2^table_bits positions.
+// ============================================================================
+
+/// Nombre de composantes MinHash par défaut (erreur-type ≤ 0,045)
+pub const DEFAULT_MINHASH_K: usize = 128;
+
+/// Borne haute de k (taille des bundles)
+pub const MAX_MINHASH_K: usize = 1 << 16;
+
+/// Tailles et intersection (exacte, ou estimée par MinHash)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SetOverlap {
+    pub own:       usize,
+    pub other:     usize,
+    pub inter:     f64,
+    pub estimated: bool,
+}
+
+impl SetOverlap {
+    pub fn exact(own: usize, other: usize, inter: usize) -> Self {
+        SetOverlap { own, other, inter: inter as f64, estimated: false }
+    }
+
+    /// J = I / (|A| + |B| - I)  ⇔  I = J (|A| + |B|) / (1 + J)
+    pub fn from_jaccard(own: usize, other: usize, jaccard: f64) -> Self {
+        let j = jaccard.clamp(0.0, 1.0);
+        let inter = (j * (own + other) as f64 / (1.0 + j)).min(own.min(other) as f64);
+        SetOverlap { own, other, inter, estimated: true }
+    }
+
+    pub fn union(&self) -> f64 {
+        (self.own + self.other) as f64 - self.inter
+    }
+
+    pub fn jaccard(&self) -> f64 {
+        let u = self.union();
+        if u > 0.0 { self.inter / u } else { 0.0 }
+    }
+
+    /// |A ∩ B| / |A| (A = base locale)
+    pub fn containment_own(&self) -> f64 {
+        if self.own > 0 { self.inter / self.own as f64 } else { 0.0 }
+    }
+
+    /// |A ∩ B| / |B|
+    pub fn containment_other(&self) -> f64 {
+        if self.other > 0 { self.inter / self.other as f64 } else { 0.0 }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Phase 0e — taille annoncée, en clair ou sous pk_other
+// ---------------------------------------------------------------------------
+
+pub fn phase0_size_share(size: usize, pk_other: &PublicKey, hidden: bool) -> Result<BigUint, CryptoError> {
+    let m = BigUint::from(size);
+    if hidden { p_encrypt(&m, pk_other) } else { Ok(m) }
+}
+
+pub fn phase0_open_size(share: &BigUint, hidden: bool, kp: &KeyPair) -> Result<usize, CryptoError> {
+    let m = if hidden { p_decrypt(share, &kp.public_key, &kp.secret_key)? } else { share.clone() };
+    m.to_usize()
+        .ok_or_else(|| CryptoError::InvalidInput("taille annoncée hors bornes".into()))
+}
+
+// ---------------------------------------------------------------------------
+// MinHash — signature et comparaisons chiffrées
+// ---------------------------------------------------------------------------
+
+pub fn check_minhash_k(k: usize) -> Result<usize, CryptoError> {
+    if (1..=MAX_MINHASH_K).contains(&k) {
+        Ok(k)
+    } else {
+        Err(CryptoError::InvalidInput(format!("minhash : k = {} hors de [1, {}]", k, MAX_MINHASH_K)))
+    }
+}
+
+/// h_i = min des 64 premiers bits de PRF_K("minhash", i ‖ x) sur les clés
+/// distinctes ; la clé de hachage K est celle des positions (Phase 0d)
+pub fn minhash_signature(keys: &[String], hasher: &PositionHasher, k: usize) -> Result<Vec<BigUint>, CryptoError> {
+    check_minhash_k(k)?;
+    let distinct: HashSet<&str> = keys.iter().map(String::as_str).collect();
+    if distinct.is_empty() {
+        return Err(CryptoError::InvalidInput("minhash : ensemble vide".into()));
+    }
+    Ok((0..k)
+        .map(|i| {
+            let min = distinct.iter()
+                .map(|x| {
+                    let tag = hasher.prf(b"minhash\0", &format!("{}\0{}", i, x));
+                    u64::from_be_bytes(tag[..8].try_into().expect("8 octets"))
+                })
+                .min()
+                .expect("ensemble non vide");
+            BigUint::from(min)
+        })
+        .collect())
+}
+
+/// Phase 3 (minhash) — Serveur : r_i·(Ft1_i - Ft2_i), mélangés
+pub fn phase3_server_compute_minhash(
+    k:   usize,
+    bd1: &DualFtBundle,
+    bd2: &DualFtBundle,
+    pk1: &PublicKey,
+    pk2: &PublicKey,
+) -> Result<(Vec<CfFst>, Vec<CfFst>), CryptoError> {
+    check_minhash_k(k)?;
+    for (label, b) in [("BD1", bd1), ("BD2", bd2)] {
+        if b.under_pk1.ft_by_pos.len() != k || b.under_pk2.ft_by_pos.len() != k {
+            return Err(CryptoError::InvalidInput(format!(
+                "{} : {} / {} composantes recues, {} attendues",
+                label, b.under_pk1.ft_by_pos.len(), b.under_pk2.ft_by_pos.len(), k
+            )));
+        }
+    }
+    println!("  [Phase 3] Serveur : {} comparaisons MinHash...", k);
+    let t_start = Instant::now();
+
+    let pairs: Vec<(usize, usize)> = (0..k).map(|i| (i, i)).collect();
+    let out_pk1 = compare_slots(&pairs, &bd1.under_pk1, &bd2.under_pk1, pk1)?;
+    let out_pk2 = compare_slots(&pairs, &bd1.under_pk2, &bd2.under_pk2, pk2)?;
+
+    println!("  [Phase 3] termine en {:.3?} ({} comparaisons x 2 cles, melangees).", t_start.elapsed(), k);
+    Ok((out_pk1, out_pk2))
+}
+
+/// Phase 4 (minhash) — BD : Ĵ = composantes égales / k
+pub fn phase4_estimate_jaccard(label: &str, cts: &[CfFst], k: usize, kp: &KeyPair) -> Result<f64, CryptoError> {
+    if cts.len() != k {
+        return Err(CryptoError::InvalidInput(format!(
+            "minhash : {} comparaisons recues, {} attendues", cts.len(), k
+        )));
+    }
+    let equal = phase4_count_zeros(label, cts, kp)?;
+    Ok(equal as f64 / k as f64)
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exactmatch::exactmatch::phase2_prepare_slot_ft;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    #[test]
+    fn test_overlap_metrics_and_minhash_estimate() {
+        let o = SetOverlap::exact(60, 40, 20);
+        assert_eq!(o.union(), 80.0);
+        assert_eq!(o.jaccard(), 0.25);
+        assert_eq!(o.containment_own(), 20.0 / 60.0);
+        assert_eq!(o.containment_other(), 0.5);
+        assert!((SetOverlap::from_jaccard(60, 40, 0.25).inter - 20.0).abs() < 1e-9);
+
+        // J(A, B) = 300 / 900 ; k = 24 sous chiffrement (clés 256 bits)
+        let hasher = PositionHasher::new([3u8; 32], 20).unwrap();
+        let a: Vec<String> = (0..600).map(|i| format!("x{}", i)).collect();
+        let b: Vec<String> = (300..900).map(|i| format!("x{}", i)).collect();
+        let k = 24;
+        let (sa, sb) = (minhash_signature(&a, &hasher, k).unwrap(), minhash_signature(&b, &hasher, k).unwrap());
+        let plain = sa.iter().zip(&sb).filter(|(x, y)| x == y).count();
+
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+        let b1 = phase2_prepare_slot_ft("A", &sa, pk1, pk2);
+        let b2 = phase2_prepare_slot_ft("B", &sb, pk1, pk2);
+        let (c1, c2) = phase3_server_compute_minhash(k, &b1, &b2, pk1, pk2).unwrap();
+        let j1 = phase4_estimate_jaccard("A", &c1, k, &kp1).unwrap();
+        assert_eq!(j1, plain as f64 / k as f64);
+        assert_eq!(phase4_estimate_jaccard("B", &c2, k, &kp2).unwrap(), j1);
+        // 4 écarts-types (√(J(1-J)/k) ≈ 0,096)
+        assert!((j1 - 1.0 / 3.0).abs() < 0.39, "Jaccard estime {}", j1);
+    }
+}
@@ -0,0 +1,568 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use num_bigint::{BigUint, RandBigInt};
+use num_traits::{One, Zero, ToPrimitive};
+use rand_core::OsRng;
+use crate::exactmatch::exactmatch::{CfFst, make_ft_for_ones};
+use crate::fiore_catalano::cf_batch::cf_batch::cf_mul_batch;
+use crate::paillier::p_batch::p_batch::p_decrypt_batch;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_keygen::PublicKey;
+use crate::parallel::default_pool;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::KeyPair;
+
+// ============================================================================
+// ExactMatch en flux (--stream) — mémoire bornée par la taille de lot
+//
+//   Phase 1 : BD : CSV lu ligne à ligne (records::stream_keys), positions
+//             triées par tri externe : passes triées de `chunk` positions
+//             écrites sur disque, puis fusion k-voies dédoublonnée
+//   Phase 2 : BD -> Serveur : lots de `chunk` Ft (sous pk1 et pk2) dans
+//             l'ordre croissant des positions, lot vide = fin
+//   Phase 3 : Serveur : jointure par fusion des deux flux triés, CF.Mul
+//             par lots de positions communes ; pour chaque clé k :
+//               alpha_k = Π C0 = Enc_k(c - Σ b·b')   (un seul chiffré)
+//               (C1, C2) -> fichier d'attente sur disque
+//             Une fois les deux flux épuisés, les paires de masques
+//             partent par lots vers le BD k, puis alpha_k
+//   Phase 4 : BD : Σ Dec(C1)·Dec(C2) cumulée lot par lot,
+//             c = Dec(alpha) + Σ b·b' mod n
+//
+// Mémoire de pointe : O(chunk) Ft ou produits de chaque côté, plus au
+// plus MERGE_FAN_IN tampons de lecture (et fichiers ouverts) : au-delà,
+// les passes triées sont fusionnées par groupes en plusieurs tours,
+// log_F(n / chunk) relectures du disque. Les triplets ne sont jamais
+// réunis : le disque (fichiers temporaires) prend le relais.
+//
+// Les paires de masques ne partent qu'après la fin de la jointure : leur
+// rythme d'émission ne révèle pas dans quelles plages de positions se
+// trouvent les communs. Comme en mode standard, le serveur voit les
+// positions actives des deux BD.
+// ============================================================================
+
+/// Taille de lot par défaut (Ft par message, CF.Mul par lot)
+pub const DEFAULT_STREAM_CHUNK: usize = 4096;
+
+/// Borne haute de la taille de lot
+pub const MAX_STREAM_CHUNK: usize = 1 << 20;
+
+/// Ft d'une position reçue en flux : (position, Ft sous pk1, Ft sous pk2)
+pub type StreamEntry = (usize, CfFst, CfFst);
+
+/// Lot de Ft sous une clé, par position croissante (format de MsgFtBundle)
+pub type StreamLot = Vec<(usize, CfFst)>;
+
+pub fn check_stream_chunk(chunk: usize) -> Result<usize, CryptoError> {
+    if (1..=MAX_STREAM_CHUNK).contains(&chunk) {
+        Ok(chunk)
+    } else {
+        Err(CryptoError::InvalidInput(format!("stream : lot de {} hors de [1, {}]", chunk, MAX_STREAM_CHUNK)))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Fichiers temporaires (supprimés à la destruction)
+// ---------------------------------------------------------------------------
+
+static SPOOL_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+fn spool_err(e: io::Error) -> CryptoError {
+    CryptoError::InvalidInput(format!("fichier temporaire : {}", e))
+}
+
+struct SpoolFile {
+    path: PathBuf,
+}
+
+impl SpoolFile {
+    fn create(tag: &str) -> Result<(Self, File), CryptoError> {
+        let path = std::env::temp_dir().join(format!(
+            "psi-{}-{}-{}.tmp", tag, std::process::id(), SPOOL_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        let file = OpenOptions::new().read(true).write(true).create_new(true).open(&path).map_err(spool_err)?;
+        Ok((SpoolFile { path }, file))
+    }
+
+    fn open(&self) -> Result<BufReader<File>, CryptoError> {
+        File::open(&self.path).map(BufReader::new).map_err(spool_err)
+    }
+}
+
+impl Drop for SpoolFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Lit exactement buf.len() octets ; Ok(false) en fin de fichier propre
+fn read_or_eof<R: Read>(r: &mut R, buf: &mut [u8]) -> Result<bool, CryptoError> {
+    match r.read_exact(buf) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(spool_err(e)),
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Phase 1 — tri externe des positions
+// ---------------------------------------------------------------------------
+
+/// Passes triées fusionnées à la fois (fichiers ouverts simultanément)
+const MERGE_FAN_IN: usize = 64;
+
+pub struct PositionSorter {
+    chunk:  usize,
+    buf:    Vec<usize>,
+    runs:   Vec<SpoolFile>,
+    pushed: usize,
+}
+
+impl PositionSorter {
+    pub fn new(chunk: usize) -> Result<Self, CryptoError> {
+        let chunk = check_stream_chunk(chunk)?;
+        Ok(PositionSorter { chunk, buf: Vec::with_capacity(chunk), runs: Vec::new(), pushed: 0 })
+    }
+
+    pub fn push(&mut self, pos: usize) -> Result<(), CryptoError> {
+        self.buf.push(pos);
+        self.pushed += 1;
+        if self.buf.len() == self.chunk {
+            self.flush_run()?;
+        }
+        Ok(())
+    }
+
+    /// Positions reçues (doublons compris)
+    pub fn pushed(&self) -> usize {
+        self.pushed
+    }
+
+    fn flush_run(&mut self) -> Result<(), CryptoError> {
+        self.buf.sort_unstable();
+        self.buf.dedup();
+        let (spool, file) = SpoolFile::create("run")?;
+        let mut w = BufWriter::new(file);
+        for &pos in &self.buf {
+            w.write_all(&(pos as u64).to_be_bytes()).map_err(spool_err)?;
+        }
+        w.flush().map_err(spool_err)?;
+        self.runs.push(spool);
+        self.buf.clear();
+        Ok(())
+    }
+
+    /// Fusion des passes : positions distinctes, croissantes
+    pub fn finish(mut self) -> Result<SortedPositions, CryptoError> {
+        if !self.buf.is_empty() {
+            self.flush_run()?;
+        }
+        // Tours intermédiaires : MERGE_FAN_IN passes -> une passe
+        let mut runs = std::mem::take(&mut self.runs);
+        while runs.len() > MERGE_FAN_IN {
+            let mut merged = Vec::with_capacity(runs.len().div_ceil(MERGE_FAN_IN));
+            let mut rest = runs.into_iter();
+            loop {
+                let group: Vec<SpoolFile> = rest.by_ref().take(MERGE_FAN_IN).collect();
+                if group.is_empty() {
+                    break;
+                }
+                let (spool, file) = SpoolFile::create("run")?;
+                let mut w = BufWriter::new(file);
+                for pos in SortedPositions::open(group)? {
+                    w.write_all(&(pos? as u64).to_be_bytes()).map_err(spool_err)?;
+                }
+                w.flush().map_err(spool_err)?;
+                merged.push(spool);
+            }
+            runs = merged;
+        }
+        SortedPositions::open(runs)
+    }
+}
+
+pub struct SortedPositions {
+    readers: Vec<BufReader<File>>,
+    heap:    BinaryHeap<Reverse<(u64, usize)>>,
+    last:    Option<u64>,
+    _runs:   Vec<SpoolFile>,
+}
+
+impl SortedPositions {
+    /// Fusion k-voies de `runs` (au plus MERGE_FAN_IN), dédoublonnée
+    fn open(runs: Vec<SpoolFile>) -> Result<Self, CryptoError> {
+        let mut readers = Vec::with_capacity(runs.len());
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+        for (i, run) in runs.iter().enumerate() {
+            let mut r = run.open()?;
+            let mut b = [0u8; 8];
+            if read_or_eof(&mut r, &mut b)? {
+                heap.push(Reverse((u64::from_be_bytes(b), i)));
+            }
+            readers.push(r);
+        }
+        Ok(SortedPositions { readers, heap, last: None, _runs: runs })
+    }
+
+    fn pop(&mut self) -> Result<Option<u64>, CryptoError> {
+        let Some(Reverse((pos, i))) = self.heap.pop() else { return Ok(None) };
+        let mut b = [0u8; 8];
+        if read_or_eof(&mut self.readers[i], &mut b)? {
+            self.heap.push(Reverse((u64::from_be_bytes(b), i)));
+        }
+        Ok(Some(pos))
+    }
+}
+
+impl Iterator for SortedPositions {
+    type Item = Result<usize, CryptoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.pop() {
+                Ok(Some(pos)) if Some(pos) == self.last => continue,
+                Ok(Some(pos)) => {
+                    self.last = Some(pos);
+                    return Some(Ok(pos as usize));
+                }
+                Ok(None) => return None,
+                Err(e)   => return Some(Err(e)),
+            }
+        }
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Phase 2 — BD : Ft d'un lot de positions triées
+// ---------------------------------------------------------------------------
+
+pub fn phase2_stream_chunk(
+    positions: &[usize],
+    pk1:       &PublicKey,
+    pk2:       &PublicKey,
+) -> (StreamLot, StreamLot) {
+    let mut rng = OsRng;
+    let b1s: Vec<BigUint> = positions.iter().map(|_| rng.gen_biguint_below(&pk1.n)).collect();
+    let b2s: Vec<BigUint> = positions.iter().map(|_| rng.gen_biguint_below(&pk2.n)).collect();
+    (
+        positions.iter().copied().zip(make_ft_for_ones(&b1s, pk1)).collect(),
+        positions.iter().copied().zip(make_ft_for_ones(&b2s, pk2)).collect(),
+    )
+}
+
+// ---------------------------------------------------------------------------
+// Phase 3 — Serveur : jointure par fusion
+// ---------------------------------------------------------------------------
+
+/// Paires de masques (C1, C2) en attente, sur disque : [u32 BE long.][octets BE]
+pub struct MaskSpool {
+    spool:  SpoolFile,
+    writer: BufWriter<File>,
+    len:    usize,
+}
+
+impl MaskSpool {
+    pub fn new() -> Result<Self, CryptoError> {
+        let (spool, file) = SpoolFile::create("masks")?;
+        Ok(MaskSpool { spool, writer: BufWriter::new(file), len: 0 })
+    }
+
+    pub fn push(&mut self, c1: &BigUint, c2: &BigUint) -> Result<(), CryptoError> {
+        for c in [c1, c2] {
+            let bytes = c.to_bytes_be();
+            self.writer.write_all(&(bytes.len() as u32).to_be_bytes()).map_err(spool_err)?;
+            self.writer.write_all(&bytes).map_err(spool_err)?;
+        }
+        self.len += 1;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Relecture par lots de `chunk` paires, dans l'ordre d'écriture
+    pub fn drain(mut self, chunk: usize) -> Result<MaskChunks, CryptoError> {
+        self.writer.flush().map_err(spool_err)?;
+        let reader = self.spool.open()?;
+        Ok(MaskChunks { reader, chunk: check_stream_chunk(chunk)?, left: self.len, _spool: self.spool })
+    }
+}
+
+pub struct MaskChunks {
+    reader: BufReader<File>,
+    chunk:  usize,
+    left:   usize,
+    _spool: SpoolFile,
+}
+
+impl MaskChunks {
+    fn read_ct(&mut self) -> Result<BigUint, CryptoError> {
+        let truncated = || CryptoError::InvalidInput("fichier temporaire : paires de masques tronquées".into());
+        let mut len = [0u8; 4];
+        if !read_or_eof(&mut self.reader, &mut len)? {
+            return Err(truncated());
+        }
+        let mut bytes = vec![0u8; u32::from_be_bytes(len) as usize];
+        if !read_or_eof(&mut self.reader, &mut bytes)? {
+            return Err(truncated());
+        }
+        Ok(BigUint::from_bytes_be(&bytes))
+    }
+}
+
+impl Iterator for MaskChunks {
+    type Item = Result<Vec<(BigUint, BigUint)>, CryptoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.left == 0 {
+            return None;
+        }
+        let n = self.chunk.min(self.left);
+        self.left -= n;
+        Some((0..n).map(|_| Ok((self.read_ct()?, self.read_ct()?))).collect())
+    }
+}
+
+/// Résultat de la jointure : pour chaque clé, alpha et les masques en attente
+pub struct StreamJoin {
+    pub common: usize,
+    pub active: [usize; 2],
+    pub alpha:  [BigUint; 2],
+    pub masks:  [MaskSpool; 2],
+}
+
+/// Tête de lecture sur le flux d'un BD
+struct Cursor<F> {
+    label: &'static str,
+    next:  F,
+    lot:   std::vec::IntoIter<StreamEntry>,
+    head:  Option<StreamEntry>,
+    last:  Option<usize>,
+    done:  bool,
+    count: usize,
+}
+
+impl<F> Cursor<F>
+where
+    F: FnMut() -> Result<Option<Vec<StreamEntry>>, CryptoError>,
+{
+    fn new(label: &'static str, next: F) -> Self {
+        Cursor { label, next, lot: Vec::new().into_iter(), head: None, last: None, done: false, count: 0 }
+    }
+
+    /// Position en tête (lot suivant demandé au besoin), croissance vérifiée
+    fn peek(&mut self) -> Result<Option<usize>, CryptoError> {
+        while self.head.is_none() && !self.done {
+            match self.lot.next() {
+                Some(e) => {
+                    if self.last.is_some_and(|l| e.0 <= l) {
+                        return Err(CryptoError::InvalidInput(format!(
+                            "{} : positions non strictement croissantes ({} après {})",
+                            self.label, e.0, self.last.unwrap_or(0)
+                        )));
+                    }
+                    self.last = Some(e.0);
+                    self.count += 1;
+                    self.head = Some(e);
+                }
+                None => match (self.next)()? {
+                    Some(lot) => self.lot = lot.into_iter(),
+                    None      => self.done = true,
+                },
+            }
+        }
+        Ok(self.head.as_ref().map(|e| e.0))
+    }
+
+    fn take(&mut self) -> Option<StreamEntry> {
+        self.head.take()
+    }
+}
+
+/// CF.Mul d'un lot de paires communes ; alpha et masques mis à jour
+fn absorb_batch(
+    batch: &mut Vec<(StreamEntry, StreamEntry)>,
+    pks:   [&PublicKey; 2],
+    alpha: &mut [BigUint; 2],
+    masks: &mut [MaskSpool; 2],
+) -> Result<(), CryptoError> {
+    for k in 0..2 {
+        let pairs: Vec<(&CfFst, &CfFst)> = batch.iter()
+            .map(|(e1, e2)| if k == 0 { (&e1.1, &e2.1) } else { (&e1.2, &e2.2) })
+            .collect();
+        for (c0, c1, c2) in cf_mul_batch(&pairs, pks[k], default_pool())? {
+            alpha[k] = (&alpha[k] * c0) % &pks[k].n_squared;
+            masks[k].push(&c1, &c2)?;
+        }
+    }
+    batch.clear();
+    Ok(())
+}
+
+/// Phase 3 (stream) — Serveur : jointure des flux triés de BD1 et BD2.
+/// Chaque flux est consommé jusqu'au bout, même si l'autre est épuisé.
+pub fn phase3_stream_join<F1, F2>(
+    next1: F1,
+    next2: F2,
+    pk1:   &PublicKey,
+    pk2:   &PublicKey,
+    chunk: usize,
+) -> Result<StreamJoin, CryptoError>
+where
+    F1: FnMut() -> Result<Option<Vec<StreamEntry>>, CryptoError>,
+    F2: FnMut() -> Result<Option<Vec<StreamEntry>>, CryptoError>,
+{
+    let chunk = check_stream_chunk(chunk)?;
+    println!("  [Phase 3] Serveur : jointure des flux tries (lots de {})...", chunk);
+    let t_start = Instant::now();
+
+    let (mut c1, mut c2) = (Cursor::new("BD1", next1), Cursor::new("BD2", next2));
+    let mut alpha = [BigUint::one(), BigUint::one()];
+    let mut masks = [MaskSpool::new()?, MaskSpool::new()?];
+    let mut batch = Vec::with_capacity(chunk);
+    let mut common = 0;
+    loop {
+        match (c1.peek()?, c2.peek()?) {
+            (Some(a), Some(b)) if a < b => { c1.take(); }
+            (Some(a), Some(b)) if a > b => { c2.take(); }
+            (Some(_), Some(_)) => {
+                let pair = (c1.take().expect("tete BD1"), c2.take().expect("tete BD2"));
+                batch.push(pair);
+                common += 1;
+                if batch.len() == chunk {
+                    absorb_batch(&mut batch, [pk1, pk2], &mut alpha, &mut masks)?;
+                }
+            }
+            (Some(_), None) => { c1.take(); }
+            (None, Some(_)) => { c2.take(); }
+            (None, None) => break,
+        }
+    }
+    absorb_batch(&mut batch, [pk1, pk2], &mut alpha, &mut masks)?;
+
+    println!(
+        "  [Phase 3] termine en {:.3?} ({} / {} positions, {} CF.Mul x 2 cles).",
+        t_start.elapsed(), c1.count, c2.count, common
+    );
+    Ok(StreamJoin { common, active: [c1.count, c2.count], alpha, masks })
+}
+
+// ---------------------------------------------------------------------------
+// Phase 4 — BD : Σ b·b' cumulée, puis c = Dec(alpha) + Σ b·b'
+// ---------------------------------------------------------------------------
+
+#[derive(Debug, Clone, Default)]
+pub struct StreamTally {
+    mask_sum:  BigUint,
+    pub pairs: usize,
+}
+
+impl StreamTally {
+    pub fn new() -> Self {
+        StreamTally { mask_sum: BigUint::zero(), pairs: 0 }
+    }
+
+    pub fn absorb(&mut self, pairs: &[(BigUint, BigUint)], kp: &KeyPair) -> Result<(), CryptoError> {
+        let pk = &kp.public_key;
+        let flat: Vec<BigUint> = pairs.iter().flat_map(|(c1, c2)| [c1.clone(), c2.clone()]).collect();
+        let ms = p_decrypt_batch(&flat, pk, &kp.secret_key, default_pool())?;
+        self.mask_sum = ms.chunks(2).fold(std::mem::take(&mut self.mask_sum), |acc, m| (acc + &m[0] * &m[1]) % &pk.n);
+        self.pairs += pairs.len();
+        Ok(())
+    }
+
+    pub fn finish(&self, label: &str, alpha: &BigUint, kp: &KeyPair) -> Result<usize, CryptoError> {
+        let n = &kp.public_key.n;
+        let c = (p_decrypt(alpha, &kp.public_key, &kp.secret_key)? + &self.mask_sum) % n;
+        let count = c.to_usize()
+            .filter(|&c| c <= self.pairs)
+            .ok_or_else(|| CryptoError::InvalidInput(format!(
+                "stream : cardinal dechiffre incoherent avec {} produits recus", self.pairs
+            )))?;
+        println!("  [Phase 4] {} : {} paires de masques -> cardinal {}", label, self.pairs, count);
+        Ok(count)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exactmatch::position_hash::PositionHasher;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+    use crate::records::{KeyConfig, load_records, stream_keys};
+
+    #[test]
+    fn test_stream_join_matches_in_memory_cardinal() {
+        // Clés lues en flux = clés du chargement en mémoire
+        let path = std::env::temp_dir().join(format!("psi-test-stream-{}.csv", std::process::id()));
+        fs::write(&path, "NSS,nom\n1,a\n\"2\",b\n3\n4,d\n2,e\n").unwrap();
+        let path_s = path.to_str().unwrap();
+        let loaded = load_records(path_s, &KeyConfig::nss(), &[]).unwrap();
+        let mut keys = stream_keys(path_s, &KeyConfig::nss()).unwrap();
+        let streamed: Vec<String> = keys.by_ref().collect::<Result<_, _>>().unwrap();
+        assert_eq!(streamed, loaded.keys);
+        assert_eq!(keys.rejected, loaded.rejected);
+        fs::remove_file(&path).unwrap();
+
+        // Tri externe : passes de 3, doublons entre passes
+        let hasher = PositionHasher::new([5u8; 32], 12).unwrap();
+        let a: Vec<String> = (0..40).chain(0..5).map(|i| format!("a{}", i)).collect();
+        let b: Vec<String> = (25..60).map(|i| format!("a{}", i)).collect();
+        let sorted = |set: &[String]| -> Vec<usize> {
+            let mut s = PositionSorter::new(3).unwrap();
+            for x in set {
+                s.push(hasher.position(x)).unwrap();
+            }
+            s.finish().unwrap().collect::<Result<_, _>>().unwrap()
+        };
+        let (pa, pb) = (sorted(&a), sorted(&b));
+        assert!(pa.windows(2).all(|w| w[0] < w[1]));
+
+        // Passes de 1 : plus de MERGE_FAN_IN passes, fusion en plusieurs tours
+        let many: Vec<String> = (0..3 * MERGE_FAN_IN + 7).map(|i| format!("a{}", i % 150)).collect();
+        let mut expected_many: Vec<usize> = many.iter().map(|x| hasher.position(x)).collect();
+        expected_many.sort_unstable();
+        expected_many.dedup();
+        let mut s = PositionSorter::new(1).unwrap();
+        for x in &many {
+            s.push(hasher.position(x)).unwrap();
+        }
+        assert_eq!(s.finish().unwrap().collect::<Result<Vec<_>, _>>().unwrap(), expected_many);
+        let expected = pa.iter().filter(|p| pb.binary_search(p).is_ok()).count();
+
+        let kp1 = p_keygen(256).unwrap();
+        let kp2 = p_keygen(256).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+        let lots = |pos: &[usize]| -> Vec<Vec<StreamEntry>> {
+            pos.chunks(4).map(|c| {
+                let (f1, f2) = phase2_stream_chunk(c, pk1, pk2);
+                f1.into_iter().zip(f2).map(|((p, a), (_, b))| (p, a, b)).collect()
+            }).collect()
+        };
+        let (mut l1, mut l2) = (lots(&pa).into_iter(), lots(&pb).into_iter());
+        let join = phase3_stream_join(|| Ok(l1.next()), || Ok(l2.next()), pk1, pk2, 2).unwrap();
+        assert_eq!((join.common, join.active), (expected, [pa.len(), pb.len()]));
+
+        let StreamJoin { alpha, masks, .. } = join;
+        for ((spool, alpha), kp) in masks.into_iter().zip(alpha).zip([&kp1, &kp2]) {
+            let mut tally = StreamTally::new();
+            for lot in spool.drain(3).unwrap() {
+                tally.absorb(&lot.unwrap(), kp).unwrap();
+            }
+            assert_eq!(tally.finish("BD", &alpha, kp).unwrap(), expected);
+        }
+    }
+}
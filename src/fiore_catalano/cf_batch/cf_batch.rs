@@ -0,0 +1,95 @@
+use num_bigint::BigUint;
+use rayon::prelude::*;
+use crate::fiore_catalano::cf_encrypt::cf_encrypt::cf_encrypt;
+use crate::fiore_catalano::cf_mul::cf_mul::cf_mul;
+use crate::fiore_catalano::cf_mul_dec::cf_mul_dec::cf_mul_dec;
+use crate::paillier::p_keygen::p_keygen::SecretKey;
+use crate::paillier::p_keygen::PublicKey;
+use crate::parallel::BatchPool;
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ---------------------------------------------------------------------------
+// Opérations Catalano-Fiore par lots
+//
+// Même contrat que p_batch : sortie dans l'ordre des entrées, première
+// erreur propagée. Les masques sont fournis par l'appelant (tirés en série
+// avec son RNG) : seul le travail modulaire est réparti entre les threads.
+// ---------------------------------------------------------------------------
+
+/// CF Première Forme : (m - b mod n, Enc(b))
+type CfFst = (BigUint, BigUint);
+
+/// CF Seconde Forme : (C0, C1, C2)
+type CfSnd = (BigUint, BigUint, BigUint);
+
+pub fn cf_encrypt_batch(
+    messages: &[BigUint],
+    masques:  &[BigUint],
+    pk:       &PublicKey,
+    pool:     &BatchPool,
+) -> Result<Vec<CfFst>, CryptoError> {
+    if messages.len() != masques.len() {
+        return Err(CryptoError::InvalidInput(format!(
+            "cf_encrypt_batch : {} messages pour {} masques",
+            messages.len(), masques.len()
+        )));
+    }
+    pool.install(|| {
+        messages
+            .par_iter()
+            .zip(masques.par_iter())
+            .map(|(m, b)| cf_encrypt(m, b, pk))
+            .collect()
+    })
+}
+
+/// pairs[i] = (ct, ct') → cf_mul(ct, ct')
+pub fn cf_mul_batch(
+    pairs: &[(&CfFst, &CfFst)],
+    pk:    &PublicKey,
+    pool:  &BatchPool,
+) -> Result<Vec<CfSnd>, CryptoError> {
+    pool.install(|| pairs.par_iter().map(|(a, b)| cf_mul(a, b, pk)).collect())
+}
+
+pub fn cf_mul_dec_batch(
+    ciphers: &[CfSnd],
+    pk:      &PublicKey,
+    sk:      &SecretKey,
+    pool:    &BatchPool,
+) -> Result<Vec<BigUint>, CryptoError> {
+    pool.install(|| ciphers.par_iter().map(|c| cf_mul_dec(c, pk, sk)).collect())
+}
+
+// ============================================================================
+// Tests — équivalence avec les versions séquentielles
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    #[test]
+    fn test_batch_order_matches_serial() {
+        let kp   = p_keygen(128).unwrap();
+        let pk   = &kp.public_key;
+        let pool = BatchPool::new(4).unwrap();
+
+        let msgs: Vec<BigUint> = (0u32..24).map(BigUint::from).collect();
+        let bs:   Vec<BigUint> = (0u32..24).map(|i| BigUint::from(1_000 + 7 * i)).collect();
+        let cts = cf_encrypt_batch(&msgs, &bs, pk, &pool).unwrap();
+        for (i, ct) in cts.iter().enumerate() {
+            // c0 est déterministe : (m - b) mod n
+            assert_eq!(ct.0, cf_encrypt(&msgs[i], &bs[i], pk).unwrap().0);
+        }
+
+        let pairs: Vec<_> = cts.iter().zip(cts.iter().rev()).collect();
+        let prods = cf_mul_batch(&pairs, pk, &pool).unwrap();
+        let decs  = cf_mul_dec_batch(&prods, pk, &kp.secret_key, &pool).unwrap();
+        for (i, d) in decs.iter().enumerate() {
+            assert_eq!(d, &BigUint::from((i * (23 - i)) as u32));
+        }
+
+        assert!(cf_encrypt_batch(&msgs, &bs[1..], pk, &pool).is_err());
+    }
+}
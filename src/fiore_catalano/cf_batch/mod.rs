@@ -0,0 +1 @@
+pub mod cf_batch;
@@ -0,0 +1,427 @@
+use std::fs;
+use std::io;
+use num_bigint::BigUint;
+use num_traits::Zero;
+use serde::{Serialize, Deserialize, Serializer, Deserializer};
+use crate::paillier::p_keygen::PublicKey;
+use crate::key_management::key_fingerprint::{
+    KeyFingerprint, KEY_FINGERPRINT_LEN,
+    key_fingerprint, fingerprint_to_hex, hex_to_fingerprint,
+};
+use crate::key_management::key_storage::{biguint_to_hex, hex_to_biguint};
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ============================================================================
+// cf_codec — Encodage versionné et auto-descriptif des chiffrés CF
+//
+// Les tuples CfFst / CfSnd ne disent ni sous quelle clé ils ont été produits,
+// ni à quel niveau (Première / Seconde Forme) ils appartiennent. CfCiphertext
+// les enveloppe avec ces métadonnées, ce qui permet de les persister, de les
+// échanger entre processus et de les valider au chargement.
+//
+// Format binaire v1 (big-endian) :
+//
+//   octets  champ
+//   ------  -----------------------------------------------------------
+//   2       magic "CF"
+//   1       version (= CF_CODEC_VERSION)
+//   1       niveau  (1 = CfFst, 2 = CfSnd)
+//   32      empreinte SHA-256 de la clé publique (key_fingerprint)
+//   2       w_n   = ⌈|n|/8⌉
+//   2       w_n2  = ⌈|n²|/8⌉
+//   ...     composantes à largeur FIXE, complétées à gauche par des zéros :
+//             niveau 1 : c0 (w_n)  || c1 (w_n2)
+//             niveau 2 : C0 (w_n2) || C1 (w_n2) || C2 (w_n2)
+//
+// Pas de préfixe de longueur par composante : la largeur est déduite de
+// l'en-tête, ce qui rend l'encodage plus compact que encode_cffst/encode_cfsnd
+// et fait de la longueur totale un contrôle d'intégrité structurelle.
+//
+// Format JSON (serde, sérialiseurs « human readable ») :
+//   { "version": 1, "level": 2, "key_fingerprint": "<64 hex>",
+//     "n_bytes": 256, "n2_bytes": 512, "components": ["<hex>", ...] }
+//
+// Les sérialiseurs binaires (bincode) reçoivent l'encodage v1 ci-dessus.
+// ============================================================================
+
+/// Version courante de l'encodage
+pub const CF_CODEC_VERSION: u8 = 1;
+
+/// Octets magiques en tête de l'encodage binaire
+pub const CF_MAGIC: [u8; 2] = *b"CF";
+
+/// Taille de l'en-tête binaire
+pub const CF_HEADER_LEN: usize = 2 + 1 + 1 + KEY_FINGERPRINT_LEN + 2 + 2;
+
+/// Taille maximale d'un fichier de chiffré CF (protection DoS, cf. key_storage)
+const MAX_CF_FILE_BYTES: u64 = 65_536;
+
+// ============================================================================
+// Niveau CF
+// ============================================================================
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CfLevel {
+    /// Première Forme (c0, c1) — sortie de cf_encrypt / cf_add
+    Fst = 1,
+    /// Seconde Forme (C0, C1, C2) — sortie de cf_mul
+    Snd = 2,
+}
+
+impl CfLevel {
+    pub fn from_u8(v: u8) -> Result<Self, CryptoError> {
+        match v {
+            1 => Ok(CfLevel::Fst),
+            2 => Ok(CfLevel::Snd),
+            _ => Err(CryptoError::MalformedCiphertext(format!("niveau {v} inconnu"))),
+        }
+    }
+
+    /// Nombre de composantes attendu à ce niveau
+    pub fn arity(self) -> usize {
+        match self {
+            CfLevel::Fst => 2,
+            CfLevel::Snd => 3,
+        }
+    }
+}
+
+// ============================================================================
+// Chiffré CF auto-descriptif
+// ============================================================================
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct CfCiphertext {
+    pub level:      CfLevel,
+    pub key_fp:     KeyFingerprint,
+    pub n_bytes:    u16,
+    pub n2_bytes:   u16,
+    pub components: Vec<BigUint>,
+}
+
+fn byte_width(x: &BigUint) -> Result<u16, CryptoError> {
+    u16::try_from(x.bits().div_ceil(8))
+        .map_err(|_| CryptoError::MalformedCiphertext("module trop grand pour l'encodage v1".into()))
+}
+
+impl CfCiphertext {
+    fn new(level: CfLevel, components: Vec<BigUint>, pk: &PublicKey) -> Result<Self, CryptoError> {
+        let ct = CfCiphertext {
+            level,
+            key_fp:   key_fingerprint(pk),
+            n_bytes:  byte_width(&pk.n)?,
+            n2_bytes: byte_width(&pk.n_squared)?,
+            components,
+        };
+        ct.validate(pk)?;
+        Ok(ct)
+    }
+
+    /// Enveloppe un CfFst produit sous pk
+    pub fn from_fst(ct: &(BigUint, BigUint), pk: &PublicKey) -> Result<Self, CryptoError> {
+        Self::new(CfLevel::Fst, vec![ct.0.clone(), ct.1.clone()], pk)
+    }
+
+    /// Enveloppe un CfSnd produit sous pk
+    pub fn from_snd(ct: &(BigUint, BigUint, BigUint), pk: &PublicKey) -> Result<Self, CryptoError> {
+        Self::new(CfLevel::Snd, vec![ct.0.clone(), ct.1.clone(), ct.2.clone()], pk)
+    }
+
+    /// Largeur fixe (octets) de la composante i
+    fn width(&self, i: usize) -> usize {
+        match (self.level, i) {
+            (CfLevel::Fst, 0) => self.n_bytes as usize,
+            _                 => self.n2_bytes as usize,
+        }
+    }
+
+    // -----------------------------------------------------------------------
+    // Validation complète vis-à-vis d'une clé publique :
+    //   - empreinte de clé identique
+    //   - largeurs cohérentes avec |n| et |n²|
+    //   - nombre de composantes cohérent avec le niveau
+    //   - plages : c0 ∈ Z_n (niveau 1), chiffrés ∈ ]0, n²[
+    // -----------------------------------------------------------------------
+    pub fn validate(&self, pk: &PublicKey) -> Result<(), CryptoError> {
+        if self.key_fp != key_fingerprint(pk) {
+            return Err(CryptoError::KeyFingerprintMismatch);
+        }
+        if self.n_bytes != byte_width(&pk.n)? || self.n2_bytes != byte_width(&pk.n_squared)? {
+            return Err(CryptoError::MalformedCiphertext(
+                "largeurs de composantes incohérentes avec la clé".into(),
+            ));
+        }
+        if self.components.len() != self.level.arity() {
+            return Err(CryptoError::MalformedCiphertext(format!(
+                "{} composantes pour le niveau {}",
+                self.components.len(), self.level as u8
+            )));
+        }
+
+        for (i, c) in self.components.iter().enumerate() {
+            let plaintext = self.level == CfLevel::Fst && i == 0;
+            if plaintext {
+                if c >= &pk.n {
+                    return Err(CryptoError::MessageOutOfRange);
+                }
+            } else if c.is_zero() || c >= &pk.n_squared {
+                return Err(CryptoError::CiphertextOutOfRange);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Extrait un CfFst après validation (erreur si niveau 2)
+    pub fn to_fst(&self, pk: &PublicKey) -> Result<(BigUint, BigUint), CryptoError> {
+        if self.level != CfLevel::Fst {
+            return Err(CryptoError::CfLevelMismatch { expected: 1, found: self.level as u8 });
+        }
+        self.validate(pk)?;
+        Ok((self.components[0].clone(), self.components[1].clone()))
+    }
+
+    /// Extrait un CfSnd après validation (erreur si niveau 1)
+    pub fn to_snd(&self, pk: &PublicKey) -> Result<(BigUint, BigUint, BigUint), CryptoError> {
+        if self.level != CfLevel::Snd {
+            return Err(CryptoError::CfLevelMismatch { expected: 2, found: self.level as u8 });
+        }
+        self.validate(pk)?;
+        Ok((
+            self.components[0].clone(),
+            self.components[1].clone(),
+            self.components[2].clone(),
+        ))
+    }
+
+    // -----------------------------------------------------------------------
+    // Encodage binaire v1
+    // -----------------------------------------------------------------------
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let body: usize = (0..self.components.len()).map(|i| self.width(i)).sum();
+        let mut out = Vec::with_capacity(CF_HEADER_LEN + body);
+        out.extend_from_slice(&CF_MAGIC);
+        out.push(CF_CODEC_VERSION);
+        out.push(self.level as u8);
+        out.extend_from_slice(&self.key_fp);
+        out.extend_from_slice(&self.n_bytes.to_be_bytes());
+        out.extend_from_slice(&self.n2_bytes.to_be_bytes());
+
+        for (i, c) in self.components.iter().enumerate() {
+            let bytes = c.to_bytes_be();
+            let width = self.width(i);
+            // validate() garantit bytes.len() <= width
+            out.resize(out.len() + width.saturating_sub(bytes.len()), 0);
+            out.extend_from_slice(&bytes);
+        }
+        out
+    }
+
+    // -----------------------------------------------------------------------
+    // Décodage binaire v1 — contrôles STRUCTURELS uniquement.
+    // Appeler validate(pk) (ou to_fst / to_snd) avant usage.
+    // -----------------------------------------------------------------------
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, CryptoError> {
+        if buf.len() < CF_HEADER_LEN {
+            return Err(CryptoError::MalformedCiphertext("en-tête tronqué".into()));
+        }
+        if buf[0..2] != CF_MAGIC {
+            return Err(CryptoError::MalformedCiphertext("octets magiques absents".into()));
+        }
+        if buf[2] != CF_CODEC_VERSION {
+            return Err(CryptoError::UnsupportedVersion { found: buf[2], supported: CF_CODEC_VERSION });
+        }
+        let level = CfLevel::from_u8(buf[3])?;
+
+        let mut key_fp = [0u8; KEY_FINGERPRINT_LEN];
+        key_fp.copy_from_slice(&buf[4..4 + KEY_FINGERPRINT_LEN]);
+        let off      = 4 + KEY_FINGERPRINT_LEN;
+        let n_bytes  = u16::from_be_bytes([buf[off],     buf[off + 1]]);
+        let n2_bytes = u16::from_be_bytes([buf[off + 2], buf[off + 3]]);
+
+        let mut ct = CfCiphertext { level, key_fp, n_bytes, n2_bytes, components: Vec::new() };
+
+        let expected: usize = CF_HEADER_LEN + (0..level.arity()).map(|i| ct.width(i)).sum::<usize>();
+        if buf.len() != expected {
+            return Err(CryptoError::MalformedCiphertext(format!(
+                "longueur {} octets, attendue {}", buf.len(), expected
+            )));
+        }
+
+        let mut pos = CF_HEADER_LEN;
+        for i in 0..level.arity() {
+            let w = ct.width(i);
+            ct.components.push(BigUint::from_bytes_be(&buf[pos..pos + w]));
+            pos += w;
+        }
+
+        Ok(ct)
+    }
+}
+
+// ============================================================================
+// Représentation JSON
+// ============================================================================
+
+#[derive(Serialize, Deserialize)]
+struct CfCiphertextJson {
+    version:         u8,
+    level:           u8,
+    key_fingerprint: String,
+    n_bytes:         u16,
+    n2_bytes:        u16,
+    components:      Vec<String>,
+}
+
+impl CfCiphertextJson {
+    fn from_ct(ct: &CfCiphertext) -> Self {
+        CfCiphertextJson {
+            version:         CF_CODEC_VERSION,
+            level:           ct.level as u8,
+            key_fingerprint: fingerprint_to_hex(&ct.key_fp),
+            n_bytes:         ct.n_bytes,
+            n2_bytes:        ct.n2_bytes,
+            components:      ct.components.iter().map(biguint_to_hex).collect(),
+        }
+    }
+
+    fn into_ct(self) -> Result<CfCiphertext, CryptoError> {
+        if self.version != CF_CODEC_VERSION {
+            return Err(CryptoError::UnsupportedVersion {
+                found:     self.version,
+                supported: CF_CODEC_VERSION,
+            });
+        }
+        let level = CfLevel::from_u8(self.level)?;
+        if self.components.len() != level.arity() {
+            return Err(CryptoError::MalformedCiphertext(format!(
+                "{} composantes pour le niveau {}", self.components.len(), self.level
+            )));
+        }
+        Ok(CfCiphertext {
+            level,
+            key_fp:     hex_to_fingerprint(&self.key_fingerprint)?,
+            n_bytes:    self.n_bytes,
+            n2_bytes:   self.n2_bytes,
+            components: self.components.iter()
+                .map(|h| hex_to_biguint(h))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl Serialize for CfCiphertext {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            CfCiphertextJson::from_ct(self).serialize(serializer)
+        } else {
+            self.to_bytes().serialize(serializer)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for CfCiphertext {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        use serde::de::Error;
+        if deserializer.is_human_readable() {
+            CfCiphertextJson::deserialize(deserializer)?
+                .into_ct()
+                .map_err(D::Error::custom)
+        } else {
+            let bytes = Vec::<u8>::deserialize(deserializer)?;
+            CfCiphertext::from_bytes(&bytes).map_err(D::Error::custom)
+        }
+    }
+}
+
+// ============================================================================
+// Persistance sur disque (JSON), avec validation au chargement
+// ============================================================================
+
+pub fn save_cf_json(ct: &CfCiphertext, filepath: &str) -> io::Result<()> {
+    let json = serde_json::to_string_pretty(ct)?;
+    fs::write(filepath, json)?;
+    Ok(())
+}
+
+/// Charge un chiffré CF et le valide contre pk (empreinte, niveau, plages).
+pub fn load_cf_json(filepath: &str, pk: &PublicKey) -> io::Result<CfCiphertext> {
+    let meta = fs::metadata(filepath)?;
+    if meta.len() > MAX_CF_FILE_BYTES {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "Fichier de chiffré trop grand : {} octets (maximum autorisé : {} octets).",
+                meta.len(), MAX_CF_FILE_BYTES
+            ),
+        ));
+    }
+    let raw = fs::read_to_string(filepath)?;
+    let ct: CfCiphertext = serde_json::from_str(&raw)?;
+    ct.validate(pk)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+    Ok(ct)
+}
+
+// ============================================================================
+// Tests — aller-retour binaire / JSON / bincode et rejet à la validation
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fiore_catalano::cf_encrypt::cf_encrypt::cf_encrypt;
+    use crate::fiore_catalano::cf_mul::cf_mul::cf_mul;
+    use crate::fiore_catalano::cf_mul_dec::cf_mul_dec::cf_mul_dec;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    #[test]
+    fn test_roundtrip_all_formats() {
+        let kp = p_keygen(128).unwrap();
+        let pk = &kp.public_key;
+        let x  = cf_encrypt(&BigUint::from(6u32), &BigUint::from(11u32), pk).unwrap();
+        let y  = cf_encrypt(&BigUint::from(7u32), &BigUint::from(13u32), pk).unwrap();
+        let snd = cf_mul(&x, &y, pk).unwrap();
+
+        let fst = CfCiphertext::from_fst(&x, pk).unwrap();
+        let bin = fst.to_bytes();
+        assert_eq!(bin.len(), CF_HEADER_LEN + fst.n_bytes as usize + fst.n2_bytes as usize);
+        assert_eq!(CfCiphertext::from_bytes(&bin).unwrap().to_fst(pk).unwrap(), x);
+
+        let ct   = CfCiphertext::from_snd(&snd, pk).unwrap();
+        let json = serde_json::to_string(&ct).unwrap();
+        let back: CfCiphertext = serde_json::from_str(&json).unwrap();
+        let snd2 = back.to_snd(pk).unwrap();
+        assert_eq!(cf_mul_dec(&snd2, pk, &kp.secret_key).unwrap(), BigUint::from(42u32));
+
+        let raw = bincode::serialize(&ct).unwrap();
+        let back: CfCiphertext = bincode::deserialize(&raw).unwrap();
+        assert_eq!(back, ct);
+    }
+
+    #[test]
+    fn test_validation_rejects() {
+        let kp    = p_keygen(128).unwrap();
+        let other = p_keygen(128).unwrap();
+        let pk    = &kp.public_key;
+        let x     = cf_encrypt(&BigUint::from(5u32), &BigUint::from(3u32), pk).unwrap();
+        let ct    = CfCiphertext::from_fst(&x, pk).unwrap();
+
+        assert!(matches!(ct.to_fst(&other.public_key), Err(CryptoError::KeyFingerprintMismatch)));
+        assert!(matches!(ct.to_snd(pk), Err(CryptoError::CfLevelMismatch { expected: 2, found: 1 })));
+
+        let mut bin = ct.to_bytes();
+        bin[2] = CF_CODEC_VERSION + 1;
+        assert!(matches!(CfCiphertext::from_bytes(&bin), Err(CryptoError::UnsupportedVersion { .. })));
+
+        let bin = ct.to_bytes();
+        assert!(matches!(
+            CfCiphertext::from_bytes(&bin[..bin.len() - 1]),
+            Err(CryptoError::MalformedCiphertext(_))
+        ));
+
+        // c0 hors de Z_n
+        let mut bad = ct.clone();
+        bad.components[0] = pk.n.clone();
+        assert!(matches!(bad.validate(pk), Err(CryptoError::MessageOutOfRange)));
+    }
+}
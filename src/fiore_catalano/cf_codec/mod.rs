@@ -0,0 +1,7 @@
+pub mod cf_codec;
+
+pub use cf_codec::{
+    CfCiphertext, CfLevel,
+    CF_CODEC_VERSION, CF_MAGIC, CF_HEADER_LEN,
+    save_cf_json, load_cf_json,
+};
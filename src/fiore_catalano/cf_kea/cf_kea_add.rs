@@ -0,0 +1,47 @@
+use num_bigint::BigUint;
+use crate::paillier::p_keygen::p_keygen::SecretKey;
+use crate::paillier::p_keygen::PublicKey;
+use crate::paillier_kea::paillier_kea_decrypt::paillier_kea_decrypt;
+use crate::karatsuba_mul::karatsuba_mul::fast_mul;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::fiore_catalano::cf_kea::CfKeaFst;
+
+// ---------------------------------------------------------------------------
+// cf_kea_add — cf_add appliqué aux deux composantes KEA
+//
+//   a_res  = a + a'            mod n
+//   β0_res = β0 · β0'          mod n²   = Enc(b + b')
+//   β1_res = β1 · β1'          mod n²   = Enc(ξ·(b + b'))
+//
+// L'addition est linéaire : la relation β1 = ξ·β0 est préservée.
+// ---------------------------------------------------------------------------
+pub fn cf_kea_add(
+	ciphert0: &CfKeaFst,
+	ciphert1: &CfKeaFst,
+	pk:       &PublicKey,
+) -> Result<CfKeaFst, CryptoError> {
+
+	let a_res  = (&ciphert0.0 + &ciphert1.0) % &pk.n;
+	let beta0  = fast_mul(&ciphert0.1.0, &ciphert1.1.0, &pk.n_squared)?;
+	let beta1  = fast_mul(&ciphert0.1.1, &ciphert1.1.1, &pk.n_squared)?;
+
+	Ok((a_res, (beta0, beta1)))
+}
+
+// ---------------------------------------------------------------------------
+// cf_kea_add_dec — vérification d'image PUIS déchiffrement
+//
+// Retourne Err(CryptoError::KeaImVerFailed) si (β0, β1) n'est pas une
+// image valide, c.-à-d. si Dec(β1) ≠ ξ·Dec(β0).
+// ---------------------------------------------------------------------------
+pub fn cf_kea_add_dec(
+	ciphert: &CfKeaFst,
+	pk:      &PublicKey,
+	sk:      &SecretKey,
+	psy:     &BigUint,
+) -> Result<BigUint, CryptoError> {
+
+	let b = paillier_kea_decrypt(pk, sk, psy, &ciphert.1)?;
+
+	Ok((&ciphert.0 + b) % &pk.n)
+}
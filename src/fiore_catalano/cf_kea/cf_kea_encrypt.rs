@@ -0,0 +1,37 @@
+use num_bigint::BigUint;
+use crate::paillier::p_keygen::PublicKey;
+use crate::paillier_kea::paillier_kea_encrypt::paillier_kea_encrypt;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::fiore_catalano::cf_kea::CfKeaFst;
+
+// ---------------------------------------------------------------------------
+// cf_kea_encrypt — CF.Enc dont la composante chiffrée est un chiffré KEA
+//
+//   CF_kea(m, b) = (a, (β0, β1))
+//     a  = m - b mod n          (en clair, comme cf_encrypt)
+//     β0 = Enc(b)               = ct_delta.0^b · r0^n
+//     β1 = Enc(ξ·b)             = ct_delta.1^b · r1^n
+//
+// ct_delta est PUBLIC (publié par le détenteur de la clé) : n'importe quel
+// client peut chiffrer. Seul le détenteur de ξ peut vérifier l'image.
+// ---------------------------------------------------------------------------
+pub fn cf_kea_encrypt(
+	message:  &BigUint,
+	masque:   &BigUint,
+	pk:       &PublicKey,
+	ct_delta: &(BigUint, BigUint),
+) -> Result<CfKeaFst, CryptoError> {
+
+	if message >= &pk.n {
+		return Err(CryptoError::MessageOutOfRange);
+	}
+
+	let b = masque % &pk.n;
+
+	// a = m - b mod n
+	let a = (message + &pk.n - &b) % &pk.n;
+
+	let beta = paillier_kea_encrypt(&b, pk, ct_delta)?;
+
+	Ok((a, beta))
+}
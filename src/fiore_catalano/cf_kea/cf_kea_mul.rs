@@ -0,0 +1,53 @@
+use crate::paillier::p_keygen::PublicKey;
+use crate::paillier_kea::paillier_kea_encrypt::paillier_kea_encrypt;
+use crate::karatsuba_mul::karatsuba_mul::fast_mul;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::fiore_catalano::cf_kea::{CfKeaFst, CfKeaSnd};
+
+// ---------------------------------------------------------------------------
+// cf_kea_mul — cf_mul appliqué aux deux composantes KEA
+//
+// Même algèbre que cf_mul, dupliquée sur la composante « ξ » :
+//
+//   C0.0 = Enc(a·a')   · β0'^{a} · β0^{a'}   = Enc(a·a' + a·b' + a'·b)
+//   C0.1 = Enc(ξ·a·a') · β1'^{a} · β1^{a'}   = Enc(ξ·(a·a' + a·b' + a'·b))
+//   C1   = (β0,  β1)
+//   C2   = (β0', β1')
+//
+// (Enc(a·a'), Enc(ξ·a·a')) est produit par paillier_kea_encrypt avec le
+// ct_delta public : le serveur n'a pas besoin de ξ.
+//
+// Garantie exacte : la vérification d'image (cf_kea_mul_dec) contrôle
+// seulement que chaque paire (Enc(x), Enc(y)) vérifie y = ξ·x. Elle
+// détecte donc une composante modifiée INDÉPENDAMMENT de l'autre
+// (corruption, paires mélangées entre chiffrés). Elle ne prouve PAS que
+// le serveur a suivi le calcul prescrit : ct_delta étant public, il peut
+// chiffrer une paire valide pour tout x de son choix (paillier_kea_encrypt)
+// et combiner librement des paires valides.
+// ---------------------------------------------------------------------------
+pub fn cf_kea_mul(
+	ciphert:  &CfKeaFst,
+	ciphert1: &CfKeaFst,
+	pk:       &PublicKey,
+	ct_delta: &(num_bigint::BigUint, num_bigint::BigUint),
+) -> Result<CfKeaSnd, CryptoError> {
+
+	let a      = &ciphert.0;
+	let beta   = &ciphert.1;
+	let a_p    = &ciphert1.0;
+	let beta_p = &ciphert1.1;
+
+	// (Enc(a·a'), Enc(ξ·a·a'))
+	let product_a = fast_mul(a, a_p, &pk.n)?;
+	let enc_prod  = paillier_kea_encrypt(&product_a, pk, ct_delta)?;
+
+	// Composante « Enc(x) »
+	let t0     = fast_mul(&enc_prod.0, &beta_p.0.modpow(a, &pk.n_squared), &pk.n_squared)?;
+	let c0_res = fast_mul(&t0, &beta.0.modpow(a_p, &pk.n_squared), &pk.n_squared)?;
+
+	// Composante « Enc(ξ·x) »
+	let t1     = fast_mul(&enc_prod.1, &beta_p.1.modpow(a, &pk.n_squared), &pk.n_squared)?;
+	let c0_xi  = fast_mul(&t1, &beta.1.modpow(a_p, &pk.n_squared), &pk.n_squared)?;
+
+	Ok(((c0_res, c0_xi), beta.clone(), beta_p.clone()))
+}
@@ -0,0 +1,85 @@
+use num_bigint::BigUint;
+use crate::paillier::p_keygen::p_keygen::SecretKey;
+use crate::paillier::p_keygen::PublicKey;
+use crate::paillier_kea::paillier_kea_decrypt::paillier_kea_decrypt;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::fiore_catalano::cf_kea::CfKeaSnd;
+
+// ---------------------------------------------------------------------------
+// cf_kea_mul_dec — cf_mul_dec précédé de la vérification d'image KEA
+//
+// Les TROIS composantes sont vérifiées avant tout usage du résultat :
+//   Dec(Ci.1) == ξ·Dec(Ci.0) mod n   pour i = 0, 1, 2
+//
+// Puis, comme cf_mul_dec :  m·m' = Dec(C0) + Dec(C1)·Dec(C2)  mod n
+//
+// Retourne Err(CryptoError::KeaImVerFailed) dès qu'une composante échoue.
+// ---------------------------------------------------------------------------
+pub fn cf_kea_mul_dec(
+	ciphert: &CfKeaSnd,
+	pk:      &PublicKey,
+	sk:      &SecretKey,
+	psy:     &BigUint,
+) -> Result<BigUint, CryptoError> {
+
+	// paillier_kea_decrypt vérifie l'image avant de déchiffrer
+	let dec_c0 = paillier_kea_decrypt(pk, sk, psy, &ciphert.0)?;
+	let dec_c1 = paillier_kea_decrypt(pk, sk, psy, &ciphert.1)?;
+	let dec_c2 = paillier_kea_decrypt(pk, sk, psy, &ciphert.2)?;
+
+	let result = (dec_c0 + dec_c1 * dec_c2) % &pk.n;
+
+	Ok(result)
+}
+
+// ============================================================================
+// Tests — pipeline CF avec contrôle d'image KEA
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fiore_catalano::cf_kea::cf_kea_encrypt::cf_kea_encrypt;
+    use crate::fiore_catalano::cf_kea::cf_kea_add::{cf_kea_add, cf_kea_add_dec};
+    use crate::fiore_catalano::cf_kea::cf_kea_mul::cf_kea_mul;
+    use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+    use crate::paillier_kea::paillier_kea_keygen::paillier_kea_keygen;
+
+    #[test]
+    fn test_kea_honest_server_accepted() {
+        let kp  = p_keygen(128).unwrap();
+        let kea = paillier_kea_keygen(&kp.public_key).unwrap();
+        let pk  = &kp.public_key;
+
+        let (m1, m2) = (BigUint::from(12_345u32), BigUint::from(678u32));
+        let ct1 = cf_kea_encrypt(&m1, &BigUint::from(99u32), pk, &kea.ct_delta).unwrap();
+        let ct2 = cf_kea_encrypt(&m2, &BigUint::from(7u32),  pk, &kea.ct_delta).unwrap();
+
+        let sum = cf_kea_add(&ct1, &ct2, pk).unwrap();
+        assert_eq!(cf_kea_add_dec(&sum, pk, &kp.secret_key, &kea.psy).unwrap(), &m1 + &m2);
+
+        let prod = cf_kea_mul(&ct1, &ct2, pk, &kea.ct_delta).unwrap();
+        assert_eq!(cf_kea_mul_dec(&prod, pk, &kp.secret_key, &kea.psy).unwrap(), &m1 * &m2);
+    }
+
+    #[test]
+    fn test_kea_tampered_result_rejected() {
+        let kp  = p_keygen(128).unwrap();
+        let kea = paillier_kea_keygen(&kp.public_key).unwrap();
+        let pk  = &kp.public_key;
+
+        let one = BigUint::from(1u32);
+        let ct1 = cf_kea_encrypt(&one, &BigUint::from(5u32), pk, &kea.ct_delta).unwrap();
+        let ct2 = cf_kea_encrypt(&one, &BigUint::from(9u32), pk, &kea.ct_delta).unwrap();
+        let mut prod = cf_kea_mul(&ct1, &ct2, pk, &kea.ct_delta).unwrap();
+
+        // Serveur déviant : ajoute 1 au produit sans mettre à jour Enc(ξ·x)
+        let enc_one = p_encrypt(&one, pk).unwrap();
+        prod.0.0 = (&prod.0.0 * enc_one) % &pk.n_squared;
+
+        assert_eq!(
+            cf_kea_mul_dec(&prod, pk, &kp.secret_key, &kea.psy),
+            Err(CryptoError::KeaImVerFailed)
+        );
+    }
+}
@@ -0,0 +1,15 @@
+pub mod cf_kea_encrypt;
+pub mod cf_kea_add;
+pub mod cf_kea_mul;
+pub mod cf_kea_mul_dec;
+
+use num_bigint::BigUint;
+
+/// Chiffré KEA P^(2) : (Enc(x), Enc(ξ·x))
+pub type KeaCt = (BigUint, BigUint);
+
+/// CF Premiere Forme protégée KEA : (a, (Enc(b), Enc(ξ·b)))
+pub type CfKeaFst = (BigUint, KeaCt);
+
+/// CF Seconde Forme protégée KEA : chaque composante est un chiffré KEA
+pub type CfKeaSnd = (KeaCt, KeaCt, KeaCt);
@@ -0,0 +1,149 @@
+use num_bigint::{BigUint, RandBigInt};
+use rand_core::OsRng;
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_keygen::PublicKey;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::karatsuba_mul::karatsuba_mul::fast_mul;
+
+// ---------------------------------------------------------------------------
+// Catalano-Fiore multi-clés (pk1 × pk2)
+//
+// Le CF classique exige que les deux opérandes soient chiffrés sous la MÊME
+// clé : chaque BD chiffre donc sa table deux fois (DualFtBundle). Ici chaque
+// BD chiffre une seule fois sous SA clé, et le serveur multiplie un chiffré
+// sous pk1 par un chiffré sous pk2.
+//
+// Espace des messages commun : Z_M, avec M public et M² << n1, n2.
+// (n1 ≠ n2 : on ne peut pas travailler modulo n, on travaille modulo M et
+//  on garantit qu'aucun calcul dans Z_{n1} ou Z_{n2} ne « déborde ».)
+//
+//   CF_mk(m, b) sous pk = (a, β)   avec  a = m - b mod M,  β = Enc_pk(b)
+//
+// Produit de (a1, β1) sous pk1 et (a2, β2) sous pk2 :
+//   m1·m2 ≡ a1·a2 + a2·b1 + a1·b2 + b1·b2   (mod M)
+//
+//   α1 = Enc1(a1·a2 + ρ) · β1^{a2}    = Enc1(a1·a2 + a2·b1 + ρ)
+//   α2 = Enc2(M - ρ)     · β2^{a1}    = Enc2(a1·b2 + M - ρ)
+//
+// Le terme croisé b1·b2 est résolu au déchiffrement (cf_mul_mk_dec) : BD1
+// récupère b1 = Dec1(β1) et calcule β2^{b1} = Enc2(b1·b2).
+//
+// ρ ∈ Z_M est tiré par le serveur : il rend chaque part (α1 seule, α2 seule)
+// uniforme modulo M, de sorte qu'aucune BD n'apprend rien de sa part isolée.
+// ---------------------------------------------------------------------------
+
+/// Marge (en bits) entre 2·|M| et min(|n1|, |n2|).
+/// Couvre l'agrégation homomorphique de 2^60 produits sans débordement.
+pub const MK_MARGIN_BITS: u64 = 64;
+
+/// CF multi-clés Seconde Forme
+#[derive(Clone, Debug, PartialEq)]
+pub struct MkCfSnd {
+    /// Enc_pk1(a1·a2 + a2·b1 + ρ)
+    pub alpha1: BigUint,
+    /// Enc_pk2(a1·b2 + M - ρ)
+    pub alpha2: BigUint,
+    /// Enc_pk1(b1)
+    pub beta1:  BigUint,
+    /// Enc_pk2(b2)
+    pub beta2:  BigUint,
+}
+
+// ---------------------------------------------------------------------------
+// Vérifie que M est compatible avec les deux modules Paillier.
+// ---------------------------------------------------------------------------
+pub fn check_mk_modulus(
+	modulus: &BigUint,
+	pk1:     &PublicKey,
+	pk2:     &PublicKey,
+) -> Result<(), CryptoError> {
+
+	if modulus.bits() < 2 {
+		return Err(CryptoError::InvalidInput(
+			"cf_mul_mk : le module M doit être >= 2".into(),
+		));
+	}
+
+	let n_bits = pk1.n.bits().min(pk2.n.bits());
+	if 2 * modulus.bits() + MK_MARGIN_BITS > n_bits {
+		return Err(CryptoError::InvalidInput(format!(
+			"cf_mul_mk : |M| = {} bits trop grand pour |n| = {} bits",
+			modulus.bits(), n_bits
+		)));
+	}
+
+	Ok(())
+}
+
+// ---------------------------------------------------------------------------
+// cf_encrypt_mk — CF.Enc dans Z_M sous une seule clé
+// ---------------------------------------------------------------------------
+pub fn cf_encrypt_mk(
+	message: &BigUint,
+	masque:  &BigUint,
+	modulus: &BigUint,
+	pk:      &PublicKey,
+) -> Result<(BigUint, BigUint), CryptoError> {
+
+	if message >= modulus {
+		return Err(CryptoError::MessageOutOfRange);
+	}
+
+	let b = masque % modulus;
+
+	// a = m - b mod M
+	let a = (message + modulus - &b) % modulus;
+
+	let beta = p_encrypt(&b, pk)?;
+
+	Ok((a, beta))
+}
+
+// ---------------------------------------------------------------------------
+// cf_mul_mk — Multiplication d'un CF sous pk1 par un CF sous pk2
+// ---------------------------------------------------------------------------
+pub fn cf_mul_mk(
+	ciphert1: &(BigUint, BigUint),
+	ciphert2: &(BigUint, BigUint),
+	modulus:  &BigUint,
+	pk1:      &PublicKey,
+	pk2:      &PublicKey,
+) -> Result<MkCfSnd, CryptoError> {
+
+	check_mk_modulus(modulus, pk1, pk2)?;
+
+	let a1    = &ciphert1.0;   // m1 - b1 mod M
+	let beta1 = &ciphert1.1;   // Enc1(b1)
+
+	let a2    = &ciphert2.0;   // m2 - b2 mod M
+	let beta2 = &ciphert2.1;   // Enc2(b2)
+
+	if a1 >= modulus || a2 >= modulus {
+		return Err(CryptoError::MessageOutOfRange);
+	}
+	if beta1 >= &pk1.n_squared || beta2 >= &pk2.n_squared {
+		return Err(CryptoError::CiphertextOutOfRange);
+	}
+
+	let mut rng = OsRng;
+	let rho = rng.gen_biguint_below(modulus);
+
+	// ── α1 = Enc1(a1·a2 + ρ) · β1^{a2} ──────────────────────────────────────
+	// a1·a2 + ρ < M² + M < n1 : pas de réduction modulo M ici, elle est
+	// faite une seule fois sur la somme finale.
+	let enc_a1a2 = p_encrypt(&(a1 * a2 + &rho), pk1)?;
+	let beta1_a2 = beta1.modpow(a2, &pk1.n_squared);
+	let alpha1   = fast_mul(&enc_a1a2, &beta1_a2, &pk1.n_squared)?;
+
+	// ── α2 = Enc2(M - ρ) · β2^{a1} ──────────────────────────────────────────
+	let enc_neg_rho = p_encrypt(&(modulus - &rho), pk2)?;
+	let beta2_a1    = beta2.modpow(a1, &pk2.n_squared);
+	let alpha2      = fast_mul(&enc_neg_rho, &beta2_a1, &pk2.n_squared)?;
+
+	Ok(MkCfSnd {
+		alpha1,
+		alpha2,
+		beta1: beta1.clone(),
+		beta2: beta2.clone(),
+	})
+}
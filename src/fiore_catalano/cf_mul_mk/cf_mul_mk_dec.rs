@@ -0,0 +1,162 @@
+use num_bigint::BigUint;
+use num_traits::One;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_keygen::p_keygen::SecretKey;
+use crate::paillier::p_keygen::PublicKey;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::karatsuba_mul::karatsuba_mul::fast_mul;
+use crate::fiore_catalano::cf_mul_mk::cf_mul_mk::MkCfSnd;
+
+// ---------------------------------------------------------------------------
+// Déchiffrement conjoint d'un CF multi-clés
+//
+// Aucune BD ne peut déchiffrer seule un MkCfSnd : α1 et β1 sont sous pk1,
+// α2 et β2 sous pk2. Le déchiffrement se fait en trois étapes :
+//
+//   1. BD1 (sk1) — linéarisation :
+//        b1  = Dec1(β1)
+//        α2' = α2 · β2^{b1} = Enc2(a1·b2 + b1·b2 + M - ρ)
+//      Le résultat (α1, α2') est une paire de chiffrés LINÉAIRES : on peut
+//      les sommer position par position (cf_mul_mk_aggregate).
+//
+//   2. Chaque BD déchiffre SA composante : part_i = Dec_i(α_i) mod M.
+//      Chaque part est uniforme modulo M (masquée par ρ).
+//
+//   3. m1·m2 = part_1 + part_2 mod M.
+// ---------------------------------------------------------------------------
+
+/// Paire (Enc_pk1(x1), Enc_pk2(x2)) telle que x1 + x2 ≡ m1·m2 (mod M)
+pub type MkCfLin = (BigUint, BigUint);
+
+// ---------------------------------------------------------------------------
+// Étape 1 — BD1 résout le terme croisé b1·b2
+// ---------------------------------------------------------------------------
+pub fn cf_mul_mk_linearize(
+	ciphert: &MkCfSnd,
+	pk1:     &PublicKey,
+	sk1:     &SecretKey,
+	pk2:     &PublicKey,
+) -> Result<MkCfLin, CryptoError> {
+
+	if ciphert.alpha2 >= pk2.n_squared || ciphert.beta2 >= pk2.n_squared {
+		return Err(CryptoError::CiphertextOutOfRange);
+	}
+
+	// b1 : masque de BD1 (BD1 le connaît déjà, on le redéchiffre pour rester
+	// sans état entre Phase 2 et Phase 4)
+	let b1 = p_decrypt(&ciphert.beta1, pk1, sk1)?;
+
+	// β2^{b1} = Enc2(b1·b2)
+	let beta2_b1 = ciphert.beta2.modpow(&b1, &pk2.n_squared);
+	let alpha2   = fast_mul(&ciphert.alpha2, &beta2_b1, &pk2.n_squared)?;
+
+	Ok((ciphert.alpha1.clone(), alpha2))
+}
+
+// ---------------------------------------------------------------------------
+// Somme homomorphique de plusieurs produits linéarisés
+//
+// Le nombre de termes est borné par MK_MARGIN_BITS (cf. check_mk_modulus) :
+// au-delà, la somme entière pourrait dépasser n et la réduction mod M
+// finale ne serait plus correcte.
+// ---------------------------------------------------------------------------
+pub fn cf_mul_mk_aggregate(
+	items: &[MkCfLin],
+	pk1:   &PublicKey,
+	pk2:   &PublicKey,
+) -> Result<MkCfLin, CryptoError> {
+
+	let mut acc1 = BigUint::one();   // Enc1(0) trivial
+	let mut acc2 = BigUint::one();   // Enc2(0) trivial
+
+	for (c1, c2) in items {
+		acc1 = fast_mul(&acc1, c1, &pk1.n_squared)?;
+		acc2 = fast_mul(&acc2, c2, &pk2.n_squared)?;
+	}
+
+	Ok((acc1, acc2))
+}
+
+// ---------------------------------------------------------------------------
+// Étape 2 — déchiffrement partiel par le détenteur de sk_i
+// ---------------------------------------------------------------------------
+pub fn cf_mul_mk_partial_dec(
+	ciphert: &BigUint,
+	modulus: &BigUint,
+	pk:      &PublicKey,
+	sk:      &SecretKey,
+) -> Result<BigUint, CryptoError> {
+
+	let x = p_decrypt(ciphert, pk, sk)?;
+
+	Ok(x % modulus)
+}
+
+// ---------------------------------------------------------------------------
+// Étape 3 — recombinaison des deux parts
+// ---------------------------------------------------------------------------
+pub fn cf_mul_mk_combine(
+	part1:   &BigUint,
+	part2:   &BigUint,
+	modulus: &BigUint,
+) -> BigUint {
+	(part1 + part2) % modulus
+}
+
+// ============================================================================
+// Tests — produit multi-clés et déchiffrement conjoint
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fiore_catalano::cf_mul_mk::cf_mul_mk::{cf_encrypt_mk, cf_mul_mk};
+    use crate::paillier::p_keygen::p_keygen::p_keygen;
+
+    #[test]
+    fn test_mk_product_and_sum() {
+        let kp1 = p_keygen(128).unwrap();
+        let kp2 = p_keygen(128).unwrap();
+        let (pk1, pk2) = (&kp1.public_key, &kp2.public_key);
+        let modulus = BigUint::from(1u64 << 32);
+
+        let pairs = [(1u32, 1u32), (0, 1), (1, 0), (7, 9), (65_535, 65_537)];
+        let mut expected = BigUint::from(0u32);
+        let mut lin = Vec::new();
+
+        for (i, &(m1, m2)) in pairs.iter().enumerate() {
+            let b1  = BigUint::from(1_000_003u32 * (i as u32 + 1));
+            let b2  = BigUint::from(4_000_037u32 + i as u32);
+            let ct1 = cf_encrypt_mk(&BigUint::from(m1), &b1, &modulus, pk1).unwrap();
+            let ct2 = cf_encrypt_mk(&BigUint::from(m2), &b2, &modulus, pk2).unwrap();
+            let mk  = cf_mul_mk(&ct1, &ct2, &modulus, pk1, pk2).unwrap();
+
+            // Un seul produit : déchiffrement conjoint direct
+            let (a1, a2) = cf_mul_mk_linearize(&mk, pk1, &kp1.secret_key, pk2).unwrap();
+            let part1 = cf_mul_mk_partial_dec(&a1, &modulus, pk1, &kp1.secret_key).unwrap();
+            let part2 = cf_mul_mk_partial_dec(&a2, &modulus, pk2, &kp2.secret_key).unwrap();
+            let prod  = BigUint::from(m1) * BigUint::from(m2) % &modulus;
+            assert_eq!(cf_mul_mk_combine(&part1, &part2, &modulus), prod);
+
+            expected = (expected + prod) % &modulus;
+            lin.push((a1, a2));
+        }
+
+        // Somme homomorphique de tous les produits
+        let (s1, s2) = cf_mul_mk_aggregate(&lin, pk1, pk2).unwrap();
+        let part1 = cf_mul_mk_partial_dec(&s1, &modulus, pk1, &kp1.secret_key).unwrap();
+        let part2 = cf_mul_mk_partial_dec(&s2, &modulus, pk2, &kp2.secret_key).unwrap();
+        assert_eq!(cf_mul_mk_combine(&part1, &part2, &modulus), expected);
+    }
+
+    #[test]
+    fn test_mk_modulus_too_large_rejected() {
+        let kp1 = p_keygen(128).unwrap();
+        let kp2 = p_keygen(128).unwrap();
+        let modulus = BigUint::from(1u32) << 128;
+        let ct = (BigUint::from(0u32), BigUint::from(1u32));
+        assert!(matches!(
+            cf_mul_mk(&ct, &ct, &modulus, &kp1.public_key, &kp2.public_key),
+            Err(CryptoError::InvalidInput(_))
+        ));
+    }
+}
@@ -0,0 +1,2 @@
+pub mod cf_mul_mk;
+pub mod cf_mul_mk_dec;
@@ -0,0 +1,38 @@
+use num_bigint::BigUint;
+
+use crate::karatsuba_mul::karatsuba_mul::fast_mul;
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ---------------------------------------------------------------------------
+// cf_scal — Multiplication d'un CF par un scalaire clair k
+//
+//   k · CF(m, r) = CF(k·m, k·r)
+//
+//   c0_res = k · c0   mod n
+//   c1_res = c1^k     mod n²   (Enc(r)^k = Enc(k·r))
+//
+// Avec k uniforme dans Z_n, k·m est uniforme dès que m ≠ 0 et
+// gcd(m, n) = 1 : seul le caractère nul de m survit au déchiffrement.
+// ---------------------------------------------------------------------------
+pub fn cf_scal(
+    ciphert:   &(BigUint, BigUint),
+    k:         &BigUint,
+    n:         &BigUint,
+    n_squared: &BigUint,
+) -> Result<(BigUint, BigUint), CryptoError> {
+
+    let c0 = &ciphert.0;
+    let c1 = &ciphert.1;
+
+    if c0 >= n || k >= n {
+        return Err(CryptoError::MessageOutOfRange);
+    }
+    if c1 >= n_squared {
+        return Err(CryptoError::CiphertextOutOfRange);
+    }
+
+    let c0_res = fast_mul(c0, k, n)?;
+    let c1_res = c1.modpow(k, n_squared);
+
+    Ok((c0_res, c1_res))
+}
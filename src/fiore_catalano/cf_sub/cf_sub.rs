@@ -0,0 +1,42 @@
+use num_bigint::BigUint;
+
+use crate::paillier::math::mod_inverse;
+use crate::karatsuba_mul::karatsuba_mul::fast_mul;
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ---------------------------------------------------------------------------
+// cf_sub — Soustraction homomorphique Catalano-Fiore
+//
+//   CF(m, r) - CF(m', r') = CF(m - m', r - r')
+//
+//   c0_res = c0 - c0'          mod n
+//   c1_res = c1 · c1'^{-1}     mod n²   (Enc(r) · Enc(r')^{-1} = Enc(r - r'))
+//
+// Se déchiffre avec cf_add_dec (Première Forme).
+// ---------------------------------------------------------------------------
+pub fn cf_sub(
+    ciphert0:  &(BigUint, BigUint),
+    ciphert1:  &(BigUint, BigUint),
+    n:         &BigUint,
+    n_squared: &BigUint,
+) -> Result<(BigUint, BigUint), CryptoError> {
+
+    let c0   = &ciphert0.0;
+    let c1   = &ciphert0.1;
+
+    let c0_p = &ciphert1.0;
+    let c1_p = &ciphert1.1;
+
+    if c0 >= n || c0_p >= n {
+        return Err(CryptoError::MessageOutOfRange);
+    }
+
+    // Composante plaintext : soustraction dans Z_n
+    let c0_res = (c0 + n - c0_p) % n;
+
+    // Composante chiffrée : c1 · c1'^{-1} dans Z_{n²}
+    let c1_p_inv = mod_inverse(c1_p, n_squared)?;
+    let c1_res   = fast_mul(c1, &c1_p_inv, n_squared)?;
+
+    Ok((c0_res, c1_res))
+}
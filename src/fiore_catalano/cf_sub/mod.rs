@@ -0,0 +1,2 @@
+pub mod cf_sub;
+pub mod cf_scal;
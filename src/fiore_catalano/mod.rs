@@ -2,6 +2,11 @@ pub mod cf_encrypt;
 pub mod cf_add;
 pub mod cf_mul_dec;
 pub mod cf_mul;
+pub mod cf_mul_mk;
+pub mod cf_kea;
+pub mod cf_codec;
+pub mod cf_batch;
+pub mod cf_sub;
 
 
 //pub use cf_keygen::cf_keygen;
@@ -0,0 +1,57 @@
+use sha2::{Digest, Sha256};
+use crate::paillier::p_keygen::PublicKey;
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ============================================================================
+// Empreinte d'une clé publique Paillier
+//
+// fp = SHA-256("paillier-pk-v1" || len(n) || n || len(g) || g)
+//
+// n_squared n'entre pas dans le calcul : il est entièrement déterminé par n
+// (cohérence vérifiée au chargement, cf. json_to_public_key). Les longueurs
+// sont préfixées (u32 BE) pour qu'aucune paire (n, g) distincte ne produise
+// la même entrée de hachage.
+//
+// Usage : identifier sans ambiguïté la clé sous laquelle un chiffré a été
+// produit (en-tête cf_codec), et vérifier qu'une session reprend avec les
+// mêmes clés.
+// ============================================================================
+
+/// Taille d'une empreinte en octets (SHA-256)
+pub const KEY_FINGERPRINT_LEN: usize = 32;
+
+/// Séparateur de domaine — à incrémenter si la formule change
+const FINGERPRINT_DOMAIN: &[u8] = b"paillier-pk-v1";
+
+pub type KeyFingerprint = [u8; KEY_FINGERPRINT_LEN];
+
+pub fn key_fingerprint(pk: &PublicKey) -> KeyFingerprint {
+    let n = pk.n.to_bytes_be();
+    let g = pk.g.to_bytes_be();
+
+    let mut h = Sha256::new();
+    h.update(FINGERPRINT_DOMAIN);
+    h.update((n.len() as u32).to_be_bytes());
+    h.update(&n);
+    h.update((g.len() as u32).to_be_bytes());
+    h.update(&g);
+    h.finalize().into()
+}
+
+/// Représentation hexadécimale (minuscules) d'une empreinte
+pub fn fingerprint_to_hex(fp: &KeyFingerprint) -> String {
+    fp.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Parse une empreinte hexadécimale de 64 caractères
+pub fn hex_to_fingerprint(hex: &str) -> Result<KeyFingerprint, CryptoError> {
+    if !hex.is_ascii() || hex.len() != 2 * KEY_FINGERPRINT_LEN {
+        return Err(CryptoError::HexParseError);
+    }
+    let mut fp = [0u8; KEY_FINGERPRINT_LEN];
+    for (i, byte) in fp.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16)
+            .map_err(|_| CryptoError::HexParseError)?;
+    }
+    Ok(fp)
+}
@@ -203,7 +203,7 @@ impl KeyRegistry {
         let guard = self.read()?;
         guard.kea
             .as_ref()
-            .map(|kea| f(kea))
+            .map(f)
             .ok_or(RegistryError::NoKeaKey)
     }
 
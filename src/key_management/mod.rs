@@ -1,5 +1,6 @@
 pub mod key_storage;
 pub mod key_registry;
+pub mod key_fingerprint;
 
 // Réexportations key_storage
 pub use key_storage::{
@@ -12,5 +13,11 @@ pub use key_storage::{
     key_file_exists, ensure_keys_directory,
 };
 
+// Réexportations key_fingerprint
+pub use key_fingerprint::{
+    KeyFingerprint, KEY_FINGERPRINT_LEN,
+    key_fingerprint, fingerprint_to_hex, hex_to_fingerprint,
+};
+
 // Réexportations key_registry
 pub use key_registry::{KeyRegistry, RegistryError};
\ No newline at end of file
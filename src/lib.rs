@@ -1,3 +1,6 @@
+// Chaque opération vit dans un dossier homonyme (ex. paillier/p_encrypt/p_encrypt.rs)
+#![allow(clippy::module_inception)]
+
 // Déclaration des modules
 pub mod crypto_error;
 pub mod paillier;
@@ -7,6 +10,9 @@ pub mod fiore_catalano;
 pub mod key_management;
 pub mod paillier_kea;
 pub mod karatsuba_mul;
+pub mod parallel;         // pool rayon des opérations par lots (p_batch, cf_batch)
+pub mod cf_stats;         // statistiques homomorphes (somme, variance, Pearson)
+pub mod records;          // CSV RFC 4180 + clés composites normalisées
 
 pub use crate::paillier::math;
 pub use crate::paillier::p_keygen;
@@ -30,9 +30,8 @@ use paillier_crypto::KeyPair;
 
 // ── Stdlib & crates externes ──────────────────────────────
 
-use rand::Rng;
 use rand_core::OsRng;
-use num_bigint::{BigUint, RandBigInt};
+use num_bigint::RandBigInt;
 use std::io::{self, Write};
 use std::time::Instant;
 
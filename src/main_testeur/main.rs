@@ -78,8 +78,8 @@ fn main() {
 
     // ── Phase 4 — Dechiffrement + comptage ────────────────────────────
     println!("\n=== Phase 4 : Dechiffrement CF et comptage ===");
-    let r1 = phase4_decrypt_and_count("BD1", &agg_bd1, &kp1);
-    let r2 = phase4_decrypt_and_count("BD2", &agg_bd2, &kp2);
+    let r1 = phase4_decrypt_and_count("BD1", &agg_bd1, &kp1).expect("Phase 4 BD1 refusee");
+    let r2 = phase4_decrypt_and_count("BD2", &agg_bd2, &kp2).expect("Phase 4 BD2 refusee");
 
     // ── Resultat final ────────────────────────────────────────────────
     println!("\n╔══════════════════════════════════════════════════════╗");
@@ -8,12 +8,27 @@ pub use net_protocol::{
     encode_biguint, decode_biguint,
     encode_cffst, decode_cffst,
     encode_cfsnd, decode_cfsnd,
+    encode_cf_ciphertext, decode_cf_ciphertext,
+    encode_mkcfsnd, decode_mkcfsnd,
+    encode_keact, decode_keact,
+    encode_cfkeafst, decode_cfkeafst,
+    encode_cfkeasnd, decode_cfkeasnd,
     // Framing socket
     send_msg, recv_msg,
     // Messages haut niveau
-    MsgPubKey, MsgFtBundle, MsgDualBundle, MsgTriplets,
+    MsgPubKey, MsgHashKeyShare, MsgCuckooSize, MsgSetSize, MsgFtBundle, MsgDualBundle, MsgTriplets, MsgPosTriplets,
+    MsgMaskPairs, MsgSndSum, MsgThresholdResult, MsgThresholdCmp, MsgDpShare, MsgDpResult,
+    MsgPartyInfo, MsgMpShares, MsgMpBundle,
+    MsgClkParams, MsgClkScores,
+    MsgDeltaSession, MsgDelta, MsgCtList, MsgDeltaResult,
+    MsgResume, MsgResumePlan,
+    MsgCardinalCommit, MsgCardinal,
+    MsgSetOps, MsgSetOpsResult,
+    MsgMultiset,
+    MsgMkQuads, MsgMkRelay, MsgMkShare,
+    MsgKeaDelta, MsgKeaFtBundle, MsgKeaDualBundle, MsgKeaTriplets,
     // Helpers instrumentés
     send_tracked, recv_tracked,
     // Mesure bande passante
-    BandwidthMeter, PhaseMetric,
+    BandwidthMeter, PhaseMetric, PaddingMetric,
 };
\ No newline at end of file
@@ -10,19 +10,105 @@
 //
 // Types de messages :
 //   MsgPubKey       Phase 0  BD → Serveur  : clé publique (n, g, n²)
+//   MsgHashKeyShare Phase 0d BD → Serveur → autre BD : Enc_other(part de clé HMAC)
 //   MsgBundle       Phase 2  BD → Serveur  : DualFtBundle sérialisé
 //   MsgTriplets     Phase 3  Serveur → BD  : Vec<CfSnd>
-//   MsgCardinal     Phase 4  BD → Serveur  : usize (résultat)
+//   MsgCardinalCommit Phase 5 BD → Serveur → autre BD : engagement SHA-256 sur le cardinal
+//   MsgCardinal     Phase 5  BD → Serveur → autre BD : Enc_other(cardinal ‖ aléa)
+//                                            (standard, --reveal, --kea, --checkpoint)
+//
+// Variante multi-clés (--multikey) :
+//   MsgFtBundle     Phase 2  BD → Serveur  : FtBundle sous pk_self uniquement
+//   MsgMkQuads      Phase 3  Serveur → BD1 : Vec<MkCfSnd>
+//   MsgMkRelay      Phase 4  BD1 → Serveur → BD2 : (A2, Enc2(part1))
+//   MsgMkShare      Phase 4  BD2 → Serveur → BD1 : Enc1(part2)
+//
+// Variante KEA (--kea, contrôle d'image ; ct_delta connu du serveur) :
+//   MsgKeaDelta     Phase 0  BD ↔ Serveur  : ct_delta = (Enc(1), Enc(ξ))
+//   MsgKeaDualBundle Phase 2 BD → Serveur  : DualKeaFtBundle sérialisé
+//   MsgKeaTriplets  Phase 3  Serveur → BD  : Vec<CfKeaSnd>
+//
+// Variante à seuil (--threshold t) :
+//   MsgMaskPairs    Phase 3  Serveur → BD  : masques (C1_i, C2_i) mélangés
+//   MsgMkShare      Phase 4  BD → Serveur  : Enc(Σ b_i b'_i)
+//   MsgThresholdResult Phase 4 Serveur → BD : (t, Enc(z)), z = c - t + 2^ℓ + r
+//   MsgCtList       Phase 4  BD → Serveur  : Enc des bits de 2(z mod 2^ℓ) + 1
+//   MsgThresholdCmp Phase 4  Serveur → BD  : chiffrés DGK aveuglés + indice h
+//
+// Variante PSI-Sum (--sum) :
+//   MsgSndSum       Phase 3  Serveur → BD  : Σ v agrégée (α, masques
+//                                            re-randomisés, mélangés)
+//
+// Variante différentiellement privée (--dp ε) :
+//   MsgMaskPairs    Phase 3  Serveur → BD  : masques (C1_i, C2_i) mélangés
+//   MsgDpShare      Phase 4  BD → Serveur  : (ε, Enc(Σ b_i b'_i), part de bruit sous pk1 et pk2)
+//   MsgDpResult     Phase 4  Serveur → BD  : (ε, Enc(c + bruit))
+//
+// Recouvrement (--similarity, mode --minhash) :
+//   MsgSetSize      Phase 0e BD → Serveur → autre BD : |table| (en clair ou
+//                                            sous pk_other), k MinHash
+//   MsgDualBundle   Phase 2  BD → Serveur  : Ft des k composantes (--minhash)
+//   MsgFtBundle     Phase 3  Serveur → BD  : r·(Ft1_i - Ft2_i) mélangés (--minhash)
+//
+// Variante incrémentale (--delta) :
+//   MsgDeltaSession Phase 0c Serveur → BD  : (session, époque, création ?)
+//   MsgDelta        Phase 2  BD → Serveur  : positions retirées + Ft des ajoutées
+//   MsgMaskPairs    Phase 3  Serveur → BD  : masques des nouveaux produits
+//   MsgCtList       Phase 3  BD → Serveur  : Enc(b·b') par paire
+//   MsgDeltaResult  Phase 4  Serveur → BD  : (époque, Enc(c))
+//
+// Reprise sur incident (--checkpoint) :
+//   MsgResume       Phase 0c BD → Serveur  : id de la session locale (0 = aucune)
+//   MsgResumePlan   Phase 0c Serveur → BD  : (session, nouvelle ?, bundle attendu ?)
+//
+// Opérations ensemblistes (--setops, avec --size-hiding --pad-to P) :
+//   MsgSetOps       Phase 0e BD → Serveur  : sorties choisies (masque) ;
+//                   Serveur → BD : les siennes, comparées par chaque BD
+//   MsgMaskPairs    Phase 3  Serveur → BD  : P masques (C1_i, C2_i)
+//                   re-randomisés, mélangés
+//   MsgSetOpsResult Phase 3  Serveur → BD  : un α_o par sortie choisie
+//
+// Multiensembles (--multiset) :
+//   MsgMultiset     Phase 0e BD → Serveur → autre BD : sémantique (min /
+//                                            product) et multiplicité maximale
+//
+// Variante PSI complète (--reveal) :
+//   MsgPosTriplets  Phase 3  Serveur → BD  : Vec<(position, CfSnd)>
+//
+// Variante N parties (mp_server / mp_client, une connexion par BD) :
+//   MsgPubKey       Phase 0a BD → Serveur  : inscription (pk)
+//   MsgPartyInfo    Phase 0b Serveur → BD  : identifiant + les N pk
+//   MsgMpShares     Phase 0d BD ↔ Serveur  : parts de clé HMAC (par destinataire)
+//   MsgMpBundle     Phase 2  BD → Serveur  : Ft sous les N clés
+//   MsgTriplets     Phase 3  Serveur → BD  : produits aveuglés (rechiffrement)
+//                                            puis produits finaux
+//   MsgFtBundle     Phase 3  BD → Serveur  : CF.Enc frais (index = rang)
+//
+// Variante cuckoo (--cuckoo) :
+//   MsgCuckooSize   Phase 0e BD → Serveur → autre BD : taille de l'ensemble, k, stash
+//   MsgDualBundle   Phase 2  BD → Serveur  : Ft indexés par numéro de slot
+//   MsgFtBundle     Phase 3  Serveur → BD  : r·(Ft1 - Ft2) mélangés (index = rang)
+//
+// Variante approximative (--fuzzy) :
+//   MsgClkParams    Phase 0e BD → Serveur → autre BD : nombre d'enregistrements, L, k
+//   MsgDualBundle   Phase 2  BD → Serveur  : Ft des bits CLK (index = enregistrement·L + bit)
+//   MsgClkScores    Phase 3  Serveur → BD  : (Σ cf_mul, |a| + |b|) par paire, mélangés
 //
 // Mesure de bande passante :
 //   BandwidthMeter accumule les octets envoyés/reçus avec horodatage.
 //   Un rapport final est imprimé à la fin du protocole.
+//   En mode --size-hiding, record_padding() y ajoute le coût du bourrage
+//   (entrées réelles / entrées envoyées, octets dus aux entrées factices).
 // =========================================================
 
 use std::io::{self, Read, Write};
 use std::net::TcpStream;
 use std::time::{Duration, Instant};
 use num_bigint::BigUint;
+use crate::fiore_catalano::cf_mul_mk::cf_mul_mk::MkCfSnd;
+use crate::fiore_catalano::cf_kea::{KeaCt, CfKeaFst, CfKeaSnd};
+use crate::fiore_catalano::cf_codec::CfCiphertext;
+use crate::cf_stats::CfSndSum;
 
 // ─────────────────────────────────────────────────────────
 // Encodage / décodage d'un BigUint en bytes big-endian
@@ -79,6 +165,89 @@ pub fn decode_cfsnd<R: Read>(r: &mut R) -> io::Result<(BigUint, BigUint, BigUint
     Ok((c0, c1, c2))
 }
 
+/// Encode un CfCiphertext auto-descriptif : [u32 BE longueur][format v1 cf_codec]
+/// Composantes à largeur fixe : plus compact que encode_cffst / encode_cfsnd.
+pub fn encode_cf_ciphertext(c: &CfCiphertext) -> Vec<u8> {
+    let body = c.to_bytes();
+    let mut out = Vec::with_capacity(4 + body.len());
+    out.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    out.extend_from_slice(&body);
+    out
+}
+
+/// Décode un CfCiphertext (contrôles structurels ; valider ensuite avec la clé)
+pub fn decode_cf_ciphertext<R: Read>(r: &mut R) -> io::Result<CfCiphertext> {
+    let mut len_buf = [0u8; 4];
+    r.read_exact(&mut len_buf)?;
+    let len = u32::from_be_bytes(len_buf) as usize;
+    let mut bytes = vec![0u8; len];
+    r.read_exact(&mut bytes)?;
+    CfCiphertext::from_bytes(&bytes)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))
+}
+
+/// Encode un MkCfSnd (α1, α2, β1, β2)
+pub fn encode_mkcfsnd(c: &MkCfSnd) -> Vec<u8> {
+    let mut out = encode_biguint(&c.alpha1);
+    out.extend(encode_biguint(&c.alpha2));
+    out.extend(encode_biguint(&c.beta1));
+    out.extend(encode_biguint(&c.beta2));
+    out
+}
+
+/// Décode un MkCfSnd
+pub fn decode_mkcfsnd<R: Read>(r: &mut R) -> io::Result<MkCfSnd> {
+    let alpha1 = decode_biguint(r)?;
+    let alpha2 = decode_biguint(r)?;
+    let beta1  = decode_biguint(r)?;
+    let beta2  = decode_biguint(r)?;
+    Ok(MkCfSnd { alpha1, alpha2, beta1, beta2 })
+}
+
+/// Encode un chiffré KEA (Enc(x), Enc(ξ·x))
+pub fn encode_keact(c: &KeaCt) -> Vec<u8> {
+    let mut out = encode_biguint(&c.0);
+    out.extend(encode_biguint(&c.1));
+    out
+}
+
+/// Décode un chiffré KEA
+pub fn decode_keact<R: Read>(r: &mut R) -> io::Result<KeaCt> {
+    let c0 = decode_biguint(r)?;
+    let c1 = decode_biguint(r)?;
+    Ok((c0, c1))
+}
+
+/// Encode un CfKeaFst = (a, (β0, β1))
+pub fn encode_cfkeafst(c: &CfKeaFst) -> Vec<u8> {
+    let mut out = encode_biguint(&c.0);
+    out.extend(encode_keact(&c.1));
+    out
+}
+
+/// Décode un CfKeaFst
+pub fn decode_cfkeafst<R: Read>(r: &mut R) -> io::Result<CfKeaFst> {
+    let a    = decode_biguint(r)?;
+    let beta = decode_keact(r)?;
+    Ok((a, beta))
+}
+
+/// Encode un CfKeaSnd = trois chiffrés KEA
+pub fn encode_cfkeasnd(c: &CfKeaSnd) -> Vec<u8> {
+    let mut out = encode_keact(&c.0);
+    out.extend(encode_keact(&c.1));
+    out.extend(encode_keact(&c.2));
+    out
+}
+
+/// Décode un CfKeaSnd
+pub fn decode_cfkeasnd<R: Read>(r: &mut R) -> io::Result<CfKeaSnd> {
+    let c0 = decode_keact(r)?;
+    let c1 = decode_keact(r)?;
+    let c2 = decode_keact(r)?;
+    Ok((c0, c1, c2))
+}
+
 // ─────────────────────────────────────────────────────────
 // Framing : envoi/réception d'un message avec en-tête 4 octets
 // ─────────────────────────────────────────────────────────
@@ -204,6 +373,162 @@ impl MsgDualBundle {
     }
 }
 
+/// Phase 3 (seuil) : paires de masques chiffrés (C1_i, C2_i)
+pub struct MsgMaskPairs {
+    pub pairs: Vec<(BigUint, BigUint)>,
+}
+
+impl MsgMaskPairs {
+    pub fn encode(&self) -> Vec<u8> {
+        let count = self.pairs.len() as u32;
+        let mut out = count.to_be_bytes().to_vec();
+        for p in &self.pairs {
+            out.extend(encode_cffst(p));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut pairs = Vec::with_capacity(count);
+        for _ in 0..count {
+            pairs.push(decode_cffst(&mut cur)?);
+        }
+        Ok(MsgMaskPairs { pairs })
+    }
+}
+
+/// Phase 3 (--sum) : somme de produits en Seconde Forme (α, masques)
+pub struct MsgSndSum {
+    pub sum: CfSndSum,
+}
+
+impl MsgSndSum {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = encode_biguint(&self.sum.alpha);
+        out.extend(MsgMaskPairs { pairs: self.sum.betas.clone() }.encode());
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let alpha = decode_biguint(&mut cur)?;
+        let rest  = &buf[cur.position() as usize..];
+        Ok(MsgSndSum { sum: CfSndSum { alpha, betas: MsgMaskPairs::decode(rest)?.pairs } })
+    }
+}
+
+/// Phase 4 (seuil) : seuil appliqué par le serveur + Enc(z) masqué
+pub struct MsgThresholdResult {
+    pub threshold: u64,
+    pub z:         BigUint,
+}
+
+impl MsgThresholdResult {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.threshold.to_be_bytes().to_vec();
+        out.extend(encode_biguint(&self.z));
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut t_buf = [0u8; 8];
+        io::Read::read_exact(&mut cur, &mut t_buf)?;
+        let z = decode_biguint(&mut cur)?;
+        Ok(MsgThresholdResult { threshold: u64::from_be_bytes(t_buf), z })
+    }
+}
+
+/// Phase 4 (seuil) : chiffrés de la comparaison bit à bit, mélangés,
+/// et indice h (bit de poids ℓ du masque ⊕ signe tiré par le serveur)
+pub struct MsgThresholdCmp {
+    pub hint: bool,
+    pub cts:  Vec<BigUint>,
+}
+
+impl MsgThresholdCmp {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![self.hint as u8];
+        out.extend((self.cts.len() as u32).to_be_bytes());
+        for ct in &self.cts {
+            out.extend(encode_biguint(ct));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut head = [0u8; 5];
+        io::Read::read_exact(&mut cur, &mut head)?;
+        if head[0] > 1 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "MsgThresholdCmp : indice hors de {0, 1}"));
+        }
+        let count = u32::from_be_bytes([head[1], head[2], head[3], head[4]]) as usize;
+        let mut cts = Vec::with_capacity(count.min(buf.len() / 4));
+        for _ in 0..count {
+            cts.push(decode_biguint(&mut cur)?);
+        }
+        Ok(MsgThresholdCmp { hint: head[0] == 1, cts })
+    }
+}
+
+/// Phase 4 (--dp) : ε annoncé, Enc_self(Σ masques), part de bruit
+/// chiffrée sous pk1 et sous pk2
+pub struct MsgDpShare {
+    pub epsilon:  f64,
+    pub mask_sum: BigUint,
+    pub noise1:   BigUint,
+    pub noise2:   BigUint,
+}
+
+impl MsgDpShare {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.epsilon.to_bits().to_be_bytes().to_vec();
+        for x in [&self.mask_sum, &self.noise1, &self.noise2] {
+            out.extend(encode_biguint(x));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut e_buf = [0u8; 8];
+        io::Read::read_exact(&mut cur, &mut e_buf)?;
+        Ok(MsgDpShare {
+            epsilon:  f64::from_bits(u64::from_be_bytes(e_buf)),
+            mask_sum: decode_biguint(&mut cur)?,
+            noise1:   decode_biguint(&mut cur)?,
+            noise2:   decode_biguint(&mut cur)?,
+        })
+    }
+}
+
+/// Phase 4 (--dp) : ε appliqué par le serveur + Enc(c + bruit)
+pub struct MsgDpResult {
+    pub epsilon: f64,
+    pub noisy:   BigUint,
+}
+
+impl MsgDpResult {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.epsilon.to_bits().to_be_bytes().to_vec();
+        out.extend(encode_biguint(&self.noisy));
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut e_buf = [0u8; 8];
+        io::Read::read_exact(&mut cur, &mut e_buf)?;
+        let noisy = decode_biguint(&mut cur)?;
+        Ok(MsgDpResult { epsilon: f64::from_bits(u64::from_be_bytes(e_buf)), noisy })
+    }
+}
+
 /// Phase 3 : liste de CfSnd = Vec<(BigUint,BigUint,BigUint)>
 pub struct MsgTriplets {
     pub triplets: Vec<(BigUint, BigUint, BigUint)>,
@@ -232,113 +557,892 @@ impl MsgTriplets {
     }
 }
 
-// ─────────────────────────────────────────────────────────
-// BandwidthMeter — compteur de bande passante par phase
-// ─────────────────────────────────────────────────────────
-
-#[derive(Debug, Clone)]
-pub struct PhaseMetric {
-    pub name:        String,
-    pub bytes_sent:  usize,
-    pub bytes_recv:  usize,
-    pub duration:    Duration,
+/// Phase 3 (multi-clés) : liste de MkCfSnd, Serveur → BD1
+pub struct MsgMkQuads {
+    pub quads: Vec<MkCfSnd>,
 }
 
-pub struct BandwidthMeter {
-    pub metrics:     Vec<PhaseMetric>,
-    phase_start:     Instant,
-    current_phase:   String,
-    cur_sent:        usize,
-    cur_recv:        usize,
-}
+impl MsgMkQuads {
+    pub fn encode(&self) -> Vec<u8> {
+        let count = self.quads.len() as u32;
+        let mut out = count.to_be_bytes().to_vec();
+        for q in &self.quads {
+            out.extend(encode_mkcfsnd(q));
+        }
+        out
+    }
 
-impl BandwidthMeter {
-    pub fn new() -> Self {
-        BandwidthMeter {
-            metrics:       Vec::new(),
-            phase_start:   Instant::now(),
-            current_phase: String::from("init"),
-            cur_sent:      0,
-            cur_recv:      0,
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut quads = Vec::with_capacity(count);
+        for _ in 0..count {
+            quads.push(decode_mkcfsnd(&mut cur)?);
         }
+        Ok(MsgMkQuads { quads })
     }
+}
 
-    /// Démarre une nouvelle phase de mesure.
-    pub fn begin(&mut self, phase: &str) {
-        self.phase_start   = Instant::now();
-        self.current_phase = phase.to_string();
-        self.cur_sent      = 0;
-        self.cur_recv      = 0;
+/// Phase 4 (multi-clés) : BD1 → Serveur → BD2
+/// aggregate = A2 (sous pk2), share = Enc2(part1)
+pub struct MsgMkRelay {
+    pub aggregate: BigUint,
+    pub share:     BigUint,
+}
+
+impl MsgMkRelay {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = encode_biguint(&self.aggregate);
+        out.extend(encode_biguint(&self.share));
+        out
     }
 
-    /// Enregistre N octets envoyés dans la phase courante.
-    pub fn add_sent(&mut self, n: usize) {
-        self.cur_sent += n;
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let aggregate = decode_biguint(&mut cur)?;
+        let share     = decode_biguint(&mut cur)?;
+        Ok(MsgMkRelay { aggregate, share })
     }
+}
 
-    /// Enregistre N octets reçus dans la phase courante.
-    pub fn add_recv(&mut self, n: usize) {
-        self.cur_recv += n;
+/// Phase 4 (multi-clés) : BD2 → Serveur → BD1, share = Enc1(part2)
+pub struct MsgMkShare {
+    pub share: BigUint,
+}
+
+impl MsgMkShare {
+    pub fn encode(&self) -> Vec<u8> {
+        encode_biguint(&self.share)
     }
 
-    /// Clôt la phase courante et enregistre la métrique.
-    pub fn end(&mut self) {
-        self.metrics.push(PhaseMetric {
-            name:       self.current_phase.clone(),
-            bytes_sent: self.cur_sent,
-            bytes_recv: self.cur_recv,
-            duration:   self.phase_start.elapsed(),
-        });
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let share = decode_biguint(&mut cur)?;
+        Ok(MsgMkShare { share })
     }
+}
 
-    /// Affiche le rapport complet de bande passante.
-    pub fn report(&self) {
-        let total_sent: usize = self.metrics.iter().map(|m| m.bytes_sent).sum();
-        let total_recv: usize = self.metrics.iter().map(|m| m.bytes_recv).sum();
-        let total_dur:  Duration = self.metrics.iter().map(|m| m.duration).sum();
+/// Phase 0d : part de la clé de hachage des positions, chiffrée sous la pk
+/// du destinataire. table_bits et psk_id circulent en clair : le serveur et
+/// l'autre BD vérifient que les deux BD utilisent la même configuration.
+pub struct MsgHashKeyShare {
+    pub share:      BigUint,
+    pub table_bits: u8,
+    pub psk_id:     u64,
+}
 
-        println!("\n╔══════════════════════════════════════════════════════════════════╗");
-        println!("║            RAPPORT BANDE PASSANTE — PSI ExactMatch               ║");
-        println!("╠═══════════════════════╦══════════════╦══════════════╦════════════╣");
-        println!("║ Phase                 ║   Envoyés    ║    Reçus     ║   Durée    ║");
-        println!("╠═══════════════════════╬══════════════╬══════════════╬════════════╣");
+impl MsgHashKeyShare {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![self.table_bits];
+        out.extend_from_slice(&self.psk_id.to_be_bytes());
+        out.extend(encode_biguint(&self.share));
+        out
+    }
 
-        for m in &self.metrics {
-            println!(
-                "║ {:<21} ║ {:>9} o   ║ {:>9} o   ║ {:>8.3?} ║",
-                m.name,
-                m.bytes_sent,
-                m.bytes_recv,
-                m.duration
-            );
-        }
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut bits_buf = [0u8; 1];
+        io::Read::read_exact(&mut cur, &mut bits_buf)?;
+        let mut psk_buf = [0u8; 8];
+        io::Read::read_exact(&mut cur, &mut psk_buf)?;
+        let share = decode_biguint(&mut cur)?;
+        Ok(MsgHashKeyShare {
+            share,
+            table_bits: bits_buf[0],
+            psk_id:     u64::from_be_bytes(psk_buf),
+        })
+    }
+}
 
-        println!("╠═══════════════════════╬══════════════╬══════════════╬════════════╣");
-        println!(
-            "║ {:<21} ║ {:>9} o   ║ {:>9} o   ║ {:>8.3?} ║",
-            "TOTAL",
-            total_sent,
-            total_recv,
-            total_dur
-        );
-        println!("╠═══════════════════════╩══════════════╩══════════════╩════════════╣");
-        println!(
-            "║  Total échangé  : {:>8} Ko  ({:.2} Mo)                              ║",
-            (total_sent + total_recv) / 1024,
-            (total_sent + total_recv) as f64 / (1024.0 * 1024.0)
-        );
+/// Phase 0e (--similarity / --minhash) : taille de l'ensemble, en clair ou
+/// chiffrée sous la pk du destinataire (hidden) ; minhash_k = 0 hors
+/// mode --minhash. Le serveur vérifie k sans lire une taille cachée.
+pub struct MsgSetSize {
+    pub size:      BigUint,
+    pub hidden:    bool,
+    pub minhash_k: u32,
+}
 
-        // Débit moyen (évite la division par zéro)
-        let secs = total_dur.as_secs_f64();
-        if secs > 0.0 {
-            let throughput_kb = (total_sent + total_recv) as f64 / 1024.0 / secs;
-            println!(
-                "║  Débit moyen    : {:>8.1} Ko/s                                       ║",
-                throughput_kb
-            );
-        }
-        println!("╚══════════════════════════════════════════════════════════════════╝\n");
+impl MsgSetSize {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![self.hidden as u8];
+        out.extend_from_slice(&self.minhash_k.to_be_bytes());
+        out.extend(encode_biguint(&self.size));
+        out
     }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut flag = [0u8; 1];
+        io::Read::read_exact(&mut cur, &mut flag)?;
+        let mut k_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut k_buf)?;
+        let size = decode_biguint(&mut cur)?;
+        Ok(MsgSetSize { size, hidden: flag[0] != 0, minhash_k: u32::from_be_bytes(k_buf) })
+    }
+}
+
+/// Phase 0c (--delta) : session annoncée par le serveur ; fresh = état
+/// créé à cette exécution (la Phase 0d suit)
+pub struct MsgDeltaSession {
+    pub session_id: u64,
+    pub epoch:      u64,
+    pub fresh:      bool,
+}
+
+impl MsgDeltaSession {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.session_id.to_be_bytes().to_vec();
+        out.extend_from_slice(&self.epoch.to_be_bytes());
+        out.push(self.fresh as u8);
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut id_buf = [0u8; 8];
+        io::Read::read_exact(&mut cur, &mut id_buf)?;
+        let mut epoch_buf = [0u8; 8];
+        io::Read::read_exact(&mut cur, &mut epoch_buf)?;
+        let mut flag = [0u8; 1];
+        io::Read::read_exact(&mut cur, &mut flag)?;
+        Ok(MsgDeltaSession {
+            session_id: u64::from_be_bytes(id_buf),
+            epoch:      u64::from_be_bytes(epoch_buf),
+            fresh:      flag[0] != 0,
+        })
+    }
+}
+
+/// Phase 5 : engagement sur le cardinal, relayé tel quel à l'autre BD
+pub struct MsgCardinalCommit {
+    pub commitment: [u8; 32],
+}
+
+impl MsgCardinalCommit {
+    pub fn encode(&self) -> Vec<u8> {
+        self.commitment.to_vec()
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let commitment: [u8; 32] = buf.try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "MsgCardinalCommit : 32 octets attendus"))?;
+        Ok(MsgCardinalCommit { commitment })
+    }
+}
+
+/// Phase 5 : ouverture de l'engagement, chiffrée sous la pk de l'autre BD
+pub struct MsgCardinal {
+    pub sealed: BigUint,
+}
+
+impl MsgCardinal {
+    pub fn encode(&self) -> Vec<u8> {
+        encode_biguint(&self.sealed)
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let sealed = decode_biguint(&mut cur)?;
+        Ok(MsgCardinal { sealed })
+    }
+}
+
+/// Phase 0e (--setops) : sorties choisies, masque de SetOps
+pub struct MsgSetOps {
+    pub ops: u8,
+}
+
+impl MsgSetOps {
+    pub fn encode(&self) -> Vec<u8> {
+        vec![self.ops]
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        match buf {
+            [ops] => Ok(MsgSetOps { ops: *ops }),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "MsgSetOps : 1 octet attendu")),
+        }
+    }
+}
+
+/// Phase 3 (--setops) : sorties appliquées par le serveur et leurs α_o
+/// chiffrés, dans l'ordre de SetOp::ALL
+pub struct MsgSetOpsResult {
+    pub ops:    u8,
+    pub alphas: Vec<BigUint>,
+}
+
+impl MsgSetOpsResult {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![self.ops];
+        out.extend_from_slice(&(self.alphas.len() as u32).to_be_bytes());
+        for a in &self.alphas {
+            out.extend(encode_biguint(a));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut ops = [0u8; 1];
+        io::Read::read_exact(&mut cur, &mut ops)?;
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut alphas = Vec::with_capacity(count.min(8));
+        for _ in 0..count {
+            alphas.push(decode_biguint(&mut cur)?);
+        }
+        Ok(MsgSetOpsResult { ops: ops[0], alphas })
+    }
+}
+
+/// Phase 0e (--multiset) : sémantique (MultisetSemantics::code) et borne
+/// des multiplicités, relayées telles quelles à l'autre BD
+pub struct MsgMultiset {
+    pub semantics: u8,
+    pub max_count: u32,
+}
+
+impl MsgMultiset {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![self.semantics];
+        out.extend_from_slice(&self.max_count.to_be_bytes());
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        match buf {
+            [semantics, m @ ..] if m.len() == 4 => Ok(MsgMultiset {
+                semantics: *semantics,
+                max_count: u32::from_be_bytes(m.try_into().expect("4 octets")),
+            }),
+            _ => Err(io::Error::new(io::ErrorKind::InvalidData, "MsgMultiset : 5 octets attendus")),
+        }
+    }
+}
+
+/// Phase 0c (--checkpoint) : session que le BD peut reprendre (0 = aucune)
+pub struct MsgResume {
+    pub session_id: u64,
+}
+
+impl MsgResume {
+    pub fn encode(&self) -> Vec<u8> {
+        self.session_id.to_be_bytes().to_vec()
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let id: [u8; 8] = buf.try_into()
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "MsgResume : 8 octets attendus"))?;
+        Ok(MsgResume { session_id: u64::from_be_bytes(id) })
+    }
+}
+
+/// Phase 0c (--checkpoint) : décision du serveur ; fresh = nouvelle session
+/// (la Phase 0d suit), send_bundle = le serveur n'a pas le bundle de ce BD
+pub struct MsgResumePlan {
+    pub session_id:  u64,
+    pub fresh:       bool,
+    pub send_bundle: bool,
+}
+
+impl MsgResumePlan {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.session_id.to_be_bytes().to_vec();
+        out.push(self.fresh as u8);
+        out.push(self.send_bundle as u8);
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        if buf.len() != 10 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "MsgResumePlan : 10 octets attendus"));
+        }
+        Ok(MsgResumePlan {
+            session_id:  u64::from_be_bytes(buf[..8].try_into().expect("8 octets")),
+            fresh:       buf[8] != 0,
+            send_bundle: buf[9] != 0,
+        })
+    }
+}
+
+/// Phase 2 (--delta) : positions retirées puis Ft des positions ajoutées
+pub struct MsgDelta {
+    pub removed: Vec<usize>,
+    pub added:   MsgDualBundle,
+}
+
+impl MsgDelta {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = (self.removed.len() as u32).to_be_bytes().to_vec();
+        for &pos in &self.removed {
+            out.extend_from_slice(&(pos as u64).to_be_bytes());
+        }
+        out.extend(self.added.encode());
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut removed = Vec::with_capacity(count.min(buf.len() / 8));
+        let mut pos_buf = [0u8; 8];
+        for _ in 0..count {
+            io::Read::read_exact(&mut cur, &mut pos_buf)?;
+            removed.push(u64::from_be_bytes(pos_buf) as usize);
+        }
+        let added = MsgDualBundle::decode(&buf[cur.position() as usize..])?;
+        Ok(MsgDelta { removed, added })
+    }
+}
+
+/// Phase 3 (--delta) : liste de chiffrés Paillier
+pub struct MsgCtList {
+    pub cts: Vec<BigUint>,
+}
+
+impl MsgCtList {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = (self.cts.len() as u32).to_be_bytes().to_vec();
+        for ct in &self.cts {
+            out.extend(encode_biguint(ct));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut cts = Vec::with_capacity(count.min(buf.len() / 4));
+        for _ in 0..count {
+            cts.push(decode_biguint(&mut cur)?);
+        }
+        Ok(MsgCtList { cts })
+    }
+}
+
+/// Phase 4 (--delta) : époque atteinte + Enc(c) courant
+pub struct MsgDeltaResult {
+    pub epoch: u64,
+    pub total: BigUint,
+}
+
+impl MsgDeltaResult {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.epoch.to_be_bytes().to_vec();
+        out.extend(encode_biguint(&self.total));
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut epoch_buf = [0u8; 8];
+        io::Read::read_exact(&mut cur, &mut epoch_buf)?;
+        let total = decode_biguint(&mut cur)?;
+        Ok(MsgDeltaResult { epoch: u64::from_be_bytes(epoch_buf), total })
+    }
+}
+
+/// Phase 3 (--reveal) : triplets accompagnés de leur position
+pub struct MsgPosTriplets {
+    pub entries: Vec<(usize, (BigUint, BigUint, BigUint))>,
+}
+
+impl MsgPosTriplets {
+    pub fn encode(&self) -> Vec<u8> {
+        let count = self.entries.len() as u32;
+        let mut out = count.to_be_bytes().to_vec();
+        for (pos, t) in &self.entries {
+            out.extend_from_slice(&(*pos as u64).to_be_bytes());
+            out.extend(encode_cfsnd(t));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut entries = Vec::with_capacity(count);
+        let mut pos_buf = [0u8; 8];
+        for _ in 0..count {
+            io::Read::read_exact(&mut cur, &mut pos_buf)?;
+            entries.push((u64::from_be_bytes(pos_buf) as usize, decode_cfsnd(&mut cur)?));
+        }
+        Ok(MsgPosTriplets { entries })
+    }
+}
+
+/// Phase 0e (cuckoo) : nombre d'éléments distincts de la BD et paramètres
+/// (k, stash), qui doivent être identiques des deux côtés.
+pub struct MsgCuckooSize {
+    pub size:   u64,
+    pub hashes: u8,
+    pub stash:  u8,
+}
+
+impl MsgCuckooSize {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.size.to_be_bytes().to_vec();
+        out.push(self.hashes);
+        out.push(self.stash);
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        if buf.len() != 10 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "MsgCuckooSize : longueur invalide"));
+        }
+        let mut size_buf = [0u8; 8];
+        size_buf.copy_from_slice(&buf[..8]);
+        Ok(MsgCuckooSize {
+            size:   u64::from_be_bytes(size_buf),
+            hashes: buf[8],
+            stash:  buf[9],
+        })
+    }
+}
+
+/// Phase 0e (fuzzy) : nombre d'enregistrements et paramètres CLK (L, k),
+/// ces derniers identiques des deux côtés.
+pub struct MsgClkParams {
+    pub records: u64,
+    pub bits:    u32,
+    pub hashes:  u8,
+}
+
+impl MsgClkParams {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.records.to_be_bytes().to_vec();
+        out.extend_from_slice(&self.bits.to_be_bytes());
+        out.push(self.hashes);
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        if buf.len() != 13 {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "MsgClkParams : longueur invalide"));
+        }
+        let mut records_buf = [0u8; 8];
+        records_buf.copy_from_slice(&buf[..8]);
+        let mut bits_buf = [0u8; 4];
+        bits_buf.copy_from_slice(&buf[8..12]);
+        Ok(MsgClkParams {
+            records: u64::from_be_bytes(records_buf),
+            bits:    u32::from_be_bytes(bits_buf),
+            hashes:  buf[12],
+        })
+    }
+}
+
+/// Phase 3 (fuzzy) : score chiffré de chaque paire — (α, [(C1, C2)…]) + Ft(|a| + |b|)
+pub struct MsgClkScores {
+    pub scores: Vec<(CfSndSum, (BigUint, BigUint))>,
+}
+
+impl MsgClkScores {
+    pub fn encode(&self) -> Vec<u8> {
+        let count = self.scores.len() as u32;
+        let mut out = count.to_be_bytes().to_vec();
+        for (inner, weight) in &self.scores {
+            out.extend(encode_biguint(&inner.alpha));
+            out.extend_from_slice(&(inner.betas.len() as u32).to_be_bytes());
+            for beta in &inner.betas {
+                out.extend(encode_cffst(beta));
+            }
+            out.extend(encode_cffst(weight));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut scores = Vec::with_capacity(count);
+        for _ in 0..count {
+            let alpha = decode_biguint(&mut cur)?;
+            io::Read::read_exact(&mut cur, &mut count_buf)?;
+            let n_betas = u32::from_be_bytes(count_buf) as usize;
+            let mut betas = Vec::with_capacity(n_betas);
+            for _ in 0..n_betas {
+                betas.push(decode_cffst(&mut cur)?);
+            }
+            scores.push((CfSndSum { alpha, betas }, decode_cffst(&mut cur)?));
+        }
+        Ok(MsgClkScores { scores })
+    }
+}
+
+/// Phase 0b (N parties) : identifiant attribué à l'inscription + les N pk
+pub struct MsgPartyInfo {
+    pub party_id: u32,
+    pub pks:      Vec<MsgPubKey>,
+}
+
+impl MsgPartyInfo {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = self.party_id.to_be_bytes().to_vec();
+        out.extend_from_slice(&(self.pks.len() as u32).to_be_bytes());
+        for pk in &self.pks {
+            out.extend(pk.encode());
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut u32_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut u32_buf)?;
+        let party_id = u32::from_be_bytes(u32_buf);
+        io::Read::read_exact(&mut cur, &mut u32_buf)?;
+        let count = u32::from_be_bytes(u32_buf) as usize;
+        let mut pks = Vec::with_capacity(count);
+        for _ in 0..count {
+            let n         = decode_biguint(&mut cur)?;
+            let g         = decode_biguint(&mut cur)?;
+            let n_squared = decode_biguint(&mut cur)?;
+            pks.push(MsgPubKey { n, g, n_squared });
+        }
+        Ok(MsgPartyInfo { party_id, pks })
+    }
+}
+
+/// Phase 0d (N parties) : parts de clé HMAC chiffrées, avec l'identifiant
+/// de l'autre partie (destinataire à l'aller, émetteur au retour).
+pub struct MsgMpShares {
+    pub table_bits: u8,
+    pub psk_id:     u64,
+    pub shares:     Vec<(u32, BigUint)>,
+}
+
+impl MsgMpShares {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = vec![self.table_bits];
+        out.extend_from_slice(&self.psk_id.to_be_bytes());
+        out.extend_from_slice(&(self.shares.len() as u32).to_be_bytes());
+        for (id, ct) in &self.shares {
+            out.extend_from_slice(&id.to_be_bytes());
+            out.extend(encode_biguint(ct));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut bits_buf = [0u8; 1];
+        io::Read::read_exact(&mut cur, &mut bits_buf)?;
+        let mut psk_buf = [0u8; 8];
+        io::Read::read_exact(&mut cur, &mut psk_buf)?;
+        let mut u32_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut u32_buf)?;
+        let count = u32::from_be_bytes(u32_buf) as usize;
+        let mut shares = Vec::with_capacity(count);
+        for _ in 0..count {
+            io::Read::read_exact(&mut cur, &mut u32_buf)?;
+            shares.push((u32::from_be_bytes(u32_buf), decode_biguint(&mut cur)?));
+        }
+        Ok(MsgMpShares { table_bits: bits_buf[0], psk_id: u64::from_be_bytes(psk_buf), shares })
+    }
+}
+
+/// Phase 2 (N parties) : un FtBundle par clé, dans l'ordre des identifiants
+pub struct MsgMpBundle {
+    pub bundles: Vec<MsgFtBundle>,
+}
+
+impl MsgMpBundle {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = (self.bundles.len() as u32).to_be_bytes().to_vec();
+        for b in &self.bundles {
+            let enc = b.encode();
+            out.extend_from_slice(&(enc.len() as u32).to_be_bytes());
+            out.extend(enc);
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut u32_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut u32_buf)?;
+        let count = u32::from_be_bytes(u32_buf) as usize;
+        let mut bundles = Vec::with_capacity(count);
+        for _ in 0..count {
+            io::Read::read_exact(&mut cur, &mut u32_buf)?;
+            let mut part = vec![0u8; u32::from_be_bytes(u32_buf) as usize];
+            io::Read::read_exact(&mut cur, &mut part)?;
+            bundles.push(MsgFtBundle::decode(&part)?);
+        }
+        Ok(MsgMpBundle { bundles })
+    }
+}
+
+/// Phase 0 (KEA) : ct_delta = (Enc(1), Enc(ξ)) publié avec pk
+pub struct MsgKeaDelta {
+    pub ct_delta: KeaCt,
+}
+
+impl MsgKeaDelta {
+    pub fn encode(&self) -> Vec<u8> {
+        encode_keact(&self.ct_delta)
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let ct_delta = decode_keact(&mut cur)?;
+        Ok(MsgKeaDelta { ct_delta })
+    }
+}
+
+/// Phase 2 (KEA) : liste de (position, CfKeaFst)
+pub struct MsgKeaFtBundle {
+    pub entries: Vec<(usize, CfKeaFst)>,
+}
+
+impl MsgKeaFtBundle {
+    pub fn encode(&self) -> Vec<u8> {
+        let count = self.entries.len() as u32;
+        let mut out = count.to_be_bytes().to_vec();
+        for (pos, ft) in &self.entries {
+            out.extend_from_slice(&(*pos as u64).to_be_bytes());
+            out.extend(encode_cfkeafst(ft));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            let mut pos_buf = [0u8; 8];
+            io::Read::read_exact(&mut cur, &mut pos_buf)?;
+            let pos = u64::from_be_bytes(pos_buf) as usize;
+            entries.push((pos, decode_cfkeafst(&mut cur)?));
+        }
+        Ok(MsgKeaFtBundle { entries })
+    }
+}
+
+/// Phase 2 (KEA) : bundle sous pk1 + bundle sous pk2
+pub struct MsgKeaDualBundle {
+    pub under_pk1: MsgKeaFtBundle,
+    pub under_pk2: MsgKeaFtBundle,
+}
+
+impl MsgKeaDualBundle {
+    pub fn encode(&self) -> Vec<u8> {
+        let enc1 = self.under_pk1.encode();
+        let enc2 = self.under_pk2.encode();
+        let mut out = (enc1.len() as u32).to_be_bytes().to_vec();
+        out.extend(enc1);
+        out.extend((enc2.len() as u32).to_be_bytes());
+        out.extend(enc2);
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut len_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut len_buf)?;
+        let mut b1 = vec![0u8; u32::from_be_bytes(len_buf) as usize];
+        io::Read::read_exact(&mut cur, &mut b1)?;
+        let under_pk1 = MsgKeaFtBundle::decode(&b1)?;
+
+        io::Read::read_exact(&mut cur, &mut len_buf)?;
+        let mut b2 = vec![0u8; u32::from_be_bytes(len_buf) as usize];
+        io::Read::read_exact(&mut cur, &mut b2)?;
+        let under_pk2 = MsgKeaFtBundle::decode(&b2)?;
+
+        Ok(MsgKeaDualBundle { under_pk1, under_pk2 })
+    }
+}
+
+/// Phase 3 (KEA) : liste de CfKeaSnd
+pub struct MsgKeaTriplets {
+    pub triplets: Vec<CfKeaSnd>,
+}
+
+impl MsgKeaTriplets {
+    pub fn encode(&self) -> Vec<u8> {
+        let count = self.triplets.len() as u32;
+        let mut out = count.to_be_bytes().to_vec();
+        for t in &self.triplets {
+            out.extend(encode_cfkeasnd(t));
+        }
+        out
+    }
+
+    pub fn decode(buf: &[u8]) -> io::Result<Self> {
+        let mut cur = io::Cursor::new(buf);
+        let mut count_buf = [0u8; 4];
+        io::Read::read_exact(&mut cur, &mut count_buf)?;
+        let count = u32::from_be_bytes(count_buf) as usize;
+        let mut triplets = Vec::with_capacity(count);
+        for _ in 0..count {
+            triplets.push(decode_cfkeasnd(&mut cur)?);
+        }
+        Ok(MsgKeaTriplets { triplets })
+    }
+}
+
+// ─────────────────────────────────────────────────────────
+// BandwidthMeter — compteur de bande passante par phase
+// ─────────────────────────────────────────────────────────
+
+#[derive(Debug, Clone)]
+pub struct PhaseMetric {
+    pub name:        String,
+    pub bytes_sent:  usize,
+    pub bytes_recv:  usize,
+    pub duration:    Duration,
+}
+
+/// Bourrage d'une phase : `real` entrées utiles sur `padded` envoyées
+#[derive(Debug, Clone)]
+pub struct PaddingMetric {
+    pub phase:  String,
+    pub real:   usize,
+    pub padded: usize,
+}
+
+pub struct BandwidthMeter {
+    pub metrics:     Vec<PhaseMetric>,
+    pub padding:     Vec<PaddingMetric>,
+    phase_start:     Instant,
+    current_phase:   String,
+    cur_sent:        usize,
+    cur_recv:        usize,
+}
+
+impl BandwidthMeter {
+    pub fn new() -> Self {
+        BandwidthMeter {
+            metrics:       Vec::new(),
+            padding:       Vec::new(),
+            phase_start:   Instant::now(),
+            current_phase: String::from("init"),
+            cur_sent:      0,
+            cur_recv:      0,
+        }
+    }
+
+    /// Démarre une nouvelle phase de mesure.
+    pub fn begin(&mut self, phase: &str) {
+        self.phase_start   = Instant::now();
+        self.current_phase = phase.to_string();
+        self.cur_sent      = 0;
+        self.cur_recv      = 0;
+    }
+
+    /// Enregistre N octets envoyés dans la phase courante.
+    pub fn add_sent(&mut self, n: usize) {
+        self.cur_sent += n;
+    }
+
+    /// Enregistre N octets reçus dans la phase courante.
+    pub fn add_recv(&mut self, n: usize) {
+        self.cur_recv += n;
+    }
+
+    /// Clôt la phase courante et enregistre la métrique.
+    pub fn end(&mut self) {
+        self.metrics.push(PhaseMetric {
+            name:       self.current_phase.clone(),
+            bytes_sent: self.cur_sent,
+            bytes_recv: self.cur_recv,
+            duration:   self.phase_start.elapsed(),
+        });
+    }
+
+    /// Signale que la phase `phase` transporte `real` entrées utiles
+    /// complétées à `padded` (mode size-hiding).
+    pub fn record_padding(&mut self, phase: &str, real: usize, padded: usize) {
+        self.padding.push(PaddingMetric { phase: phase.to_string(), real, padded });
+    }
+
+    /// Affiche le rapport complet de bande passante.
+    pub fn report(&self) {
+        let total_sent: usize = self.metrics.iter().map(|m| m.bytes_sent).sum();
+        let total_recv: usize = self.metrics.iter().map(|m| m.bytes_recv).sum();
+        let total_dur:  Duration = self.metrics.iter().map(|m| m.duration).sum();
+
+        println!("\n╔══════════════════════════════════════════════════════════════════╗");
+        println!("║            RAPPORT BANDE PASSANTE — PSI ExactMatch               ║");
+        println!("╠═══════════════════════╦══════════════╦══════════════╦════════════╣");
+        println!("║ Phase                 ║   Envoyés    ║    Reçus     ║   Durée    ║");
+        println!("╠═══════════════════════╬══════════════╬══════════════╬════════════╣");
+
+        for m in &self.metrics {
+            println!(
+                "║ {:<21} ║ {:>9} o   ║ {:>9} o   ║ {:>8.3?} ║",
+                m.name,
+                m.bytes_sent,
+                m.bytes_recv,
+                m.duration
+            );
+        }
+
+        println!("╠═══════════════════════╬══════════════╬══════════════╬════════════╣");
+        println!(
+            "║ {:<21} ║ {:>9} o   ║ {:>9} o   ║ {:>8.3?} ║",
+            "TOTAL",
+            total_sent,
+            total_recv,
+            total_dur
+        );
+        println!("╠═══════════════════════╩══════════════╩══════════════╩════════════╣");
+        println!(
+            "║  Total échangé  : {:>8} Ko  ({:.2} Mo)                              ║",
+            (total_sent + total_recv) / 1024,
+            (total_sent + total_recv) as f64 / (1024.0 * 1024.0)
+        );
+
+        // Débit moyen (évite la division par zéro)
+        let secs = total_dur.as_secs_f64();
+        if secs > 0.0 {
+            let throughput_kb = (total_sent + total_recv) as f64 / 1024.0 / secs;
+            println!(
+                "║  Débit moyen    : {:>8.1} Ko/s                                       ║",
+                throughput_kb
+            );
+        }
+
+        // Coût du mode size-hiding : part des octets due aux entrées factices
+        // (au prorata des entrées, la taille d'un Ft étant constante)
+        for p in &self.padding {
+            let bytes: usize = self.metrics.iter()
+                .filter(|m| m.name == p.phase)
+                .map(|m| m.bytes_sent + m.bytes_recv)
+                .sum();
+            let dummy = p.padded.saturating_sub(p.real);
+            let extra = bytes as f64 * dummy as f64 / p.padded.max(1) as f64;
+            println!("╠══════════════════════════════════════════════════════════════════╣");
+            println!("║  Bourrage       : {}", p.phase);
+            println!(
+                "║    {} réelles / {} envoyées (x{:.1}), surcoût ≈ {:.1} Ko",
+                p.real, p.padded, p.padded as f64 / p.real.max(1) as f64, extra / 1024.0
+            );
+        }
+        println!("╚══════════════════════════════════════════════════════════════════╝\n");
+    }
+}
+
+impl Default for BandwidthMeter {
+    fn default() -> Self { Self::new() }
 }
 
 // ─────────────────────────────────────────────────────────
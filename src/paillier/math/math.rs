@@ -157,7 +157,7 @@ fn combined_sieve(sophie_germain: &BigUint) -> bool {
 
         // 2p'+1 divisible par sp → rejeter
         // (2*r + 1) % sp == 0  (pas de débordement : r < 3000, 2r+1 < 6001, tient en u64)
-        if (2 * r + 1) % sp == 0 {
+        if (2 * r + 1).is_multiple_of(sp) {
             return true;
         }
     }
@@ -0,0 +1 @@
+pub mod p_batch;
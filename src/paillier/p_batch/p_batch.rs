@@ -0,0 +1,31 @@
+use num_bigint::BigUint;
+use rayon::prelude::*;
+use crate::paillier::p_encrypt::p_encrypt::p_encrypt;
+use crate::paillier::p_decrypt::p_decrypt::p_decrypt;
+use crate::paillier::p_keygen::p_keygen::SecretKey;
+use crate::paillier::p_keygen::PublicKey;
+use crate::parallel::BatchPool;
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ---------------------------------------------------------------------------
+// Chiffrement / déchiffrement Paillier par lots
+//
+// out[i] = p_encrypt(messages[i]) / p_decrypt(ciphers[i]), dans l'ordre.
+// La première erreur rencontrée est propagée.
+// ---------------------------------------------------------------------------
+pub fn p_encrypt_batch(
+    messages: &[BigUint],
+    pk:       &PublicKey,
+    pool:     &BatchPool,
+) -> Result<Vec<BigUint>, CryptoError> {
+    pool.install(|| messages.par_iter().map(|m| p_encrypt(m, pk)).collect())
+}
+
+pub fn p_decrypt_batch(
+    ciphers: &[BigUint],
+    pk:      &PublicKey,
+    sk:      &SecretKey,
+    pool:    &BatchPool,
+) -> Result<Vec<BigUint>, CryptoError> {
+    pool.install(|| ciphers.par_iter().map(|c| p_decrypt(c, pk, sk)).collect())
+}
@@ -20,7 +20,7 @@ pub struct PublicKey {
 fn zeroize_biguint(n: &mut BigUint) {
     let bits = n.bits() as usize;
     if bits > 0 {
-        *n = BigUint::from_bytes_be(&vec![0u8; (bits + 7) / 8]);
+        *n = BigUint::from_bytes_be(&vec![0u8; bits.div_ceil(8)]);
     }
     *n = BigUint::default();
 }
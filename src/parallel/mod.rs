@@ -0,0 +1,3 @@
+pub mod parallel;
+
+pub use parallel::{BatchPool, default_pool, set_default_threads, THREADS_ENV_VAR};
@@ -0,0 +1,76 @@
+use std::sync::OnceLock;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use crate::crypto_error::crypto_error::CryptoError;
+
+// ============================================================================
+// Pool de threads pour les opérations par lots (p_batch, cf_batch)
+//
+// Chaque opération par lot reçoit un &BatchPool explicite et exécute son
+// par_iter() à l'intérieur de pool.install(...). Les résultats sont collectés
+// dans l'ORDRE des entrées (itérateurs indexés rayon) : le parallélisme ne
+// change donc jamais la sortie du protocole.
+//
+// Nombre de threads :
+//   - BatchPool::new(n)           : explicite (0 = un thread par cœur)
+//   - default_pool()              : pool partagé du processus, utilisé par
+//                                   les phases ExactMatch ; taille fixée par
+//                                   set_default_threads(n) avant le premier
+//                                   appel, sinon par PSI_THREADS, sinon 0.
+// ============================================================================
+
+/// Variable d'environnement lue par default_pool()
+pub const THREADS_ENV_VAR: &str = "PSI_THREADS";
+
+pub struct BatchPool {
+    pool: ThreadPool,
+}
+
+impl BatchPool {
+    pub fn new(threads: usize) -> Result<Self, CryptoError> {
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|i| format!("psi-batch-{}", i))
+            .build()
+            .map_err(|e| CryptoError::InvalidInput(format!("pool de threads : {}", e)))?;
+        Ok(BatchPool { pool })
+    }
+
+    pub fn threads(&self) -> usize {
+        self.pool.current_num_threads()
+    }
+
+    /// Exécute op dans le pool (les par_iter internes l'utilisent)
+    pub fn install<R: Send>(&self, op: impl FnOnce() -> R + Send) -> R {
+        self.pool.install(op)
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Pool par défaut du processus
+// ---------------------------------------------------------------------------
+
+static DEFAULT_POOL:    OnceLock<BatchPool> = OnceLock::new();
+static DEFAULT_THREADS: AtomicUsize         = AtomicUsize::new(usize::MAX);
+
+/// Fixe la taille du pool par défaut. Sans effet une fois le pool créé :
+/// retourne false dans ce cas.
+pub fn set_default_threads(threads: usize) -> bool {
+    DEFAULT_THREADS.store(threads, Ordering::SeqCst);
+    DEFAULT_POOL.get().is_none()
+}
+
+pub fn default_pool() -> &'static BatchPool {
+    DEFAULT_POOL.get_or_init(|| {
+        let threads = match DEFAULT_THREADS.load(Ordering::SeqCst) {
+            usize::MAX => std::env::var(THREADS_ENV_VAR)
+                .ok()
+                .and_then(|v| v.trim().parse().ok())
+                .unwrap_or(0),
+            n => n,
+        };
+        BatchPool::new(threads)
+            .or_else(|_| BatchPool::new(0))
+            .expect("impossible de créer le pool de threads")
+    })
+}
@@ -0,0 +1,19 @@
+pub mod records;
+pub mod normalize;
+pub mod synth;
+
+// Réexportations records
+pub use records::{
+    RecordError, RejectedRow, CsvRecord, CsvTable, LoadedRecords,
+    parse_csv, derive_keys, load_records, verify_hash_column,
+    KeyStream, REJECTED_KEEP, stream_keys,
+};
+
+// Réexportations normalize
+pub use normalize::{
+    KeyConfig, KeyField, NormRule, DEFAULT_KEY_SEPARATOR,
+    normalize, strip_accents, sha256_hex,
+};
+
+// Réexportations synth
+pub use synth::{SynthConfig, SynthData, SynthRow, Identity, TruthPair, generate, nss_hash};
@@ -0,0 +1,332 @@
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::fs;
+use crate::records::records::RecordError;
+
+// ============================================================================
+// Règles de normalisation et configuration des clés composites
+//
+// Fichier JSON (--key-config) :
+//   {
+//     "separator": "|",
+//     "fields": [
+//       { "column": "NSS", "rules": [{ "rule": "digits_only" }] },
+//       { "column": "date_naissance",
+//         "rules": [{ "rule": "date", "formats": ["DD/MM/YYYY", "YYYY-MM-DD"] }] },
+//       { "column": "nom",
+//         "rules": [{ "rule": "strip_accents" }, { "rule": "lowercase" }] }
+//     ]
+//   }
+//
+// Les règles s'appliquent dans l'ordre, après un trim implicite. Les deux
+// BD doivent utiliser la même configuration : la clé dérivée est la chaîne
+// hachée en Phase 1. Le séparateur par défaut (U+001F) ne peut pas
+// apparaître dans un champ et évite « ab|c » = « a|bc ».
+// ============================================================================
+
+/// Séparateur par défaut entre composantes (Unit Separator)
+pub const DEFAULT_KEY_SEPARATOR: &str = "\u{1f}";
+
+/// Taille maximale d'un fichier de configuration (64 Ko)
+const MAX_CONFIG_BYTES: u64 = 65_536;
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(tag = "rule", rename_all = "snake_case")]
+pub enum NormRule {
+    /// Espaces de bord (déjà implicite, explicite pour la lisibilité)
+    Trim,
+    Lowercase,
+    Uppercase,
+    /// « Élodie Müller » -> « Elodie Muller », œ -> oe, ß -> ss
+    StripAccents,
+    /// Suites d'espaces réduites à un seul espace
+    CollapseSpaces,
+    /// Chiffres uniquement (« 1 80 04 » -> « 18004 »)
+    DigitsOnly,
+    /// Lettres et chiffres uniquement
+    AlphanumOnly,
+    /// SHA-256 de la valeur, en hexadécimal minuscule : même clé qu'une
+    /// colonne HASH(NSS) précalculée
+    Sha256,
+    /// Empreinte déjà calculée : 64 chiffres hexadécimaux, mis en
+    /// minuscules (sinon la ligne est rejetée)
+    Sha256Hex,
+    /// Date lue selon le premier format qui convient (jetons DD, MM,
+    /// YYYY, YY ; autres caractères littéraux), réécrite selon `output`
+    /// (défaut YYYY-MM-DD). YY : 00-49 -> 20xx, 50-99 -> 19xx.
+    Date {
+        formats: Vec<String>,
+        #[serde(default = "default_date_output")]
+        output:  String,
+    },
+}
+
+fn default_date_output() -> String {
+    "YYYY-MM-DD".to_string()
+}
+
+fn default_separator() -> String {
+    DEFAULT_KEY_SEPARATOR.to_string()
+}
+
+/// Une composante de la clé
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KeyField {
+    pub column: String,
+    #[serde(default)]
+    pub rules:  Vec<NormRule>,
+    /// Composante vide acceptée (sinon la ligne est rejetée)
+    #[serde(default)]
+    pub optional: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct KeyConfig {
+    #[serde(default = "default_separator")]
+    pub separator: String,
+    pub fields:    Vec<KeyField>,
+}
+
+impl KeyConfig {
+    /// Comportement historique : colonne NSS seule, sans espaces de bord
+    pub fn nss() -> Self {
+        Self::columns(&["NSS"])
+    }
+
+    /// --input hash : colonne HASH(NSS) seule (identifiants pseudonymisés)
+    pub fn nss_hash() -> Self {
+        let mut cfg = Self::columns(&["HASH(NSS)"]);
+        cfg.fields[0].rules.push(NormRule::Sha256Hex);
+        cfg
+    }
+
+    /// --input verify : SHA-256(NSS) recalculé, même clé que nss_hash()
+    pub fn nss_sha256() -> Self {
+        let mut cfg = Self::nss();
+        cfg.fields[0].rules.push(NormRule::Sha256);
+        cfg
+    }
+
+    /// Colonnes brutes (trim seulement), dans l'ordre donné
+    pub fn columns(cols: &[&str]) -> Self {
+        KeyConfig {
+            separator: default_separator(),
+            fields: cols.iter()
+                .map(|c| KeyField { column: c.to_string(), rules: Vec::new(), optional: false })
+                .collect(),
+        }
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, RecordError> {
+        let cfg: KeyConfig = serde_json::from_str(text).map_err(|e| RecordError::Config(e.to_string()))?;
+        if cfg.fields.is_empty() {
+            return Err(RecordError::Config("aucune composante de clé".into()));
+        }
+        for f in &cfg.fields {
+            for r in &f.rules {
+                if let NormRule::Date { formats, output } = r {
+                    if formats.is_empty() {
+                        return Err(RecordError::Config(format!("{} : règle date sans format", f.column)));
+                    }
+                    for fmt in formats.iter().chain(std::iter::once(output)) {
+                        check_date_format(fmt)?;
+                    }
+                }
+            }
+        }
+        Ok(cfg)
+    }
+
+    pub fn from_json_file(path: &str) -> Result<Self, RecordError> {
+        let io_err = |e: std::io::Error| RecordError::Io { path: path.to_string(), msg: e.to_string() };
+        if fs::metadata(path).map_err(io_err)?.len() > MAX_CONFIG_BYTES {
+            return Err(RecordError::Config(format!("{} dépasse {} octets", path, MAX_CONFIG_BYTES)));
+        }
+        Self::from_json(&fs::read_to_string(path).map_err(io_err)?)
+    }
+
+    /// Colonnes lues, dans l'ordre des composantes
+    pub fn column_names(&self) -> Vec<&str> {
+        self.fields.iter().map(|f| f.column.as_str()).collect()
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Application des règles
+// ---------------------------------------------------------------------------
+
+/// Err(raison) si une règle ne s'applique pas (date invalide)
+pub fn normalize(value: &str, rules: &[NormRule]) -> Result<String, String> {
+    let mut v = value.trim().to_string();
+    for rule in rules {
+        v = match rule {
+            NormRule::Trim           => v.trim().to_string(),
+            NormRule::Lowercase      => v.to_lowercase(),
+            NormRule::Uppercase      => v.to_uppercase(),
+            NormRule::StripAccents   => strip_accents(&v),
+            NormRule::CollapseSpaces => v.split_whitespace().collect::<Vec<_>>().join(" "),
+            NormRule::DigitsOnly     => v.chars().filter(char::is_ascii_digit).collect(),
+            NormRule::AlphanumOnly   => v.chars().filter(|c| c.is_alphanumeric()).collect(),
+            NormRule::Sha256         => if v.is_empty() { v } else { sha256_hex(&v) },
+            NormRule::Sha256Hex      => {
+                if !v.is_empty() && (v.len() != 64 || !v.bytes().all(|b| b.is_ascii_hexdigit())) {
+                    return Err(format!("empreinte « {} » : 64 chiffres hexadécimaux attendus", v));
+                }
+                v.to_ascii_lowercase()
+            }
+            NormRule::Date { formats, output } => {
+                if v.is_empty() {
+                    v
+                } else {
+                    let (y, m, d) = formats.iter()
+                        .find_map(|f| parse_date(&v, f))
+                        .ok_or_else(|| format!("date « {} » invalide", v))?;
+                    format_date(y, m, d, output)
+                }
+            }
+        };
+    }
+    Ok(v)
+}
+
+/// SHA-256 en hexadécimal minuscule (format des colonnes HASH(NSS))
+pub fn sha256_hex(value: &str) -> String {
+    Sha256::digest(value.as_bytes()).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Diacritiques latins courants (pas de dépendance Unicode complète)
+pub fn strip_accents(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        let rep = match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' => "a",
+            'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' | 'Ā' => "A",
+            'ç' | 'ć' | 'č' => "c",
+            'Ç' | 'Ć' | 'Č' => "C",
+            'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ę' | 'ě' => "e",
+            'È' | 'É' | 'Ê' | 'Ë' | 'Ē' | 'Ę' | 'Ě' => "E",
+            'ì' | 'í' | 'î' | 'ï' | 'ī' => "i",
+            'Ì' | 'Í' | 'Î' | 'Ï' | 'Ī' => "I",
+            'ñ' | 'ń' | 'ň' => "n",
+            'Ñ' | 'Ń' | 'Ň' => "N",
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' => "o",
+            'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' | 'Ō' => "O",
+            'ù' | 'ú' | 'û' | 'ü' | 'ū' | 'ů' => "u",
+            'Ù' | 'Ú' | 'Û' | 'Ü' | 'Ū' | 'Ů' => "U",
+            'ý' | 'ÿ' => "y",
+            'Ý' | 'Ÿ' => "Y",
+            'š' | 'ś' => "s",
+            'Š' | 'Ś' => "S",
+            'ž' | 'ź' | 'ż' => "z",
+            'Ž' | 'Ź' | 'Ż' => "Z",
+            'ł' => "l",
+            'Ł' => "L",
+            'œ' => "oe",
+            'Œ' => "OE",
+            'æ' => "ae",
+            'Æ' => "AE",
+            'ß' => "ss",
+            _ => {
+                out.push(c);
+                continue;
+            }
+        };
+        out.push_str(rep);
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// Dates : jetons DD, MM, YYYY, YY
+// ---------------------------------------------------------------------------
+
+#[derive(Clone, Copy, PartialEq)]
+enum DateToken {
+    Day,
+    Month,
+    Year4,
+    Year2,
+    Lit(char),
+}
+
+fn tokenize(fmt: &str) -> Vec<DateToken> {
+    let mut out = Vec::new();
+    let mut rest = fmt;
+    while let Some(c) = rest.chars().next() {
+        let (tok, len) = if rest.starts_with("YYYY") {
+            (DateToken::Year4, 4)
+        } else if rest.starts_with("YY") {
+            (DateToken::Year2, 2)
+        } else if rest.starts_with("DD") {
+            (DateToken::Day, 2)
+        } else if rest.starts_with("MM") {
+            (DateToken::Month, 2)
+        } else {
+            (DateToken::Lit(c), c.len_utf8())
+        };
+        out.push(tok);
+        rest = &rest[len..];
+    }
+    out
+}
+
+fn check_date_format(fmt: &str) -> Result<(), RecordError> {
+    let toks = tokenize(fmt);
+    let has = |t: DateToken| toks.contains(&t);
+    if has(DateToken::Day) && has(DateToken::Month) && (has(DateToken::Year4) || has(DateToken::Year2)) {
+        Ok(())
+    } else {
+        Err(RecordError::Config(format!("format de date « {} » : DD, MM et YYYY (ou YY) requis", fmt)))
+    }
+}
+
+/// DD et MM acceptent 1 ou 2 chiffres ; la date doit exister
+fn parse_date(s: &str, fmt: &str) -> Option<(u32, u32, u32)> {
+    let (mut y, mut m, mut d) = (None, None, None);
+    let mut rest = s;
+    for tok in tokenize(fmt) {
+        let take_digits = |rest: &str, min: usize, max: usize| -> Option<(u32, usize)> {
+            let n = rest.chars().take(max).take_while(char::is_ascii_digit).count();
+            if n < min { None } else { Some((rest[..n].parse().ok()?, n)) }
+        };
+        let consumed = match tok {
+            DateToken::Day   => { let (v, n) = take_digits(rest, 1, 2)?; d = Some(v); n }
+            DateToken::Month => { let (v, n) = take_digits(rest, 1, 2)?; m = Some(v); n }
+            DateToken::Year4 => { let (v, n) = take_digits(rest, 4, 4)?; y = Some(v); n }
+            DateToken::Year2 => {
+                let (v, n) = take_digits(rest, 2, 2)?;
+                y = Some(if v < 50 { 2000 + v } else { 1900 + v });
+                n
+            }
+            DateToken::Lit(c) => {
+                if !rest.starts_with(c) {
+                    return None;
+                }
+                c.len_utf8()
+            }
+        };
+        rest = &rest[consumed..];
+    }
+    let (y, m, d) = (y?, m?, d?);
+    let leap = (y % 4 == 0 && y % 100 != 0) || y % 400 == 0;
+    let days = match m {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if leap => 29,
+        2 => 28,
+        _ => return None,
+    };
+    if rest.is_empty() && (1..=days).contains(&d) { Some((y, m, d)) } else { None }
+}
+
+fn format_date(y: u32, m: u32, d: u32, fmt: &str) -> String {
+    tokenize(fmt).into_iter()
+        .map(|t| match t {
+            DateToken::Day    => format!("{:02}", d),
+            DateToken::Month  => format!("{:02}", m),
+            DateToken::Year4  => format!("{:04}", y),
+            DateToken::Year2  => format!("{:02}", y % 100),
+            DateToken::Lit(c) => c.to_string(),
+        })
+        .collect()
+}
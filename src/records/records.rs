@@ -0,0 +1,512 @@
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, BufReader};
+use std::cell::RefCell;
+use std::iter::Peekable;
+use std::path::Path;
+use std::rc::Rc;
+use crate::crypto_error::crypto_error::CryptoError;
+use crate::records::normalize::{KeyConfig, normalize, sha256_hex};
+
+// ============================================================================
+// Chargement des enregistrements — CSV RFC 4180 + clés composites
+//
+// Remplace le découpage naïf sur ',' de load_nss_from_csv :
+//   - champs entre guillemets (virgules, sauts de ligne, "" échappés)
+//   - fins de ligne LF ou CRLF, BOM UTF-8 ignoré
+//   - aucune panique : erreurs fatales (fichier, en-tête, colonne
+//     absente) en RecordError, lignes invalides dans `rejected`
+//
+// Une ligne est rejetée (avec son numéro et la raison) si elle est
+// mal formée, n'a pas le nombre de champs de l'en-tête, ou si une
+// composante de la clé est vide ou invalide après normalisation.
+// ============================================================================
+
+/// Taille maximale d'un fichier d'enregistrements (1 Go) : lecture en mémoire
+const MAX_RECORD_FILE_BYTES: u64 = 1 << 30;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordError {
+    /// Fichier illisible (chemin, message système)
+    Io { path: String, msg: String },
+    /// Fichier vide ou sans en-tête exploitable
+    EmptyFile,
+    /// Colonne demandée absente de l'en-tête
+    MissingColumn(String),
+    /// Fichier de configuration des clés invalide
+    Config(String),
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RecordError::Io { path, msg } =>
+                write!(f, "Impossible de lire {path} : {msg}"),
+            RecordError::EmptyFile =>
+                write!(f, "Fichier CSV vide (en-tête manquant)"),
+            RecordError::MissingColumn(c) =>
+                write!(f, "Colonne '{c}' introuvable dans l'en-tête"),
+            RecordError::Config(msg) =>
+                write!(f, "Configuration des clés invalide : {msg}"),
+        }
+    }
+}
+
+impl std::error::Error for RecordError {}
+
+impl From<RecordError> for CryptoError {
+    fn from(e: RecordError) -> Self {
+        CryptoError::InvalidInput(e.to_string())
+    }
+}
+
+/// Ligne écartée : numéro de ligne (1 = en-tête) et raison
+#[derive(Debug, Clone, PartialEq)]
+pub struct RejectedRow {
+    pub line:   usize,
+    pub reason: String,
+}
+
+// ---------------------------------------------------------------------------
+// Lecteur CSV RFC 4180
+// ---------------------------------------------------------------------------
+
+/// Une ligne logique : numéro de sa première ligne physique + champs
+/// (Err si les guillemets sont mal formés)
+pub type CsvRecord = (usize, Result<Vec<String>, String>);
+
+/// Découpe `text` en enregistrements RFC 4180. Les lignes vides sont ignorées.
+pub fn parse_csv(text: &str) -> Vec<CsvRecord> {
+    let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+    let mut out   = Vec::new();
+    let mut chars = text.chars().peekable();
+    let mut line  = 1;
+    while let Some(rec) = next_record(&mut chars, &mut line) {
+        out.push(rec);
+    }
+    out
+}
+
+/// Enregistrement suivant (lignes vides sautées), None en fin de texte
+fn next_record<I: Iterator<Item = char>>(chars: &mut Peekable<I>, line: &mut usize) -> Option<CsvRecord> {
+    while chars.peek().is_some() {
+        let start = *line;
+        let mut fields = Vec::new();
+        let mut field  = String::new();
+        let mut error: Option<String> = None;
+        let mut quoted = false;      // à l'intérieur d'un champ entre guillemets
+        let mut was_quoted = false;  // le champ courant a commencé par '"'
+
+        loop {
+            match chars.next() {
+                None => {
+                    if quoted {
+                        error.get_or_insert_with(|| "guillemet non refermé en fin de fichier".into());
+                    }
+                    break;
+                }
+                Some('"') if quoted => {
+                    if chars.peek() == Some(&'"') {
+                        chars.next();
+                        field.push('"');
+                    } else {
+                        quoted = false;
+                    }
+                }
+                Some('\n') if quoted => { *line += 1; field.push('\n'); }
+                Some(c) if quoted => field.push(c),
+                Some('"') if field.is_empty() && !was_quoted => { quoted = true; was_quoted = true; }
+                Some('"') => {
+                    error.get_or_insert_with(|| format!("guillemet inattendu dans le champ {}", fields.len() + 1));
+                    field.push('"');
+                }
+                Some(',') => {
+                    fields.push(std::mem::take(&mut field));
+                    was_quoted = false;
+                }
+                Some('\r') if chars.peek() == Some(&'\n') => {}
+                Some('\n') => { *line += 1; break; }
+                Some(c) => {
+                    if was_quoted {
+                        error.get_or_insert_with(|| format!("texte après le guillemet fermant (champ {})", fields.len() + 1));
+                    }
+                    field.push(c);
+                }
+            }
+        }
+        fields.push(field);
+
+        let blank = fields.len() == 1 && fields[0].trim().is_empty() && !was_quoted;
+        if !blank {
+            return Some((start, error.map_or(Ok(fields), Err)));
+        }
+    }
+    None
+}
+
+/// Caractères d'un BufRead, ligne physique par ligne physique ;
+/// une erreur de lecture (ou d'UTF-8) termine le flux et reste dans `error`
+/// (partagé : l'itérateur est enveloppé dans un Peekable)
+struct ReaderChars<R: BufRead> {
+    reader: R,
+    buf:    String,
+    pos:    usize,
+    error:  Rc<RefCell<Option<io::Error>>>,
+}
+
+impl<R: BufRead> Iterator for ReaderChars<R> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        if self.pos >= self.buf.len() {
+            self.buf.clear();
+            self.pos = 0;
+            match self.reader.read_line(&mut self.buf) {
+                Ok(0) => return None,
+                Ok(_) => {}
+                Err(e) => { *self.error.borrow_mut() = Some(e); return None; }
+            }
+        }
+        let c = self.buf[self.pos..].chars().next()?;
+        self.pos += c.len_utf8();
+        Some(c)
+    }
+}
+
+/// Fichier CSV lu : en-tête + lignes bien formées (numéro, champs)
+pub struct CsvTable {
+    pub header:   Vec<String>,
+    pub rows:     Vec<(usize, Vec<String>)>,
+    pub rejected: Vec<RejectedRow>,
+}
+
+impl CsvTable {
+    pub fn parse(text: &str) -> Result<Self, RecordError> {
+        let mut records = parse_csv(text).into_iter();
+        let header: Vec<String> = match records.next() {
+            Some((_, Ok(h))) => h.into_iter().map(|c| c.trim().to_string()).collect(),
+            Some((line, Err(e))) => {
+                return Err(RecordError::Config(format!("en-tête ligne {} mal formé : {}", line, e)));
+            }
+            None => return Err(RecordError::EmptyFile),
+        };
+
+        let mut rows     = Vec::new();
+        let mut rejected = Vec::new();
+        for (line, rec) in records {
+            match rec {
+                Ok(f) if f.len() == header.len() => rows.push((line, f)),
+                Ok(f) => rejected.push(RejectedRow {
+                    line,
+                    reason: format!("{} champ(s), {} attendu(s)", f.len(), header.len()),
+                }),
+                Err(reason) => rejected.push(RejectedRow { line, reason }),
+            }
+        }
+        Ok(CsvTable { header, rows, rejected })
+    }
+
+    pub fn read(path: &str) -> Result<Self, RecordError> {
+        let io_err = |e: std::io::Error| RecordError::Io { path: path.to_string(), msg: e.to_string() };
+        let size = fs::metadata(Path::new(path)).map_err(io_err)?.len();
+        if size > MAX_RECORD_FILE_BYTES {
+            return Err(RecordError::Io {
+                path: path.to_string(),
+                msg:  format!("{} octets, maximum {}", size, MAX_RECORD_FILE_BYTES),
+            });
+        }
+        Self::parse(&fs::read_to_string(path).map_err(io_err)?)
+    }
+
+    pub fn column(&self, name: &str) -> Result<usize, RecordError> {
+        self.header.iter()
+            .position(|c| c == name)
+            .ok_or_else(|| RecordError::MissingColumn(name.to_string()))
+    }
+}
+
+// ---------------------------------------------------------------------------
+// Dérivation des clés
+// ---------------------------------------------------------------------------
+
+/// Enregistrements retenus, dans l'ordre du fichier
+pub struct LoadedRecords {
+    /// Clé composite : composantes normalisées jointes par le séparateur
+    pub keys:     Vec<String>,
+    /// Composantes normalisées de chaque clé
+    pub fields:   Vec<Vec<String>>,
+    /// Colonnes annexes demandées (valeur brute sans espaces de bord)
+    pub extra:    Vec<Vec<String>>,
+    /// Numéro de ligne source de chaque enregistrement
+    pub lines:    Vec<usize>,
+    pub rejected: Vec<RejectedRow>,
+}
+
+impl LoadedRecords {
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    /// Résumé des rejets (les `max` premiers détaillés)
+    pub fn print_rejected(&self, label: &str, max: usize) {
+        if self.rejected.is_empty() {
+            return;
+        }
+        println!("[{}] {} ligne(s) rejetée(s) :", label, self.rejected.len());
+        for r in self.rejected.iter().take(max) {
+            println!("[{}]   ligne {} : {}", label, r.line, r.reason);
+        }
+        if self.rejected.len() > max {
+            println!("[{}]   … et {} autre(s).", label, self.rejected.len() - max);
+        }
+    }
+}
+
+/// Composantes normalisées de la clé d'une ligne, ou raison du rejet
+fn row_key(cfg: &KeyConfig, key_cols: &[usize], row: &[String]) -> Result<Vec<String>, String> {
+    cfg.fields.iter().zip(key_cols)
+        .map(|(f, &i)| {
+            let v = normalize(&row[i], &f.rules).map_err(|e| format!("{} : {}", f.column, e))?;
+            if v.is_empty() && !f.optional {
+                return Err(format!("{} vide", f.column));
+            }
+            Ok(v)
+        })
+        .collect()
+}
+
+/// Applique `cfg` à chaque ligne ; `extra` : colonnes annexes (non vides)
+pub fn derive_keys(table: &CsvTable, cfg: &KeyConfig, extra: &[&str]) -> Result<LoadedRecords, RecordError> {
+    let key_cols: Vec<usize> = cfg.fields.iter().map(|f| table.column(&f.column)).collect::<Result<_, _>>()?;
+    let extra_cols: Vec<usize> = extra.iter().map(|c| table.column(c)).collect::<Result<_, _>>()?;
+
+    let mut out = LoadedRecords {
+        keys: Vec::new(), fields: Vec::new(), extra: Vec::new(), lines: Vec::new(),
+        rejected: table.rejected.clone(),
+    };
+    for (line, row) in &table.rows {
+        let parts = row_key(cfg, &key_cols, row);
+        let extras: Result<Vec<String>, String> = extra.iter().zip(&extra_cols)
+            .map(|(name, &i)| {
+                let v = row[i].trim();
+                if v.is_empty() { Err(format!("{} vide", name)) } else { Ok(v.to_string()) }
+            })
+            .collect();
+
+        match (parts, extras) {
+            (Ok(parts), Ok(extras)) => {
+                out.keys.push(parts.join(&cfg.separator));
+                out.fields.push(parts);
+                out.extra.push(extras);
+                out.lines.push(*line);
+            }
+            (Err(reason), _) | (_, Err(reason)) => out.rejected.push(RejectedRow { line: *line, reason }),
+        }
+    }
+    out.rejected.sort_by_key(|r| r.line);
+    Ok(out)
+}
+
+/// --input verify : recalcule SHA-256(`value_col`) sur chaque ligne de
+/// `table` et le compare à `hash_col` ; les lignes en désaccord sont
+/// retirées de `loaded` et ajoutées à ses rejets. Retourne leur nombre.
+pub fn verify_hash_column(
+    table:     &CsvTable,
+    loaded:    &mut LoadedRecords,
+    value_col: &str,
+    hash_col:  &str,
+) -> Result<usize, RecordError> {
+    let (vi, hi) = (table.column(value_col)?, table.column(hash_col)?);
+    let mismatched: Vec<RejectedRow> = table.rows.iter()
+        .filter(|(_, row)| {
+            let value = row[vi].trim();
+            !value.is_empty() && sha256_hex(value) != row[hi].trim().to_ascii_lowercase()
+        })
+        .map(|(line, _)| RejectedRow { line: *line, reason: format!("{} ≠ SHA-256({})", hash_col, value_col) })
+        .collect();
+
+    let bad: std::collections::HashSet<usize> = mismatched.iter().map(|r| r.line).collect();
+    let keep: Vec<bool> = loaded.lines.iter().map(|l| !bad.contains(l)).collect();
+    let mut it = keep.iter();
+    loaded.keys.retain(|_| *it.next().expect("une ligne par clé"));
+    let mut it = keep.iter();
+    loaded.fields.retain(|_| *it.next().expect("une ligne par clé"));
+    let mut it = keep.iter();
+    loaded.extra.retain(|_| *it.next().expect("une ligne par clé"));
+    loaded.lines.retain(|l| !bad.contains(l));
+    // une ligne déjà rejetée (NSS invalide, etc.) garde sa première raison
+    let already: std::collections::HashSet<usize> = loaded.rejected.iter().map(|r| r.line).collect();
+    loaded.rejected.extend(mismatched.iter().filter(|r| !already.contains(&r.line)).cloned());
+    loaded.rejected.sort_by_key(|r| r.line);
+    Ok(mismatched.len())
+}
+
+/// Lecture + dérivation des clés en une étape
+pub fn load_records(path: &str, cfg: &KeyConfig, extra: &[&str]) -> Result<LoadedRecords, RecordError> {
+    derive_keys(&CsvTable::read(path)?, cfg, extra)
+}
+
+// ---------------------------------------------------------------------------
+// Lecture en flux (--stream) : une ligne logique à la fois
+//
+// Mêmes règles que CsvTable + derive_keys, sans borne de taille de
+// fichier : la mémoire est bornée par la plus longue ligne. Seules les
+// REJECTED_KEEP premières lignes rejetées sont détaillées.
+// ---------------------------------------------------------------------------
+
+/// Rejets détaillés conservés par KeyStream (les suivants sont comptés)
+pub const REJECTED_KEEP: usize = 100;
+
+pub struct KeyStream<R: BufRead> {
+    chars:    Peekable<ReaderChars<R>>,
+    error:    Rc<RefCell<Option<io::Error>>>,
+    line:     usize,
+    path:     String,
+    width:    usize,
+    key_cols: Vec<usize>,
+    cfg:      KeyConfig,
+    /// Clés produites jusqu'ici
+    pub accepted:       usize,
+    pub rejected:       Vec<RejectedRow>,
+    pub rejected_count: usize,
+}
+
+/// Ouvre `path` et lit l'en-tête ; les clés sont dérivées à la demande
+pub fn stream_keys(path: &str, cfg: &KeyConfig) -> Result<KeyStream<BufReader<fs::File>>, RecordError> {
+    let file = fs::File::open(Path::new(path))
+        .map_err(|e| RecordError::Io { path: path.to_string(), msg: e.to_string() })?;
+    let error = Rc::new(RefCell::new(None));
+    let mut chars = ReaderChars {
+        reader: BufReader::new(file), buf: String::new(), pos: 0, error: Rc::clone(&error),
+    }.peekable();
+    if chars.peek() == Some(&'\u{feff}') {
+        chars.next();
+    }
+    let mut line = 1;
+    let header: Vec<String> = match next_record(&mut chars, &mut line) {
+        Some((_, Ok(h))) => h.into_iter().map(|c| c.trim().to_string()).collect(),
+        Some((line, Err(e))) => {
+            return Err(RecordError::Config(format!("en-tête ligne {} mal formé : {}", line, e)));
+        }
+        None => return Err(RecordError::EmptyFile),
+    };
+    let key_cols = cfg.fields.iter()
+        .map(|f| header.iter().position(|c| *c == f.column).ok_or_else(|| RecordError::MissingColumn(f.column.clone())))
+        .collect::<Result<_, _>>()?;
+    if let Some(e) = error.borrow_mut().take() {
+        return Err(RecordError::Io { path: path.to_string(), msg: e.to_string() });
+    }
+    Ok(KeyStream {
+        chars, error, line, path: path.to_string(), width: header.len(), key_cols, cfg: cfg.clone(),
+        accepted: 0, rejected: Vec::new(), rejected_count: 0,
+    })
+}
+
+impl<R: BufRead> KeyStream<R> {
+    fn reject(&mut self, line: usize, reason: String) {
+        self.rejected_count += 1;
+        if self.rejected.len() < REJECTED_KEEP {
+            self.rejected.push(RejectedRow { line, reason });
+        }
+    }
+
+    /// Résumé des rejets (les `max` premiers détaillés)
+    pub fn print_rejected(&self, label: &str, max: usize) {
+        if self.rejected_count == 0 {
+            return;
+        }
+        println!("[{}] {} ligne(s) rejetée(s) :", label, self.rejected_count);
+        for r in self.rejected.iter().take(max) {
+            println!("[{}]   ligne {} : {}", label, r.line, r.reason);
+        }
+        if self.rejected_count > max {
+            println!("[{}]   … et {} autre(s).", label, self.rejected_count - max.min(self.rejected.len()));
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for KeyStream<R> {
+    type Item = Result<String, RecordError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let Some((line, rec)) = next_record(&mut self.chars, &mut self.line) else {
+                let e = self.error.borrow_mut().take()?;
+                return Some(Err(RecordError::Io { path: self.path.clone(), msg: e.to_string() }));
+            };
+            match rec {
+                Ok(f) if f.len() == self.width => match row_key(&self.cfg, &self.key_cols, &f) {
+                    Ok(parts) => {
+                        self.accepted += 1;
+                        return Some(Ok(parts.join(&self.cfg.separator)));
+                    }
+                    Err(reason) => self.reject(line, reason),
+                },
+                Ok(f) => {
+                    let reason = format!("{} champ(s), {} attendu(s)", f.len(), self.width);
+                    self.reject(line, reason);
+                }
+                Err(reason) => self.reject(line, reason),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc4180_and_composite_keys() {
+        let text = "\u{feff}id,nom,NSS,date\r\n\
+                    1,\"Dupont, Jean\",123 456,12/04/1980\r\n\
+                    2,\"Le \"\"Grand\"\"\nRené\",789,1980-13-01\r\n\
+                    3,Martin,,01/01/1990\n\
+                    4,trop,de,champs,ici\n\
+                    \n\
+                    5,\"Élodie\",42,3/7/2001\n";
+        let table = CsvTable::parse(text).unwrap();
+        assert_eq!(table.header, vec!["id", "nom", "NSS", "date"]);
+        assert_eq!(table.rows.len(), 4);
+        assert_eq!(table.rows[1].1[1], "Le \"Grand\"\nRené");
+        assert_eq!(table.rejected, vec![RejectedRow { line: 6, reason: "5 champ(s), 4 attendu(s)".into() }]);
+
+        let cfg = KeyConfig::from_json(r#"{
+            "separator": "|",
+            "fields": [
+                { "column": "nom",  "rules": [{ "rule": "strip_accents" }, { "rule": "lowercase" }, { "rule": "collapse_spaces" }] },
+                { "column": "NSS",  "rules": [{ "rule": "digits_only" }] },
+                { "column": "date", "rules": [{ "rule": "date", "formats": ["DD/MM/YYYY", "YYYY-MM-DD"] }] }
+            ]
+        }"#).unwrap();
+        let recs = derive_keys(&table, &cfg, &["id"]).unwrap();
+        assert_eq!(recs.keys, vec!["dupont, jean|123456|1980-04-12", "elodie|42|2001-07-03"]);
+        assert_eq!(recs.extra, vec![vec!["1".to_string()], vec!["5".to_string()]]);
+        assert_eq!(recs.lines, vec![2, 8]);
+        let lines: Vec<usize> = recs.rejected.iter().map(|r| r.line).collect();
+        assert_eq!(lines, vec![3, 5, 6]);
+
+        assert_eq!(parse_csv("a,\"b\nc")[0].1, Err("guillemet non refermé en fin de fichier".into()));
+        assert!(matches!(derive_keys(&table, &KeyConfig::nss(), &["absente"]), Err(RecordError::MissingColumn(_))));
+    }
+
+    #[test]
+    fn test_hash_column_input_and_verify() {
+        // HASH(NSS) précalculé, ou vérifié contre SHA-256(NSS)
+        let h42 = sha256_hex("42");
+        let table = CsvTable::parse(&format!(
+            "NSS,HASH(NSS)\n42,{}\n43,{}\n44,pas-hex\n", h42.to_uppercase(), h42
+        )).unwrap();
+        let hashed = derive_keys(&table, &KeyConfig::nss_hash(), &[]).unwrap();
+        assert_eq!(hashed.keys, vec![h42.clone(), h42.clone()]);
+        assert_eq!(hashed.rejected.iter().map(|r| r.line).collect::<Vec<_>>(), vec![4]);
+        let mut verified = derive_keys(&table, &KeyConfig::nss_sha256(), &[]).unwrap();
+        assert_eq!(verify_hash_column(&table, &mut verified, "NSS", "HASH(NSS)").unwrap(), 2);
+        assert_eq!(verified.keys, vec![h42]);
+        assert_eq!(verified.rejected.iter().map(|r| r.line).collect::<Vec<_>>(), vec![3, 4]);
+    }
+}
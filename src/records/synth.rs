@@ -0,0 +1,380 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use crate::records::normalize::sha256_hex;
+use crate::records::records::RecordError;
+
+// ============================================================================
+// Jeux de données synthétiques — paires de CSV pour les bancs d'essai PSI
+//
+// Même schéma que « src/base de donnes » : identification,NSS,HASH(NSS)
+// (HASH = SHA-256 hexadécimal du NSS). Entièrement déterminé par la graine.
+//
+//   size_a, size_b : NSS distincts de chaque base
+//   overlap        : entités communes aux deux bases
+//   typo_rate      : part des entités communes dont le NSS de B porte une
+//                    faute de frappe (un chiffre remplacé, ou deux chiffres
+//                    voisins inversés) — paires à retrouver en --fuzzy,
+//                    absentes de l'intersection exacte
+//   dup_rate       : lignes ajoutées à chaque base, copies d'un NSS déjà
+//                    présent sous une autre identification (sans effet
+//                    sur l'intersection des NSS distincts)
+//   identity       : ajoute nom,prenom,date_naissance (colonnes --fields
+//                    par défaut de --fuzzy). Une entité commune a la même
+//                    identité dans A et B ; pour une paire à faute, le nom
+//                    ou le prénom de B porte aussi une faute (une lettre
+//                    remplacée, ou deux lettres voisines inversées). Tirée
+//                    d'un générateur distinct : A et B gardent les mêmes
+//                    NSS et identifications que sans identity.
+//
+// Vérité terrain, à comparer à la sortie d'ExactMatch :
+//   <préfixe>_truth.csv   NSS_A,NSS_B,typo — une ligne par entité commune
+//   <préfixe>_truth.json  tailles, graine, |A ∩ B| exact, paires à faute
+// ============================================================================
+
+/// NSS à 15 chiffres, sans zéro de tête
+const NSS_MIN: u64 = 100_000_000_000_000;
+const NSS_MAX: u64 = 1_000_000_000_000_000;
+
+const ID_ALPHABET: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+/// Décorrèle le générateur des identités de celui des NSS
+const IDENTITY_SEED: u64 = 0x6964_656e_7469_7479;
+
+const NOMS: &[&str] = &[
+    "Martin", "Bernard", "Dubois", "Thomas", "Robert", "Richard", "Petit", "Durand", "Leroy", "Moreau",
+    "Simon", "Laurent", "Lefebvre", "Michel", "Garcia", "David", "Bertrand", "Roux", "Vincent", "Fournier",
+    "Morel", "Girard", "Andre", "Lefevre", "Mercier", "Dupont", "Lambert", "Bonnet", "Francois", "Martinez",
+];
+const PRENOMS: &[&str] = &[
+    "Marie", "Jean", "Pierre", "Michel", "Anne", "Philippe", "Nathalie", "Isabelle", "Alain", "Sylvie",
+    "Nicolas", "Catherine", "Christophe", "Sophie", "Patrick", "Julien", "Camille", "Louise", "Hugo", "Emma",
+];
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SynthConfig {
+    pub size_a:    usize,
+    pub size_b:    usize,
+    pub overlap:   usize,
+    pub dup_rate:  f64,
+    pub typo_rate: f64,
+    pub seed:      u64,
+    pub identity:  bool,
+}
+
+impl SynthConfig {
+    pub fn validate(&self) -> Result<(), RecordError> {
+        if self.overlap > self.size_a.min(self.size_b) {
+            return Err(RecordError::Config(format!(
+                "overlap {} > min(|A|, |B|) = {}", self.overlap, self.size_a.min(self.size_b)
+            )));
+        }
+        for (name, r) in [("dup_rate", self.dup_rate), ("typo_rate", self.typo_rate)] {
+            if !(0.0..=1.0).contains(&r) {
+                return Err(RecordError::Config(format!("{} = {} hors de [0, 1]", name, r)));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Colonnes d'identité (SynthConfig::identity)
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identity {
+    pub nom:            String,
+    pub prenom:         String,
+    pub date_naissance: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthRow {
+    pub id:       String,
+    pub nss:      String,
+    pub identity: Option<Identity>,
+}
+
+/// Entité commune : NSS dans A et dans B (différents si typo)
+#[derive(Debug, Clone, PartialEq)]
+pub struct TruthPair {
+    pub nss_a: String,
+    pub nss_b: String,
+    pub typo:  bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SynthData {
+    pub config: SynthConfig,
+    pub a:      Vec<SynthRow>,
+    pub b:      Vec<SynthRow>,
+    pub truth:  Vec<TruthPair>,
+}
+
+#[derive(Serialize)]
+struct TruthSummary<'a> {
+    config:             &'a SynthConfig,
+    rows_a:             usize,
+    rows_b:             usize,
+    exact_intersection: usize,
+    typo_pairs:         usize,
+}
+
+pub fn nss_hash(nss: &str) -> String {
+    sha256_hex(nss)
+}
+
+fn random_id(rng: &mut StdRng) -> String {
+    let len = rng.gen_range(8..=14);
+    (0..len).map(|_| *ID_ALPHABET.choose(rng).expect("alphabet non vide") as char).collect()
+}
+
+fn fresh_nss(rng: &mut StdRng, used: &mut HashSet<String>) -> String {
+    loop {
+        let nss = rng.gen_range(NSS_MIN..NSS_MAX).to_string();
+        if used.insert(nss.clone()) {
+            return nss;
+        }
+    }
+}
+
+/// Faute de frappe : substitution d'un chiffre ou inversion de deux
+/// chiffres voisins ; le résultat est un NSS à 15 chiffres inédit
+fn typo_of(nss: &str, rng: &mut StdRng, used: &mut HashSet<String>) -> String {
+    loop {
+        let mut d: Vec<u8> = nss.bytes().collect();
+        let i = rng.gen_range(0..d.len() - 1);
+        if rng.gen_bool(0.5) {
+            d.swap(i, i + 1);
+        } else {
+            let i = rng.gen_range(0..d.len());
+            d[i] = b'0' + rng.gen_range(0..10u8);
+        }
+        let t = String::from_utf8(d).expect("chiffres ASCII");
+        if !t.starts_with('0') && used.insert(t.clone()) {
+            return t;
+        }
+    }
+}
+
+fn random_identity(rng: &mut StdRng) -> Identity {
+    Identity {
+        nom:            NOMS.choose(rng).expect("liste non vide").to_string(),
+        prenom:         PRENOMS.choose(rng).expect("liste non vide").to_string(),
+        date_naissance: format!("{}-{:02}-{:02}", rng.gen_range(1930..2006), rng.gen_range(1..=12), rng.gen_range(1..=28)),
+    }
+}
+
+/// Faute de frappe sur un nom : substitution d'une lettre ou inversion
+/// de deux lettres voisines ; le résultat diffère toujours de `name`
+fn name_typo(name: &str, rng: &mut StdRng) -> String {
+    let letters: Vec<char> = name.chars().collect();
+    loop {
+        let mut d = letters.clone();
+        let i = rng.gen_range(1..d.len());
+        if rng.gen_bool(0.5) {
+            d.swap(i - 1, i);
+        } else {
+            d[i] = rng.gen_range(b'a'..=b'z') as char;
+        }
+        let t: String = d.into_iter().collect();
+        if t.to_lowercase() != name.to_lowercase() {
+            return t;
+        }
+    }
+}
+
+/// Identité de chaque NSS : partagée par les entités communes, avec une
+/// faute sur le nom ou le prénom de B pour les paires à faute
+fn build_identities(truth: &[TruthPair], nss_a: &[String], nss_b: &[String], seed: u64) -> HashMap<String, Identity> {
+    let mut rng = StdRng::seed_from_u64(seed ^ IDENTITY_SEED);
+    let mut out = HashMap::with_capacity(nss_a.len() + nss_b.len());
+    for t in truth {
+        let id = random_identity(&mut rng);
+        let mut id_b = id.clone();
+        if t.typo {
+            if rng.gen_bool(0.5) {
+                id_b.nom = name_typo(&id.nom, &mut rng);
+            } else {
+                id_b.prenom = name_typo(&id.prenom, &mut rng);
+            }
+        }
+        out.insert(t.nss_a.clone(), id);
+        out.insert(t.nss_b.clone(), id_b);
+    }
+    for n in nss_a[truth.len()..].iter().chain(&nss_b[truth.len()..]) {
+        out.insert(n.clone(), random_identity(&mut rng));
+    }
+    out
+}
+
+/// Lignes d'une base : un NSS par entité, puis les doublons, mélangés
+fn build_rows(nss: &[String], dup_rate: f64, identities: &HashMap<String, Identity>, rng: &mut StdRng) -> Vec<SynthRow> {
+    let dups = (nss.len() as f64 * dup_rate).round() as usize;
+    let row = |n: &String, rng: &mut StdRng| SynthRow {
+        id:       random_id(rng),
+        nss:      n.clone(),
+        identity: identities.get(n).cloned(),
+    };
+    let mut rows: Vec<SynthRow> = nss.iter().map(|n| row(n, rng)).collect();
+    for _ in 0..dups {
+        let n = nss.choose(rng).expect("dups = 0 si la base est vide");
+        rows.push(row(n, rng));
+    }
+    rows.shuffle(rng);
+    rows
+}
+
+pub fn generate(cfg: &SynthConfig) -> Result<SynthData, RecordError> {
+    cfg.validate()?;
+    let mut rng = StdRng::seed_from_u64(cfg.seed);
+    let mut used = HashSet::with_capacity(cfg.size_a + cfg.size_b);
+
+    let common: Vec<String> = (0..cfg.overlap).map(|_| fresh_nss(&mut rng, &mut used)).collect();
+    let typos = (cfg.overlap as f64 * cfg.typo_rate).round() as usize;
+    let truth: Vec<TruthPair> = common.iter()
+        .enumerate()
+        .map(|(i, n)| {
+            let typo = i < typos;
+            let nss_b = if typo { typo_of(n, &mut rng, &mut used) } else { n.clone() };
+            TruthPair { nss_a: n.clone(), nss_b, typo }
+        })
+        .collect();
+
+    let mut nss_a: Vec<String> = common.clone();
+    nss_a.extend((cfg.overlap..cfg.size_a).map(|_| fresh_nss(&mut rng, &mut used)));
+    let mut nss_b: Vec<String> = truth.iter().map(|t| t.nss_b.clone()).collect();
+    nss_b.extend((cfg.overlap..cfg.size_b).map(|_| fresh_nss(&mut rng, &mut used)));
+
+    let identities = if cfg.identity {
+        build_identities(&truth, &nss_a, &nss_b, cfg.seed)
+    } else {
+        HashMap::new()
+    };
+    let a = build_rows(&nss_a, cfg.dup_rate, &identities, &mut rng);
+    let b = build_rows(&nss_b, cfg.dup_rate, &identities, &mut rng);
+    Ok(SynthData { config: cfg.clone(), a, b, truth })
+}
+
+fn write_text(path: &Path, text: &str) -> Result<(), RecordError> {
+    fs::write(path, text).map_err(|e| RecordError::Io { path: path.display().to_string(), msg: e.to_string() })
+}
+
+impl SynthData {
+    /// |A ∩ B| attendu d'ExactMatch (NSS distincts)
+    pub fn exact_intersection(&self) -> usize {
+        self.truth.iter().filter(|t| !t.typo).count()
+    }
+
+    pub fn typo_pairs(&self) -> usize {
+        self.truth.iter().filter(|t| t.typo).count()
+    }
+
+    fn rows_csv(&self, rows: &[SynthRow]) -> String {
+        let mut out = String::from("identification,NSS,HASH(NSS)");
+        out.push_str(if self.config.identity { ",nom,prenom,date_naissance\n" } else { "\n" });
+        for r in rows {
+            out.push_str(&format!("{},{},{}", r.id, r.nss, nss_hash(&r.nss)));
+            if let Some(i) = &r.identity {
+                out.push_str(&format!(",{},{},{}", i.nom, i.prenom, i.date_naissance));
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    /// Écrit <préfixe>_A.csv, <préfixe>_B.csv, <préfixe>_truth.csv et
+    /// <préfixe>_truth.json ; retourne les chemins dans cet ordre
+    pub fn write(&self, prefix: &str) -> Result<[String; 4], RecordError> {
+        let paths = ["_A.csv", "_B.csv", "_truth.csv", "_truth.json"].map(|s| format!("{}{}", prefix, s));
+        if let Some(dir) = Path::new(prefix).parent().filter(|d| !d.as_os_str().is_empty()) {
+            fs::create_dir_all(dir).map_err(|e| RecordError::Io { path: dir.display().to_string(), msg: e.to_string() })?;
+        }
+        write_text(Path::new(&paths[0]), &self.rows_csv(&self.a))?;
+        write_text(Path::new(&paths[1]), &self.rows_csv(&self.b))?;
+
+        let mut truth = String::from("NSS_A,NSS_B,typo\n");
+        for t in &self.truth {
+            truth.push_str(&format!("{},{},{}\n", t.nss_a, t.nss_b, t.typo as u8));
+        }
+        write_text(Path::new(&paths[2]), &truth)?;
+
+        let summary = TruthSummary {
+            config:             &self.config,
+            rows_a:             self.a.len(),
+            rows_b:             self.b.len(),
+            exact_intersection: self.exact_intersection(),
+            typo_pairs:         self.typo_pairs(),
+        };
+        let json = serde_json::to_string_pretty(&summary).map_err(|e| RecordError::Config(e.to_string()))?;
+        write_text(Path::new(&paths[3]), &json)?;
+        Ok(paths)
+    }
+}
+
+// ============================================================================
+// Tests
+// ============================================================================
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::records::{load_records, KeyConfig};
+
+    #[test]
+    fn test_synthetic_pair_matches_ground_truth() {
+        let cfg = SynthConfig { size_a: 300, size_b: 200, overlap: 80, dup_rate: 0.1, typo_rate: 0.25, seed: 7, identity: false };
+        let data = generate(&cfg).unwrap();
+        assert_eq!(data, generate(&cfg).unwrap());
+        assert_eq!((data.a.len(), data.b.len()), (330, 220));
+        assert_eq!((data.exact_intersection(), data.typo_pairs()), (60, 20));
+
+        let prefix = std::env::temp_dir().join(format!("synth_{}", std::process::id()));
+        let paths = data.write(prefix.to_str().unwrap()).unwrap();
+        let a = load_records(&paths[0], &KeyConfig::nss(), &["HASH(NSS)"]).unwrap();
+        let b = load_records(&paths[1], &KeyConfig::nss(), &[]).unwrap();
+        assert!(a.rejected.is_empty() && b.rejected.is_empty());
+        assert_eq!(a.extra[0][0], nss_hash(&a.keys[0]));
+        let set_a: HashSet<&String> = a.keys.iter().collect();
+        let set_b: HashSet<&String> = b.keys.iter().collect();
+        assert_eq!((set_a.len(), set_b.len()), (300, 200));
+        assert_eq!(set_a.intersection(&set_b).count(), data.exact_intersection());
+        for p in &paths {
+            fs::remove_file(p).unwrap();
+        }
+
+        assert!(generate(&SynthConfig { overlap: 201, ..cfg.clone() }).is_err());
+    }
+
+    #[test]
+    fn test_synthetic_identity_columns_carry_typos() {
+        let cfg = SynthConfig { size_a: 120, size_b: 90, overlap: 40, dup_rate: 0.1, typo_rate: 0.5, seed: 3, identity: true };
+        let data = generate(&cfg).unwrap();
+        let plain = generate(&SynthConfig { identity: false, ..cfg.clone() }).unwrap();
+        let strip = |rows: &[SynthRow]| rows.iter().map(|r| (r.id.clone(), r.nss.clone())).collect::<Vec<_>>();
+        assert_eq!((strip(&data.a), strip(&data.b)), (strip(&plain.a), strip(&plain.b)));
+
+        let ident = |rows: &[SynthRow]| -> HashMap<String, Identity> {
+            rows.iter().map(|r| (r.nss.clone(), r.identity.clone().unwrap())).collect()
+        };
+        let (ia, ib) = (ident(&data.a), ident(&data.b));
+        for t in &data.truth {
+            let (x, y) = (&ia[&t.nss_a], &ib[&t.nss_b]);
+            assert_eq!(x.date_naissance, y.date_naissance);
+            let differing = (x.nom != y.nom) as u8 + (x.prenom != y.prenom) as u8;
+            assert_eq!(differing, t.typo as u8);
+        }
+
+        let prefix = std::env::temp_dir().join(format!("synth_id_{}", std::process::id()));
+        let paths = data.write(prefix.to_str().unwrap()).unwrap();
+        let cols = ["nom", "prenom", "date_naissance"];
+        let b = load_records(&paths[1], &KeyConfig::nss(), &cols).unwrap();
+        assert!(b.rejected.is_empty());
+        let i = &ib[&b.keys[0]];
+        assert_eq!(b.extra[0], vec![i.nom.clone(), i.prenom.clone(), i.date_naissance.clone()]);
+        for p in &paths {
+            fs::remove_file(p).unwrap();
+        }
+    }
+}